@@ -0,0 +1,228 @@
+//! 清理从 wiki 获取的文字：解码 HTML 实体、去掉 HTML 标签和 wiki 标记、规范空白。
+//!
+//! 各函数可以单独使用，[`clean_text`] 依次执行全部步骤；
+//! [`clean`] 和 [`clean_opt`] 用于 `#[serde(deserialize_with = "...")]`，读取时直接清理。
+//! 不完整的标记（例如缺少 `>` 的 `<`、缺少 `}}` 的 `{{`）原样保留，不会出错。
+
+use serde::{Deserialize, Deserializer};
+
+/// 依次解码 HTML 实体、去掉 wiki 标记和 HTML 标签、规范空白
+pub fn clean_text(s: &str) -> String {
+    normalize_whitespace(&strip_tags(&strip_wiki(&decode_entities(s))))
+}
+
+/// 解码 `&lt;`、`&amp;`、`&nbsp;` 等常见的命名实体和 `&#20013;`、`&#x4E2D;` 等数字实体，
+/// 无法识别的实体原样保留。没有分号的 `&lt`、`&gt` 也会解码
+pub fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        match decode_entity(rest) {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 解码 `s` 开头的实体，返回字符和实体的长度
+fn decode_entity(s: &str) -> Option<(char, usize)> {
+    const NAMED: [(&str, char); 8] = [
+        ("lt", '<'),
+        ("gt", '>'),
+        ("amp", '&'),
+        ("quot", '"'),
+        ("apos", '\''),
+        ("nbsp", ' '),
+        ("middot", '·'),
+        ("hellip", '…'),
+    ];
+    let body = &s[1..];
+    if let Some(number) = body.strip_prefix('#') {
+        let end = number.find(';')?;
+        let code = match number[..end].strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number[..end].parse().ok()?,
+        };
+        return char::from_u32(code).map(|c| (c, end + 3));
+    }
+    NAMED.iter().find_map(|&(name, c)| {
+        let after = body.strip_prefix(name)?;
+        match after.starts_with(';') {
+            true => Some((c, name.len() + 2)),
+            // wiki 数据中常见没有分号的 `&lt`、`&gt`
+            false if matches!(name, "lt" | "gt") => Some((c, name.len() + 1)),
+            false => None,
+        }
+    })
+}
+
+/// 去掉 HTML 标签和游戏文本中的 `<@ba.kw>`、`<$ba.stun>`、`</>` 标记，`<br>` 换为换行。
+/// 只处理 `<` 之后是字母、`/`、`!`、`@` 或 `$` 且有对应 `>` 的标签，例如 “攻击力<+10%>” 中的 `<+10%>` 会保留
+pub fn strip_tags(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(lt) = rest.find('<') {
+        out.push_str(&rest[..lt]);
+        let tag = &rest[lt + 1..];
+        let is_tag = tag
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '@' | '$'));
+        match tag.find('>').filter(|_| is_tag) {
+            Some(gt) => {
+                let name = tag[..gt].trim_start_matches('/').trim_end_matches('/');
+                if name.trim().eq_ignore_ascii_case("br") {
+                    out.push('\n');
+                }
+                rest = &tag[gt + 1..];
+            }
+            None => {
+                out.push('<');
+                rest = tag;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 去掉 wiki 标记：
+/// - 模板 `{{名称|参数|...|文字}}` 保留最后一个参数，没有参数的模板整个去掉，可以嵌套；
+/// - 内部链接 `[[页面|文字]]` 保留文字，`[[页面]]` 保留页面名；
+/// - 外部链接 `[https://... 文字]` 保留文字；
+/// - 粗体、斜体的 `'''`、`''` 去掉。
+pub fn strip_wiki(s: &str) -> String {
+    let s = replace_pairs(s, "{{", "}}", |inner| {
+        inner
+            .rsplit_once('|')
+            .map_or(String::new(), |(_, last)| last.to_string())
+    });
+    let s = replace_pairs(&s, "[[", "]]", |inner| {
+        inner.rsplit('|').next().unwrap_or_default().to_string()
+    });
+    let s = replace_pairs(&s, "[http", "]", |inner| {
+        inner
+            .split_once(' ')
+            .map_or(String::new(), |(_, label)| label.to_string())
+    });
+    s.replace("'''", "").replace("''", "")
+}
+
+/// 从内向外替换 `open ... close` 包围的内容，`replace` 收到不含 `open`、`close` 的内容。
+/// 找不到对应的 `close` 时原样保留
+fn replace_pairs(s: &str, open: &str, close: &str, replace: impl Fn(&str) -> String) -> String {
+    let mut s = s.to_string();
+    // 最后一个 open 之后的第一个 close 一定是最内层的一对
+    let mut search_end = s.len();
+    while let Some(start) = s[..search_end].rfind(open) {
+        let inner_start = start + open.len();
+        match s[inner_start..].find(close) {
+            Some(len) => {
+                let replaced = replace(&s[inner_start..inner_start + len]);
+                s.replace_range(start..inner_start + len + close.len(), &replaced);
+                search_end = s.len().min(start + replaced.len());
+            }
+            None => search_end = start,
+        }
+    }
+    s
+}
+
+/// 每行去掉首尾空白，行内连续的空白合并为一个空格，去掉空行
+pub fn normalize_whitespace(s: &str) -> String {
+    s.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 读取字符串并清理，见 [`clean_text`]
+pub fn clean<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(clean_text(&String::deserialize(deserializer)?))
+}
+
+/// 读取可选的字符串并清理，见 [`clean_text`]。字段需要同时设置 `#[serde(default)]`
+pub fn clean_opt<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.map(|s| clean_text(&s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("a &lt;b&gt; &amp; &#20013;&#x6587;"),
+            "a <b> & 中文"
+        );
+        assert_eq!(decode_entities("&ltspan&gt"), "<span>");
+        assert_eq!(
+            decode_entities("AT&T &unknown; &#zz;"),
+            "AT&T &unknown; &#zz;"
+        );
+    }
+
+    #[test]
+    fn test_strip_tags() {
+        assert_eq!(
+            strip_tags("<span style=\"color:red\">攻击力</span><br/>+10%"),
+            "攻击力\n+10%"
+        );
+        assert_eq!(strip_tags("攻击力<@ba.kw>+8%</>"), "攻击力+8%");
+        // 不完整或不像标签的 `<` 保留
+        assert_eq!(strip_tags("a < b <+10%> <span"), "a < b <+10%> <span");
+    }
+
+    #[test]
+    fn test_strip_wiki() {
+        assert_eq!(
+            strip_wiki("{{color|#0098DC|{{术语|cost|部署费用}}}}-1，见[[干员|干员列表]]"),
+            "部署费用-1，见干员列表"
+        );
+        assert_eq!(
+            strip_wiki("[[阿米娅]] '''粗体''' {{clear}}"),
+            "阿米娅 粗体 "
+        );
+        assert_eq!(
+            strip_wiki("[https://prts.wiki 首页] {{未闭合"),
+            "首页 {{未闭合"
+        );
+    }
+
+    #[test]
+    fn test_clean_text() {
+        // 缺少对应的 `gt;` 时保留
+        assert_eq!(
+            clean_text("  &lt;span&gt;攻击  速度&lt;/span&gt;\n\n &lt 提升 "),
+            "攻击 速度\n< 提升"
+        );
+    }
+
+    #[test]
+    fn test_clean_fields() {
+        let file: crate::CharFile = serde_json::from_str(
+            r#"{"Name": "a", "sex": "女", "race": "[[卡特斯]]&lt;br/&gt;", "skill": null}"#,
+        )
+        .unwrap();
+        assert_eq!(file.race.as_deref(), Some("卡特斯"));
+        assert_eq!((file.skill, file.height), (None, None));
+    }
+}
@@ -4,13 +4,33 @@ use serde::{Deserialize, Deserializer, Serialize, de::Error as serdeError};
 use serde_json::Value;
 use std::{collections::HashMap, result::Result};
 
+pub mod clean;
+pub mod parse;
+#[cfg(feature = "prts")]
+pub mod prts;
+pub mod slide;
+pub mod wikitext;
+
+// 让 `#[derive(ToSlide)]` 生成的 `::structs::...` 路径在本 crate 中也可以使用
+extern crate self as structs;
+
+use slide::ToSlide;
+
 #[derive(Deserialize, Serialize)]
 pub struct CharFile {
     pub Name: String,
     pub sex: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub combatExperience: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub birthPlace: Option<String>,
     #[serde(
         deserialize_with = "date_of_birth",
@@ -18,27 +38,71 @@ pub struct CharFile {
         skip_serializing_if = "Option::is_none"
     )]
     pub dateOfBirth: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub race: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub height: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub infectionStatus: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub cellOriginiumAssimilation: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub bloodOriginiumCrystalDensity: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub phy: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub flex: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub tolerance: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub plan: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub skill: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        deserialize_with = "clean::clean_opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
     pub adapt: Option<String>,
 }
 
@@ -214,6 +278,7 @@ pub struct Char {
 pub struct Memory {
     pub Name: String,
     pub storySetName: String,
+    #[serde(deserialize_with = "clean::clean")]
     pub storyIntro: String,
     #[serde(deserialize_with = "story_txt")]
     pub storyTxt: String,
@@ -228,13 +293,13 @@ pub struct Mod {
     #[serde(alias = "type")]
     pub 类型: String,
     #[serde(
-        deserialize_with = "del_lt_gt",
+        deserialize_with = "clean::clean_opt",
         default,
         skip_serializing_if = "Option::is_none"
     )]
     pub mission1: Option<String>,
     #[serde(
-        deserialize_with = "del_lt_gt",
+        deserialize_with = "clean::clean_opt",
         default,
         skip_serializing_if = "Option::is_none"
     )]
@@ -243,11 +308,11 @@ pub struct Mod {
     pub mission2Operation: Option<String>,
     #[serde(deserialize_with = "traitadd")]
     pub traitadd: bool,
-    #[serde(alias = "trait", deserialize_with = "del_lt_gt")]
+    #[serde(alias = "trait", deserialize_with = "clean::clean_opt")]
     pub 等级1特性: Option<String>,
-    #[serde(deserialize_with = "del_lt_gt")]
+    #[serde(deserialize_with = "clean::clean_opt")]
     pub talent2: Option<String>,
-    #[serde(deserialize_with = "del_lt_gt")]
+    #[serde(deserialize_with = "clean::clean_opt")]
     pub talent3: Option<String>,
     pub hp: String,
     pub atk: String,
@@ -265,7 +330,7 @@ where
     D: Deserializer<'de>,
 {
     Ok(
-        Option::<String>::deserialize(deserializer)?.map(|s| match s.split_once('月') {
+        clean::clean_opt(deserializer)?.map(|s| match s.split_once('月') {
             Some((m, d)) => format!("{:0>2}月{:0>2}日", m, d.trim_end_matches('日')),
             None => s.to_string(),
         }),
@@ -283,8 +348,12 @@ where
                 .map_err(|_| serdeError::custom(format!("invalid rarity value: {n}"))),
             None => Err(serdeError::custom(format!("invalid rarity value: {v}"))),
         },
-        Value::String(s) => match s.parse::<u8>() {
-            Ok(n) => Ok(n + 1),
+        Value::String(ref s) => match s.parse::<u8>() {
+            // 字符串形式的稀有度从 0 开始
+            Ok(n) => {
+                parse::coerce("rarity", &v, n + 1)?;
+                Ok(n + 1)
+            }
             Err(_) => Err(serdeError::custom(format!("invalid rarity value: {s}"))),
         },
         _ => Err(serdeError::custom(format!("invalid rarity value: {v}"))),
@@ -316,25 +385,14 @@ where
     match v {
         Value::Null => Ok(false),
         Value::Bool(b) => Ok(b),
-        Value::String(_) => Ok(true),
+        Value::String(_) => {
+            parse::coerce("traitadd", &v, true)?;
+            Ok(true)
+        }
         _ => Err(serdeError::custom(format!("invalid traidadd value: {v}"))),
     }
 }
 
-fn del_lt_gt<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let v = Option::<String>::deserialize(deserializer)?;
-    Ok(v.map(|mut s| {
-        while let Some(lt) = s.find("&lt") {
-            let gt = s.find("gt;").unwrap();
-            s.replace_range(lt..gt + 3, "");
-        }
-        s
-    }))
-}
-
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Mastery {
     pub name: String,
@@ -375,25 +433,33 @@ pub struct RealName {
     pub source: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSlide)]
 pub struct Skin {
+    #[slide(image, dir = "skins/", ext = "png")]
+    #[slide(text, scale = 90)]
     pub name: String,
     pub is_animated: bool,
     pub has_intro_animation: bool,
     pub has_exclusive_voice: bool,
     pub has_multiple_actions: bool,
+    #[slide(text, scale = 60)]
     pub owner: String,
+    #[serde(deserialize_with = "clean::clean")]
     pub essay: String,
+    #[slide(text, scale = 48)]
     pub painter: String,
+    #[slide(text, scale = 48)]
     pub access: String,
     pub brand_group: String,
     pub date_launch: Option<(u16, u8, u8)>,
+    #[serde(deserialize_with = "clean::clean")]
     pub description: String,
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct Brand {
     pub name: String,
+    #[serde(deserialize_with = "clean::clean")]
     pub intro: String,
     pub skin: Vec<Skin>,
 }
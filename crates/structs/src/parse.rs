@@ -0,0 +1,327 @@
+//! 读取 JSON 数据的两种模式。
+//!
+//! - [`ParseMode::Lenient`]：与直接使用 `serde_json` 相同，接受数据源中常见的不规范写法
+//!   （例如 `traitadd` 为任意字符串时视为 `true`），并在 [`ParseReport`] 中列出每一处转换。
+//! - [`ParseMode::Strict`]：拒绝未知的字段（只检查最外层的结构体）和需要转换的值，
+//!   错误中包含行号和字段名。
+
+use serde::{
+    Deserializer,
+    de::{DeserializeOwned, Error as _, Visitor, value::Error as ValueError},
+    forward_to_deserialize_any,
+};
+use serde_json::Value;
+use std::{cell::RefCell, fmt};
+
+/// 读取模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    Strict,
+    #[default]
+    Lenient,
+}
+
+/// 宽松模式中的一处转换
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coercion {
+    /// 所在的行（数组中的序号），读取单个对象时为 `None`
+    pub row: Option<usize>,
+    pub field: &'static str,
+    /// 原始值的 JSON
+    pub value: String,
+    /// 转换后的值
+    pub result: String,
+}
+
+impl fmt::Display for Coercion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(row) = self.row {
+            write!(f, "row {row} ")?;
+        }
+        write!(f, "{}: {} -> {}", self.field, self.value, self.result)
+    }
+}
+
+/// 读取过程中的转换
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseReport {
+    pub coerced: Vec<Coercion>,
+}
+
+/// 读取错误，包含行号和字段路径
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub row: Option<usize>,
+    /// 出错的字段，无法确定时为 `None`
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.row, &self.field) {
+            (Some(row), Some(field)) => write!(f, "[{row}].{field}: ")?,
+            (Some(row), None) => write!(f, "[{row}]: ")?,
+            (None, Some(field)) => write!(f, "{field}: ")?,
+            (None, None) => {}
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+struct Context {
+    mode: ParseMode,
+    row: Option<usize>,
+    coerced: Vec<Coercion>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+/// 由自定义的反序列化函数调用：宽松模式记录转换，严格模式返回错误。
+/// 不通过本模块读取时（例如直接使用 `serde_json`）按宽松模式处理，不记录
+pub(crate) fn coerce<E: serde::de::Error>(
+    field: &'static str,
+    value: &Value,
+    result: impl fmt::Display,
+) -> Result<(), E> {
+    CONTEXT.with_borrow_mut(|context| match context {
+        Some(Context {
+            mode: ParseMode::Strict,
+            ..
+        }) => Err(E::custom(format!(
+            "{field}: {value} requires coercion (strict mode)"
+        ))),
+        Some(context) => {
+            context.coerced.push(Coercion {
+                row: context.row,
+                field,
+                value: value.to_string(),
+                result: result.to_string(),
+            });
+            Ok(())
+        }
+        None => Ok(()),
+    })
+}
+
+/// 读取一个对象
+///
+/// # Errors
+/// - JSON 无效、字段类型不符，或严格模式中有未知字段、需要转换的值时返回 `Err`。
+///
+pub fn parse_one<T: DeserializeOwned>(
+    json: &[u8],
+    mode: ParseMode,
+) -> Result<(T, ParseReport), ParseError> {
+    let value = serde_json::from_slice(json).map_err(|e| error(None, None, e))?;
+    with_context(mode, || parse_value(value, mode, None))
+}
+
+/// 读取对象数组，错误中包含出错的行号
+///
+/// # Errors
+/// - 同 [`parse_one`]，数据不是数组时也返回 `Err`。
+///
+pub fn parse_rows<T: DeserializeOwned>(
+    json: &[u8],
+    mode: ParseMode,
+) -> Result<(Vec<T>, ParseReport), ParseError> {
+    let value = serde_json::from_slice(json).map_err(|e| error(None, None, e))?;
+    let Value::Array(rows) = value else {
+        return Err(error(None, None, "expected an array of rows"));
+    };
+    with_context(mode, || {
+        rows.into_iter()
+            .enumerate()
+            .map(|(row, value)| parse_value(value, mode, Some(row)))
+            .collect()
+    })
+}
+
+fn with_context<R>(
+    mode: ParseMode,
+    parse: impl FnOnce() -> Result<R, ParseError>,
+) -> Result<(R, ParseReport), ParseError> {
+    CONTEXT.set(Some(Context {
+        mode,
+        row: None,
+        coerced: Vec::new(),
+    }));
+    let result = parse();
+    let coerced = CONTEXT
+        .take()
+        .map(|context| context.coerced)
+        .unwrap_or_default();
+    result.map(|parsed| (parsed, ParseReport { coerced }))
+}
+
+fn parse_value<T: DeserializeOwned>(
+    value: Value,
+    mode: ParseMode,
+    row: Option<usize>,
+) -> Result<T, ParseError> {
+    CONTEXT.with_borrow_mut(|context| {
+        if let Some(context) = context {
+            context.row = row;
+        }
+    });
+    if mode == ParseMode::Strict
+        && let Value::Object(object) = &value
+    {
+        let fields = field_names::<T>();
+        if !fields.is_empty()
+            && let Some(unknown) = object.keys().find(|key| !fields.contains(&key.as_str()))
+        {
+            return Err(error(row, Some(unknown.clone()), "unknown field"));
+        }
+    }
+    match serde_json::from_value(value.clone()) {
+        Ok(parsed) => Ok(parsed),
+        Err(e) => Err(error(row, culprit::<T>(&value, &e.to_string()), e)),
+    }
+}
+
+/// 找出导致错误的字段：去掉某个字段后错误信息发生变化，即认为是这个字段
+fn culprit<T: DeserializeOwned>(value: &Value, message: &str) -> Option<String> {
+    let Value::Object(object) = value else {
+        return None;
+    };
+    if let Some(missing) = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next())
+    {
+        return Some(missing.to_string());
+    }
+    object
+        .keys()
+        .find(|key| {
+            let mut without = object.clone();
+            without.remove(*key);
+            let error = serde_json::from_value::<T>(Value::Object(without)).err();
+            error.map(|e| e.to_string()).as_deref() != Some(message)
+        })
+        .cloned()
+}
+
+fn error(row: Option<usize>, field: Option<String>, message: impl fmt::Display) -> ParseError {
+    ParseError {
+        row,
+        field,
+        message: message.to_string(),
+    }
+}
+
+/// 结构体 `T` 的字段名（包括别名），`T` 不是结构体时为空
+pub fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// 只记录 `deserialize_struct` 收到的字段名，然后返回错误
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(ValueError::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(ValueError::custom("field names only"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharData, CharInfo, Mod};
+
+    const MOD: &str = r#"{
+        "Name": "阿米娅", "name": "模组", "charModuleN": "1", "type": "X",
+        "mission1": null, "mission2": null, "traitadd": "是",
+        "trait": null, "talent2": null, "talent3": null,
+        "hp": "1", "atk": "1", "def": "1", "res": "0", "time": "0",
+        "cost": "0", "block": "0", "atkspd": "0", "other": ""
+    }"#;
+
+    fn char_data(trust: &str) -> String {
+        format!(
+            r#"{{"Name": "a", "hp": "1", "atk": "1", "def": "1", "res": "0",
+                "reDeploy": "70s", "cost": "18", "block": "1", "atkSpeed": "1.3", {trust}}}"#
+        )
+    }
+
+    #[test]
+    fn test_lenient_reports_coercions() {
+        let (parsed, report) = parse_one::<Mod>(MOD.as_bytes(), ParseMode::Lenient).unwrap();
+        assert!(parsed.traitadd);
+        assert_eq!(report.coerced.len(), 1);
+        assert_eq!(report.coerced[0].to_string(), r#"traitadd: "是" -> true"#);
+
+        let rows = format!("[{}]", [MOD, MOD].join(","));
+        let (_, report) = parse_rows::<Mod>(rows.as_bytes(), ParseMode::Lenient).unwrap();
+        assert_eq!(report.coerced[1].row, Some(1));
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let error = parse_one::<Mod>(MOD.as_bytes(), ParseMode::Strict)
+            .err()
+            .unwrap();
+        assert_eq!(error.field.as_deref(), Some("traitadd"));
+
+        // 别名是已知的字段
+        let rows = format!(
+            "[{}, {}]",
+            char_data(r#""trust": "+100""#),
+            char_data(r#""trust": "+100", "extra": 1"#)
+        );
+        let error = parse_rows::<CharData>(rows.as_bytes(), ParseMode::Strict)
+            .err()
+            .unwrap();
+        assert_eq!(
+            (error.row, error.field.as_deref()),
+            (Some(1), Some("extra"))
+        );
+        assert_eq!(error.to_string(), "[1].extra: unknown field");
+        assert!(parse_rows::<CharData>(rows.as_bytes(), ParseMode::Lenient).is_ok());
+
+        let missing = char_data(r#""cost2": "1""#).replace(r#""cost": "18", "#, "");
+        let error = parse_one::<CharData>(missing.as_bytes(), ParseMode::Lenient)
+            .err()
+            .unwrap();
+        assert_eq!(error.field.as_deref(), Some("cost"));
+    }
+
+    #[test]
+    fn test_type_error_field() {
+        let info = r#"[{"Name": "a", "en": "a", "profession": "p", "subProfession": "s",
+            "position": "近战位", "rarity": true, "logo": "", "tag": ""}]"#;
+        let error = parse_rows::<CharInfo>(info.as_bytes(), ParseMode::Lenient)
+            .err()
+            .unwrap();
+        assert_eq!(
+            (error.row, error.field.as_deref()),
+            (Some(0), Some("rarity"))
+        );
+        assert_eq!(field_names::<CharInfo>()[0], "Name");
+    }
+}
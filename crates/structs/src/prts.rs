@@ -0,0 +1,131 @@
+//! 通过 MediaWiki API 从 prts.wiki（或其他 MediaWiki 站点）下载页面和图片。
+//!
+//! 页面用 `action=parse` 读取 wikitext，再由 [`Page`] 解析为 [`CharFile`](crate::CharFile)、
+//! [`CharInfo`](crate::CharInfo) 和 [`Skin`](crate::Skin)；图片用 `action=query&prop=imageinfo`
+//! 查询地址后下载到缓存目录，已经存在的文件不再下载。
+
+use crate::wikitext::Page;
+use reqwest::blocking::Client;
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// prts.wiki 的 API 地址
+pub const PRTS_API: &str = "https://prts.wiki/api.php";
+
+pub struct Fetcher {
+    client: Client,
+    api: String,
+    cache_dir: PathBuf,
+}
+
+impl Fetcher {
+    /// 使用 [`PRTS_API`]，图片下载到 `cache_dir`
+    ///
+    /// # Errors
+    /// - 无法创建 HTTP 客户端时返回 `Err`。
+    ///
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(concat!("DataToVideo/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(60))
+            .build()?;
+        Ok(Self {
+            client,
+            api: PRTS_API.to_string(),
+            cache_dir: cache_dir.into(),
+        })
+    }
+
+    /// 使用其他 MediaWiki 站点的 `api.php`
+    pub fn api(mut self, api: impl Into<String>) -> Self {
+        self.api = api.into();
+        self
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    fn query(&self, params: &[(&str, &str)]) -> Result<Value> {
+        let value: Value = self
+            .client
+            .get(&self.api)
+            .query(&[("format", "json"), ("formatversion", "2")])
+            .query(params)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        if let Some(error) = value.get("error") {
+            let info = error
+                .get("info")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            return Err(format!("MediaWiki API error: {info}").into());
+        }
+        Ok(value)
+    }
+
+    /// 下载页面的 wikitext，跟随重定向，返回的标题为重定向之后的标题
+    ///
+    /// # Errors
+    /// - 请求失败或页面不存在时返回 `Err`。
+    ///
+    pub fn page(&self, title: &str) -> Result<Page> {
+        let value = self.query(&[
+            ("action", "parse"),
+            ("page", title),
+            ("prop", "wikitext"),
+            ("redirects", "1"),
+        ])?;
+        let parse = &value["parse"];
+        let wikitext = parse["wikitext"]
+            .as_str()
+            .ok_or_else(|| format!("No wikitext for page {title:?}"))?;
+        let title = parse["title"].as_str().unwrap_or(title);
+        Ok(Page::new(title, wikitext))
+    }
+
+    /// 下载图片到缓存目录，返回保存的路径。文件已经存在时不再下载
+    ///
+    /// # Errors
+    /// - 图片不存在或下载、保存失败时返回 `Err`。
+    ///
+    pub fn download_image(&self, file: &str) -> Result<PathBuf> {
+        let path = self.cache_dir.join(file.replace(['/', '\\'], "_"));
+        if path.exists() {
+            return Ok(path);
+        }
+        let value = self.query(&[
+            ("action", "query"),
+            ("titles", &format!("File:{file}")),
+            ("prop", "imageinfo"),
+            ("iiprop", "url"),
+        ])?;
+        let url = value["query"]["pages"][0]["imageinfo"][0]["url"]
+            .as_str()
+            .ok_or_else(|| format!("Image {file:?} not found"))?;
+        let bytes = self.client.get(url).send()?.error_for_status()?.bytes()?;
+        fs::create_dir_all(&self.cache_dir)?;
+        fs::write(&path, bytes)?;
+        Ok(path)
+    }
+
+    /// 下载页面引用的所有图片，返回保存的路径
+    ///
+    /// # Errors
+    /// - 任一图片下载失败时返回 `Err`。
+    ///
+    pub fn download_images(&self, page: &Page) -> Result<Vec<PathBuf>> {
+        page.images()
+            .iter()
+            .map(|file| self.download_image(file))
+            .collect()
+    }
+}
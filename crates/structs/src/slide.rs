@@ -0,0 +1,148 @@
+//! 数据结构到 slide 布局的映射，通常通过 `#[derive(ToSlide)]` 在结构体旁边声明：
+//!
+//! ```ignore
+//! #[derive(Deserialize, Serialize, ToSlide)]
+//! pub struct Skin {
+//!     #[slide(image, dir = "skins/", ext = "png")]
+//!     #[slide(text, scale = 100, x = 0.55, y = 0.1, height = 0.12)]
+//!     pub name: String,
+//!     #[slide(text)]
+//!     pub painter: String,
+//!     pub essay: String,
+//! }
+//! ```
+//!
+//! 只有带 `#[slide(...)]` 属性的字段会出现在 slide 中，每个属性一列，同一字段可以有多个属性。
+//! [`ToSlide::slide_row`] 按属性的声明顺序生成一行数据，[`ToSlide::slide_fields`] 描述每一列的绘制方式，由 `to_video` 转换为操作。
+
+pub use structs_derive::ToSlide;
+
+/// 一列的绘制方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlideKind {
+    /// 文字，`scale` 为最大字号
+    Text { scale: f32 },
+    /// 图片，该列为图片路径
+    Image,
+}
+
+/// 一列的布局
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlideField {
+    /// 字段名
+    pub name: &'static str,
+    pub kind: SlideKind,
+    /// 相对 slide 的 `(left, top, height)`，`None` 时自动排列
+    pub pos: Option<(f32, f32, f32)>,
+}
+
+/// 声明了 slide 布局的数据结构
+pub trait ToSlide {
+    /// 各列的布局，与 [`ToSlide::slide_row`] 的各列一一对应
+    fn slide_fields() -> Vec<SlideField>;
+    /// 一行数据
+    fn slide_row(&self) -> Vec<String>;
+}
+
+/// 字段转换为单元格中的文字
+pub trait SlideValue {
+    fn slide_value(&self) -> String;
+}
+
+impl SlideValue for String {
+    fn slide_value(&self) -> String {
+        self.clone()
+    }
+}
+
+impl SlideValue for str {
+    fn slide_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl SlideValue for bool {
+    fn slide_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// `None` 为空的单元格
+impl<T: SlideValue> SlideValue for Option<T> {
+    fn slide_value(&self) -> String {
+        self.as_ref().map(T::slide_value).unwrap_or_default()
+    }
+}
+
+/// 用 `、` 连接各项，例如画师的作品列表
+impl<T: SlideValue> SlideValue for Vec<T> {
+    fn slide_value(&self) -> String {
+        self.iter()
+            .map(T::slide_value)
+            .collect::<Vec<_>>()
+            .join("、")
+    }
+}
+
+macro_rules! impl_slide_value_number {
+    ($($ty:ty),*) => {
+        $(impl SlideValue for $ty {
+            fn slide_value(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+impl_slide_value_number!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+/// 图片路径：`dir` + `value` + `.ext`，`ext` 为空时不加扩展名。`value` 为空时返回空字符串，不绘制图片
+pub fn image_path(dir: &str, value: &str, ext: &str) -> String {
+    match (value.trim(), ext) {
+        ("", _) => String::new(),
+        (value, "") => format!("{dir}{value}"),
+        (value, ext) => format!("{dir}{value}.{ext}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(ToSlide)]
+    struct Card {
+        #[slide(image, dir = "avatars/", ext = "png")]
+        #[slide(text)]
+        name: String,
+        #[slide(text, scale = 100, x = 0.1, y = 0.2, height = 0.1)]
+        title: Option<String>,
+        #[allow(dead_code)]
+        hidden: u32,
+        #[slide(text)]
+        rarity: u8,
+    }
+
+    #[test]
+    fn test_derive() {
+        let card = Card {
+            name: "阿米娅".to_string(),
+            title: None,
+            hidden: 1,
+            rarity: 5,
+        };
+        assert_eq!(card.slide_row(), ["avatars/阿米娅.png", "阿米娅", "", "5"]);
+        let fields = Card::slide_fields();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[0].kind, SlideKind::Image);
+        assert_eq!(fields[1].name, "name");
+        assert_eq!(
+            fields[2],
+            SlideField {
+                name: "title",
+                kind: SlideKind::Text { scale: 100.0 },
+                pos: Some((0.1, 0.2, 0.1)),
+            }
+        );
+        assert_eq!(fields[3].pos, None);
+    }
+}
@@ -0,0 +1,361 @@
+//! 解析 MediaWiki 页面的 wikitext：读取模板参数、填充结构体、找出引用的图片。
+//!
+//! 模板参数都是文字，填充结构体时按字段的类型转换：`bool` 接受 `true`/`false`、`1`/`0`、`是`/`否`，
+//! 数字去掉首尾空白后解析，`Option` 字段的空参数为 `None`，元组按非数字字符拆分（例如日期 `2021-03-05`）。
+//! 下载页面见 `prts` 模块（需要启用 `prts` feature）。
+
+use crate::{CharFile, CharInfo, Skin};
+use indexmap::IndexMap;
+use serde::{
+    Deserializer,
+    de::{
+        DeserializeOwned, Error as _, IntoDeserializer, Visitor,
+        value::{Error as ValueError, MapDeserializer, SeqDeserializer},
+    },
+    forward_to_deserialize_any,
+};
+
+/// 图片文件的扩展名，模板参数以这些扩展名结尾时视为引用的图片
+const IMAGE_EXTENSIONS: [&str; 5] = [".png", ".jpg", ".jpeg", ".webp", ".gif"];
+/// 图片链接的命名空间
+const FILE_NAMESPACES: [&str; 4] = ["File:", "文件:", "Image:", "图像:"];
+
+/// 一个页面
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub title: String,
+    pub wikitext: String,
+}
+
+/// 读取数据使用的模板名称
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Templates {
+    pub char_file: String,
+    pub char_info: String,
+    pub skin: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            char_file: "人员档案".to_string(),
+            char_info: "干员信息".to_string(),
+            skin: "时装".to_string(),
+        }
+    }
+}
+
+impl Page {
+    pub fn new(title: impl Into<String>, wikitext: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            wikitext: wikitext.into(),
+        }
+    }
+
+    /// 从第一个 `template` 模板读取干员档案，模板中没有 `Name` 时使用页面标题
+    ///
+    /// # Errors
+    /// - 页面中没有该模板，或参数无法转换为字段的类型时返回 `Err`。
+    ///
+    pub fn char_file(&self, template: &str) -> Result<CharFile, ValueError> {
+        self.record(template, "Name")
+    }
+
+    /// 从第一个 `template` 模板读取干员信息，模板中没有 `Name` 时使用页面标题
+    ///
+    /// # Errors
+    /// - 同 [`Page::char_file`]。
+    ///
+    pub fn char_info(&self, template: &str) -> Result<CharInfo, ValueError> {
+        self.record(template, "Name")
+    }
+
+    /// 从所有 `template` 模板读取时装，模板中没有 `owner` 时使用页面标题
+    ///
+    /// # Errors
+    /// - 参数无法转换为字段的类型时返回 `Err`，没有该模板时返回空列表。
+    ///
+    pub fn skins(&self, template: &str) -> Result<Vec<Skin>, ValueError> {
+        templates(&self.wikitext, template)
+            .into_iter()
+            .map(|params| from_params(self.with_title(params, "owner")))
+            .collect()
+    }
+
+    /// 页面引用的图片文件名，见 [`referenced_images`]
+    pub fn images(&self) -> Vec<String> {
+        referenced_images(&self.wikitext)
+    }
+
+    fn record<T: DeserializeOwned>(
+        &self,
+        template: &str,
+        title_field: &str,
+    ) -> Result<T, ValueError> {
+        let params = templates(&self.wikitext, template)
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ValueError::custom(format!("{}: template {template:?} not found", self.title))
+            })?;
+        from_params(self.with_title(params, title_field))
+    }
+
+    fn with_title(
+        &self,
+        mut params: IndexMap<String, String>,
+        field: &str,
+    ) -> IndexMap<String, String> {
+        params
+            .entry(field.to_string())
+            .or_insert_with(|| self.title.clone());
+        params
+    }
+}
+
+/// 页面中所有名为 `name` 的模板（包括嵌套在其他模板中的）的参数。
+/// 命名参数 `key=value` 按名称保存，位置参数按 `1`、`2` 等序号保存，名称和值都去掉首尾空白
+pub fn templates(wikitext: &str, name: &str) -> Vec<IndexMap<String, String>> {
+    let mut found = Vec::new();
+    let mut rest = wikitext;
+    while let Some(start) = rest.find("{{") {
+        let inner = &rest[start + 2..];
+        let Some(len) = matching_close(inner) else {
+            break;
+        };
+        let parts = split_top_level(&inner[..len]);
+        if parts[0].trim().replace('_', " ") == name.replace('_', " ") {
+            let mut params = IndexMap::new();
+            let mut position = 0;
+            for part in &parts[1..] {
+                match part.split_once('=') {
+                    Some((key, value)) if !key.contains(['{', '[']) => {
+                        params.insert(key.trim().to_string(), value.trim().to_string());
+                    }
+                    _ => {
+                        position += 1;
+                        params.insert(position.to_string(), part.trim().to_string());
+                    }
+                }
+            }
+            found.push(params);
+        }
+        // 从模板内部继续查找，嵌套的模板也能找到
+        rest = inner;
+    }
+    found
+}
+
+/// `s` 紧接在 `{{` 之后，返回对应的 `}}` 的位置，没有时为 `None`
+fn matching_close(s: &str) -> Option<usize> {
+    let mut depth = 1;
+    let mut i = 0;
+    while i < s.len() {
+        if s[i..].starts_with("{{") {
+            depth += 1;
+            i += 2;
+        } else if s[i..].starts_with("}}") {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+            i += 2;
+        } else {
+            i += s[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// 按不在 `{{ }}`、`[[ ]]` 中的 `|` 拆分
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start, mut i) = (0usize, 0, 0);
+    while i < s.len() {
+        let rest = &s[i..];
+        if rest.starts_with("{{") || rest.starts_with("[[") {
+            depth += 1;
+            i += 2;
+        } else if rest.starts_with("}}") || rest.starts_with("]]") {
+            depth = depth.saturating_sub(1);
+            i += 2;
+        } else if rest.starts_with('|') && depth == 0 {
+            parts.push(&s[start..i]);
+            i += 1;
+            start = i;
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// 页面引用的图片：`[[File:...]]`、`[[文件:...]]` 链接，以及值为图片文件名的模板参数。
+/// 去掉重复的文件名，保持出现的顺序
+pub fn referenced_images(wikitext: &str) -> Vec<String> {
+    let mut images: Vec<String> = Vec::new();
+    let mut push = |name: &str| {
+        let name = name.trim().replace('_', " ");
+        if !name.is_empty() && !images.contains(&name) {
+            images.push(name);
+        }
+    };
+    let mut rest = wikitext;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        if let Some(file) = FILE_NAMESPACES
+            .iter()
+            .find_map(|namespace| rest.strip_prefix(namespace))
+        {
+            let end = file.find(['|', ']']).unwrap_or(file.len());
+            push(&file[..end]);
+        }
+    }
+    let mut rest = wikitext;
+    while let Some(eq) = rest.find('=') {
+        rest = &rest[eq + 1..];
+        let end = rest.find(['|', '}', '\n']).unwrap_or(rest.len());
+        let value = rest[..end].trim();
+        let lower = value.to_lowercase();
+        if IMAGE_EXTENSIONS.iter().any(|ext| lower.ends_with(ext)) && !value.contains(['[', '{']) {
+            push(value);
+        }
+    }
+    images
+}
+
+/// 用模板参数填充结构体，见模块说明中的类型转换
+///
+/// # Errors
+/// - 缺少必需的字段，或参数无法转换为字段的类型时返回 `Err`。
+///
+pub fn from_params<T: DeserializeOwned>(params: IndexMap<String, String>) -> Result<T, ValueError> {
+    T::deserialize(MapDeserializer::new(
+        params.into_iter().map(|(key, value)| (key, Param(value))),
+    ))
+}
+
+/// 一个模板参数，按读取的类型转换
+struct Param(String);
+
+impl IntoDeserializer<'_, ValueError> for Param {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident $visit:ident $ty:ty),*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            let n: $ty = self.0.trim().parse().map_err(|_| {
+                ValueError::custom(format!("invalid {}: {:?}", stringify!($ty), self.0))
+            })?;
+            visitor.$visit(n)
+        })*
+    };
+}
+
+impl<'de> Deserializer<'de> for Param {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0.trim().to_lowercase().as_str() {
+            "true" | "1" | "是" | "yes" => visitor.visit_bool(true),
+            "false" | "0" | "否" | "no" | "" => visitor.visit_bool(false),
+            _ => Err(ValueError::custom(format!("invalid bool: {:?}", self.0))),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_u8 visit_u8 u8,
+        deserialize_u16 visit_u16 u16,
+        deserialize_u32 visit_u32 u32,
+        deserialize_u64 visit_u64 u64,
+        deserialize_i8 visit_i8 i8,
+        deserialize_i16 visit_i16 i16,
+        deserialize_i32 visit_i32 i32,
+        deserialize_i64 visit_i64 i64,
+        deserialize_f32 visit_f32 f32,
+        deserialize_f64 visit_f64 f64
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0.trim() {
+            "" => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let parts = self
+            .0
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|part| !part.is_empty())
+            .map(|part| Param(part.to_string()))
+            .collect::<Vec<_>>();
+        let mut seq = SeqDeserializer::new(parts.into_iter());
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct newtype_struct seq
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "{{干员信息|稀有度=5|职业=近卫|[[File:头像_阿米娅.png|50px]]}}
+{{时装
+|name=夏日 | is_animated=否|has_intro_animation=1|has_exclusive_voice=false
+|has_multiple_actions=0|essay={{color|#999|介绍}}|painter=某画师|access=限时获得
+|brand_group=忒斯特收藏|date_launch=2021年3月5日|description=|image=立绘_阿米娅_skin1.png}}
+{{时装|name=冬日|is_animated=是|has_intro_animation=0|has_exclusive_voice=0
+|has_multiple_actions=0|essay=|painter=|access=|brand_group=|date_launch=|description=}}";
+
+    #[test]
+    fn test_templates() {
+        let params = templates(PAGE, "干员信息");
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0]["稀有度"], "5");
+        assert_eq!(params[0]["1"], "[[File:头像_阿米娅.png|50px]]");
+        assert_eq!(templates(PAGE, "时装")[0]["essay"], "{{color|#999|介绍}}");
+        assert!(templates("{{时装|name=a", "时装").is_empty());
+    }
+
+    #[test]
+    fn test_skins() {
+        let page = Page::new("阿米娅", PAGE);
+        let skins = page.skins("时装").unwrap();
+        assert_eq!(skins.len(), 2);
+        let skin = &skins[0];
+        assert_eq!(
+            (skin.name.as_str(), skin.owner.as_str()),
+            ("夏日", "阿米娅")
+        );
+        assert!(!skin.is_animated && skin.has_intro_animation && !skin.has_exclusive_voice);
+        assert_eq!(skin.essay, "介绍");
+        assert_eq!(skin.date_launch, Some((2021, 3, 5)));
+        assert!(skins[1].is_animated);
+        assert_eq!(skins[1].date_launch, None);
+
+        assert_eq!(page.images(), ["头像 阿米娅.png", "立绘 阿米娅 skin1.png"]);
+        assert!(page.char_file("人员档案").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! `#[derive(ToSlide)]`：在数据结构旁边声明它在 slide 中的布局，见 `structs::slide`。
+//!
+//! 只有带 `#[slide(...)]` 属性的字段会出现在 slide 中，按声明的顺序排列，
+//! 同一字段可以有多个属性，例如同时显示为图片和文字：
+//! - `#[slide(text)]`、`#[slide(text, scale = 100)]`：文字，`scale` 为最大字号；
+//! - `#[slide(image, dir = "skins/", ext = "png")]`：图片，路径为 `dir` + 字段值 + `.ext`；
+//! - 两种字段都可以用 `x`、`y`、`height`（相对 slide 的比例）指定位置，未指定时自动排列。
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, Lit, LitStr, meta::ParseNestedMeta, parse_macro_input};
+
+#[proc_macro_derive(ToSlide, attributes(slide))]
+pub fn derive_to_slide(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// 一个字段的 `#[slide(...)]` 属性
+struct FieldAttr {
+    image: Option<bool>,
+    scale: Option<f32>,
+    dir: String,
+    ext: String,
+    x: Option<f32>,
+    y: Option<f32>,
+    height: Option<f32>,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ToSlide can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ToSlide requires named fields",
+        ));
+    };
+
+    let mut layouts = Vec::new();
+    let mut values = Vec::new();
+    let attrs = fields.named.iter().flat_map(|field| {
+        let slide = field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("slide"));
+        slide.map(move |attr| (field, attr))
+    });
+    for (field, attr) in attrs {
+        let mut parsed = FieldAttr {
+            image: None,
+            scale: None,
+            dir: String::new(),
+            ext: String::new(),
+            x: None,
+            y: None,
+            height: None,
+        };
+        attr.parse_nested_meta(|meta| parse_field_attr(&mut parsed, &meta))?;
+        let Some(image) = parsed.image else {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "expected `#[slide(text, ...)]` or `#[slide(image, ...)]`",
+            ));
+        };
+
+        let ident = field.ident.as_ref().expect("named field");
+        let name = ident.to_string();
+        let kind = if image {
+            quote!(::structs::slide::SlideKind::Image)
+        } else {
+            let scale = parsed.scale.unwrap_or(80.0);
+            quote!(::structs::slide::SlideKind::Text { scale: #scale })
+        };
+        let pos = match (parsed.x, parsed.y, parsed.height) {
+            (Some(x), Some(y), Some(height)) => {
+                quote!(::core::option::Option::Some((#x, #y, #height)))
+            }
+            (None, None, None) => quote!(::core::option::Option::None),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`x`, `y` and `height` must be set together",
+                ));
+            }
+        };
+        layouts.push(quote! {
+            ::structs::slide::SlideField {
+                name: #name,
+                kind: #kind,
+                pos: #pos,
+            }
+        });
+        let value = quote!(::structs::slide::SlideValue::slide_value(&self.#ident));
+        values.push(if image {
+            let (dir, ext) = (&parsed.dir, &parsed.ext);
+            quote!(::structs::slide::image_path(#dir, &#value, #ext))
+        } else {
+            value
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::structs::slide::ToSlide for #ident #ty_generics #where_clause {
+            fn slide_fields() -> ::std::vec::Vec<::structs::slide::SlideField> {
+                ::std::vec![#(#layouts),*]
+            }
+
+            fn slide_row(&self) -> ::std::vec::Vec<::std::string::String> {
+                ::std::vec![#(#values),*]
+            }
+        }
+    })
+}
+
+fn parse_field_attr(parsed: &mut FieldAttr, meta: &ParseNestedMeta) -> syn::Result<()> {
+    let kind = |parsed: &mut FieldAttr, image| match parsed.image {
+        Some(_) => Err(meta.error("only one of `text` and `image` can be set")),
+        None => {
+            parsed.image = Some(image);
+            Ok(())
+        }
+    };
+    let path = &meta.path;
+    if path.is_ident("text") {
+        kind(parsed, false)
+    } else if path.is_ident("image") {
+        kind(parsed, true)
+    } else if path.is_ident("scale") {
+        parsed.scale = Some(parse_f32(meta)?);
+        Ok(())
+    } else if path.is_ident("x") {
+        parsed.x = Some(parse_f32(meta)?);
+        Ok(())
+    } else if path.is_ident("y") {
+        parsed.y = Some(parse_f32(meta)?);
+        Ok(())
+    } else if path.is_ident("height") {
+        parsed.height = Some(parse_f32(meta)?);
+        Ok(())
+    } else if path.is_ident("dir") {
+        parsed.dir = meta.value()?.parse::<LitStr>()?.value();
+        Ok(())
+    } else if path.is_ident("ext") {
+        parsed.ext = meta.value()?.parse::<LitStr>()?.value();
+        Ok(())
+    } else {
+        Err(meta.error("unknown slide attribute"))
+    }
+}
+
+/// 读取 `= 100` 或 `= 0.5` 形式的数值
+fn parse_f32(meta: &ParseNestedMeta) -> syn::Result<f32> {
+    match meta.value()?.parse::<Lit>()? {
+        Lit::Int(lit) => lit.base10_parse(),
+        Lit::Float(lit) => lit.base10_parse(),
+        lit => Err(syn::Error::new_spanned(lit, "expected a number")),
+    }
+}
@@ -6,6 +6,34 @@ use std::ops::Deref;
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Color(pub [u8; 3]);
 
+impl Color {
+    /// `#rrggbb` 形式的颜色，可以作为 ffmpeg 的颜色参数
+    pub fn hex(&self) -> String {
+        let [r, g, b] = self.0;
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+
+    /// WCAG 定义的相对亮度，0.0（黑）~ 1.0（白）
+    pub fn luminance(&self) -> f32 {
+        let linear = |c: u8| {
+            let c = f32::from(c) / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let [r, g, b] = self.0;
+        0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+    }
+
+    /// WCAG 对比度，1.0（相同亮度）~ 21.0（黑白）
+    pub fn contrast_ratio(&self, other: &Color) -> f32 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+}
+
 impl Deref for Color {
     type Target = [u8; 3];
 
@@ -62,6 +90,22 @@ mod tests {
         assert_eq!(color.0, [255, 87, 51]);
     }
 
+    #[test]
+    fn test_color_to_hex() {
+        let color = Color::try_from("#FF5733").unwrap();
+        assert_eq!(color.hex(), "#ff5733");
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let (black, white) = (Color([0, 0, 0]), Color([255, 255, 255]));
+        assert!((black.contrast_ratio(&white) - 21.0).abs() < 0.01);
+        assert!((white.contrast_ratio(&white) - 1.0).abs() < 0.01);
+        // #777777 在白色上约为 4.48
+        let gray = Color([0x77, 0x77, 0x77]);
+        assert!((gray.contrast_ratio(&white) - 4.48).abs() < 0.01);
+    }
+
     #[test]
     fn test_color_from_invalid_hex() {
         let color = Color::try_from("#ZZZZZZ");
@@ -0,0 +1,655 @@
+use crate::Result;
+use crate::shaping::{Direction, ShapedGlyph};
+use ab_glyph::{
+    Font, FontArc, Glyph, GlyphId, GlyphSvg, Outline, OutlineCurve, OutlinedGlyph, Point, Rect,
+    point, v2::GlyphImage,
+};
+use owned_ttf_parser::{
+    Face, RgbaColor,
+    colr::{ClipBox, CompositeMode, Paint, Painter},
+};
+use rustybuzz::UnicodeBuffer;
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::Range,
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// 启用 `bundled-font` feature 时内置的 MiSans Demibold 字体
+#[cfg(feature = "bundled-font")]
+pub static BUNDLED_FONT: &[u8] = include_bytes!("../../../example/MiSans-Demibold.ttf");
+
+/// 读取主字体和后备字体，组成 [`FontChain`]
+///
+/// `font` 为 `None` 时使用 `bundled-font` feature 内置的字体 `BUNDLED_FONT`。
+///
+/// # Errors
+/// - 字体文件无法读取或解析、字形总数超过 65536 时返回 `Err`。
+/// - `font` 为 `None` 且没有启用 `bundled-font` feature 时返回 `Err`。
+///
+pub fn load_font_chain(font: Option<&Path>, fallbacks: &[impl AsRef<Path>]) -> Result<FontChain> {
+    let load = |path: &Path| -> Result<FontArc> {
+        let font_buf = std::fs::read(path)?;
+        FontArc::try_from_vec(font_buf)
+            .map_err(|_| format!("Invalid font file: {}", path.display()).into())
+    };
+    let main = match font {
+        Some(font) => load(font)?,
+        None => bundled_font()?,
+    };
+    let mut fonts = vec![main];
+    for fallback in fallbacks {
+        fonts.push(load(fallback.as_ref())?);
+    }
+    FontChain::new(fonts)
+}
+
+#[cfg(feature = "bundled-font")]
+fn bundled_font() -> Result<FontArc> {
+    Ok(FontArc::try_from_slice(BUNDLED_FONT)?)
+}
+
+#[cfg(not(feature = "bundled-font"))]
+fn bundled_font() -> Result<FontArc> {
+    Err("Font not set".into())
+}
+
+/// 按顺序查找字形的字体链：主字体中没有的字符（emoji、生僻字、其他语言的标点等）
+/// 依次从后备字体中查找，避免绘制成方框。
+///
+/// 各字体的字形编号依次排列在同一个 [`GlyphId`] 空间中，后备字体的字形按主字体的
+/// `units_per_em` 缩放，行高、上下边距都使用主字体的。
+#[derive(Clone)]
+pub struct FontChain {
+    fonts: Arc<[FontArc]>,
+    /// 每个字体第一个字形在字体链中的编号
+    offsets: Arc<[u16]>,
+    /// 克隆的字体链共用同一个缓存
+    cache: Arc<GlyphCache>,
+}
+
+impl FontChain {
+    /// # Errors
+    /// - `fonts` 为空，或字形总数超过 65536 时返回 `Err`。
+    pub fn new(fonts: Vec<FontArc>) -> Result<Self> {
+        if fonts.is_empty() {
+            return Err("FontChain needs at least one font".into());
+        }
+        let mut offsets = Vec::with_capacity(fonts.len());
+        let mut next = 0usize;
+        for font in &fonts {
+            offsets.push(u16::try_from(next).map_err(|_| "too many glyphs in font chain")?);
+            next += font.glyph_count();
+        }
+        if next > usize::from(u16::MAX) + 1 {
+            return Err("too many glyphs in font chain".into());
+        }
+        Ok(Self {
+            fonts: fonts.into(),
+            offsets: offsets.into(),
+            cache: Arc::default(),
+        })
+    }
+
+    /// 字体链的字形缓存，用于查看命中率
+    pub fn glyph_cache(&self) -> &GlyphCache {
+        &self.cache
+    }
+
+    fn primary(&self) -> &FontArc {
+        &self.fonts[0]
+    }
+
+    /// 第 `index` 个字体的单位换算到主字体单位的缩放比例
+    fn ratio(&self, index: usize) -> f32 {
+        match (
+            self.primary().units_per_em(),
+            self.fonts[index].units_per_em(),
+        ) {
+            (Some(primary), Some(upem)) if index > 0 => primary / upem,
+            _ => 1.0,
+        }
+    }
+
+    /// 字形所在的字体、字形在该字体中的编号，以及换算到主字体单位的缩放比例
+    fn resolve(&self, id: GlyphId) -> (&FontArc, GlyphId, f32) {
+        let index = self.offsets.partition_point(|&offset| offset <= id.0) - 1;
+        let font = &self.fonts[index];
+        (font, GlyphId(id.0 - self.offsets[index]), self.ratio(index))
+    }
+
+    /// 按字符所在的字体把文字分段，返回每段使用的字体序号和字节范围。
+    ///
+    /// 每个字符使用第一个有该字符的字体；附加符号和零宽连接符在前一段的字体有该字符时
+    /// 跟随前一段，以便与基本字符一起整形
+    fn font_runs(&self, text: &str) -> Vec<(usize, Range<usize>)> {
+        let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+        for (i, c) in text.char_indices() {
+            let end = i + c.len_utf8();
+            let has = |index: usize| self.fonts[index].glyph_id(c).0 != 0;
+            let attached =
+                c == '\u{200D}' || unicode_bidi::bidi_class(c) == unicode_bidi::BidiClass::NSM;
+            let index = match runs.last() {
+                Some(&(last, _)) if attached && has(last) => last,
+                _ => (0..self.fonts.len()).find(|&index| has(index)).unwrap_or(0),
+            };
+            match runs.last_mut() {
+                Some((last, range)) if *last == index => range.end = end,
+                _ => runs.push((index, i..end)),
+            }
+        }
+        runs
+    }
+}
+
+impl From<FontArc> for FontChain {
+    fn from(font: FontArc) -> Self {
+        Self {
+            fonts: Arc::new([font]),
+            offsets: Arc::new([0]),
+            cache: Arc::default(),
+        }
+    }
+}
+
+impl Font for FontChain {
+    fn units_per_em(&self) -> Option<f32> {
+        self.primary().units_per_em()
+    }
+
+    fn ascent_unscaled(&self) -> f32 {
+        self.primary().ascent_unscaled()
+    }
+
+    fn descent_unscaled(&self) -> f32 {
+        self.primary().descent_unscaled()
+    }
+
+    fn line_gap_unscaled(&self) -> f32 {
+        self.primary().line_gap_unscaled()
+    }
+
+    fn italic_angle(&self) -> f32 {
+        self.primary().italic_angle()
+    }
+
+    fn glyph_id(&self, c: char) -> GlyphId {
+        self.fonts
+            .iter()
+            .zip(self.offsets.iter())
+            .map(|(font, offset)| (font.glyph_id(c), offset))
+            .find(|(id, _)| id.0 != 0)
+            .map_or(GlyphId(0), |(id, offset)| GlyphId(id.0 + offset))
+    }
+
+    fn h_advance_unscaled(&self, id: GlyphId) -> f32 {
+        let (font, id, ratio) = self.resolve(id);
+        font.h_advance_unscaled(id) * ratio
+    }
+
+    fn h_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+        let (font, id, ratio) = self.resolve(id);
+        font.h_side_bearing_unscaled(id) * ratio
+    }
+
+    fn v_advance_unscaled(&self, id: GlyphId) -> f32 {
+        let (font, id, ratio) = self.resolve(id);
+        font.v_advance_unscaled(id) * ratio
+    }
+
+    fn v_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+        let (font, id, ratio) = self.resolve(id);
+        font.v_side_bearing_unscaled(id) * ratio
+    }
+
+    fn kern_unscaled(&self, first: GlyphId, second: GlyphId) -> f32 {
+        let ((font, first, ratio), (font2, second, _)) =
+            (self.resolve(first), self.resolve(second));
+        // 不同字体的字形之间没有字距调整
+        if std::ptr::eq(font, font2) {
+            font.kern_unscaled(first, second) * ratio
+        } else {
+            0.0
+        }
+    }
+
+    fn outline(&self, id: GlyphId) -> Option<Outline> {
+        let (font, id, ratio) = self.resolve(id);
+        let outline = font.outline(id)?;
+        if ratio == 1.0 {
+            return Some(outline);
+        }
+        let p = |p: Point| point(p.x * ratio, p.y * ratio);
+        Some(Outline {
+            bounds: Rect {
+                min: p(outline.bounds.min),
+                max: p(outline.bounds.max),
+            },
+            curves: outline
+                .curves
+                .into_iter()
+                .map(|curve| match curve {
+                    OutlineCurve::Line(a, b) => OutlineCurve::Line(p(a), p(b)),
+                    OutlineCurve::Quad(a, b, c) => OutlineCurve::Quad(p(a), p(b), p(c)),
+                    OutlineCurve::Cubic(a, b, c, d) => OutlineCurve::Cubic(p(a), p(b), p(c), p(d)),
+                })
+                .collect(),
+        })
+    }
+
+    fn glyph_count(&self) -> usize {
+        self.fonts.iter().map(Font::glyph_count).sum()
+    }
+
+    /// 只包含主字体的字符
+    fn codepoint_ids(&self) -> ab_glyph::CodepointIdIter<'_> {
+        self.primary().codepoint_ids()
+    }
+
+    fn glyph_raster_image2(&self, id: GlyphId, pixel_size: u16) -> Option<GlyphImage<'_>> {
+        let (font, id, _) = self.resolve(id);
+        font.glyph_raster_image2(id, pixel_size)
+    }
+
+    /// 只支持主字体中的 SVG 字形，后备字体的字形编号与 SVG 记录不对应
+    fn glyph_svg_image(&self, id: GlyphId) -> Option<GlyphSvg<'_>> {
+        (usize::from(id.0) < self.primary().glyph_count())
+            .then(|| self.primary().glyph_svg_image(id))
+            .flatten()
+    }
+
+    fn font_data(&self) -> &[u8] {
+        self.primary().font_data()
+    }
+}
+
+/// 彩色字形（COLR 表）的一层：用 `color` (RGBA) 填充字形 `glyph` 的轮廓
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorLayer {
+    pub glyph: GlyphId,
+    pub color: [u8; 4],
+}
+
+/// 绘制文字时需要的 [`Font`] 之外的字体信息：COLR 彩色字形和文字整形
+pub trait TextFont: Font {
+    /// 字形 `id` 的彩色图层，不是彩色字形时为空。
+    ///
+    /// 只支持纯色图层，渐变使用第一个颜色，变换和裁剪被忽略；
+    /// 使用前景色的图层绘制为黑色。
+    fn color_layers(&self, id: GlyphId) -> Vec<ColorLayer>;
+
+    /// 用 rustybuzz 整形方向为 `direction` 的一段文字，返回按显示顺序从左到右排列的字形，
+    /// 见 [`shaping`](crate::shaping)
+    fn shape_run(&self, text: &str, direction: Direction) -> Vec<ShapedGlyph>;
+
+    /// 绘制文字时复用光栅化结果的字形缓存，没有缓存时为 `None`
+    fn glyph_cache(&self) -> Option<&GlyphCache> {
+        None
+    }
+}
+
+impl TextFont for FontArc {
+    fn color_layers(&self, id: GlyphId) -> Vec<ColorLayer> {
+        let Ok(face) = Face::parse(self.font_data(), 0) else {
+            return Vec::new();
+        };
+        let glyph = owned_ttf_parser::GlyphId(id.0);
+        if !face.is_color_glyph(glyph) {
+            return Vec::new();
+        }
+        let mut painter = LayerPainter::default();
+        let black = RgbaColor::new(0, 0, 0, 255);
+        face.paint_color_glyph(glyph, 0, black, &mut painter);
+        painter.layers
+    }
+
+    fn shape_run(&self, text: &str, direction: Direction) -> Vec<ShapedGlyph> {
+        let Some(face) = rustybuzz::Face::from_slice(self.font_data(), 0) else {
+            // 无法整形的字体逐字排列
+            let mut glyphs: Vec<ShapedGlyph> = text
+                .chars()
+                .map(|c| {
+                    let id = self.glyph_id(c);
+                    ShapedGlyph {
+                        id,
+                        advance: self.h_advance_unscaled(id),
+                        offset: (0.0, 0.0),
+                    }
+                })
+                .collect();
+            if direction == Direction::RightToLeft {
+                glyphs.reverse();
+            }
+            return glyphs;
+        };
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(match direction {
+            Direction::LeftToRight => rustybuzz::Direction::LeftToRight,
+            Direction::RightToLeft => rustybuzz::Direction::RightToLeft,
+        });
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(&face, &[], buffer);
+        output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .map(|(info, position)| ShapedGlyph {
+                id: GlyphId(info.glyph_id as u16),
+                advance: position.x_advance as f32,
+                offset: (position.x_offset as f32, position.y_offset as f32),
+            })
+            .collect()
+    }
+}
+
+impl TextFont for FontChain {
+    fn color_layers(&self, id: GlyphId) -> Vec<ColorLayer> {
+        let (font, local, _) = self.resolve(id);
+        let offset = id.0 - local.0;
+        font.color_layers(local)
+            .into_iter()
+            .map(|layer| ColorLayer {
+                glyph: GlyphId(layer.glyph.0 + offset),
+                ..layer
+            })
+            .collect()
+    }
+
+    /// 按 [`font_runs`](Self::font_runs) 分段，每段用各自的字体整形
+    fn shape_run(&self, text: &str, direction: Direction) -> Vec<ShapedGlyph> {
+        let mut runs: Vec<Vec<ShapedGlyph>> = self
+            .font_runs(text)
+            .into_iter()
+            .map(|(index, range)| {
+                let (offset, ratio) = (self.offsets[index], self.ratio(index));
+                self.fonts[index]
+                    .shape_run(&text[range], direction)
+                    .into_iter()
+                    .map(|glyph| ShapedGlyph {
+                        id: GlyphId(glyph.id.0 + offset),
+                        advance: glyph.advance * ratio,
+                        offset: (glyph.offset.0 * ratio, glyph.offset.1 * ratio),
+                    })
+                    .collect()
+            })
+            .collect();
+        if direction == Direction::RightToLeft {
+            runs.reverse();
+        }
+        runs.concat()
+    }
+
+    fn glyph_cache(&self) -> Option<&GlyphCache> {
+        Some(&self.cache)
+    }
+}
+
+impl<F: TextFont> TextFont for &F {
+    fn color_layers(&self, id: GlyphId) -> Vec<ColorLayer> {
+        (*self).color_layers(id)
+    }
+
+    fn shape_run(&self, text: &str, direction: Direction) -> Vec<ShapedGlyph> {
+        (*self).shape_run(text, direction)
+    }
+
+    fn glyph_cache(&self) -> Option<&GlyphCache> {
+        (*self).glyph_cache()
+    }
+}
+
+/// 缓存中最多保存的字形数，超过后新的字形不再缓存
+const MAX_CACHED_GLYPHS: usize = 16384;
+
+/// 字形缓存的键：字形编号、字号和原点的小数部分。
+///
+/// 原点的整数部分只平移覆盖率，小数部分会改变抗锯齿的结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    id: u16,
+    scale: [u32; 2],
+    offset: [u32; 2],
+}
+
+impl GlyphKey {
+    fn new(glyph: &Glyph) -> Self {
+        let p = glyph.position;
+        Self {
+            id: glyph.id.0,
+            scale: [glyph.scale.x.to_bits(), glyph.scale.y.to_bits()],
+            offset: [(p.x - p.x.floor()).to_bits(), (p.y - p.y.floor()).to_bits()],
+        }
+    }
+}
+
+/// 光栅化后的字形覆盖率
+#[derive(Debug)]
+pub struct GlyphCoverage {
+    key: GlyphKey,
+    /// 像素边界，相对于原点取整后的位置
+    bounds: Rect,
+    width: u32,
+    coverage: Vec<f32>,
+}
+
+impl GlyphCoverage {
+    pub(crate) fn rasterize(glyph: &OutlinedGlyph) -> Self {
+        let bounds = glyph.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        let mut coverage = vec![0.0; (width * height) as usize];
+        glyph.draw(|x, y, v| {
+            if x < width && y < height {
+                coverage[(y * width + x) as usize] = v;
+            }
+        });
+        let origin = glyph.glyph().position;
+        let floor = point(origin.x.floor(), origin.y.floor());
+        Self {
+            key: GlyphKey::new(glyph.glyph()),
+            bounds: Rect {
+                min: bounds.min - floor,
+                max: bounds.max - floor,
+            },
+            width,
+            coverage,
+        }
+    }
+
+    /// 光栅化的字形在缓存中的键
+    pub fn key(&self) -> GlyphKey {
+        self.key
+    }
+
+    /// 覆盖率的宽度和高度
+    pub fn size(&self) -> (u32, u32) {
+        match self.width {
+            0 => (0, 0),
+            width => (width, self.coverage.len() as u32 / width),
+        }
+    }
+
+    /// 原点位于 `glyph` 的位置时的像素边界
+    pub fn px_bounds(&self, glyph: &Glyph) -> Rect {
+        let floor = point(glyph.position.x.floor(), glyph.position.y.floor());
+        Rect {
+            min: self.bounds.min + floor,
+            max: self.bounds.max + floor,
+        }
+    }
+
+    /// 与 [`OutlinedGlyph::draw`] 相同，按行依次传入每个像素的坐标和覆盖率
+    pub fn draw(&self, mut o: impl FnMut(u32, u32, f32)) {
+        if self.width == 0 {
+            return;
+        }
+        for (i, &v) in self.coverage.iter().enumerate() {
+            let i = i as u32;
+            o(i % self.width, i / self.width, v);
+        }
+    }
+}
+
+/// 字形命中率统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlyphCacheStats {
+    /// 绘制时直接使用缓存的字形数
+    pub hits: u64,
+    /// 绘制时需要光栅化的字形数
+    pub misses: u64,
+    /// 缓存中的字形数
+    pub glyphs: usize,
+}
+
+impl GlyphCacheStats {
+    /// 命中率，没有绘制过字形时为 0
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for GlyphCacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "glyph cache: {} hits, {} misses ({:.1}%), {} glyphs",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0,
+            self.glyphs
+        )
+    }
+}
+
+/// 按字形编号、字号和原点小数部分缓存光栅化的字形覆盖率。
+///
+/// 同一次运行中各 slide 重复的文字（标签、单位等）只需光栅化一次。
+#[derive(Debug, Default)]
+pub struct GlyphCache {
+    glyphs: Mutex<HashMap<GlyphKey, Arc<GlyphCoverage>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl GlyphCache {
+    /// 查找已缓存的字形，不计入命中率
+    pub fn get(&self, glyph: &Glyph) -> Option<Arc<GlyphCoverage>> {
+        let glyphs = self.glyphs.lock().unwrap_or_else(|e| e.into_inner());
+        glyphs.get(&GlyphKey::new(glyph)).cloned()
+    }
+
+    /// 记录一次命中
+    pub fn record_hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 光栅化 `glyph` 并加入缓存，记录一次未命中
+    pub fn insert(&self, glyph: &OutlinedGlyph) -> Arc<GlyphCoverage> {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let coverage = Arc::new(GlyphCoverage::rasterize(glyph));
+        let mut glyphs = self.glyphs.lock().unwrap_or_else(|e| e.into_inner());
+        if glyphs.len() < MAX_CACHED_GLYPHS {
+            glyphs.insert(GlyphKey::new(glyph.glyph()), coverage.clone());
+        }
+        coverage
+    }
+
+    pub fn stats(&self) -> GlyphCacheStats {
+        GlyphCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            glyphs: self.glyphs.lock().unwrap_or_else(|e| e.into_inner()).len(),
+        }
+    }
+}
+
+/// 把 COLR 的绘制指令收集为 [`ColorLayer`]
+#[derive(Default)]
+struct LayerPainter {
+    glyph: Option<GlyphId>,
+    layers: Vec<ColorLayer>,
+}
+
+impl<'a> Painter<'a> for LayerPainter {
+    fn outline_glyph(&mut self, glyph_id: owned_ttf_parser::GlyphId) {
+        self.glyph = Some(GlyphId(glyph_id.0));
+    }
+
+    fn paint(&mut self, paint: Paint<'a>) {
+        let color = match paint {
+            Paint::Solid(color) => Some(color),
+            Paint::LinearGradient(gradient) => gradient.stops(0, &[]).next().map(|s| s.color),
+            Paint::RadialGradient(gradient) => gradient.stops(0, &[]).next().map(|s| s.color),
+            Paint::SweepGradient(gradient) => gradient.stops(0, &[]).next().map(|s| s.color),
+        };
+        if let (Some(glyph), Some(c)) = (self.glyph.take(), color) {
+            self.layers.push(ColorLayer {
+                glyph,
+                color: [c.red, c.green, c.blue, c.alpha],
+            });
+        }
+    }
+
+    fn push_clip(&mut self) {}
+
+    fn push_clip_box(&mut self, _: ClipBox) {}
+
+    fn pop_clip(&mut self) {}
+
+    fn push_layer(&mut self, _: CompositeMode) {}
+
+    fn pop_layer(&mut self) {}
+
+    fn push_transform(&mut self, _: owned_ttf_parser::Transform) {}
+
+    fn pop_transform(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> FontArc {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_load_bundled_font() {
+        let chain = load_font_chain(None, &[] as &[&Path]);
+        assert_eq!(chain.is_ok(), cfg!(feature = "bundled-font"));
+    }
+
+    #[test]
+    fn test_font_chain_glyph_ids() {
+        let font = font();
+        let chain = FontChain::new(vec![font.clone(), font.clone()]).unwrap();
+        let count = font.glyph_count() as u16;
+
+        // 主字体中有的字符使用主字体
+        let id = chain.glyph_id('字');
+        assert_eq!(id, font.glyph_id('字'));
+        assert_eq!(chain.h_advance_unscaled(id), font.h_advance_unscaled(id));
+        // 第二个字体的字形编号排在主字体之后，度量不变
+        let second = GlyphId(id.0 + count);
+        assert_eq!(chain.resolve(second).1, id);
+        assert_eq!(
+            chain.outline(second).unwrap().bounds,
+            font.outline(id).unwrap().bounds
+        );
+        assert_eq!(chain.kern_unscaled(id, second), 0.0);
+        assert_eq!(chain.glyph_count(), usize::from(count) * 2);
+        assert!(chain.color_layers(id).is_empty());
+    }
+}
@@ -1,10 +1,15 @@
 #![allow(dead_code)]
 use super::{
-    BresenhamLineIter, Plotter, Point, draw_ellipse, draw_if_in_bounds, plot_wu_line, rect::Rect,
+    BresenhamLineIter, Plotter, Point, draw_ellipse, draw_if_in_bounds,
+    nine_patch::{NinePatch, in_center, map_axis},
+    plot_wu_line,
+    rect::Rect,
 };
 use image::GenericImage;
 use std::{
     cmp::{max, min},
+    collections::HashMap,
+    f32::consts::SQRT_2,
     mem::swap,
 };
 
@@ -223,6 +228,128 @@ pub trait DrawMut: GenericImage + Sized {
         }
     }
 
+    /// 在图像上绘制抗锯齿的空心圆。只绘制位于图像边界内的圆轮廓
+    ///
+    /// 使用 Wu 算法：每一列计算圆弧的精确位置，按小数部分把颜色分配给相邻的两个像素。
+    ///
+    /// blend 的参数为(线条颜色，原始颜色，线条权重)
+    fn draw_antialiased_hollow_circle_mut<B>(
+        &mut self,
+        center: (i32, i32),
+        radius: i32,
+        color: Self::Pixel,
+        blend: B,
+    ) where
+        B: Fn(Self::Pixel, Self::Pixel, f32) -> Self::Pixel,
+    {
+        let r = radius as f32;
+        // 八个对称区域的交界处会重复出现同一像素，取最大权重避免重复混合
+        let mut weights: HashMap<(i32, i32), f32> = HashMap::new();
+        for x in 0..=(r / SQRT_2).ceil() as i32 {
+            let y = (r * r - (x * x) as f32).max(0.0).sqrt();
+            let (y_floor, frac) = (y.floor() as i32, y.fract());
+            for (y, weight) in [(y_floor, 1.0 - frac), (y_floor + 1, frac)] {
+                for (dx, dy) in [
+                    (x, y),
+                    (y, x),
+                    (-y, x),
+                    (-x, y),
+                    (-x, -y),
+                    (-y, -x),
+                    (y, -x),
+                    (x, -y),
+                ] {
+                    let entry = weights.entry((center.0 + dx, center.1 + dy)).or_default();
+                    *entry = entry.max(weight);
+                }
+            }
+        }
+
+        let mut plotter = Plotter {
+            image: self,
+            transform: |x, y| (x, y),
+            blend,
+        };
+        for ((x, y), weight) in weights {
+            if weight > 0.0 {
+                plotter.plot(x, y, color, weight);
+            }
+        }
+    }
+
+    /// 在图像上绘制抗锯齿的实心椭圆。只绘制位于图像边界内的部分
+    ///
+    /// 按像素中心到椭圆边界的近似距离计算覆盖率，边缘像素与原始颜色混合。
+    ///
+    /// blend 的参数为(填充颜色，原始颜色，覆盖率)
+    fn draw_antialiased_filled_ellipse_mut<B>(
+        &mut self,
+        center: (i32, i32),
+        width_radius: i32,
+        height_radius: i32,
+        color: Self::Pixel,
+        blend: B,
+    ) where
+        B: Fn(Self::Pixel, Self::Pixel, f32) -> Self::Pixel,
+    {
+        if width_radius <= 0 || height_radius <= 0 {
+            return;
+        }
+        let (a, b) = (width_radius as f32, height_radius as f32);
+        let mut plotter = Plotter {
+            image: self,
+            transform: |x, y| (x, y),
+            blend,
+        };
+        for dy in -height_radius - 1..=height_radius + 1 {
+            for dx in -width_radius - 1..=width_radius + 1 {
+                let coverage = ellipse_coverage(dx as f32, dy as f32, a, b);
+                if coverage > 0.0 {
+                    plotter.plot(center.0 + dx, center.1 + dy, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// 在图像上绘制圆角为抗锯齿的实心圆角矩形，`radius` 最大为短边的一半
+    ///
+    /// blend 的参数为(填充颜色，原始颜色，覆盖率)
+    fn draw_antialiased_filled_rounded_rect_mut<B>(
+        &mut self,
+        rect: Rect,
+        radius: i32,
+        color: Self::Pixel,
+        blend: B,
+    ) where
+        B: Fn(Self::Pixel, Self::Pixel, f32) -> Self::Pixel,
+    {
+        let radius = radius.clamp(0, (rect.width().min(rect.height()) / 2) as i32);
+        let (left, right, top, bottom) = (rect.left(), rect.right(), rect.top(), rect.bottom());
+        // 绘制四个圆角，内侧部分随后被矩形覆盖
+        for center in [
+            (left + radius, top + radius),
+            (left + radius, bottom - radius),
+            (right - radius, top + radius),
+            (right - radius, bottom - radius),
+        ] {
+            self.draw_antialiased_filled_ellipse_mut(center, radius, radius, color, &blend);
+        }
+
+        let inner = 2 * radius as u32;
+        if rect.height() > inner {
+            self.draw_filled_rect_mut(
+                Rect::at(left, top + radius).of_size(rect.width(), rect.height() - inner),
+                color,
+            );
+        }
+        if rect.width() > inner {
+            self.draw_filled_rect_mut(
+                Rect::at(left + radius, top).of_size(rect.width() - inner, rect.height()),
+                color,
+            );
+        }
+    }
+
     /// 在图像上绘制一个彩色十字。处理图像边界外的坐标。
     ///
     /// Draws a colored cross on an image in place.
@@ -277,6 +404,66 @@ pub trait DrawMut: GenericImage + Sized {
         }
     }
 
+    /// 在图像上绘制指定宽度的线段，线段两端为平头。只绘制位于图像边界内的部分
+    ///
+    /// 到线段距离不超过 `width / 2` 的像素都会被填充，`width` 不大于 1 时与
+    /// `draw_line_segment_mut` 相同。
+    fn draw_line_segment_with_width_mut(
+        &mut self,
+        start: (f32, f32),
+        end: (f32, f32),
+        width: f32,
+        color: Self::Pixel,
+    ) {
+        if width <= 1.0 {
+            self.draw_line_segment_mut(start, end, color);
+            return;
+        }
+        let half = width / 2.0;
+        let (image_width, image_height) = self.dimensions();
+        let clamp_x = |v: f32| (v.max(0.0) as u32).min(image_width);
+        let clamp_y = |v: f32| (v.max(0.0) as u32).min(image_height);
+        let x_range = clamp_x(start.0.min(end.0) - half)..clamp_x(start.0.max(end.0) + half + 1.0);
+        let y_range = clamp_y(start.1.min(end.1) - half)..clamp_y(start.1.max(end.1) + half + 1.0);
+
+        for y in y_range {
+            for x in x_range.clone() {
+                if segment_distance((x as f32, y as f32), start, end).is_some_and(|d| d <= half) {
+                    self.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// 在图像上绘制指定宽度的虚线，`dash` 为每段实线的长度，`gap` 为间隔长度（像素）
+    fn draw_dashed_line_segment_mut(
+        &mut self,
+        start: (f32, f32),
+        end: (f32, f32),
+        width: f32,
+        dash: f32,
+        gap: f32,
+        color: Self::Pixel,
+    ) {
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 || dash <= 0.0 {
+            return;
+        }
+        let (ux, uy) = (dx / length, dy / length);
+        let mut offset = 0.0;
+        while offset < length {
+            let stop = (offset + dash).min(length);
+            self.draw_line_segment_with_width_mut(
+                (start.0 + ux * offset, start.1 + uy * offset),
+                (start.0 + ux * stop, start.1 + uy * stop),
+                width,
+                color,
+            );
+            offset = stop + gap.max(0.0);
+        }
+    }
+
     /// 在图像上绘制抗锯齿的线段。绘制起点和终点之间位于图像边界内的线段部分
     ///
     /// blend 的参数为(线条颜色，原始颜色，线条宽度)
@@ -499,6 +686,31 @@ pub trait DrawMut: GenericImage + Sized {
         }
     }
 
+    /// 在图像的 `rect` 区域内绘制九宫格图片。只绘制位于图像边界内的部分
+    ///
+    /// 四个角按原尺寸绘制，边和中间部分拉伸（最近邻采样）以填满 `rect`。
+    fn draw_nine_patch_mut(&mut self, src: &NinePatch<Self::Pixel>, rect: Rect) {
+        let [left, top, right, bottom] = src.insets;
+        let (src_width, src_height) = src.image.dimensions();
+        let (width, height) = (rect.width(), rect.height());
+        for y in 0..height {
+            let src_y = map_axis(y, height, src_height, top, bottom);
+            let center_row = in_center(y, height, top, bottom);
+            for x in 0..width {
+                if !src.fill_center && center_row && in_center(x, width, left, right) {
+                    continue;
+                }
+                let src_x = map_axis(x, width, src_width, left, right);
+                draw_if_in_bounds(
+                    self,
+                    rect.left() + x as i32,
+                    rect.top() + y as i32,
+                    *src.image.get_pixel(src_x, src_y),
+                );
+            }
+        }
+    }
+
     fn draw_hollow_rounded_rect_mut(&mut self, _rect: Rect, _radius: i32, _color: Self::Pixel) {
         todo!()
         // let left = rect.left() as f32;
@@ -533,6 +745,33 @@ pub trait DrawMut: GenericImage + Sized {
     }
 }
 
+/// 点 `p` 到线段 `start`-`end` 所在直线的距离，`p` 的投影不在线段上时返回 `None`（平头端点）
+fn segment_distance(p: (f32, f32), start: (f32, f32), end: (f32, f32)) -> Option<f32> {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let len2 = dx * dx + dy * dy;
+    if len2 == 0.0 {
+        return (p == start).then_some(0.0);
+    }
+    let t = ((p.0 - start.0) * dx + (p.1 - start.1) * dy) / len2;
+    if !(0.0..=1.0).contains(&t) {
+        return None;
+    }
+    let (cx, cy) = (start.0 + t * dx, start.1 + t * dy);
+    Some(((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt())
+}
+
+/// 像素 (x, y)（相对椭圆中心）被半轴为 a、b 的实心椭圆覆盖的比例
+///
+/// 用隐函数值除以梯度长度近似像素中心到边界的有向距离，距离在 ±0.5 像素内线性过渡。
+fn ellipse_coverage(x: f32, y: f32, a: f32, b: f32) -> f32 {
+    let f = (x / a).powi(2) + (y / b).powi(2) - 1.0;
+    let grad = 2.0 * ((x / (a * a)).powi(2) + (y / (b * b)).powi(2)).sqrt();
+    if grad == 0.0 {
+        return 1.0;
+    }
+    (0.5 - f / grad).clamp(0.0, 1.0)
+}
+
 impl<I: GenericImage> DrawMut for I {
     fn draw_antialiased_line_segment_mut<B>(
         &mut self,
@@ -666,6 +905,73 @@ mod tests {
         assert_eq!(img.get_pixel(50, 70), &color);
     }
 
+    fn blend(line: Rgba<u8>, original: Rgba<u8>, weight: f32) -> Rgba<u8> {
+        crate::imageproc::weighted_sum(original, line, 1.0 - weight, weight)
+    }
+
+    #[test]
+    fn test_draw_antialiased_hollow_circle_mut() {
+        let mut img = RgbaImage::new(100, 100);
+        let color = Rgba([255, 0, 0, 255]);
+        img.draw_antialiased_hollow_circle_mut((50, 50), 20, color, blend);
+
+        assert_eq!(img.get_pixel(50, 30), &color);
+        assert_eq!(img.get_pixel(70, 50), &color);
+        assert_eq!(img.get_pixel(50, 50), &Rgba([0, 0, 0, 0]));
+        // 斜向的圆弧落在像素之间，应当出现部分覆盖的像素
+        assert!(img.pixels().any(|p| p[3] > 0 && p[3] < 255));
+    }
+
+    #[test]
+    fn test_draw_antialiased_filled_ellipse_mut() {
+        let mut img = RgbaImage::new(100, 100);
+        let color = Rgba([255, 128, 0, 255]);
+        img.draw_antialiased_filled_ellipse_mut((50, 50), 30, 20, color, blend);
+
+        assert_eq!(img.get_pixel(50, 50), &color);
+        assert_eq!(img.get_pixel(75, 50), &color);
+        assert_eq!(img.get_pixel(50, 75), &Rgba([0, 0, 0, 0]));
+        assert!(img.pixels().any(|p| p[3] > 0 && p[3] < 255));
+    }
+
+    #[test]
+    fn test_draw_antialiased_filled_rounded_rect_mut_small() {
+        let mut img = RgbaImage::new(20, 20);
+        let color = Rgba([0, 0, 255, 255]);
+        img.draw_antialiased_filled_rounded_rect_mut(
+            Rect::at(2, 2).of_size(12, 6),
+            10,
+            color,
+            blend,
+        );
+
+        assert_eq!(img.get_pixel(8, 5), &color);
+        assert_eq!(img.get_pixel(8, 9), &Rgba([0, 0, 0, 0]));
+        assert_eq!(img.get_pixel(16, 5), &Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_ellipse_coverage() {
+        assert_eq!(ellipse_coverage(0.0, 0.0, 10.0, 5.0), 1.0);
+        assert_eq!(ellipse_coverage(20.0, 0.0, 10.0, 5.0), 0.0);
+        assert!((ellipse_coverage(10.0, 0.0, 10.0, 5.0) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_draw_nine_patch_mut() {
+        // 3x3 的源图，每个像素颜色不同，边宽为 1
+        let src = RgbaImage::from_fn(3, 3, |x, y| Rgba([x as u8 * 100, y as u8 * 100, 0, 255]));
+        let patch = NinePatch::uniform(src, 1);
+        let mut img = RgbaImage::new(20, 20);
+        img.draw_nine_patch_mut(&patch, Rect::at(5, 5).of_size(10, 8));
+
+        assert_eq!(img.get_pixel(5, 5), &Rgba([0, 0, 0, 255]));
+        assert_eq!(img.get_pixel(14, 12), &Rgba([200, 200, 0, 255]));
+        assert_eq!(img.get_pixel(10, 5), &Rgba([100, 0, 0, 255]));
+        assert_eq!(img.get_pixel(10, 9), &Rgba([100, 100, 0, 255]));
+        assert_eq!(img.get_pixel(4, 5), &Rgba([0, 0, 0, 0]));
+    }
+
     #[test]
     fn test_draw_cross_mut() {
         let mut img = RgbaImage::new(100, 100);
@@ -692,6 +998,29 @@ mod tests {
         assert_eq!(img.get_pixel(90, 90), &color);
     }
 
+    #[test]
+    fn test_draw_line_segment_with_width_mut() {
+        let mut img = RgbaImage::new(100, 100);
+        let color = Rgba([255, 0, 0, 255]);
+        img.draw_line_segment_with_width_mut((50.0, 10.0), (50.0, 90.0), 5.0, color);
+
+        assert_eq!(img.get_pixel(48, 50), &color);
+        assert_eq!(img.get_pixel(52, 50), &color);
+        assert_eq!(img.get_pixel(53, 50), &Rgba([0, 0, 0, 0]));
+        assert_eq!(img.get_pixel(50, 9), &Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_draw_dashed_line_segment_mut() {
+        let mut img = RgbaImage::new(100, 10);
+        let color = Rgba([255, 0, 0, 255]);
+        img.draw_dashed_line_segment_mut((0.0, 5.0), (99.0, 5.0), 3.0, 10.0, 10.0, color);
+
+        assert_eq!(img.get_pixel(5, 5), &color);
+        assert_eq!(img.get_pixel(15, 5), &Rgba([0, 0, 0, 0]));
+        assert_eq!(img.get_pixel(25, 6), &color);
+    }
+
     // #[test]
     // fn test_draw_antialiased_line_segment_mut() {
     //     let mut img = RgbaImage::new(100, 100);
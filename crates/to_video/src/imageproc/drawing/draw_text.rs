@@ -1,36 +1,170 @@
 use super::{definitions::Clamp, rect::Rect, weighted_sum};
-use ab_glyph::{Font, GlyphId, OutlinedGlyph, PxScale, ScaleFont, point};
-use image::{GenericImage, Pixel};
+use crate::{
+    font::{GlyphCache, GlyphCoverage, TextFont},
+    rich_text::{Span, SpanStyle},
+    shaping::shape,
+};
+use ab_glyph::{Glyph, GlyphImageFormat, OutlinedGlyph, PxScale, ScaleFont, point};
+use image::{GenericImage, GrayImage, Luma, Pixel, imageops::FilterType};
+use std::sync::Arc;
 
-fn layout_glyphs(
+/// 轮廓字形的来源：字体的字形缓存中已有的覆盖率，或需要光栅化的轮廓
+enum OutlineSource {
+    Cached(Arc<GlyphCoverage>),
+    Outlined(OutlinedGlyph),
+}
+
+impl OutlineSource {
+    /// 依次传入每个像素的坐标和覆盖率，`cache` 不为 `None` 时记录命中并缓存新光栅化的字形
+    fn draw(&self, cache: Option<&GlyphCache>, o: impl FnMut(u32, u32, f32)) {
+        match (self, cache) {
+            (Self::Cached(coverage), cache) => {
+                if let Some(cache) = cache {
+                    cache.record_hit();
+                }
+                coverage.draw(o);
+            }
+            (Self::Outlined(glyph), Some(cache)) => cache.insert(glyph).draw(o),
+            (Self::Outlined(glyph), None) => glyph.draw(o),
+        }
+    }
+
+    /// 字形的覆盖率，与 [`draw`](Self::draw) 一样记录命中并缓存新光栅化的字形
+    fn coverage(self, cache: Option<&GlyphCache>) -> Arc<GlyphCoverage> {
+        match (self, cache) {
+            (Self::Cached(coverage), cache) => {
+                if let Some(cache) = cache {
+                    cache.record_hit();
+                }
+                coverage
+            }
+            (Self::Outlined(glyph), Some(cache)) => cache.insert(&glyph),
+            (Self::Outlined(glyph), None) => Arc::new(GlyphCoverage::rasterize(&glyph)),
+        }
+    }
+}
+
+/// 查找或生成字形的轮廓及其像素边界
+fn outline(font: &impl TextFont, glyph: Glyph) -> Option<(OutlineSource, ab_glyph::Rect)> {
+    if let Some(coverage) = font.glyph_cache().and_then(|cache| cache.get(&glyph)) {
+        let bounds = coverage.px_bounds(&glyph);
+        return Some((OutlineSource::Cached(coverage), bounds));
+    }
+    let glyph = font.outline_glyph(glyph)?;
+    let bounds = glyph.px_bounds();
+    Some((OutlineSource::Outlined(glyph), bounds))
+}
+
+/// 排版后的一个字形
+enum GlyphDraw<'a> {
+    /// 轮廓字形，`color` 为彩色字形图层的 RGBA 颜色，`None` 时使用文字颜色
+    Outline {
+        glyph: OutlineSource,
+        bounds: ab_glyph::Rect,
+        color: Option<[u8; 4]>,
+    },
+    /// 位图字形（CBDT / sbix 中的 PNG），`(x, y)` 为左上角，已按字号缩放
+    Png {
+        data: &'a [u8],
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+}
+
+fn layout_glyphs<'a>(
     scale: impl Into<PxScale> + Copy,
-    font: &impl Font,
+    font: &'a impl TextFont,
     text: &str,
-    mut f: impl FnMut(OutlinedGlyph, ab_glyph::Rect),
+    mut f: impl FnMut(GlyphDraw<'a>),
 ) -> (u32, u32) {
     let (mut w, mut h) = (0f32, 0f32);
 
-    let font = font.as_scaled(scale);
-    let mut last: Option<GlyphId> = None;
+    let scaled = font.as_scaled(scale);
+    let (h_factor, v_factor) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+
+    for shaped in shape(font, text) {
+        let glyph_id = shaped.id;
+        let origin = point(
+            w + shaped.offset.0 * h_factor,
+            scaled.ascent() - shaped.offset.1 * v_factor,
+        );
+        w += shaped.advance * h_factor;
 
-    for c in text.chars() {
-        let glyph_id = font.glyph_id(c);
-        let glyph = glyph_id.with_scale_and_position(scale, point(w, font.ascent()));
-        w += font.h_advance(glyph_id);
-        if let Some(g) = font.outline_glyph(glyph) {
-            if let Some(last) = last {
-                w += font.kern(glyph_id, last);
+        // 彩色字形：按图层依次绘制
+        let layers = font.color_layers(glyph_id);
+        if !layers.is_empty() {
+            for layer in layers {
+                let glyph = layer.glyph.with_scale_and_position(scale, origin);
+                if let Some((g, bounds)) = outline(font, glyph) {
+                    h = h.max(bounds.height());
+                    f(GlyphDraw::Outline {
+                        glyph: g,
+                        bounds,
+                        color: Some(layer.color),
+                    });
+                }
             }
-            last = Some(glyph_id);
-            let bb = g.px_bounds();
-            h = h.max(bb.height());
-            f(g, bb);
+            continue;
+        }
+
+        let glyph = glyph_id.with_scale_and_position(scale, origin);
+        if let Some((g, bounds)) = outline(font, glyph) {
+            h = h.max(bounds.height());
+            f(GlyphDraw::Outline {
+                glyph: g,
+                bounds,
+                color: None,
+            });
+        } else if let Some(image) = font
+            .glyph_raster_image2(glyph_id, scale.into().y.round() as u16)
+            .filter(|image| matches!(image.format, GlyphImageFormat::Png))
+        {
+            // 位图的 y 为下边缘相对于基线的偏移，向上为正
+            let factor = scale.into().y / f32::from(image.pixels_per_em.max(1));
+            let (width, height) = (
+                f32::from(image.width) * factor,
+                f32::from(image.height) * factor,
+            );
+            h = h.max(height);
+            f(GlyphDraw::Png {
+                data: image.data,
+                x: origin.x + image.origin.x * factor,
+                y: origin.y - image.origin.y * factor - height,
+                width,
+                height,
+            });
         }
     }
 
     (w as u32, h as u32)
 }
 
+/// 文字中是否有彩色字形。超采样时文字只绘制覆盖率，含有彩色字形时不使用超采样
+fn has_color_glyphs(font: &impl TextFont, text: &str) -> bool {
+    text.chars().any(|c| {
+        let id = font.glyph_id(c);
+        !font.color_layers(id).is_empty()
+            || (font.outline(id).is_none() && font.glyph_raster_image2(id, 64).is_some())
+    })
+}
+
+/// 彩色字形的像素：RGB(A) 图片使用字形的颜色，其他图片（如灰度）使用文字颜色
+fn glyph_pixel<P: Pixel>(color: P, rgb: [u8; 3]) -> P
+where
+    P::Subpixel: Clamp<f32>,
+{
+    let mut pixel = color;
+    let channels = pixel.channels_mut();
+    if channels.len() >= 3 {
+        for (channel, value) in channels.iter_mut().zip(rgb) {
+            *channel = Clamp::clamp(f32::from(value));
+        }
+    }
+    pixel
+}
+
 /// 获取给定文本的宽度和高度，使用给定的字体和比例渲染。
 ///
 /// 注意，此函数*不支持*换行，您必须手动处理。
@@ -38,8 +172,248 @@ fn layout_glyphs(
 /// Get the width and height of the given text, rendered with the given font and scale.
 ///
 /// Note that this function *does not* support newlines, you must do this manually.
-pub fn text_size(scale: impl Into<PxScale> + Copy, font: &impl Font, text: &str) -> (u32, u32) {
-    layout_glyphs(scale, font, text, |_, _| {})
+pub fn text_size(scale: impl Into<PxScale> + Copy, font: &impl TextFont, text: &str) -> (u32, u32) {
+    layout_glyphs(scale, font, text, |_| {})
+}
+
+/// 在 `rect` 中居中排列多行文字，返回缩小后的字号和每行左上角的位置
+fn center_lines<'t>(
+    rect: Rect,
+    scale: impl Into<PxScale> + Copy,
+    font: &impl TextFont,
+    text: &'t str,
+) -> (PxScale, Vec<(i32, i32, &'t str)>) {
+    // 将文本按行分割并去除每行的前后空格
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    let row = u32::try_from(lines.len()).unwrap();
+
+    // 解构矩形区域
+    let (rect_left, rect_top, rect_width, rect_height) =
+        (rect.left(), rect.top(), rect.width(), rect.height());
+
+    // 根据矩形区域和文本原始尺寸计算最终字体大小
+    let scale = fit_text_scale((rect_width, rect_height), scale, font, text);
+
+    // 重新计算文本高度
+    let h = font.as_scaled(scale).height() as u32;
+
+    // 计算文本顶部位置
+    let top_ = rect_top + i32::try_from(rect_height - h * row).unwrap() / 2;
+
+    let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(row, line)| {
+            (
+                rect_left
+                    + i32::try_from((rect_width - text_size(scale, font, line).0) / 2).unwrap(),
+                top_ + i32::try_from(h).unwrap() * i32::try_from(row).unwrap(),
+                line,
+            )
+        })
+        .collect();
+    (scale, lines)
+}
+
+/// 排版后的一个轮廓字形，见 [`layout_text_center`]
+#[derive(Debug, Clone)]
+pub struct PlacedGlyph {
+    pub coverage: Arc<GlyphCoverage>,
+    /// 覆盖率左上角在图片中的位置
+    pub x: i32,
+    pub y: i32,
+}
+
+/// 与 [`DrawText::draw_text_center_mut`] 的排版相同，但不绘制，而是返回每个字形的覆盖率和位置，
+/// 供 GPU 通过字形图集绘制文字。
+///
+/// 文字中有彩色字形或位图字形时返回 `None`。
+pub fn layout_text_center(
+    rect: Rect,
+    scale: impl Into<PxScale> + Copy,
+    font: &impl TextFont,
+    text: &str,
+) -> Option<Vec<PlacedGlyph>> {
+    let (scale, lines) = center_lines(rect, scale, font, text);
+    let mut glyphs = Vec::new();
+    let mut colored = false;
+    for (x, y, line) in lines {
+        layout_glyphs(scale, font, line, |glyph| match glyph {
+            GlyphDraw::Outline {
+                glyph,
+                bounds,
+                color: None,
+            } => glyphs.push(PlacedGlyph {
+                coverage: glyph.coverage(font.glyph_cache()),
+                x: x + bounds.min.x.round() as i32,
+                y: y + bounds.min.y.round() as i32,
+            }),
+            _ => colored = true,
+        });
+        if colored {
+            return None;
+        }
+    }
+    Some(glyphs)
+}
+
+/// 按换行拆分样式不同的几段文字，并去掉每行首尾的空白
+fn rich_lines(spans: &[Span]) -> Vec<Vec<(String, SpanStyle)>> {
+    let mut lines = vec![Vec::new()];
+    for span in spans {
+        for (i, part) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Vec::new());
+            }
+            if let Some(line) = lines.last_mut() {
+                line.push((part.to_string(), span.style));
+            }
+        }
+    }
+    for line in &mut lines {
+        if let Some((first, _)) = line.first_mut() {
+            *first = first.trim_start().to_string();
+        }
+        if let Some((last, _)) = line.last_mut() {
+            *last = last.trim_end().to_string();
+        }
+        line.retain(|(text, _)| !text.is_empty());
+    }
+    lines
+}
+
+fn span_scale(scale: PxScale, style: &SpanStyle) -> PxScale {
+    PxScale {
+        x: scale.x * style.size,
+        y: scale.y * style.size,
+    }
+}
+
+/// 粗体重复绘制时错开的距离
+fn bold_offset(scale: PxScale) -> i32 {
+    (scale.y / 24.0).round().max(1.0) as i32
+}
+
+struct LineMetrics {
+    width: u32,
+    /// 行内最大的上边距，即基线到行顶部的距离
+    ascent: f32,
+    height: f32,
+}
+
+fn line_metrics(line: &[(String, SpanStyle)], scale: PxScale, font: &impl TextFont) -> LineMetrics {
+    let mut metrics = LineMetrics {
+        width: 0,
+        ascent: font.as_scaled(scale).ascent(),
+        height: font.as_scaled(scale).height(),
+    };
+    if !line.is_empty() {
+        metrics.ascent = 0.0;
+        metrics.height = 0.0;
+    }
+    for (text, style) in line {
+        let span_scale = span_scale(scale, style);
+        let scaled = font.as_scaled(span_scale);
+        metrics.width += text_size(span_scale, font, text).0;
+        if style.bold {
+            metrics.width += bold_offset(span_scale) as u32;
+        }
+        metrics.ascent = metrics.ascent.max(scaled.ascent());
+        metrics.height = metrics.height.max(scaled.height());
+    }
+    metrics
+}
+
+/// 计算多行文字在 `size` 内完整显示时使用的字号：放得下时为 `scale`，否则等比缩小。
+///
+/// 与 `DrawText::draw_text_center_mut` 的缩放规则相同，可在绘制前检查文字是否被缩小。
+pub fn fit_text_scale(
+    size: (u32, u32),
+    scale: impl Into<PxScale> + Copy,
+    font: &impl TextFont,
+    text: &str,
+) -> PxScale {
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+
+    // 计算文本原始尺寸
+    let row = u32::try_from(lines.len()).unwrap();
+    let text_raw_height = row * font.as_scaled(scale).height() as u32;
+    let text_raw_width = lines
+        .iter()
+        .map(|line| text_size(scale, font, line).0)
+        .max()
+        .unwrap_or(0);
+
+    if text_raw_width > size.0 || text_raw_height > size.1 {
+        let x_radio = size.0 as f32 / text_raw_width as f32;
+        let y_radio = size.1 as f32 / text_raw_height as f32;
+        PxScale::from(scale.into().x * (x_radio.min(y_radio)))
+    } else {
+        scale.into()
+    }
+}
+
+/// 与 [`fit_text_scale`] 相同，用于带标记的文字（见 `DrawText::draw_rich_text_center_mut`）
+pub fn fit_rich_text_scale(
+    size: (u32, u32),
+    scale: impl Into<PxScale> + Copy,
+    font: &impl TextFont,
+    spans: &[Span],
+) -> PxScale {
+    let scale = scale.into();
+    let metrics: Vec<LineMetrics> = rich_lines(spans)
+        .iter()
+        .map(|line| line_metrics(line, scale, font))
+        .collect();
+    let width = metrics.iter().map(|m| m.width).max().unwrap_or(0);
+    let height: f32 = metrics.iter().map(|m| m.height).sum();
+    if width > size.0 || height > size.1 as f32 {
+        let ratio = (size.0 as f32 / width as f32).min(size.1 as f32 / height);
+        PxScale {
+            x: scale.x * ratio,
+            y: scale.y * ratio,
+        }
+    } else {
+        scale
+    }
+}
+
+/// 按 `width` 自动换行：每行尽量放下更多文字，优先在空白处断开，
+/// 没有空白时（如中文）在任意字符处断开。已有的换行保留。
+pub fn wrap_text(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl TextFont,
+    text: &str,
+    width: u32,
+) -> String {
+    let mut lines = Vec::new();
+    for line in text.lines().map(str::trim) {
+        let mut rest = line;
+        while !rest.is_empty() {
+            // 能放下的最长前缀，至少一个字符
+            let mut end = 0;
+            for i in rest
+                .char_indices()
+                .skip(1)
+                .map(|(i, _)| i)
+                .chain([rest.len()])
+            {
+                if end > 0 && text_size(scale, font, rest[..i].trim_end()).0 > width {
+                    break;
+                }
+                end = i;
+            }
+            if end < rest.len() && !rest[end..].starts_with(char::is_whitespace) {
+                end = rest[..end]
+                    .rfind(char::is_whitespace)
+                    .filter(|&space| space > 0)
+                    .unwrap_or(end);
+            }
+            lines.push(rest[..end].trim_end());
+            rest = rest[end..].trim_start();
+        }
+    }
+    lines.join("\n")
 }
 
 pub trait DrawText: GenericImage {
@@ -60,7 +434,7 @@ pub trait DrawText: GenericImage {
         x: i32,
         y: i32,
         scale: impl Into<PxScale> + Copy,
-        font: &impl Font,
+        font: &impl TextFont,
         text: &str,
     );
 
@@ -72,9 +446,37 @@ pub trait DrawText: GenericImage {
         color: Self::Pixel,
         rect: Rect,
         scale: impl Into<PxScale> + Copy,
-        font: &impl Font,
+        font: &impl TextFont,
         text: &str,
     );
+
+    /// 与 `draw_text_center_mut` 相同，但先以 `factor` 倍分辨率把文字覆盖率绘制到中间缓冲区，
+    /// 再按块平均缩小后与原图混合，大字号时边缘更平滑。
+    ///
+    /// `factor` 为 0 或 1、或文字中含有彩色字形时等同于 `draw_text_center_mut`。
+    fn draw_text_center_supersampled_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: Rect,
+        scale: impl Into<PxScale> + Copy,
+        font: &impl TextFont,
+        text: &str,
+        factor: u32,
+    );
+
+    /// 在 `rect` 内绘制样式不同的几段文字（见 [`rich_text`](crate::rich_text)），
+    /// 每行分别居中，同一行中字号不同的文字按基线对齐。
+    ///
+    /// 与 `draw_text_center_mut` 一样，文字超出 `rect` 时整体缩小。
+    /// 粗体通过错开一个像素重复绘制实现。
+    fn draw_rich_text_center_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: Rect,
+        scale: impl Into<PxScale> + Copy,
+        font: &impl TextFont,
+        spans: &[Span],
+    );
 }
 
 impl<I: GenericImage> DrawText for I
@@ -87,26 +489,71 @@ where
         x: i32,
         y: i32,
         scale: impl Into<PxScale> + Copy,
-        font: &impl Font,
+        font: &impl TextFont,
         text: &str,
     ) {
         let image_width = self.width() as i32;
         let image_height = self.height() as i32;
 
-        layout_glyphs(scale, font, text, |g, bb| {
-            g.draw(|gx, gy, gv| {
-                let image_x = gx as i32 + x + bb.min.x.round() as i32;
-                let image_y = gy as i32 + y + bb.min.y.round() as i32;
-                let gv = gv.clamp(0.0, 1.0);
-
-                if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
-                    let image_x = image_x as u32;
-                    let image_y = image_y as u32;
-                    let pixel = self.get_pixel(image_x, image_y);
-                    let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
-                    self.put_pixel(image_x, image_y, weighted_color);
+        let mut blend = |image_x: i32, image_y: i32, color: Self::Pixel, weight: f32| {
+            if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
+                let image_x = image_x as u32;
+                let image_y = image_y as u32;
+                let pixel = self.get_pixel(image_x, image_y);
+                let weighted_color = weighted_sum(pixel, color, 1.0 - weight, weight);
+                self.put_pixel(image_x, image_y, weighted_color);
+            }
+        };
+
+        layout_glyphs(scale, font, text, |glyph| match glyph {
+            GlyphDraw::Outline {
+                glyph,
+                bounds,
+                color: layer,
+            } => {
+                let (color, alpha) = match layer {
+                    Some([r, g, b, a]) => (glyph_pixel(color, [r, g, b]), f32::from(a) / 255.0),
+                    None => (color, 1.0),
+                };
+                glyph.draw(font.glyph_cache(), |gx, gy, gv| {
+                    blend(
+                        gx as i32 + x + bounds.min.x.round() as i32,
+                        gy as i32 + y + bounds.min.y.round() as i32,
+                        color,
+                        gv.clamp(0.0, 1.0) * alpha,
+                    );
+                });
+            }
+            GlyphDraw::Png {
+                data,
+                x: left,
+                y: top,
+                width,
+                height,
+            } => {
+                let Ok(png) = image::load_from_memory(data) else {
+                    return;
+                };
+                let png = png
+                    .resize_exact(
+                        (width.round() as u32).max(1),
+                        (height.round() as u32).max(1),
+                        FilterType::Triangle,
+                    )
+                    .into_rgba8();
+                let (left, top) = (x + left.round() as i32, y + top.round() as i32);
+                for (px, py, p) in png.enumerate_pixels() {
+                    let [r, g, b, a] = p.0;
+                    if a > 0 {
+                        blend(
+                            left + px as i32,
+                            top + py as i32,
+                            glyph_pixel(color, [r, g, b]),
+                            f32::from(a) / 255.0,
+                        );
+                    }
                 }
-            });
+            }
         });
     }
 
@@ -115,53 +562,227 @@ where
         color: Self::Pixel,
         rect: Rect,
         scale: impl Into<PxScale> + Copy,
-        font: &impl Font,
+        font: &impl TextFont,
         text: &str,
     ) {
-        // 将文本按行分割并去除每行的前后空格
-        let lines: Vec<&str> = text.lines().map(str::trim).collect();
+        let (scale, lines) = center_lines(rect, scale, font, text);
+        // 遍历每行文本并绘制
+        for (x, y, line) in lines {
+            self.draw_text_mut(color, x, y, scale, font, line);
+        }
+    }
+
+    fn draw_text_center_supersampled_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: Rect,
+        scale: impl Into<PxScale> + Copy,
+        font: &impl TextFont,
+        text: &str,
+        factor: u32,
+    ) {
+        if factor <= 1 || has_color_glyphs(font, text) {
+            self.draw_text_center_mut(color, rect, scale, font, text);
+            return;
+        }
 
-        // 计算文本原始高度
-        let row = u32::try_from(lines.len()).unwrap();
-        let text_raw_height = row * font.as_scaled(scale).height() as u32;
+        // 在放大的灰度缓冲区中绘制文字覆盖率
+        let (width, height) = (rect.width(), rect.height());
+        let mut mask = GrayImage::new(width * factor, height * factor);
+        let scale = scale.into();
+        mask.draw_text_center_mut(
+            Luma([255]),
+            Rect::at(0, 0).of_size(width * factor, height * factor),
+            PxScale {
+                x: scale.x * factor as f32,
+                y: scale.y * factor as f32,
+            },
+            font,
+            text,
+        );
 
-        // 计算文本原始宽度
-        let text_raw_width = lines
+        // 按 factor x factor 的块求平均覆盖率并混合到原图
+        let samples = (factor * factor) as f32 * 255.0;
+        for y in 0..height {
+            for x in 0..width {
+                let image_x = rect.left() + x as i32;
+                let image_y = rect.top() + y as i32;
+                if !(0..self.width() as i32).contains(&image_x)
+                    || !(0..self.height() as i32).contains(&image_y)
+                {
+                    continue;
+                }
+                let mut sum = 0u32;
+                for dy in 0..factor {
+                    for dx in 0..factor {
+                        sum += u32::from(mask.get_pixel(x * factor + dx, y * factor + dy)[0]);
+                    }
+                }
+                if sum == 0 {
+                    continue;
+                }
+                let coverage = sum as f32 / samples;
+                let pixel = self.get_pixel(image_x as u32, image_y as u32);
+                let weighted_color = weighted_sum(pixel, color, 1.0 - coverage, coverage);
+                self.put_pixel(image_x as u32, image_y as u32, weighted_color);
+            }
+        }
+    }
+
+    fn draw_rich_text_center_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: Rect,
+        scale: impl Into<PxScale> + Copy,
+        font: &impl TextFont,
+        spans: &[Span],
+    ) {
+        let lines = rich_lines(spans);
+        let scale = fit_rich_text_scale((rect.width(), rect.height()), scale, font, spans);
+        let metrics: Vec<LineMetrics> = lines
             .iter()
-            .map(|line| text_size(scale, &font, line).0)
-            .max()
-            .unwrap_or(0);
-
-        // 解构矩形区域
-        let (rect_left, rect_top, rect_width, rect_height) =
-            (rect.left(), rect.top(), rect.width(), rect.height());
-
-        // 根据矩形区域和文本原始尺寸计算最终字体大小
-        let scale = if text_raw_width > rect_width || text_raw_height > rect_height {
-            let x_radio = rect_width as f32 / text_raw_width as f32;
-            let y_radio = rect_height as f32 / text_raw_height as f32;
-            PxScale::from(scale.into().x * (x_radio.min(y_radio)))
-        } else {
-            scale.into()
-        };
+            .map(|line| line_metrics(line, scale, font))
+            .collect();
 
-        // 重新计算文本高度
-        let h = font.as_scaled(scale).height() as u32;
+        let height: f32 = metrics.iter().map(|m| m.height).sum();
+        let mut top = rect.top() as f32 + (rect.height() as f32 - height).max(0.0) / 2.0;
+        for (line, metrics) in lines.iter().zip(&metrics) {
+            let mut x = rect.left() + (rect.width().saturating_sub(metrics.width) / 2) as i32;
+            for (text, style) in line {
+                let span_scale = span_scale(scale, style);
+                let span_color = style
+                    .color
+                    .map_or(color, |span_color| glyph_pixel(color, span_color.0));
+                let y = (top + metrics.ascent - font.as_scaled(span_scale).ascent()).round() as i32;
+                self.draw_text_mut(span_color, x, y, span_scale, font, text);
+                let mut advance = text_size(span_scale, font, text).0 as i32;
+                if style.bold {
+                    let offset = bold_offset(span_scale);
+                    self.draw_text_mut(span_color, x + offset, y, span_scale, font, text);
+                    advance += offset;
+                }
+                x += advance;
+            }
+            top += metrics.height;
+        }
+    }
+}
 
-        // 计算文本顶部位置
-        let top_ = rect_top + i32::try_from(rect_height - h * row).unwrap() / 2;
+#[cfg(test)]
+mod tests {
+    use super::DrawText;
+    use crate::imageproc::rect::Rect;
+    use ab_glyph::FontArc;
+    use image::{GrayImage, Luma, Rgba, RgbaImage};
 
-        // 遍历每行文本并绘制
-        for (row, line) in lines.iter().enumerate() {
-            self.draw_text_mut(
-                color,
-                rect_left
-                    + i32::try_from((rect_width - text_size(scale, font, line).0) / 2).unwrap(),
-                top_ + i32::try_from(h).unwrap() * i32::try_from(row).unwrap(),
-                scale,
-                font,
-                line,
-            );
+    fn font() -> FontArc {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn supersampled_text_has_partial_coverage() {
+        let font = font();
+        let rect = Rect::at(0, 0).of_size(200, 100);
+        let mut normal = GrayImage::new(200, 100);
+        normal.draw_text_center_mut(Luma([255]), rect, 80.0, &font, "Ag");
+        let mut smooth = GrayImage::new(200, 100);
+        smooth.draw_text_center_supersampled_mut(Luma([255]), rect, 80.0, &font, "Ag", 4);
+
+        let inked = |img: &GrayImage| img.pixels().filter(|p| p[0] > 0).count();
+        assert!(inked(&smooth) > 0);
+        // 覆盖率大致相同：超采样不应明显改变字形大小
+        let (a, b) = (inked(&normal) as f32, inked(&smooth) as f32);
+        assert!((a - b).abs() / a < 0.2, "{a} vs {b}");
+    }
+
+    #[test]
+    fn factor_one_matches_normal() {
+        let font = font();
+        let rect = Rect::at(0, 0).of_size(120, 60);
+        let mut normal = GrayImage::new(120, 60);
+        normal.draw_text_center_mut(Luma([255]), rect, 40.0, &font, "text");
+        let mut same = GrayImage::new(120, 60);
+        same.draw_text_center_supersampled_mut(Luma([255]), rect, 40.0, &font, "text", 1);
+        assert_eq!(normal, same);
+    }
+
+    #[test]
+    fn layout_matches_draw() {
+        let font = font();
+        let rect = Rect::at(5, 0).of_size(120, 90);
+        let text = "text\n文字";
+        let mut drawn = GrayImage::new(130, 90);
+        drawn.draw_text_center_mut(Luma([255]), rect, 40.0, &font, text);
+
+        // 按排版的位置依次混合每个字形的覆盖率，结果与直接绘制相同
+        let mut placed = GrayImage::new(130, 90);
+        let glyphs = super::layout_text_center(rect, 40.0, &font, text).unwrap();
+        assert_eq!(glyphs.len(), 6);
+        for glyph in glyphs {
+            glyph.coverage.draw(|gx, gy, v| {
+                let (x, y) = (glyph.x + gx as i32, glyph.y + gy as i32);
+                if let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y))
+                    && x < 130
+                    && y < 90
+                {
+                    let pixel = placed.get_pixel(x, y);
+                    let blended = crate::imageproc::weighted_sum(*pixel, Luma([255]), 1.0 - v, v);
+                    placed.put_pixel(x, y, blended);
+                }
+            });
+        }
+        assert_eq!(drawn, placed);
+    }
+
+    #[test]
+    fn glyph_cache_matches_uncached() {
+        let font = font();
+        let chain = crate::font::FontChain::from(font.clone());
+        let rect = Rect::at(0, 0).of_size(300, 80);
+        let mut plain = GrayImage::new(300, 80);
+        plain.draw_text_center_mut(Luma([255]), rect, 40.0, &font, "攻击力 100");
+        let mut stats = Vec::new();
+        for _ in 0..2 {
+            let mut cached = GrayImage::new(300, 80);
+            cached.draw_text_center_mut(Luma([255]), rect, 40.0, &chain, "攻击力 100");
+            assert_eq!(plain, cached);
+            stats.push(chain.glyph_cache().stats());
         }
+
+        let (first, second) = (stats[0], stats[1]);
+        assert!(first.misses > 0);
+        assert_eq!(first.glyphs as u64, first.misses);
+        // 第二次绘制全部命中
+        assert_eq!(second.misses, first.misses);
+        assert_eq!(second.hits - first.hits, first.hits + first.misses);
+        assert_eq!(second.hit_rate(), 0.5, "{second}");
+    }
+
+    #[test]
+    fn rich_text_uses_span_colors() {
+        let font = font();
+        let rect = Rect::at(0, 0).of_size(300, 100);
+        let spans = crate::rich_text::parse_markup("ab<color=#ff0000><b>12</b></color>");
+        let mut img = RgbaImage::new(300, 100);
+        img.draw_rich_text_center_mut(Rgba([0, 0, 255, 255]), rect, 60.0, &font, &spans);
+
+        let red = img.pixels().filter(|p| p.0 == [255, 0, 0, 255]).count();
+        let blue = img.pixels().filter(|p| p.0 == [0, 0, 255, 255]).count();
+        assert!(red > 0 && blue > 0, "{red} {blue}");
+        // 红色的数字在蓝色的字母右侧
+        let first_red = img
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p.0 == [255, 0, 0, 255]);
+        let max_blue_x = img
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p.0 == [0, 0, 255, 255])
+            .map(|(x, _, _)| x)
+            .max()
+            .unwrap();
+        assert!(first_red.map(|(x, _, _)| x).min().unwrap() > max_blue_x);
     }
 }
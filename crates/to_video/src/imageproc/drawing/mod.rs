@@ -1,11 +1,18 @@
 mod draw;
 mod draw_mut;
 mod draw_text;
+mod nine_patch;
 
 use image::GenericImage;
 use std::mem::swap;
 
-pub use self::{draw_mut::DrawMut, draw_text::DrawText};
+pub use self::{
+    draw_mut::DrawMut,
+    draw_text::{
+        DrawText, PlacedGlyph, fit_rich_text_scale, fit_text_scale, layout_text_center, wrap_text,
+    },
+    nine_patch::NinePatch,
+};
 use super::{definitions, rect, weighted_sum};
 
 // Set pixel at (x, y) to color if this point lies within image bounds,
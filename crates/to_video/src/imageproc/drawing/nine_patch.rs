@@ -0,0 +1,90 @@
+use crate::imageproc::definitions::Image;
+use image::Pixel;
+
+/// 九宫格图片：四个角保持原样，四条边沿一个方向拉伸，中间部分沿两个方向拉伸，
+/// 用一张小图即可包住任意尺寸的卡片。
+///
+/// `insets` 为 (左, 上, 右, 下) 四个方向上不拉伸部分的宽度（像素）。
+#[derive(Clone)]
+pub struct NinePatch<P: Pixel> {
+    pub image: Image<P>,
+    pub insets: [u32; 4],
+    /// 是否绘制中间部分，边框素材中间通常是空的
+    pub fill_center: bool,
+}
+
+impl<P: Pixel> NinePatch<P> {
+    /// `insets` 超出图片尺寸时会被截断，保证左右、上下之和不超过图片宽、高
+    pub fn new(image: Image<P>, insets: [u32; 4]) -> Self {
+        let [mut left, mut top, mut right, mut bottom] = insets;
+        let (width, height) = image.dimensions();
+        left = left.min(width);
+        right = right.min(width - left);
+        top = top.min(height);
+        bottom = bottom.min(height - top);
+        Self {
+            image,
+            insets: [left, top, right, bottom],
+            fill_center: true,
+        }
+    }
+
+    /// 四周使用相同宽度的边
+    pub fn uniform(image: Image<P>, border: u32) -> Self {
+        Self::new(image, [border; 4])
+    }
+}
+
+/// 把目标上 `[0, dst_len)` 中的坐标映射回源图的坐标：
+/// 前 `start` 个和后 `end` 个像素一一对应，中间部分按比例拉伸。
+/// 目标比两端之和还短时，两端按比例压缩。
+pub(super) fn map_axis(dst: u32, dst_len: u32, src_len: u32, start: u32, end: u32) -> u32 {
+    if start + end >= dst_len {
+        // 两端放不下：整体按比例缩放
+        return (u64::from(dst) * u64::from(src_len) / u64::from(dst_len.max(1))) as u32;
+    }
+    if dst < start {
+        dst
+    } else if dst >= dst_len - end {
+        src_len - (dst_len - dst)
+    } else {
+        let src_mid = src_len - start - end;
+        let dst_mid = dst_len - start - end;
+        start + (u64::from(dst - start) * u64::from(src_mid) / u64::from(dst_mid)) as u32
+    }
+}
+
+/// 判断坐标是否落在中间部分
+pub(super) fn in_center(dst: u32, dst_len: u32, start: u32, end: u32) -> bool {
+    start + end < dst_len && dst >= start && dst < dst_len - end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_axis_keeps_corners() {
+        // 源 10 像素，两端各 3；目标 20 像素
+        assert_eq!(map_axis(0, 20, 10, 3, 3), 0);
+        assert_eq!(map_axis(2, 20, 10, 3, 3), 2);
+        assert_eq!(map_axis(17, 20, 10, 3, 3), 7);
+        assert_eq!(map_axis(19, 20, 10, 3, 3), 9);
+        // 中间 14 像素映射到源的 4 像素
+        assert_eq!(map_axis(3, 20, 10, 3, 3), 3);
+        assert_eq!(map_axis(16, 20, 10, 3, 3), 6);
+    }
+
+    #[test]
+    fn map_axis_shrinks_when_too_small() {
+        assert_eq!(map_axis(0, 4, 10, 3, 3), 0);
+        assert_eq!(map_axis(3, 4, 10, 3, 3), 7);
+    }
+
+    #[test]
+    fn insets_are_clamped() {
+        let image = Image::<image::Luma<u8>>::new(6, 4);
+        let patch = NinePatch::new(image, [5, 3, 5, 3]);
+        assert_eq!(patch.insets, [5, 3, 1, 1]);
+    }
+}
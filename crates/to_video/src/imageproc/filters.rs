@@ -0,0 +1,162 @@
+use super::definitions::{Clamp, Image};
+use image::{Pixel, Rgba, RgbaImage};
+
+/// 方框模糊：每个像素取以其为中心、大小为 `(2 * x_radius + 1) x (2 * y_radius + 1)` 的
+/// 方框内像素的平均值，边缘外的像素按最近的边缘像素计算。
+///
+/// Convolves an image with a box of size `(2 * x_radius + 1) x (2 * y_radius + 1)`.
+pub fn box_blur<P>(image: &Image<P>, x_radius: u32, y_radius: u32) -> Image<P>
+where
+    P: Pixel,
+    P::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let x_kernel = vec![1.0; 2 * x_radius as usize + 1];
+    let y_kernel = vec![1.0; 2 * y_radius as usize + 1];
+    separable_filter(image, &x_kernel, &y_kernel)
+}
+
+/// 高斯模糊，`sigma` 为标准差（像素），不大于 0 时返回原图的副本。
+///
+/// Blurs an image using a Gaussian of standard deviation `sigma`.
+pub fn gaussian_blur_f32<P>(image: &Image<P>, sigma: f32) -> Image<P>
+where
+    P: Pixel,
+    P::Subpixel: Into<f32> + Clamp<f32>,
+{
+    if sigma <= 0.0 {
+        return image.clone();
+    }
+    let kernel = gaussian_kernel(sigma);
+    separable_filter(image, &kernel, &kernel)
+}
+
+/// 根据图层的 alpha 通道生成投影：用 `color` 填充不透明区域并做高斯模糊。
+///
+/// 返回的图像四周各扩展了 `padding` 像素，避免模糊后的阴影被裁掉，
+/// 绘制时需要把位置减去 `padding`。
+pub fn drop_shadow(layer: &RgbaImage, sigma: f32, color: Rgba<u8>) -> (RgbaImage, u32) {
+    let padding = (sigma * 3.0).ceil().max(0.0) as u32;
+    let (width, height) = layer.dimensions();
+    let mut mask = RgbaImage::new(width + 2 * padding, height + 2 * padding);
+    for (x, y, pixel) in layer.enumerate_pixels() {
+        let alpha = (u16::from(pixel[3]) * u16::from(color[3]) / 255) as u8;
+        mask.put_pixel(
+            x + padding,
+            y + padding,
+            Rgba([color[0], color[1], color[2], alpha]),
+        );
+    }
+    // 只模糊 alpha 通道，颜色保持不变，避免边缘混入黑色
+    let mut alpha = Image::<image::Luma<u8>>::new(mask.width(), mask.height());
+    for (x, y, pixel) in mask.enumerate_pixels() {
+        alpha.put_pixel(x, y, image::Luma([pixel[3]]));
+    }
+    let alpha = gaussian_blur_f32(&alpha, sigma);
+    for (x, y, pixel) in mask.enumerate_pixels_mut() {
+        *pixel = Rgba([color[0], color[1], color[2], alpha.get_pixel(x, y)[0]]);
+    }
+    (mask, padding)
+}
+
+/// 标准差为 `sigma` 的归一化一维高斯核，半径为 `3 * sigma`
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil() as i32;
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.into_iter().map(|k| k / sum).collect()
+}
+
+/// 先水平后垂直地用两个一维核做卷积，核会被归一化，边缘像素向外延伸
+fn separable_filter<P>(image: &Image<P>, x_kernel: &[f32], y_kernel: &[f32]) -> Image<P>
+where
+    P: Pixel,
+    P::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return image.clone();
+    }
+    let channels = P::CHANNEL_COUNT as usize;
+    let source: Vec<f32> = image.as_raw().iter().map(|&c| c.into()).collect();
+
+    let horizontal = convolve(&source, width, height, channels, x_kernel, true);
+    let vertical = convolve(&horizontal, width, height, channels, y_kernel, false);
+
+    let raw = vertical
+        .into_iter()
+        .map(|c| <P::Subpixel as Clamp<f32>>::clamp(c.round()))
+        .collect();
+    Image::from_raw(width, height, raw).expect("buffer size matches image dimensions")
+}
+
+fn convolve(
+    data: &[f32],
+    width: u32,
+    height: u32,
+    channels: usize,
+    kernel: &[f32],
+    horizontal: bool,
+) -> Vec<f32> {
+    let (width, height) = (width as i64, height as i64);
+    let radius = (kernel.len() / 2) as i64;
+    let sum: f32 = kernel.iter().sum();
+    let mut out = vec![0.0; data.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let index = ((y * width + x) as usize) * channels;
+            for (k, weight) in kernel.iter().enumerate() {
+                let offset = k as i64 - radius;
+                let (sx, sy) = if horizontal {
+                    ((x + offset).clamp(0, width - 1), y)
+                } else {
+                    (x, (y + offset).clamp(0, height - 1))
+                };
+                let source = ((sy * width + sx) as usize) * channels;
+                for c in 0..channels {
+                    out[index + c] += data[source + c] * weight / sum;
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn box_blur_averages_neighbours() {
+        let image = GrayImage::from_raw(3, 1, vec![0, 90, 0]).unwrap();
+        let blurred = box_blur(&image, 1, 0);
+        assert_eq!(blurred.into_raw(), vec![30, 30, 30]);
+    }
+
+    #[test]
+    fn gaussian_blur_keeps_uniform_image() {
+        let image = GrayImage::from_pixel(10, 10, Luma([200]));
+        assert_eq!(gaussian_blur_f32(&image, 2.0), image);
+    }
+
+    #[test]
+    fn gaussian_kernel_is_normalized() {
+        let kernel = gaussian_kernel(1.5);
+        assert!((kernel.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert_eq!(kernel.len(), 11);
+    }
+
+    #[test]
+    fn drop_shadow_spreads_alpha() {
+        let layer = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+        let (shadow, padding) = drop_shadow(&layer, 1.0, Rgba([0, 0, 0, 128]));
+        assert_eq!(padding, 3);
+        assert_eq!(shadow.dimensions(), (10, 10));
+        // 中心仍然不透明，模糊后的阴影延伸到原图范围外
+        assert!(shadow.get_pixel(5, 5)[3] > 100);
+        assert!(shadow.get_pixel(2, 5)[3] > 0);
+        assert_eq!(shadow.get_pixel(0, 0)[3], 0);
+    }
+}
@@ -0,0 +1,104 @@
+use super::{definitions::Image, weighted_sum};
+use crate::imageproc::definitions::Clamp;
+use image::Pixel;
+
+/// 插值方式
+///
+/// How to handle pixels whose pre-image lies between input pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    /// 取最近的像素
+    Nearest,
+    /// 双线性插值
+    Bilinear,
+}
+
+/// 以图像中心为原点旋转 `theta` 弧度（顺时针），输出与输入尺寸相同，
+/// 超出范围的部分被裁掉，没有对应源像素的位置填充 `default`。
+///
+/// Rotate an image clockwise about its center by theta radians.
+/// The output image has the same dimensions as the input.
+/// Output pixels whose pre-image lies outside the input image are set to `default`.
+pub fn rotate_about_center<P>(
+    image: &Image<P>,
+    theta: f32,
+    interpolation: Interpolation,
+    default: P,
+) -> Image<P>
+where
+    P: Pixel,
+    P::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let (width, height) = image.dimensions();
+    let center = (width as f32 / 2.0, height as f32 / 2.0);
+    let (sin, cos) = theta.sin_cos();
+    Image::from_fn(width, height, |x, y| {
+        // 逆向映射：输出像素中心旋转 -theta 得到源图中的位置
+        let (dx, dy) = (x as f32 + 0.5 - center.0, y as f32 + 0.5 - center.1);
+        let src_x = cos * dx + sin * dy + center.0 - 0.5;
+        let src_y = -sin * dx + cos * dy + center.1 - 0.5;
+        match interpolation {
+            Interpolation::Nearest => nearest(image, src_x, src_y, default),
+            Interpolation::Bilinear => bilinear(image, src_x, src_y, default),
+        }
+    })
+}
+
+fn nearest<P: Pixel>(image: &Image<P>, x: f32, y: f32, default: P) -> P {
+    let (x, y) = (x.round(), y.round());
+    if x < 0.0 || y < 0.0 || x >= image.width() as f32 || y >= image.height() as f32 {
+        return default;
+    }
+    *image.get_pixel(x as u32, y as u32)
+}
+
+fn bilinear<P>(image: &Image<P>, x: f32, y: f32, default: P) -> P
+where
+    P: Pixel,
+    P::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    if x <= -1.0 || y <= -1.0 || x >= width || y >= height {
+        return default;
+    }
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let get = |px: f32, py: f32| {
+        if px < 0.0 || py < 0.0 || px >= width || py >= height {
+            default
+        } else {
+            *image.get_pixel(px as u32, py as u32)
+        }
+    };
+    let top = weighted_sum(get(x0, y0), get(x0 + 1.0, y0), 1.0 - fx, fx);
+    let bottom = weighted_sum(get(x0, y0 + 1.0), get(x0 + 1.0, y0 + 1.0), 1.0 - fx, fx);
+    weighted_sum(top, bottom, 1.0 - fy, fy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Luma, Rgba, RgbaImage};
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn rotate_zero_is_identity() {
+        let image = RgbaImage::from_fn(4, 3, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let rotated = rotate_about_center(&image, 0.0, Interpolation::Bilinear, Rgba([0; 4]));
+        assert_eq!(image, rotated);
+    }
+
+    #[test]
+    fn rotate_quarter_turn() {
+        let image = Image::<Luma<u8>>::from_raw(3, 3, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+        let rotated = rotate_about_center(&image, FRAC_PI_2, Interpolation::Nearest, Luma([0]));
+        assert_eq!(rotated.into_raw(), vec![7, 4, 1, 8, 5, 2, 9, 6, 3]);
+    }
+
+    #[test]
+    fn rotate_half_turn() {
+        let image = Image::<Luma<u8>>::from_raw(2, 2, vec![1, 2, 3, 4]).unwrap();
+        let rotated = rotate_about_center(&image, PI, Interpolation::Nearest, Luma([0]));
+        assert_eq!(rotated.into_raw(), vec![4, 3, 2, 1]);
+    }
+}
@@ -12,10 +12,13 @@
 // version = "0.25.0"
 pub mod definitions;
 pub mod drawing;
+pub mod filters;
+pub mod geometric_transformations;
+pub mod palette;
 pub mod rect;
 
 use definitions::Clamp;
-use image::Pixel;
+use image::{GenericImage, GenericImageView, Pixel, Rgba};
 
 /// Adds pixels with the given weights. Results are clamped to prevent arithmetical overflows.
 ///
@@ -43,6 +46,52 @@ where
     })
 }
 
+/// 按 source-over 把 `top` 混合到 `bottom` 上。
+///
+/// 使用整数运算，不透明的 `bottom` 混合后仍然完全不透明。
+pub fn source_over(bottom: Rgba<u8>, top: Rgba<u8>) -> Rgba<u8> {
+    let top_alpha = u32::from(top[3]);
+    match top_alpha {
+        255 => return top,
+        0 => return bottom,
+        _ => {}
+    }
+    let bottom_weight = u32::from(bottom[3]) * (255 - top_alpha);
+    let top_weight = top_alpha * 255;
+    // 以 255 * 255 为单位的输出不透明度
+    let alpha = top_weight + bottom_weight;
+    let channel = |i: usize| {
+        ((u32::from(top[i]) * top_weight + u32::from(bottom[i]) * bottom_weight + alpha / 2)
+            / alpha) as u8
+    };
+    Rgba([
+        channel(0),
+        channel(1),
+        channel(2),
+        ((alpha + 127) / 255) as u8,
+    ])
+}
+
+/// 把 `top` 按 [`source_over`] 混合到 `bottom` 的 `(x, y)` 处，超出 `bottom` 的部分被裁剪
+pub fn overlay_over<I, J>(bottom: &mut I, top: &J, x: i64, y: i64)
+where
+    I: GenericImage<Pixel = Rgba<u8>>,
+    J: GenericImageView<Pixel = Rgba<u8>>,
+{
+    let (width, height) = (i64::from(bottom.width()), i64::from(bottom.height()));
+    let (top_width, top_height) = (i64::from(top.width()), i64::from(top.height()));
+    for ty in (-y).max(0)..top_height.min(height - y) {
+        for tx in (-x).max(0)..top_width.min(width - x) {
+            let (bx, by) = ((x + tx) as u32, (y + ty) as u32);
+            let pixel = source_over(
+                bottom.get_pixel(bx, by),
+                top.get_pixel(tx as u32, ty as u32),
+            );
+            bottom.put_pixel(bx, by, pixel);
+        }
+    }
+}
+
 #[inline(always)]
 fn weighted_channel_sum<C>(left: C, right: C, left_weight: f32, right_weight: f32) -> C
 where
@@ -50,3 +99,34 @@ where
 {
     Clamp::clamp(left.into() * left_weight + right.into() * right_weight)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_source_over() {
+        let red = Rgba([200, 0, 0, 255]);
+        assert_eq!(
+            source_over(red, Rgba([0, 0, 200, 128])),
+            Rgba([100, 0, 100, 255])
+        );
+        assert_eq!(
+            source_over(Rgba([0, 0, 0, 0]), Rgba([10, 20, 30, 40])),
+            Rgba([10, 20, 30, 40])
+        );
+        assert_eq!(source_over(red, Rgba([1, 2, 3, 0])), red);
+
+        let mut img = RgbaImage::from_pixel(3, 3, red);
+        overlay_over(
+            &mut img,
+            &RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255])),
+            2,
+            -1,
+        );
+        assert_eq!(img.get_pixel(2, 0).0, [0, 0, 255, 255]);
+        assert_eq!(img.get_pixel(2, 1).0, [200, 0, 0, 255]);
+        assert_eq!(img.get_pixel(1, 0).0, [200, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,100 @@
+//! 用中位切分（median cut）提取图片的主要颜色
+
+use image::{GenericImageView, Rgba};
+
+/// 不透明度低于此值的像素不参与统计
+const MIN_ALPHA: u8 = 128;
+
+/// 提取 `image` 中最多 `k` 种主要颜色，按像素数从多到少排列。
+///
+/// 反复把像素最多、颜色范围最大的一组沿范围最大的通道在中位数处一分为二，
+/// 每组的平均颜色作为一种主要颜色。透明的像素被忽略，图片没有不透明的像素时返回空列表。
+pub fn dominant_colors(image: &impl GenericImageView<Pixel = Rgba<u8>>, k: usize) -> Vec<[u8; 3]> {
+    let pixels: Vec<[u8; 3]> = image
+        .pixels()
+        .filter(|(_, _, p)| p[3] >= MIN_ALPHA)
+        .map(|(_, _, p)| [p[0], p[1], p[2]])
+        .collect();
+    if pixels.is_empty() || k == 0 {
+        return Vec::new();
+    }
+    let mut boxes = vec![pixels];
+    while boxes.len() < k {
+        // 按 像素数 × 最大通道范围 选择下一个切分的组
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, pixels)| pixels.len() > 1)
+            .map(|(index, pixels)| {
+                let (channel, range) = widest_channel(pixels);
+                (index, channel, pixels.len() * usize::from(range))
+            })
+            .filter(|&(_, _, score)| score > 0)
+            .max_by_key(|&(_, _, score)| score)
+            .map(|(index, channel, _)| (index, channel))
+        else {
+            break;
+        };
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|p| p[channel]);
+        // 与中位数相同的值分到同一组，通道范围大于 0 保证两组都不为空
+        let median = pixels[pixels.len() / 2][channel];
+        let mut split = pixels.partition_point(|p| p[channel] < median);
+        if split == 0 {
+            split = pixels.partition_point(|p| p[channel] <= median);
+        }
+        let upper = pixels.split_off(split);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+    boxes.sort_by_key(|pixels| std::cmp::Reverse(pixels.len()));
+    boxes.iter().map(|pixels| average(pixels)).collect()
+}
+
+/// 范围最大的通道及其范围
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let (min, max) = pixels.iter().fold((u8::MAX, u8::MIN), |(min, max), p| {
+                (min.min(p[c]), max.max(p[c]))
+            });
+            (c, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn average(pixels: &[[u8; 3]]) -> [u8; 3] {
+    let mut sum = [0u64; 3];
+    for p in pixels {
+        for c in 0..3 {
+            sum[c] += u64::from(p[c]);
+        }
+    }
+    let n = pixels.len().max(1) as u64;
+    sum.map(|s| (s / n) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_dominant_colors() {
+        // 左边 3/4 为红色，右边 1/4 为蓝色，最下面一行透明
+        let image = RgbaImage::from_fn(8, 5, |x, y| match (x, y) {
+            (_, 4) => Rgba([0, 255, 0, 0]),
+            (0..6, _) => Rgba([200, 10, 10, 255]),
+            _ => Rgba([10, 10, 200, 255]),
+        });
+        assert_eq!(
+            dominant_colors(&image, 2),
+            vec![[200, 10, 10], [10, 10, 200]]
+        );
+        // 颜色不足 k 种时不再切分
+        assert_eq!(dominant_colors(&image, 4).len(), 2);
+        assert_eq!(dominant_colors(&image, 1).len(), 1);
+        assert!(dominant_colors(&RgbaImage::new(4, 4), 3).is_empty());
+    }
+}
@@ -1,6 +1,11 @@
 pub mod color;
 pub mod constants;
+pub mod font;
 pub mod imageproc;
+pub mod presets;
+pub mod rich_text;
+pub mod shaping;
+pub mod theme;
 pub mod video;
 
 pub use {constants::*, video::slide};
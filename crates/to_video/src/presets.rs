@@ -0,0 +1,240 @@
+//! 按名称查找的预设：常用画面比例下的行布局和配色。
+//!
+//! [`list`] 返回全部预设，GUI 可以据此生成下拉菜单；[`get`] 按名称查找，
+//! 例如 `presets::get("4_2")` 对应常量 [`POSITION_4_2`]。
+
+use crate::{
+    COLOR_2_1, COLOR_2_2, COLOR_2_3, COLOR_2_4, COLOR_3_1, COLOR_4_1, POSITION_3_1, POSITION_4_1,
+    POSITION_4_2, color::Color, slide::Position,
+};
+use serde::{Deserialize, Serialize};
+
+/// 一个预设
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub description: String,
+    pub kind: PresetKind,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub enum PresetKind {
+    /// 从上到下的各行位置，第一行通常放图片
+    Layout {
+        /// 适用的分辨率，位置为 [`Position::Relative`] 时为 `None`，适用于任何分辨率
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        screen: Option<(u32, u32)>,
+        rows: Vec<Position>,
+    },
+    /// 一组配色，依次用于各行的底色
+    Palette { colors: Vec<Color> },
+}
+
+impl Preset {
+    fn layout(
+        name: &str,
+        description: &str,
+        screen: Option<(u32, u32)>,
+        rows: &[Position],
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            kind: PresetKind::Layout {
+                screen,
+                rows: rows.to_vec(),
+            },
+        }
+    }
+
+    fn palette(name: &str, description: &str, colors: &[Color]) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            kind: PresetKind::Palette {
+                colors: colors.to_vec(),
+            },
+        }
+    }
+
+    /// 行布局的各行位置，配色预设为 `None`
+    pub fn rows(&self) -> Option<&[Position]> {
+        match &self.kind {
+            PresetKind::Layout { rows, .. } => Some(rows),
+            PresetKind::Palette { .. } => None,
+        }
+    }
+
+    /// 配色预设的颜色，行布局为 `None`
+    pub fn colors(&self) -> Option<&[Color]> {
+        match &self.kind {
+            PresetKind::Layout { .. } => None,
+            PresetKind::Palette { colors } => Some(colors),
+        }
+    }
+}
+
+const LANDSCAPE: Option<(u32, u32)> = Some((1920, 1080));
+const PORTRAIT: Option<(u32, u32)> = Some((1080, 1920));
+
+/// 全部预设，先是行布局，后是配色
+pub fn list() -> Vec<Preset> {
+    let (p31, p41, p42) = (POSITION_3_1, POSITION_4_1, POSITION_4_2);
+    vec![
+        Preset::layout(
+            "2_1",
+            "16:9, large image and one text row",
+            LANDSCAPE,
+            &[Position::new(1, 0, 720), Position::new(1, 720, 360)],
+        ),
+        Preset::layout(
+            "3_1",
+            "16:9, image and two text rows",
+            LANDSCAPE,
+            &[p31.0, p31.1, p31.2],
+        ),
+        Preset::layout(
+            "4_1",
+            "16:9, image and three text rows",
+            LANDSCAPE,
+            &[p41.0, p41.1, p41.2, p41.3],
+        ),
+        Preset::layout(
+            "4_2",
+            "16:9, image, two text rows and a short footer",
+            LANDSCAPE,
+            &[p42.0, p42.1, p42.2, p42.3],
+        ),
+        Preset::layout(
+            "5_1",
+            "16:9, image and four text rows",
+            LANDSCAPE,
+            &[
+                Position::new(1, 0, 480),
+                Position::new(1, 480, 150),
+                Position::new(1, 630, 150),
+                Position::new(1, 780, 150),
+                Position::new(1, 930, 150),
+            ],
+        ),
+        Preset::layout(
+            "3_1_portrait",
+            "9:16, image and two text rows",
+            PORTRAIT,
+            &[
+                Position::new(1, 0, 1100),
+                Position::new(1, 1100, 320),
+                Position::new(1, 1420, 500),
+            ],
+        ),
+        Preset::layout(
+            "4_2_portrait",
+            "9:16, image, two text rows and a short footer",
+            PORTRAIT,
+            &[
+                Position::new(1, 0, 1000),
+                Position::new(1, 1000, 360),
+                Position::new(1, 1360, 360),
+                Position::new(1, 1720, 200),
+            ],
+        ),
+        Preset::layout(
+            "4_2_relative",
+            "any resolution, same proportions as 4_2",
+            None,
+            &relative(&[p42.0, p42.1, p42.2, p42.3], 1080),
+        ),
+        Preset::palette("color_2_1", "orange and cream", &[COLOR_2_1.0, COLOR_2_1.1]),
+        Preset::palette("color_2_2", "cyan and ivory", &[COLOR_2_2.0, COLOR_2_2.1]),
+        Preset::palette("color_2_3", "purple and gray", &[COLOR_2_3.0, COLOR_2_3.1]),
+        Preset::palette("color_2_4", "blue and navy", &[COLOR_2_4.0, COLOR_2_4.1]),
+        Preset::palette(
+            "color_3_1",
+            "orange, cream and cyan",
+            &[COLOR_3_1.0, COLOR_3_1.1, COLOR_3_1.2],
+        ),
+        Preset::palette(
+            "color_4_1",
+            "orange, cream, cyan and ivory",
+            &[COLOR_4_1.0, COLOR_4_1.1, COLOR_4_1.2, COLOR_4_1.3],
+        ),
+    ]
+}
+
+/// 按名称查找预设
+pub fn get(name: &str) -> Option<Preset> {
+    list().into_iter().find(|preset| preset.name == name)
+}
+
+/// 全部行布局
+pub fn layouts() -> impl Iterator<Item = Preset> {
+    list().into_iter().filter(|preset| preset.rows().is_some())
+}
+
+/// 全部配色
+pub fn palettes() -> impl Iterator<Item = Preset> {
+    list()
+        .into_iter()
+        .filter(|preset| preset.colors().is_some())
+}
+
+/// 把高度为 `height` 的画面中的像素位置换算为比例
+fn relative(rows: &[Position], height: u32) -> Vec<Position> {
+    let height = height as f32;
+    rows.iter()
+        .map(|pos| match *pos {
+            Position::Absolute {
+                left,
+                top,
+                height: h,
+            } => Position::relative(left as f32 / height, top as f32 / height, h as f32 / height),
+            relative => relative,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        let presets = list();
+        let mut names: Vec<_> = presets.iter().map(|preset| &preset.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), presets.len(), "preset names must be unique");
+
+        assert_eq!(get("4_2").unwrap().rows().unwrap()[3], POSITION_4_2.3);
+        assert_eq!(get("color_3_1").unwrap().colors().unwrap()[0], COLOR_3_1.0);
+        assert!(get("missing").is_none());
+
+        // 行布局从上到下排列，互不重叠且不超出画面
+        for preset in layouts() {
+            let PresetKind::Layout {
+                screen: Some((_, height)),
+                rows,
+            } = &preset.kind
+            else {
+                continue;
+            };
+            let mut bottom = 0;
+            for row in rows {
+                let Position::Absolute { top, height, .. } = *row else {
+                    panic!("{} mixes units", preset.name);
+                };
+                assert!(top >= bottom, "{}", preset.name);
+                bottom = top + height as i32;
+            }
+            assert!(bottom <= *height as i32, "{}", preset.name);
+        }
+        assert_eq!(palettes().count(), 6);
+    }
+
+    #[test]
+    fn test_preset_serde() {
+        let preset = get("4_2_relative").unwrap();
+        let json = serde_json::to_string(&preset).unwrap();
+        assert_eq!(serde_json::from_str::<Preset>(&json).unwrap(), preset);
+    }
+}
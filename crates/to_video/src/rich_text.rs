@@ -0,0 +1,158 @@
+//! 文字中的简单标记，用于在一段文字中突出显示部分内容：
+//!
+//! - `<b>粗体</b>`
+//! - `<color=#ff0000>红色</color>`
+//! - `<size=1.5>放大 1.5 倍</size>`，相对于元素的字号
+//!
+//! 标记可以嵌套，无法识别的标记按原样显示。
+
+use crate::color::Color;
+
+/// 一段文字的样式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpanStyle {
+    pub bold: bool,
+    /// `None` 时使用元素的颜色
+    pub color: Option<Color>,
+    /// 相对于元素字号的倍数
+    pub size: f32,
+}
+
+impl Default for SpanStyle {
+    fn default() -> Self {
+        Self {
+            bold: false,
+            color: None,
+            size: 1.0,
+        }
+    }
+}
+
+/// 样式相同的一段文字，可以包含换行
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tag {
+    Bold,
+    Color(Color),
+    Size(f32),
+}
+
+impl Tag {
+    fn name(&self) -> &'static str {
+        match self {
+            Tag::Bold => "b",
+            Tag::Color(_) => "color",
+            Tag::Size(_) => "size",
+        }
+    }
+
+    fn parse(tag: &str) -> Option<Self> {
+        match tag.split_once('=') {
+            None if tag == "b" => Some(Tag::Bold),
+            Some(("color", value)) => Color::try_from(value).ok().map(Tag::Color),
+            Some(("size", value)) => value
+                .parse()
+                .ok()
+                .filter(|size: &f32| size.is_finite() && *size > 0.0)
+                .map(Tag::Size),
+            _ => None,
+        }
+    }
+}
+
+/// 文字中是否有可以识别的标记，没有时按普通文字绘制
+pub fn has_markup(text: &str) -> bool {
+    parse_markup(text)
+        .iter()
+        .any(|span| span.style != SpanStyle::default())
+}
+
+/// 把带标记的文字解析为样式不同的几段
+pub fn parse_markup(text: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut stack: Vec<Tag> = Vec::new();
+    let mut push = |text: &str, stack: &[Tag]| {
+        if text.is_empty() {
+            return;
+        }
+        let style = style_of(stack);
+        match spans.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(text),
+            _ => spans.push(Span {
+                text: text.to_string(),
+                style,
+            }),
+        }
+    };
+
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + len];
+        push(&rest[..start], &stack);
+        if let Some(name) = tag.strip_prefix('/') {
+            match stack.iter().rposition(|open| open.name() == name) {
+                Some(index) => {
+                    stack.remove(index);
+                }
+                None => push(&rest[start..=start + len], &stack),
+            }
+        } else if let Some(open) = Tag::parse(tag) {
+            stack.push(open);
+        } else {
+            push(&rest[start..=start + len], &stack);
+        }
+        rest = &rest[start + len + 1..];
+    }
+    push(rest, &stack);
+    spans
+}
+
+fn style_of(stack: &[Tag]) -> SpanStyle {
+    stack.iter().fold(SpanStyle::default(), |mut style, tag| {
+        match *tag {
+            Tag::Bold => style.bold = true,
+            Tag::Color(color) => style.color = Some(color),
+            Tag::Size(size) => style.size *= size,
+        }
+        style
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_markup() {
+        let spans =
+            parse_markup("攻击 <color=#ff0000><b>+30%</b></color> 持续<size=1.5>10</size>秒");
+        let texts: Vec<&str> = spans.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["攻击 ", "+30%", " 持续", "10", "秒"]);
+        assert_eq!(
+            spans[1].style,
+            SpanStyle {
+                bold: true,
+                color: Some(Color([255, 0, 0])),
+                size: 1.0
+            }
+        );
+        assert_eq!(spans[3].style.size, 1.5);
+        assert!(has_markup("<b>a</b>"));
+    }
+
+    #[test]
+    fn test_unknown_markup_is_literal() {
+        let spans = parse_markup("a < b <i>c</i> </b> <color=red>d");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].text, "a < b <i>c</i> </b> <color=red>d");
+        assert!(!has_markup("1 < 2"));
+    }
+}
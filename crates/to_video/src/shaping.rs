@@ -0,0 +1,101 @@
+//! 文字整形：先用 unicode-bidi 按 Unicode 双向算法把一行文字分成方向相同的段，
+//! 按显示顺序排列，再由 [`TextFont::shape_run`] 用 rustybuzz 整形每一段，
+//! 处理阿拉伯字母连写、连字、附加符号定位和 GPOS 字距调整。
+
+use crate::font::TextFont;
+use ab_glyph::GlyphId;
+use std::ops::Range;
+use unicode_bidi::BidiInfo;
+
+/// 一段文字的书写方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    LeftToRight,
+    RightToLeft,
+}
+
+/// 整形后的一个字形，单位为主字体的字体单位（未缩放）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapedGlyph {
+    pub id: GlyphId,
+    /// 绘制之后水平前进的距离
+    pub advance: f32,
+    /// 字形相对于当前位置的偏移，y 向上为正
+    pub offset: (f32, f32),
+}
+
+/// 把文字分成方向相同的段，按显示顺序从左到右排列，返回每段的字节范围和方向
+pub fn visual_runs(text: &str) -> Vec<(Range<usize>, Direction)> {
+    let bidi = BidiInfo::new(text, None);
+    if !bidi.has_rtl() {
+        return vec![(0..text.len(), Direction::LeftToRight)];
+    }
+    bidi.paragraphs
+        .iter()
+        .flat_map(|paragraph| {
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            runs.into_iter().map(move |run| {
+                let direction = if levels[run.start].is_rtl() {
+                    Direction::RightToLeft
+                } else {
+                    Direction::LeftToRight
+                };
+                (run, direction)
+            })
+        })
+        .collect()
+}
+
+/// 把一行文字整形为按显示顺序从左到右排列的字形
+pub fn shape(font: &impl TextFont, text: &str) -> Vec<ShapedGlyph> {
+    visual_runs(text)
+        .into_iter()
+        .flat_map(|(run, direction)| font.shape_run(&text[run], direction))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ab_glyph::{Font, FontArc};
+
+    #[test]
+    fn test_visual_runs() {
+        fn runs(text: &str) -> Vec<(&str, Direction)> {
+            visual_runs(text)
+                .into_iter()
+                .map(|(range, direction)| (&text[range], direction))
+                .collect()
+        }
+        use Direction::*;
+        assert_eq!(runs("abc 123"), [("abc 123", LeftToRight)]);
+        // 希伯来文段落从右到左，数字仍然从左到右
+        assert_eq!(
+            runs("אבג 123"),
+            [("123", LeftToRight), ("אבג ", RightToLeft)]
+        );
+        // 左到右段落中嵌入的从右到左文字
+        assert_eq!(
+            runs("ab אבג cd"),
+            [
+                ("ab ", LeftToRight),
+                ("אבג", RightToLeft),
+                (" cd", LeftToRight)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shape_latin() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        let font = FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap();
+        let glyphs = shape(&font, "ab");
+        let ids: Vec<GlyphId> = glyphs.iter().map(|glyph| glyph.id).collect();
+        assert_eq!(ids, [font.glyph_id('a'), font.glyph_id('b')]);
+        assert_eq!(glyphs[0].advance, font.h_advance_unscaled(ids[0]));
+        assert!(shape(&font, "").is_empty());
+    }
+}
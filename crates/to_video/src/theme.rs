@@ -0,0 +1,137 @@
+//! 主题：按用途命名的一组颜色。
+//!
+//! info 文件中的颜色可以写作 `"@primary"` 这样的引用，读取时替换为主题中的颜色，
+//! 更换整个视频的配色只需修改主题。
+
+use crate::{BLACK, COLOR_3_1, WHITE, color::Color};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// 按用途命名的颜色，未给出的颜色使用 [`Theme::default`]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct Theme {
+    /// 主色，例如名字所在行的底色
+    pub primary: Color,
+    /// 辅助色
+    pub secondary: Color,
+    /// 强调色
+    pub accent: Color,
+    /// 卡片、表格等内容区域的底色
+    pub surface: Color,
+    /// 整个画面的背景色
+    pub background: Color,
+    /// 背景上的文字
+    pub text: Color,
+    /// 主色上的文字
+    pub on_primary: Color,
+    /// 辅助色上的文字
+    pub on_secondary: Color,
+    /// 内容区域上的文字
+    pub on_surface: Color,
+    /// 其他自定义的颜色，同样以 `@名称` 引用
+    #[serde(flatten)]
+    pub custom: BTreeMap<String, Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            primary: COLOR_3_1.0,
+            secondary: COLOR_3_1.1,
+            accent: COLOR_3_1.2,
+            surface: WHITE,
+            background: WHITE,
+            text: BLACK,
+            on_primary: BLACK,
+            on_secondary: BLACK,
+            on_surface: BLACK,
+            custom: BTreeMap::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// 按名称查找颜色，名称不带 `@`
+    pub fn get(&self, name: &str) -> Option<Color> {
+        match name {
+            "primary" => Some(self.primary),
+            "secondary" => Some(self.secondary),
+            "accent" => Some(self.accent),
+            "surface" => Some(self.surface),
+            "background" => Some(self.background),
+            "text" => Some(self.text),
+            "on_primary" => Some(self.on_primary),
+            "on_secondary" => Some(self.on_secondary),
+            "on_surface" => Some(self.on_surface),
+            _ => self.custom.get(name).copied(),
+        }
+    }
+
+    /// 把 `value` 中值为 `"@名称"` 的字符串替换为主题中的颜色，返回替换的数量。
+    ///
+    /// 颜色替换为 `[r, g, b]`；`back_color` 是传给 ffmpeg 的字符串，替换为 `"#rrggbb"`。
+    /// 主题中没有的名称保持不变，之后解析为颜色时会报错。
+    pub fn resolve(&self, value: &mut Value) -> usize {
+        self.resolve_as(value, false)
+    }
+
+    fn resolve_as(&self, value: &mut Value, hex: bool) -> usize {
+        match value {
+            Value::String(s) => {
+                let Some(color) = s.strip_prefix('@').and_then(|name| self.get(name)) else {
+                    return 0;
+                };
+                *value = if hex {
+                    Value::String(color.hex())
+                } else {
+                    Value::from(color.0.to_vec())
+                };
+                1
+            }
+            Value::Array(values) => values.iter_mut().map(|v| self.resolve_as(v, false)).sum(),
+            Value::Object(map) => map
+                .iter_mut()
+                .map(|(key, v)| self.resolve_as(v, key == "back_color"))
+                .sum(),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_theme() {
+        let theme: Theme = serde_json::from_value(json!({
+            "primary": [10, 20, 30],
+            "brand": [1, 2, 3]
+        }))
+        .unwrap();
+        assert_eq!(theme.secondary, Theme::default().secondary);
+        assert_eq!(theme.get("brand"), Some(Color([1, 2, 3])));
+
+        let mut value = json!({
+            "operations": [
+                { "Color": { "color": "@primary", "z_index": 0 } },
+                { "Text": { "color": "@brand", "header": ["@unknown"] } }
+            ],
+            "config": { "back_color": "@text" }
+        });
+        assert_eq!(theme.resolve(&mut value), 3);
+        assert_eq!(
+            value,
+            json!({
+                "operations": [
+                    { "Color": { "color": [10, 20, 30], "z_index": 0 } },
+                    { "Text": { "color": [1, 2, 3], "header": ["@unknown"] } }
+                ],
+                "config": { "back_color": "#000000" }
+            })
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! 动态图片：GIF、APNG 或 webm、mp4 等视频作为图片元素（`animate: true`），在幻灯片模式中播放。
+//!
+//! 每页的静态图片中不绘制动态图片，生成这一页的视频时由 ffmpeg 循环解码，
+//! 缩放到元素的范围内后叠加在这一页上。叠加的画面位于所有元素之上，并忽略旋转、投影和平移缩放；
+//! [`Element::Panel`](super::slide::Element::Panel) 中的图片不会播放。
+//!
+//! 滚动模式仍然只使用静态画面：GIF、APNG 显示第一帧，视频不显示。
+
+use super::{Renderer, assets::is_video, chunk::Chunk, ffmpeg::FfmpegCommand};
+use crate::Result;
+use std::path::PathBuf;
+
+/// 叠加在一页视频上的动态图片，坐标和尺寸都是输出视频中的像素
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimatedOverlay {
+    /// 动态图片的绝对路径，ffmpeg 在临时目录中运行
+    pub path: PathBuf,
+    pub x: u32,
+    pub y: u32,
+    /// 元素的范围，动态图片保持宽高比缩放到范围内并居中
+    pub size: (u32, u32),
+}
+
+impl AnimatedOverlay {
+    /// 添加循环播放的输入：视频无限循环，GIF、APNG 按文件中的循环次数播放
+    pub fn input(&self, command: FfmpegCommand) -> FfmpegCommand {
+        let looping = if is_video(&self.path) {
+            ["-stream_loop", "-1"]
+        } else {
+            ["-ignore_loop", "0"]
+        };
+        command.args(looping).input(&self.path)
+    }
+
+    /// 把第 `input` 路输入叠加到 `[base]` 上的滤镜，输出没有标签，可以继续连接其他滤镜
+    pub fn filter(&self, input: usize, base: &str) -> String {
+        let (width, height) = self.size;
+        format!(
+            "[{input}]format=rgba,scale={width}:{height}:force_original_aspect_ratio=decrease,\
+             pad={width}:{height}:(ow-iw)/2:(oh-ih)/2:color=black@0[{base}_anim];\
+             [{base}][{base}_anim]overlay=x={}:y={}",
+            self.x, self.y
+        )
+    }
+}
+
+impl Renderer<'_> {
+    /// 一页中所有动态图片在屏幕上的位置，`x` 为这一页的图片在屏幕上的 x 坐标
+    ///
+    /// # Errors
+    /// - 无法获取动态图片的绝对路径时返回 `Err`。
+    ///
+    pub(super) fn animated_overlays(&self, page: &Chunk, x: u32) -> Result<Vec<AnimatedOverlay>> {
+        let height = self.config.screen.1;
+        let scaled = |value: i64| (value.max(0) as f32 * self.scale).round() as u32;
+        let mut left = 0;
+        let mut overlays = Vec::new();
+        for slide in &page.slides {
+            let width = slide.width().unwrap_or(self.config.width_slides);
+            for (path, rect) in slide.animated_images((width, height)) {
+                overlays.push(AnimatedOverlay {
+                    path: std::path::absolute(path)?,
+                    x: x + scaled(left + i64::from(rect.left())),
+                    y: scaled(rect.top().into()),
+                    size: (
+                        scaled(rect.width().into()).max(1),
+                        scaled(rect.height().into()).max(1),
+                    ),
+                });
+            }
+            left += i64::from(width);
+        }
+        Ok(overlays)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::ffmpeg::Ffmpeg;
+    use std::path::Path;
+
+    #[test]
+    fn test_animated_overlay() {
+        let overlay = AnimatedOverlay {
+            path: PathBuf::from("/assets/idle.gif"),
+            x: 100,
+            y: 50,
+            size: (320, 240),
+        };
+        assert_eq!(
+            overlay.filter(2, "p0"),
+            "[2]format=rgba,scale=320:240:force_original_aspect_ratio=decrease,\
+             pad=320:240:(ow-iw)/2:(oh-ih)/2:color=black@0[p0_anim];\
+             [p0][p0_anim]overlay=x=100:y=50"
+        );
+
+        let ffmpeg = Ffmpeg::new("ffmpeg");
+        let args = |overlay: &AnimatedOverlay| -> Vec<String> {
+            overlay
+                .input(ffmpeg.command())
+                .get_args()
+                .iter()
+                .map(|arg| arg.to_str().unwrap().to_string())
+                .collect()
+        };
+        assert!(
+            args(&overlay)
+                .ends_with(&["-ignore_loop", "0", "-i", "/assets/idle.gif"].map(String::from))
+        );
+        let video = AnimatedOverlay {
+            path: Path::new("/assets/idle.webm").to_path_buf(),
+            ..overlay
+        };
+        assert!(
+            args(&video)
+                .ends_with(&["-stream_loop", "-1", "-i", "/assets/idle.webm"].map(String::from))
+        );
+    }
+}
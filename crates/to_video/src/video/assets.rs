@@ -0,0 +1,270 @@
+use crate::Result;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// 缓存的默认容量（张）
+pub const DEFAULT_IMAGE_CACHE_SIZE: usize = 64;
+
+/// 按 (路径, 目标尺寸) 缓存已解码并缩放的图片，最近最少使用的条目先被淘汰。
+///
+/// 同一张 logo、背景在每个 slide 中都会出现，封面也会再次渲染前几个 slide，
+/// 缓存后只需解码一次。可在多个线程间共享。
+pub struct ImageCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+/// (路径, 目标尺寸)
+type CacheKey = (PathBuf, (u32, u32));
+
+#[derive(Default)]
+struct CacheState {
+    /// 图片及其最近一次被访问时的 `tick`
+    entries: HashMap<CacheKey, (Arc<DynamicImage>, u64)>,
+    /// 每次访问递增，用于找出最久未使用的条目
+    tick: u64,
+}
+
+impl ImageCache {
+    /// `capacity` 为 0 时不缓存
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::default(),
+        }
+    }
+
+    /// 与 `load_image` 相同，但优先返回缓存中的结果
+    pub fn load(&self, path: &Path, size: (u32, u32)) -> Result<Arc<DynamicImage>> {
+        let key = (path.to_path_buf(), size);
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.tick += 1;
+            let tick = state.tick;
+            if let Some((img, last_used)) = state.entries.get_mut(&key) {
+                *last_used = tick;
+                return Ok(Arc::clone(img));
+            }
+        }
+
+        // 解码时不持有锁，其他线程可以同时读取缓存
+        let img = Arc::new(load_image(path, size)?);
+        if self.capacity == 0 {
+            return Ok(img);
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.entries.len() >= self.capacity
+            && !state.entries.contains_key(&key)
+            && let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone())
+        {
+            state.entries.remove(&oldest);
+        }
+        let tick = state.tick;
+        state.entries.insert(key, (Arc::clone(&img), tick));
+        Ok(img)
+    }
+
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_IMAGE_CACHE_SIZE)
+    }
+}
+
+/// 读取图片并缩放到不超过 `size` (宽, 高)，保持宽高比。
+///
+/// 位图使用 `image` 读取后缩小；SVG（需要开启 `svg` feature）直接按目标尺寸栅格化，
+/// 不会因为放大而模糊。
+pub fn load_image(path: &Path, size: (u32, u32)) -> Result<DynamicImage> {
+    if is_svg(path) {
+        return load_svg(path, size);
+    }
+    Ok(open_image(path)?.thumbnail(size.0, size.1))
+}
+
+/// 按原尺寸读取位图。按文件内容（而不是扩展名）识别格式，格式未启用时给出包含格式名的错误
+pub fn open_image(path: &Path) -> Result<DynamicImage> {
+    let reader = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("{path:?}: {e}"))?;
+    match reader.format() {
+        Some(format) if !format.reading_enabled() => Err(unsupported(path, format).into()),
+        Some(_) => Ok(reader.decode().map_err(|e| format!("{path:?}: {e}"))?),
+        None => Err(format!("{path:?}: unrecognized image format").into()),
+    }
+}
+
+fn unsupported(path: &Path, format: ImageFormat) -> String {
+    let hint = match format {
+        ImageFormat::Avif => " (enable the `avif` feature, requires dav1d)",
+        _ => "",
+    };
+    format!("{path:?}: {format:?} images are not supported{hint}")
+}
+
+/// 按扩展名判断是否为视频文件，视频只能作为动态图片使用，见 [`animated`](super::animated)
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["webm", "mp4", "mov", "mkv"]
+                .iter()
+                .any(|video| ext.eq_ignore_ascii_case(video))
+        })
+}
+
+fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg") || ext.eq_ignore_ascii_case("svgz"))
+}
+
+#[cfg(feature = "svg")]
+fn load_svg(path: &Path, size: (u32, u32)) -> Result<DynamicImage> {
+    use resvg::{tiny_skia, usvg};
+
+    let data = std::fs::read(path).map_err(|e| format!("{path:?}: {e}"))?;
+    let tree = usvg::Tree::from_data(&data, &usvg::Options::default())
+        .map_err(|e| format!("{path:?}: {e}"))?;
+    let svg_size = tree.size();
+    let scale = (size.0 as f32 / svg_size.width()).min(size.1 as f32 / svg_size.height());
+    let (width, height) = (
+        ((svg_size.width() * scale).round() as u32).max(1),
+        ((svg_size.height() * scale).round() as u32).max(1),
+    );
+    let mut pixmap =
+        tiny_skia::Pixmap::new(width, height).ok_or_else(|| format!("{path:?}: invalid size"))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny_skia 使用预乘 alpha，转换回普通 RGBA
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    let img = image::RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| format!("{path:?}: invalid pixel buffer"))?;
+    Ok(DynamicImage::ImageRgba8(img))
+}
+
+#[cfg(not(feature = "svg"))]
+fn load_svg(path: &Path, _size: (u32, u32)) -> Result<DynamicImage> {
+    Err(format!("{path:?}: SVG support requires the `svg` feature").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_svg() {
+        assert!(is_svg(Path::new("logo.svg")));
+        assert!(is_svg(Path::new("logo.SVG")));
+        assert!(!is_svg(Path::new("logo.png")));
+        assert!(!is_svg(Path::new("svg")));
+    }
+
+    #[test]
+    fn test_unsupported_format_is_named() {
+        let path =
+            std::env::temp_dir().join(format!("to_video_test_unknown_{}.bin", std::process::id()));
+        std::fs::write(&path, b"definitely not an image").unwrap();
+        let err = load_image(&path, (10, 10)).unwrap_err().to_string();
+        assert!(err.contains("unrecognized image format"), "{err}");
+        std::fs::remove_file(path).unwrap();
+
+        let err = unsupported(Path::new("a.avif"), ImageFormat::Avif);
+        assert!(
+            err.contains("Avif") && err.contains("`avif` feature"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_image_cache() {
+        let dir = std::env::temp_dir();
+        let paths: Vec<PathBuf> = (0..3)
+            .map(|i| {
+                dir.join(format!(
+                    "to_video_test_cache_{}_{i}.png",
+                    std::process::id()
+                ))
+            })
+            .collect();
+        for path in &paths {
+            image::RgbaImage::new(4, 4).save(path).unwrap();
+        }
+
+        let cache = ImageCache::new(2);
+        let first = cache.load(&paths[0], (2, 2)).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.load(&paths[0], (2, 2)).unwrap()));
+        // 尺寸不同视为不同条目
+        cache.load(&paths[0], (4, 4)).unwrap();
+        assert_eq!(cache.len(), 2);
+        // 访问 (0, 2x2) 后它成为最近使用的，插入新条目时淘汰 (0, 4x4)
+        cache.load(&paths[0], (2, 2)).unwrap();
+        cache.load(&paths[1], (2, 2)).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(Arc::ptr_eq(&first, &cache.load(&paths[0], (2, 2)).unwrap()));
+
+        for path in &paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_load_webp() {
+        let path =
+            std::env::temp_dir().join(format!("to_video_test_image_{}.webp", std::process::id()));
+        let img = image::RgbaImage::from_pixel(8, 4, image::Rgba([0, 0, 255, 255]));
+        img.save(&path).unwrap();
+        let loaded = load_image(&path, (4, 4)).unwrap();
+        assert_eq!((loaded.width(), loaded.height()), (4, 2));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_load_svg_scales_to_rect() {
+        let path =
+            std::env::temp_dir().join(format!("to_video_test_logo_{}.svg", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="20">
+                <rect width="10" height="20" fill="red"/>
+            </svg>"#,
+        )
+        .unwrap();
+        let img = load_image(&path, (100, 100)).unwrap().into_rgba8();
+        assert_eq!(img.dimensions(), (50, 100));
+        assert_eq!(img.get_pixel(25, 50).0, [255, 0, 0, 255]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
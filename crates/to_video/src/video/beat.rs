@@ -0,0 +1,339 @@
+//! 节拍同步：按背景音乐的节拍调整滚动，使 slide 的分界线正好在节拍上经过屏幕左边缘。
+//!
+//! 节拍由固定的 BPM 给出，或者由 ffmpeg 解码音乐后检测音量突增的位置（onset）。
+//! 每个分界线按 `swip_pixels_per_sec` 计算到达的时间后，移到最近的节拍上，
+//! 相邻分界线之间匀速移动；最后背景音乐混入视频的音轨。
+
+use super::{
+    chunk::Chunk,
+    ffmpeg::{Ffmpeg, FfmpegCommand},
+    scroll::Keyframe,
+};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// 检测节拍时解码音乐的采样率
+const ONSET_SAMPLE_RATE: u32 = 22050;
+/// 检测节拍时每一帧的采样数
+const ONSET_FRAME: usize = 1024;
+/// 两个节拍之间至少间隔的秒数
+const MIN_BEAT_GAP: f64 = 0.1;
+
+/// 与背景音乐的节拍同步滚动，见 [`beat`](super::beat)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BeatSync {
+    /// 背景音乐，ffmpeg 能读取的任何音频格式
+    pub path: PathBuf,
+    pub beats: Beats,
+    /// 背景音乐的音量增益（dB），负数为减小音量
+    #[serde(default)]
+    pub gain_db: f32,
+}
+
+/// 节拍的来源
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Beats {
+    /// 固定的速度，第一拍在音乐的 `offset_sec` 秒
+    Bpm {
+        bpm: f32,
+        #[serde(default)]
+        offset_sec: f32,
+    },
+    /// 检测音量突增的位置，`sensitivity` 越大检测到的节拍越多
+    Onsets {
+        #[serde(default = "default_sensitivity")]
+        sensitivity: f32,
+    },
+}
+
+fn default_sensitivity() -> f32 {
+    1.0
+}
+
+impl BeatSync {
+    pub fn new(path: impl Into<PathBuf>, beats: Beats) -> Self {
+        Self {
+            path: path.into(),
+            beats,
+            gain_db: 0.0,
+        }
+    }
+
+    pub fn gain_db(mut self, gain_db: f32) -> Self {
+        self.gain_db = gain_db;
+        self
+    }
+
+    /// 前 `until` 秒内所有节拍的时间（秒），按时间排序
+    ///
+    /// # Errors
+    /// - 检测节拍时 ffmpeg 无法解码音乐返回 `Err`。
+    ///
+    pub fn beat_times(&self, ffmpeg_path: &Path, until: f64) -> Result<Vec<f64>> {
+        match self.beats {
+            Beats::Bpm { bpm, offset_sec } => {
+                let interval = 60.0 / f64::from(bpm);
+                let offset = f64::from(offset_sec.max(0.0));
+                Ok((0..)
+                    .map(|i| offset + f64::from(i) * interval)
+                    .take_while(|&t| t <= until)
+                    .collect())
+            }
+            Beats::Onsets { sensitivity } => {
+                let samples = decode_mono(ffmpeg_path, &self.path, ONSET_SAMPLE_RATE)?;
+                Ok(detect_onsets(&samples, ONSET_SAMPLE_RATE, sensitivity))
+            }
+        }
+    }
+}
+
+/// 用 ffmpeg 把 `path` 解码为单声道 32 位浮点采样
+fn decode_mono(ffmpeg_path: &Path, path: &Path, sample_rate: u32) -> Result<Vec<f32>> {
+    let output = Command::new(ffmpeg_path)
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args([
+            "-ac",
+            "1",
+            "-ar",
+            &sample_rate.to_string(),
+            "-f",
+            "f32le",
+            "-",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "cannot decode {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// 检测音量突增的位置（秒）。
+///
+/// 每 [`ONSET_FRAME`] 个采样计算一次能量，能量的对数比前一帧（开头之前视为静音）增加的部分
+/// 为突增强度，强度超过附近约一秒内平均值加上标准差 / `sensitivity`、
+/// 并且是前后 [`MIN_BEAT_GAP`] 秒内最大值的位置视为一个节拍。
+pub fn detect_onsets(samples: &[f32], sample_rate: u32, sensitivity: f32) -> Vec<f64> {
+    const SILENCE: f64 = 1e-10;
+    let energies: Vec<f64> = std::iter::once(SILENCE.ln())
+        .chain(samples.chunks(ONSET_FRAME).map(|frame| {
+            let sum: f64 = frame.iter().map(|&s| f64::from(s) * f64::from(s)).sum();
+            (sum / frame.len() as f64 + SILENCE).ln()
+        }))
+        .collect();
+    let flux: Vec<f64> = energies
+        .windows(2)
+        .map(|w| (w[1] - w[0]).max(0.0))
+        .collect();
+    let frame_sec = ONSET_FRAME as f64 / f64::from(sample_rate.max(1));
+    let radius = ((0.5 / frame_sec).round() as usize).max(1);
+    let peak_radius = ((MIN_BEAT_GAP / frame_sec).round() as usize).max(1);
+    let sensitivity = f64::from(sensitivity.max(0.01));
+    let around =
+        |i: usize, radius: usize| &flux[i.saturating_sub(radius)..(i + radius + 1).min(flux.len())];
+
+    let mut onsets: Vec<f64> = Vec::new();
+    for (i, &value) in flux.iter().enumerate() {
+        let window = around(i, radius);
+        let mean = window.iter().sum::<f64>() / window.len() as f64;
+        let deviation =
+            (window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / window.len() as f64).sqrt();
+        let is_peak = around(i, peak_radius).iter().all(|&v| v <= value);
+        let t = i as f64 * frame_sec;
+        if is_peak
+            && value > 0.0
+            && value > mean + deviation / sensitivity
+            && onsets.last().is_none_or(|&last| t - last >= MIN_BEAT_GAP)
+        {
+            onsets.push(t);
+        }
+    }
+    onsets
+}
+
+/// 整个视频的滚动关键帧（全局坐标），每个 slide 的分界线对齐到节拍。
+///
+/// # Parameters
+/// - `boundaries`: 各 slide 左边缘的 x 坐标，不含 0，最后一项为长条图的总滚动距离。
+/// - `start`: 开始滚动的时间（封面结束）。
+/// - `speed`: 名义的滚动速度（像素/秒），每个分界线先按这个速度计算到达时间。
+/// - `beats`: 节拍的时间，按时间排序。
+///
+/// 到达时间移到最近的节拍上，但滚动速度最多为名义速度的两倍；
+/// 超出音乐长度或没有合适的节拍时保持名义速度。
+pub fn beat_keyframes(boundaries: &[u32], start: f64, speed: u32, beats: &[f64]) -> Vec<Keyframe> {
+    let speed = f64::from(speed.max(1));
+    let mut keyframes = vec![Keyframe { t: start, x: 0.0 }];
+    for &x in boundaries {
+        let last = *keyframes.last().unwrap();
+        let x = f64::from(x);
+        if x <= last.x {
+            continue;
+        }
+        let nominal = last.t + (x - last.x) / speed;
+        let earliest = last.t + (x - last.x) / speed / 2.0;
+        let i = beats.partition_point(|&b| b < earliest);
+        let t = [beats.get(i), beats.get(i + 1)]
+            .into_iter()
+            .flatten()
+            .copied()
+            .min_by(|a, b| (a - nominal).abs().total_cmp(&(b - nominal).abs()))
+            // 节拍离名义时间太远（超过一倍）时说明音乐已经结束或节拍太稀疏
+            .filter(|&b| b - last.t <= 2.0 * (nominal - last.t))
+            .unwrap_or(nominal);
+        keyframes.push(Keyframe { t, x });
+    }
+    keyframes
+}
+
+/// 从全局关键帧中取出 `chunk` 滚动的部分，时间从 chunk 开始时算起
+pub(super) fn chunk_keyframes(global: &[Keyframe], chunk: &Chunk) -> Vec<Keyframe> {
+    let (from, to) = (
+        f64::from(chunk.offset),
+        f64::from(chunk.offset + chunk.distance),
+    );
+    let start = global.iter().find(|k| k.x >= from).map_or(0.0, |k| k.t);
+    global
+        .iter()
+        .filter(|k| k.x >= from && k.x <= to)
+        .map(|k| Keyframe {
+            t: k.t - start,
+            x: k.x - from,
+        })
+        .collect()
+}
+
+/// 把背景音乐混入 `source` 的命令，视频流直接复制，音频编码为 AAC，视频结束时音乐也结束。
+///
+/// `keep_audio` 为 `true` 时与 `source` 原有的音频混合，否则替换。
+pub fn music_command(
+    ffmpeg: &Ffmpeg,
+    source: &Path,
+    music: &BeatSync,
+    keep_audio: bool,
+    output: &Path,
+    metadata: &HashMap<String, String>,
+) -> FfmpegCommand {
+    let mut filters = format!("[1]volume={}dB", music.gain_db);
+    if keep_audio {
+        filters.push_str("[bgm];[0:a][bgm]amix=inputs=2:duration=longest:normalize=0");
+    }
+    filters.push_str("[music]");
+    ffmpeg
+        .command()
+        .input(source)
+        .input(&music.path)
+        .filter_complex(&filters)
+        .args(["-map", "0:v"])
+        .map("music")
+        .args(["-c:v", "copy", "-c:a", "aac", "-shortest"])
+        .metadata(metadata)
+        .output(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpm_beat_times() {
+        let sync = BeatSync::new(
+            "bgm.mp3",
+            Beats::Bpm {
+                bpm: 120.0,
+                offset_sec: 0.25,
+            },
+        );
+        let beats = sync.beat_times(Path::new("ffmpeg"), 2.0).unwrap();
+        assert_eq!(beats, [0.25, 0.75, 1.25, 1.75]);
+    }
+
+    #[test]
+    fn test_detect_onsets() {
+        // 每 0.5 秒一次短促的声音
+        let rate = ONSET_SAMPLE_RATE;
+        let samples: Vec<f32> = (0..rate * 3)
+            .map(|i| {
+                let t = f64::from(i) / f64::from(rate);
+                if t % 0.5 < 0.05 { 0.8 } else { 0.001 }
+            })
+            .collect();
+        let onsets = detect_onsets(&samples, rate, 1.0);
+        assert_eq!(onsets.len(), 6, "{onsets:?}");
+        let frame_sec = ONSET_FRAME as f64 / f64::from(rate);
+        for (i, t) in onsets.iter().enumerate() {
+            assert!((t - i as f64 * 0.5).abs() <= frame_sec, "{onsets:?}");
+        }
+    }
+
+    fn assert_keyframes(keyframes: &[Keyframe], expected: &[(f64, f64)]) {
+        assert_eq!(keyframes.len(), expected.len(), "{keyframes:?}");
+        for (k, &(t, x)) in keyframes.iter().zip(expected) {
+            assert!((k.t - t).abs() < 1e-9 && k.x == x, "{keyframes:?}");
+        }
+    }
+
+    #[test]
+    fn test_beat_keyframes() {
+        // 名义上每 3 秒经过一个分界线，节拍每 1 秒一次，从 0.4 秒开始
+        let beats: Vec<f64> = (0..20).map(|i| 0.4 + f64::from(i)).collect();
+        let global = beat_keyframes(&[480, 960, 1120], 2.0, 160, &beats);
+        assert_keyframes(
+            &global,
+            &[(2.0, 0.0), (5.4, 480.0), (8.4, 960.0), (9.4, 1120.0)],
+        );
+        // 音乐结束后保持名义速度
+        let keyframes = beat_keyframes(&[480, 960], 2.0, 160, &beats[..4]);
+        assert_keyframes(&keyframes, &[(2.0, 0.0), (5.0, 480.0), (8.0, 960.0)]);
+
+        let chunk = Chunk {
+            slides: Vec::new(),
+            distance: 640,
+            offset: 480,
+            first_slide: 1,
+            keyframes: None,
+        };
+        assert_keyframes(
+            &chunk_keyframes(&global, &chunk),
+            &[(0.0, 0.0), (3.0, 480.0), (4.0, 640.0)],
+        );
+    }
+
+    #[test]
+    fn test_music_command() {
+        let music = BeatSync::new("bgm.mp3", Beats::Onsets { sensitivity: 1.0 }).gain_db(-3.0);
+        let command = music_command(
+            &Ffmpeg::new("ffmpeg"),
+            Path::new("narration.mp4"),
+            &music,
+            true,
+            Path::new("out.mp4"),
+            &HashMap::new(),
+        );
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert!(args.contains(
+            &"[1]volume=-3dB[bgm];[0:a][bgm]amix=inputs=2:duration=longest:normalize=0[music]"
+        ));
+        assert!(args.contains(&"-shortest"));
+        assert_eq!(args.last(), Some(&"out.mp4"));
+    }
+}
@@ -0,0 +1,140 @@
+use super::slide::Slide;
+use crate::Result;
+use md5::{Digest, Md5};
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// 增量生成的视频缓存，保存在 `work_dir/cache`。
+///
+/// 每段视频以输入的哈希命名，输入相同时直接复用缓存的视频。
+/// 一次完整运行结束后删除本次没有用到的缓存，
+/// 因此多个视频共用同一个 `work_dir` 时缓存会互相淘汰。
+pub(super) struct RenderCache {
+    dir: PathBuf,
+    used: HashSet<PathBuf>,
+}
+
+impl RenderCache {
+    pub(super) fn open(work_dir: &Path) -> Result<Self> {
+        let dir = work_dir.join("cache");
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            used: HashSet::new(),
+        })
+    }
+
+    fn entry(&mut self, name: &Path, key: u64) -> PathBuf {
+        let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+        let entry = self.dir.join(format!("{stem}_{key:016x}.mp4"));
+        self.used.insert(entry.clone());
+        entry
+    }
+
+    /// 缓存中有 `key` 对应的视频时复制到 `dest` 并返回 `true`
+    pub(super) fn restore(&mut self, name: &Path, key: u64, dest: &Path) -> Result<bool> {
+        let entry = self.entry(name, key);
+        if !entry.is_file() {
+            return Ok(false);
+        }
+        fs::copy(entry, dest)?;
+        Ok(true)
+    }
+
+    /// 把新生成的视频 `src` 保存为 `key` 对应的缓存
+    pub(super) fn store(&mut self, name: &Path, key: u64, src: &Path) -> Result<()> {
+        let entry = self.entry(name, key);
+        fs::copy(src, entry)?;
+        Ok(())
+    }
+
+    /// 删除本次运行没有用到的缓存
+    pub(super) fn prune(&self) -> Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !self.used.contains(&path) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 文件的修改时间（UNIX 时间，纳秒），文件不存在时为 `None`
+pub(super) fn modified(path: &Path) -> Option<u128> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+/// 计算一段视频的输入哈希：`params` 为影响输出的参数，
+/// 加上 slide 的内容和 slide 引用的图片的修改时间。
+///
+/// 哈希保存在磁盘上，因此使用 MD5 而不是各 Rust 版本间可能变化的 `DefaultHasher`
+pub(super) fn inputs_hash(params: &impl Debug, slides: &[Slide]) -> u64 {
+    let mut hasher = Md5::new();
+    hasher.update(format!("{params:?}\n"));
+    for slide in slides {
+        hasher.update(format!("{slide:?}\n"));
+        for path in slide.asset_paths() {
+            hasher.update(format!("{:?}\n", modified(path)));
+        }
+    }
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().expect("MD5 digest is 16 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BLACK, slide::Position};
+
+    fn slide(text: &str) -> Slide {
+        let mut slide = Slide::new();
+        slide.add_text(text, 60.0, BLACK, Position::relative(0.0, 0.0, 1.0));
+        slide
+    }
+
+    #[test]
+    fn test_inputs_hash() {
+        let slides = [slide("a"), slide("b")];
+        let key = inputs_hash(&(30, "libx264"), &slides);
+        assert_eq!(key, inputs_hash(&(30, "libx264"), &slides));
+        assert_ne!(key, inputs_hash(&(60, "libx264"), &slides));
+        assert_ne!(
+            key,
+            inputs_hash(&(30, "libx264"), &[slide("a"), slide("c")])
+        );
+        // 缓存保存在磁盘上，同样的输入在不同版本中必须得到同样的哈希
+        assert_eq!(inputs_hash(&(30, "libx264"), &[]), 0x28f2_0a44_6902_7fef);
+    }
+
+    #[test]
+    fn test_restore_and_prune() {
+        let work_dir =
+            std::env::temp_dir().join(format!("to_video_test_cache_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&work_dir);
+        fs::create_dir_all(&work_dir).unwrap();
+        let (video, dest) = (work_dir.join("00.mp4"), work_dir.join("copy.mp4"));
+        fs::write(&video, b"video").unwrap();
+
+        let mut cache = RenderCache::open(&work_dir).unwrap();
+        let name = Path::new("00.mp4");
+        assert!(!cache.restore(name, 1, &dest).unwrap());
+        cache.store(name, 1, &video).unwrap();
+        cache.store(name, 2, &video).unwrap();
+
+        // 下一次运行只用到 key 1，key 2 被删除
+        let mut cache = RenderCache::open(&work_dir).unwrap();
+        assert!(cache.restore(name, 1, &dest).unwrap());
+        assert_eq!(fs::read(&dest).unwrap(), b"video");
+        cache.prune().unwrap();
+        assert!(!cache.restore(name, 2, &dest).unwrap());
+        assert_eq!(fs::read_dir(work_dir.join("cache")).unwrap().count(), 1);
+        fs::remove_dir_all(work_dir).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+use super::slide::RenderContext;
+use crate::{
+    color::Color,
+    imageproc::{
+        drawing::{DrawMut, DrawText, Point},
+        rect::Rect,
+    },
+};
+use image::{GenericImage, Rgba};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+/// 图表样式
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct ChartStyle {
+    /// 数值上限，超出的数值按上限绘制
+    pub max: f32,
+    /// 柱子或雷达区域的颜色
+    pub color: Color,
+    /// 坐标轴和网格线颜色，`None` 时不绘制
+    #[serde(default)]
+    pub axis_color: Option<Color>,
+    pub label_scale: f32,
+    pub label_color: Color,
+}
+
+/// 把数据中的文本解析为图表数值。
+///
+/// 数字直接解析；干员档案中的能力评级（卓越、优良、标准、普通、缺陷）按 5 ~ 1 计，
+/// 其余无法识别的内容计为 0。
+pub fn parse_chart_value(text: &str) -> f32 {
+    let text = text.trim();
+    if let Ok(value) = text.parse() {
+        return value;
+    }
+    match text {
+        "卓越" => 5.0,
+        "优良" => 4.0,
+        "标准" => 3.0,
+        "普通" => 2.0,
+        "缺陷" => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// 数值相对于上限的比例，限制在 0.0 ~ 1.0
+fn ratio(value: f32, max: f32) -> f32 {
+    if max <= 0.0 {
+        0.0
+    } else {
+        (value / max).clamp(0.0, 1.0)
+    }
+}
+
+/// 横向柱状图：每行左侧为标签，右侧为长度与数值成比例的柱子
+pub fn draw_bar_chart(
+    img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+    rect: Rect,
+    labels: &[String],
+    values: &[f32],
+    style: &ChartStyle,
+    ctx: &RenderContext,
+) {
+    let rows = labels.len().min(values.len()) as u32;
+    if rows == 0 || rect.height() < rows {
+        return;
+    }
+    let row_height = rect.height() / rows;
+    let label_width = rect.width() * 3 / 10;
+    let bar_space = rect.width() - label_width;
+    // 柱子上下各留出行高的 1/5
+    let gap = row_height / 5;
+
+    for (i, (label, value)) in labels.iter().zip(values).enumerate() {
+        let top = rect.top() + (i as u32 * row_height) as i32;
+        if label_width > 0 {
+            img.draw_text_center_supersampled_mut(
+                style.label_color.into(),
+                Rect::at(rect.left(), top).of_size(label_width, row_height),
+                style.label_scale,
+                &ctx.font,
+                label,
+                ctx.text_quality.factor(),
+            );
+        }
+        let bar_width = (bar_space as f32 * ratio(*value, style.max)).round() as u32;
+        if bar_width > 0 && row_height > 2 * gap {
+            img.draw_filled_rect_mut(
+                Rect::at(rect.left() + label_width as i32, top + gap as i32)
+                    .of_size(bar_width, row_height - 2 * gap),
+                style.color.into(),
+            );
+        }
+    }
+
+    if let Some(axis) = style.axis_color {
+        let x = (rect.left() + label_width as i32) as f32;
+        img.draw_line_segment_mut(
+            (x, rect.top() as f32),
+            (x, (rect.top() + (rows * row_height) as i32 - 1) as f32),
+            axis.into(),
+        );
+    }
+}
+
+/// 雷达图各顶点的坐标：第一个轴朝上，顺时针排列
+pub fn radar_points(center: (f32, f32), radius: f32, ratios: &[f32]) -> Vec<(f32, f32)> {
+    let n = ratios.len() as f32;
+    ratios
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let angle = i as f32 * TAU / n - FRAC_PI_2;
+            (
+                center.0 + radius * r * angle.cos(),
+                center.1 + radius * r * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+/// 雷达图：至少需要 3 个维度，标签绘制在各轴外侧
+pub fn draw_radar_chart(
+    img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+    rect: Rect,
+    labels: &[String],
+    values: &[f32],
+    style: &ChartStyle,
+    ctx: &RenderContext,
+) {
+    let n = labels.len().min(values.len());
+    if n < 3 {
+        return;
+    }
+    let center = (
+        rect.left() as f32 + rect.width() as f32 / 2.0,
+        rect.top() as f32 + rect.height() as f32 / 2.0,
+    );
+    // 为标签留出外圈
+    let label_size = (rect.width().min(rect.height()) / 6).max(1);
+    let radius = (rect.width().min(rect.height()) as f32 / 2.0 - label_size as f32).max(1.0);
+
+    if let Some(axis) = style.axis_color {
+        for level in 1..=4 {
+            let ring: Vec<Point<f32>> =
+                radar_points(center, radius * level as f32 / 4.0, &vec![1.0; n])
+                    .into_iter()
+                    .map(|(x, y)| Point::new(x, y))
+                    .collect();
+            img.draw_hallow_polygon_mut(&ring, axis.into());
+        }
+        for (x, y) in radar_points(center, radius, &vec![1.0; n]) {
+            img.draw_line_segment_mut(center, (x, y), axis.into());
+        }
+    }
+
+    let ratios: Vec<f32> = values[..n].iter().map(|v| ratio(*v, style.max)).collect();
+    let mut polygon: Vec<Point<i32>> = radar_points(center, radius, &ratios)
+        .into_iter()
+        .map(|(x, y)| Point::new(x.round() as i32, y.round() as i32))
+        .collect();
+    polygon.dedup();
+    while polygon.len() > 1 && polygon.first() == polygon.last() {
+        polygon.pop();
+    }
+    if polygon.len() >= 3 {
+        img.draw_polygon_mut(&polygon, style.color.into());
+    }
+
+    for (label, (x, y)) in labels.iter().zip(radar_points(
+        center,
+        radius + label_size as f32 / 2.0,
+        &vec![1.0; n],
+    )) {
+        let half = label_size as f32 / 2.0;
+        img.draw_text_center_supersampled_mut(
+            style.label_color.into(),
+            Rect::at((x - half * 2.0) as i32, (y - half) as i32)
+                .of_size(label_size * 2, label_size),
+            style.label_scale,
+            &ctx.font,
+            label,
+            ctx.text_quality.factor(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::DynamicImage;
+
+    #[test]
+    fn test_parse_chart_value() {
+        assert_eq!(parse_chart_value(" 3.5 "), 3.5);
+        assert_eq!(parse_chart_value("优良"), 4.0);
+        assert_eq!(parse_chart_value("■■"), 0.0);
+    }
+
+    #[test]
+    fn test_draw_charts() {
+        let font = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        ))
+        .unwrap();
+        let ctx = RenderContext::new(ab_glyph::FontArc::try_from_vec(font).unwrap());
+        let style = ChartStyle {
+            max: 5.0,
+            color: Color([255, 0, 0]),
+            axis_color: Some(Color([0, 0, 0])),
+            label_scale: 20.0,
+            label_color: Color([0, 0, 0]),
+        };
+        let labels: Vec<String> = ["phy", "flex", "tolerance"].map(String::from).to_vec();
+        let rect = Rect::at(0, 0).of_size(200, 120);
+
+        let mut img = DynamicImage::new_rgba8(200, 120);
+        draw_bar_chart(&mut img, rect, &labels, &[5.0, 2.5, 0.0], &style, &ctx);
+        let img = img.into_rgba8();
+        // 第一行的柱子填满右侧 70% 的宽度
+        assert_eq!(img.get_pixel(199, 20).0, [255, 0, 0, 255]);
+        assert_eq!(img.get_pixel(199, 60).0, [0, 0, 0, 0]);
+
+        // 全为 0 的雷达图退化为一个点，不能 panic
+        let mut img = DynamicImage::new_rgba8(200, 120);
+        draw_radar_chart(&mut img, rect, &labels, &[0.0; 3], &style, &ctx);
+        draw_radar_chart(&mut img, rect, &labels, &[5.0, 3.0, 1.0], &style, &ctx);
+        assert_eq!(img.into_rgba8().get_pixel(100, 55).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_radar_points() {
+        let points = radar_points((50.0, 50.0), 10.0, &[1.0, 1.0, 1.0, 1.0]);
+        let round = |(x, y): (f32, f32)| (x.round(), y.round());
+        assert_eq!(round(points[0]), (50.0, 40.0));
+        assert_eq!(round(points[1]), (60.0, 50.0));
+        assert_eq!(round(points[2]), (50.0, 60.0));
+        assert_eq!(round(points[3]), (40.0, 50.0));
+    }
+}
@@ -0,0 +1,158 @@
+use super::{scroll::Keyframe, slide::Slide};
+use crate::Result;
+use std::ops::Range;
+
+/// 组合成一张长条图、生成一段视频的若干 slide。
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub slides: Vec<Slide>,
+    /// 长条图向左滚动的像素数。
+    ///
+    /// 除最后一个 chunk 外，滚动结束时屏幕上显示的内容与下一个 chunk 的开头相同，
+    /// 因此各段视频可以无缝拼接。
+    pub distance: u32,
+    /// 第一个 slide 的左边缘在整个视频中的 x 坐标
+    pub offset: u32,
+    /// 第一个 slide 在所有 slide 中的序号
+    pub first_slide: usize,
+    /// 固定的滚动关键帧，例如与背景音乐的节拍对齐（见 [`beat`](super::beat)），
+    /// 设置时不再按滚动速度和停顿计算
+    pub keyframes: Option<Vec<Keyframe>>,
+}
+
+/// 按像素宽度把 slide 分成若干 chunk。
+///
+/// 每个 chunk 的长条图宽度不超过 `max_width`（单个 slide 比它还宽时除外），
+/// 下一个 chunk 从滚动结束时屏幕左边缘所在的 slide 开始。
+///
+/// # Parameters
+/// - `widths`: 每个 slide 的宽度。
+/// - `max_width`: 长条图的最大宽度。
+/// - `screen_width`: 屏幕宽度。
+///
+/// # Results
+/// 每个 chunk 的 slide 范围和滚动距离。
+///
+/// # Errors
+/// - 所有 slide 的总宽度小于屏幕宽度时返回 `Err`。
+///
+pub fn chunk_ranges(
+    widths: &[u32],
+    max_width: u32,
+    screen_width: u32,
+) -> Result<Vec<(Range<usize>, u32)>> {
+    // offsets[i] 为第 i 个 slide 左边缘的 x 坐标，最后一项为总宽度
+    let offsets: Vec<u32> = std::iter::once(0)
+        .chain(widths.iter().scan(0, |x, w| {
+            *x += w;
+            Some(*x)
+        }))
+        .collect();
+    let total = offsets[widths.len()];
+    if total < screen_width {
+        return Err("slides data is shorter than screen".into());
+    }
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    loop {
+        if total - offsets[start] <= max_width {
+            ranges.push((start..widths.len(), total - offsets[start] - screen_width));
+            return Ok(ranges);
+        }
+        // 下一个 chunk 的起点：在不超过 max_width 的前提下尽量靠后
+        let next = (start + 1..widths.len())
+            .take_while(|&j| offsets[j] - offsets[start] + screen_width <= max_width)
+            .last()
+            .unwrap_or(start + 1);
+        let end_x = offsets[next] + screen_width;
+        if end_x >= total {
+            // 超宽的 slide 导致下一个 chunk 只剩最后一屏，直接滚动到结尾
+            ranges.push((start..widths.len(), total - offsets[start] - screen_width));
+            return Ok(ranges);
+        }
+        let end = (next..=widths.len())
+            .find(|&e| offsets[e] >= end_x)
+            .unwrap_or(widths.len());
+        ranges.push((start..end, offsets[next] - offsets[start]));
+        start = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::chunk_ranges;
+    use std::ops::Range;
+
+    /// 检查分块覆盖所有 slide，且相邻 chunk 的衔接处内容相同
+    fn assert_seamless(widths: &[u32], ranges: &[(Range<usize>, u32)], screen_width: u32) {
+        let x = |i: usize| widths[..i].iter().sum::<u32>();
+        assert_eq!(ranges.first().unwrap().0.start, 0);
+        assert_eq!(ranges.last().unwrap().0.end, widths.len());
+        for pair in ranges.windows(2) {
+            let ((prev, distance), (next, _)) = (&pair[0], &pair[1]);
+            assert!(next.start > prev.start, "{ranges:?}");
+            // 上一段滚动结束时屏幕左边缘正好是下一段的第一个 slide
+            assert_eq!(x(prev.start) + distance, x(next.start), "{ranges:?}");
+            assert!(x(prev.end) >= x(next.start) + screen_width, "{ranges:?}");
+        }
+        let (last, distance) = ranges.last().unwrap();
+        assert_eq!(x(last.start) + distance + screen_width, x(widths.len()));
+    }
+
+    #[test]
+    fn test_every_length_is_covered() {
+        for len in 4..=80 {
+            let widths = vec![480; len];
+            let ranges = chunk_ranges(&widths, 20 * 480, 1920).unwrap();
+            assert_seamless(&widths, &ranges, 1920);
+            assert!(ranges.iter().all(|(r, _)| r.len() <= 20), "{ranges:?}");
+            // 只有相邻 chunk 之间的 4 个 slide 重叠
+            for pair in ranges.windows(2) {
+                assert_eq!(pair[0].0.end - pair[1].0.start, 4, "{ranges:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_slide_wider_than_strip() {
+        let widths = [480, 480, 480, 480, 20000, 480, 480, 480, 480];
+        let ranges = chunk_ranges(&widths, 20 * 480, 1920).unwrap();
+        assert_eq!(ranges, [(0..5, 1920), (4..9, 20000)]);
+        assert_seamless(&widths, &ranges, 1920);
+    }
+
+    #[test]
+    fn test_uniform_widths() {
+        // 与按 slide 数量分块的结果相同：step = 20，overlap = 4
+        let ranges = chunk_ranges(&[480; 36], 20 * 480, 1920).unwrap();
+        assert_eq!(ranges, [(0..20, 16 * 480), (16..36, 16 * 480)]);
+
+        let ranges = chunk_ranges(&[480; 37], 20 * 480, 1920).unwrap();
+        assert_eq!(
+            ranges,
+            [(0..20, 16 * 480), (16..36, 16 * 480), (32..37, 480)]
+        );
+
+        let ranges = chunk_ranges(&[480; 4], 20 * 480, 1920).unwrap();
+        assert_eq!(ranges, [(0..4, 0)]);
+    }
+
+    #[test]
+    fn test_variable_widths() {
+        let widths = [480, 960, 480, 640, 320, 480, 960, 480];
+        let ranges = chunk_ranges(&widths, 3200, 1920).unwrap();
+        // 第一个 chunk 滚动到 slide 1 的左边缘（480），长条图需要覆盖到 480 + 1920
+        assert_eq!(
+            ranges,
+            [(0..4, 480), (1..6, 960), (2..8, 1120), (4..8, 320)]
+        );
+        assert_seamless(&widths, &ranges, 1920);
+    }
+
+    #[test]
+    fn test_too_short() {
+        assert!(chunk_ranges(&[480, 480], 9600, 1920).is_err());
+        assert!(chunk_ranges(&[], 9600, 1920).is_err());
+    }
+}
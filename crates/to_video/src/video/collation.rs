@@ -0,0 +1,150 @@
+//! 文字的排序规则。
+//!
+//! 默认按 Unicode 码位比较，中文名称的顺序没有意义。[`Collation::Pinyin`] 按拼音排序中文，
+//! 与同一字母开头的拉丁字母排在一起，例如 “Amiya” 和 “阿米娅” 都排在 “B” 之前。
+//!
+//! 拼音顺序来自 `pinyin.txt`：每行以拼音的首字母开头，之后的汉字按拼音（含声调）排列，
+//! 覆盖 CJK 统一汉字基本区（U+4E00 ~ U+9FFF），由 Perl `Unicode::Collate::CJK::Pinyin` 的数据生成。
+
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashMap, sync::OnceLock};
+
+/// 文字的排序规则
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Collation {
+    /// 按 Unicode 码位比较
+    #[default]
+    Codepoint,
+    /// 拉丁字母不区分大小写并忽略常见的重音符号，例如 “é” 与 “e” 相同，数字排在字母之前
+    Folded,
+    /// 在 `Folded` 的基础上，汉字按拼音排序
+    Pinyin,
+}
+
+impl Collation {
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        if self == Collation::Codepoint {
+            return a.cmp(b);
+        }
+        let key = |s: &str| s.chars().map(|c| self.weight(c)).collect::<Vec<_>>();
+        // 权重相同时（例如只有大小写不同）按码位比较，保证顺序确定
+        key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+    }
+
+    /// 字符的排序权重：标点和空白 < 数字 < 字母（同一字母的汉字排在拉丁字母之后）< 其他字符
+    fn weight(self, c: char) -> (u32, u32) {
+        const LETTERS: u32 = 0x100;
+        const OTHERS: u32 = 0x1000;
+        if c.is_ascii_digit() {
+            return (u32::from(c), 0);
+        }
+        if let Some(letter) = fold_latin(c) {
+            return (LETTERS + (letter as u32 - 'a' as u32) * 2, 0);
+        }
+        if c.is_ascii() {
+            return (0, u32::from(c));
+        }
+        if self == Collation::Pinyin
+            && let Some(&(letter, rank)) = pinyin_table().get(&c)
+        {
+            return (LETTERS + (letter as u32 - 'a' as u32) * 2 + 1, rank);
+        }
+        (OTHERS, u32::from(c))
+    }
+}
+
+/// 拉丁字母转为不带重音的小写字母，不是拉丁字母时为 `None`
+fn fold_latin(c: char) -> Option<char> {
+    let folded = match c {
+        'a'..='z' => c,
+        'A'..='Z' => c.to_ascii_lowercase(),
+        'À'..='Å' | 'à'..='å' | 'ā' | 'Ā' => 'a',
+        'Ç' | 'ç' => 'c',
+        'È'..='Ë' | 'è'..='ë' | 'ē' | 'Ē' => 'e',
+        'Ì'..='Ï' | 'ì'..='ï' | 'ī' | 'Ī' => 'i',
+        'Ñ' | 'ñ' => 'n',
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' | 'ō' | 'Ō' => 'o',
+        'ß' => 's',
+        'Ù'..='Ü' | 'ù'..='ü' | 'ū' | 'Ū' => 'u',
+        'Ý' | 'ý' | 'ÿ' => 'y',
+        _ => return None,
+    };
+    Some(folded)
+}
+
+/// 汉字 → (拼音首字母, 拼音顺序)
+fn pinyin_table() -> &'static HashMap<char, (char, u32)> {
+    static TABLE: OnceLock<HashMap<char, (char, u32)>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = HashMap::new();
+        let mut rank = 0;
+        for line in include_str!("pinyin.txt").lines() {
+            let mut chars = line.chars();
+            let Some(letter) = chars.next() else {
+                continue;
+            };
+            for c in chars.skip(1) {
+                table.insert(c, (letter, rank));
+                rank += 1;
+            }
+        }
+        table
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(collation: Collation, names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(ToString::to_string).collect();
+        names.sort_by(|a, b| collation.compare(a, b));
+        names
+    }
+
+    #[test]
+    fn test_pinyin() {
+        let names = [
+            "陈",
+            "张三",
+            "Bob",
+            "阿米娅",
+            "李四",
+            "Amiya",
+            "王五",
+            "赵六",
+        ];
+        assert_eq!(
+            sorted(Collation::Pinyin, &names),
+            [
+                "Amiya",
+                "阿米娅",
+                "Bob",
+                "陈",
+                "李四",
+                "王五",
+                "张三",
+                "赵六"
+            ]
+        );
+        // 码位顺序与拼音无关
+        assert_ne!(
+            sorted(Collation::Codepoint, &names),
+            sorted(Collation::Pinyin, &names)
+        );
+    }
+
+    #[test]
+    fn test_folded() {
+        assert_eq!(
+            sorted(Collation::Folded, &["b", "Émile", "a2", "A1", "eve", "zed"]),
+            ["A1", "a2", "b", "Émile", "eve", "zed"]
+        );
+        assert_eq!(Collation::Folded.compare("Abc", "abc"), Ordering::Less);
+        assert_eq!(
+            pinyin_table().get(&'阿').map(|&(letter, _)| letter),
+            Some('a')
+        );
+    }
+}
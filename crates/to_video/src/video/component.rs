@@ -0,0 +1,203 @@
+//! 可复用的组件：定义一次的一组操作，在每个 slide 中以不同的位置和参数多次使用
+
+use super::slide::{Operation, Position};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// 一组位置相对于组件左上角的操作，例如由底色、头像和名字组成的卡片。
+///
+/// `operations` 中值为 `"$参数名"` 的字符串在实例化时替换为参数的值，
+/// 参数可以出现在任何字段中，例如颜色、字号或位置。
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct Component {
+    /// 参数的默认值，实例中没有给出的参数使用默认值
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub params: Map<String, Value>,
+    /// 组件中的操作，格式与 [`Operation`] 相同
+    pub operations: Vec<Value>,
+}
+
+/// 组件左上角在 slide 中的位置，与组件中操作的 [`Position`] 使用相同的单位
+#[derive(Clone, Debug, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum Offset {
+    Absolute { left: i32, top: i32 },
+    Relative { rel_left: f32, rel_top: f32 },
+}
+
+/// 组件的一次使用
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ComponentInstance {
+    /// 组件名称
+    pub component: String,
+    pub offset: Offset,
+    /// 加到组件中每个操作的 `z_index` 上
+    #[serde(default)]
+    pub z_index: u8,
+    /// 覆盖组件的参数
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub params: Map<String, Value>,
+}
+
+impl Component {
+    /// 按 `instance` 的参数、位置和层级生成操作
+    ///
+    /// # Errors
+    /// - 使用了没有值的参数，或替换后的操作无法解析时返回 `Err`。
+    /// - 操作的位置与 `instance.offset` 的单位不同（一个为像素，一个为比例）时返回 `Err`。
+    ///
+    pub fn instantiate(&self, instance: &ComponentInstance) -> Result<Vec<Operation>> {
+        let name = &instance.component;
+        self.operations
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| {
+                let value = substitute(operation, &instance.params, &self.params)
+                    .map_err(|e| format!("component {name:?} operation {index}: {e}"))?;
+                let mut operation: Operation = serde_json::from_value(value)
+                    .map_err(|e| format!("component {name:?} operation {index}: {e}"))?;
+                let pos = operation.pos_mut();
+                *pos = translate(pos, &instance.offset)
+                    .ok_or_else(|| format!("component {name:?} operation {index}: position and offset use different units"))?;
+                let z_index = operation.z_index_mut();
+                *z_index = z_index.saturating_add(instance.z_index);
+                Ok(operation)
+            })
+            .collect()
+    }
+}
+
+/// 依次实例化 `instances`，返回所有生成的操作
+///
+/// # Errors
+/// - 实例使用了不存在的组件，或实例化失败时返回 `Err`，见 [`Component::instantiate`]。
+///
+pub fn expand_components(
+    components: &HashMap<String, Component>,
+    instances: &[ComponentInstance],
+) -> Result<Vec<Operation>> {
+    let mut operations = Vec::new();
+    for instance in instances {
+        let component = components
+            .get(&instance.component)
+            .ok_or_else(|| format!("unknown component {:?}", instance.component))?;
+        operations.extend(component.instantiate(instance)?);
+    }
+    Ok(operations)
+}
+
+/// 把值为 `"$参数名"` 的字符串替换为参数的值，`params` 优先于 `defaults`
+fn substitute(
+    value: &Value,
+    params: &Map<String, Value>,
+    defaults: &Map<String, Value>,
+) -> Result<Value> {
+    Ok(match value {
+        Value::String(s) => match s.strip_prefix('$') {
+            Some(param) => params
+                .get(param)
+                .or_else(|| defaults.get(param))
+                .cloned()
+                .ok_or_else(|| format!("missing parameter {param:?}"))?,
+            None => value.clone(),
+        },
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| substitute(value, params, defaults))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| Ok((key.clone(), substitute(value, params, defaults)?)))
+                .collect::<Result<_>>()?,
+        ),
+        _ => value.clone(),
+    })
+}
+
+/// 把组件中的位置平移到 `offset`，单位不同时为 `None`
+fn translate(pos: &Position, offset: &Offset) -> Option<Position> {
+    match (*pos, *offset) {
+        (Position::Absolute { left, top, height }, Offset::Absolute { left: x, top: y }) => {
+            Some(Position::new(left + x, top + y, height))
+        }
+        (
+            Position::Relative {
+                rel_left,
+                rel_top,
+                rel_height,
+            },
+            Offset::Relative {
+                rel_left: x,
+                rel_top: y,
+            },
+        ) => Some(Position::relative(rel_left + x, rel_top + y, rel_height)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use serde_json::json;
+
+    fn card() -> Component {
+        serde_json::from_value(json!({
+            "params": { "color": [255, 255, 255] },
+            "operations": [
+                { "Color": { "color": "$color", "pos": { "left": 0, "top": 0, "height": 200 }, "z_index": 0 } },
+                { "Text": { "scale": "$scale", "color": [0, 0, 0], "pos": { "left": 20, "top": 40, "height": 100 }, "z_index": 1 } }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let components = HashMap::from([("card".to_string(), card())]);
+        let instances: Vec<ComponentInstance> = serde_json::from_value(json!([
+            { "component": "card", "offset": { "left": 100, "top": 50 }, "params": { "scale": 60.0 } },
+            { "component": "card", "offset": { "left": 100, "top": 300 }, "z_index": 10,
+              "params": { "scale": 40.0, "color": [255, 0, 0] } }
+        ]))
+        .unwrap();
+        let operations = expand_components(&components, &instances).unwrap();
+        assert_eq!(operations.len(), 4);
+
+        assert_eq!(*operations[1].pos(), Position::new(120, 90, 100));
+        assert_eq!(*operations[3].pos(), Position::new(120, 340, 100));
+        assert_eq!(operations[3].z_index(), 11);
+        let Operation::Color { color, .. } = &operations[0] else {
+            panic!("expected Color");
+        };
+        assert_eq!(*color, Color([255, 255, 255]));
+        let Operation::Color { color, .. } = &operations[2] else {
+            panic!("expected Color");
+        };
+        assert_eq!(*color, Color([255, 0, 0]));
+        let Operation::Text { scale, .. } = &operations[3] else {
+            panic!("expected Text");
+        };
+        assert_eq!(*scale, 40.0);
+    }
+
+    #[test]
+    fn test_instantiate_errors() {
+        let components = HashMap::from([("card".to_string(), card())]);
+        let instance = |value| serde_json::from_value::<ComponentInstance>(value).unwrap();
+        // 缺少参数
+        let missing = instance(json!({ "component": "card", "offset": { "left": 0, "top": 0 } }));
+        assert!(expand_components(&components, &[missing]).is_err());
+        // 单位不同
+        let relative = instance(json!({
+            "component": "card", "offset": { "rel_left": 0.1, "rel_top": 0.1 }, "params": { "scale": 60.0 }
+        }));
+        assert!(expand_components(&components, &[relative]).is_err());
+        let unknown = instance(json!({ "component": "badge", "offset": { "left": 0, "top": 0 } }));
+        assert!(expand_components(&components, &[unknown]).is_err());
+    }
+}
@@ -1,25 +1,157 @@
-use super::ffmpeg::MotionType;
+use super::{
+    beat::{BeatSync, Beats},
+    ffmpeg::{CoverEntry, EndingMode, IntermediateFormat, MotionType, parse_back_color},
+    group::GroupBy,
+    narration::CommandNarrator,
+    orientation::Orientation,
+    progress::ProgressIndicator,
+    ranking::Ranking,
+    redact::Redaction,
+    row::RowConfig,
+    safe_area::SafeArea,
+    scroll::ChunkOverride,
+    slide::{Compositor, TextContrast, TextOverflow, TextQuality},
+    slideshow::Presentation,
+    sound::SoundEffect,
+    timeline::Timeline,
+    versus::Versus,
+};
 use crate::{Result, color::Color};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{collections::HashMap, fmt, path::PathBuf};
 
+/// 序列化的结果是增量生成的缓存键的一部分（见 [`cache`](super::cache)），
+/// 只有不影响片段视频的字段标记为 `skip`
+#[derive(Serialize)]
 pub struct VideoConfig {
     pub(super) encoder: String,
     pub(super) screen: (u32, u32),
     pub(super) fps: u32,
+    #[serde(skip)]
     pub(super) work_dir: PathBuf,
     pub(super) back_color: String,
     pub(super) cover_sec: f32,
+    pub(super) cover_entry: CoverEntry,
     pub(super) motion_type: MotionType,
     pub(super) ending_sec: u32,
+    pub(super) ending: EndingMode,
     pub(super) swip_pixels_per_sec: u32,
     pub(super) width_slides: u32,
+    #[serde(skip)]
     pub(super) save_path: PathBuf,
     pub(super) step: u32,
+    pub(super) tile_width: Option<u32>,
+    pub(super) rgb8: bool,
+    pub(super) intermediate_format: IntermediateFormat,
+    pub(super) debug_overlay: bool,
     pub(super) overlap: u32,
-    pub(super) font: PathBuf,
+    /// `None` 时使用内置字体
+    pub(super) font: Option<PathBuf>,
+    pub(super) fallback_fonts: Vec<PathBuf>,
     pub(super) split_line_color: Option<Color>,
+    pub(super) split_line_width: u32,
+    pub(super) text_quality: TextQuality,
+    pub(super) compositor: Compositor,
+    pub(super) min_text_scale: f32,
+    pub(super) text_overflow: TextOverflow,
+    pub(super) text_contrast: TextContrast,
+    #[serde(skip)]
     pub(super) clean_temp: bool,
+    pub(super) ffmpeg_path: PathBuf,
+    pub(super) chunk_overrides: Vec<ChunkOverride>,
+    pub(super) group_by: Option<GroupBy>,
+    pub(super) timeline: Option<Timeline>,
+    pub(super) ranking: Option<Ranking>,
+    pub(super) versus: Option<Versus>,
+    pub(super) redact: Vec<Redaction>,
+    #[serde(skip)]
+    pub(super) metadata: HashMap<String, String>,
+    pub(super) preset: String,
+    /// 预览模式的缩放比例，见 [`VideoBuilder::build_preview`](super::VideoBuilder::build_preview)
+    pub(super) preview_scale: Option<f32>,
+    #[serde(skip)]
+    pub(super) debug_commands: bool,
+    #[serde(skip)]
+    pub(super) print_commands: bool,
+    #[serde(skip)]
+    pub(super) incremental: bool,
+    pub(super) deterministic: bool,
+    pub(super) auto_palette: bool,
+    pub(super) safe_area: Option<SafeArea>,
+    #[serde(skip)]
+    pub(super) renditions: Vec<(u32, u32)>,
+    pub(super) orientation: Orientation,
+    pub(super) presentation: Presentation,
+    #[serde(skip)]
+    pub(super) boundary_sound: Option<SoundEffect>,
+    pub(super) narration: Option<CommandNarrator>,
+    pub(super) beat_sync: Option<BeatSync>,
+    #[serde(skip)]
+    pub(super) progress: Option<ProgressIndicator>,
+    pub(super) rows: Vec<RowConfig>,
+}
+
+/// 配置中的一个问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigIssue {
+    /// 有问题的配置项，例如 `width_slides`
+    pub field: &'static str,
+    pub message: String,
+    /// 建议的修改方式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl ConfigIssue {
+    fn new(field: &'static str, message: impl Into<String>, suggestion: Option<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+            suggestion,
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({suggestion})")?;
+        }
+        Ok(())
+    }
+}
+
+/// [`VideoConfigBuilder::build`] 发现的所有问题，可以通过 `downcast_ref` 从错误中取出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssues(pub Vec<ConfigIssue>);
+
+impl fmt::Display for ConfigIssues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0[..] {
+            [issue] => write!(f, "{issue}"),
+            issues => {
+                write!(f, "{} config issues:", issues.len())?;
+                for issue in issues {
+                    write!(f, "\n- {}: {issue}", issue.field)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigIssues {}
+
+/// 与 `value` 最接近的 `n` 的约数，作为 `width_slides` 的建议值
+fn nearest_divisors(n: u32, value: u32) -> Option<String> {
+    let below = (1..=value.min(n)).rev().find(|d| n.is_multiple_of(*d));
+    let above = (value.max(1)..=n).find(|d| n.is_multiple_of(*d));
+    match (below, above) {
+        (Some(below), Some(above)) if below != above => Some(format!("try {below} or {above}")),
+        (Some(d), _) | (_, Some(d)) => Some(format!("try {d}")),
+        (None, None) => None,
+    }
 }
 
 impl VideoConfig {
@@ -40,15 +172,131 @@ pub struct VideoConfigBuilder {
     pub work_dir: Option<PathBuf>,
     pub back_color: String,
     pub cover_sec: f32,
+    /// 封面各列的入场方向和时间，默认从下方依次升起
+    #[serde(default)]
+    pub cover_entry: CoverEntry,
     pub motion_type: MotionType,
     pub ending_sec: u32,
+    /// 结尾效果：停留、淡出到指定颜色或淡出到背景色以便循环播放
+    #[serde(default)]
+    pub ending: EndingMode,
     pub swip_pixels_per_sec: u32,
     pub width_slides: u32,
     pub save_path: Option<PathBuf>,
     pub step: u32,
+    /// 把每个 chunk 的长条图按不超过此宽度（像素）分段保存，由 ffmpeg 叠加成完整画面，
+    /// 降低长 chunk 的内存占用。单个 slide 比它还宽时单独成段。`None` 时保存为一张图片
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile_width: Option<u32>,
+    /// 长条图和封面图片先用 `back_color` 填充透明部分，保存为不带透明通道的 RGB8 图片，
+    /// 减少内存占用和 PNG 编码时间。要求 `back_color` 为不透明的颜色
+    #[serde(default)]
+    pub rgb8: bool,
+    /// chunk 长条图交给 ffmpeg 之前的保存格式，见 [`IntermediateFormat`]
+    #[serde(default)]
+    pub intermediate_format: IntermediateFormat,
+    /// 在 chunk 长条图上绘制 slide 序号、slide 和元素的边框，以及与相邻 chunk 重叠部分的边界，
+    /// 用于排查拼接处的画面跳动
+    #[serde(default)]
+    pub debug_overlay: bool,
+    /// 主字体，`None` 时使用 `bundled-font` feature 内置的字体
     pub font: Option<PathBuf>,
+    /// 后备字体，`font` 中没有的字符（如 emoji）依次从这些字体中查找
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_fonts: Vec<PathBuf>,
     pub split_line_color: Option<Color>,
+    #[serde(default = "default_split_line_width")]
+    pub split_line_width: u32,
+    #[serde(default)]
+    pub text_quality: TextQuality,
+    /// slide 的合成方式，见 [`Compositor`]
+    #[serde(default)]
+    pub compositor: Compositor,
+    /// 文字自动缩小的下限（像素），0 表示不限制，见 [`Video::validate`](super::Video::validate)
+    #[serde(default)]
+    pub min_text_scale: f32,
+    /// 文字需要缩小到 `min_text_scale` 以下时的处理方式
+    #[serde(default)]
+    pub text_overflow: TextOverflow,
+    /// 文字与下方色块对比度不足时的处理方式
+    #[serde(default)]
+    pub text_contrast: TextContrast,
     pub clean_temp: bool,
+    /// ffmpeg 可执行文件，`None` 时使用 PATH 中的 `ffmpeg`
+    #[serde(default)]
+    pub ffmpeg_path: Option<PathBuf>,
+    /// 单独设置某些 chunk 的滚动速度和停顿
+    #[serde(default)]
+    pub chunk_overrides: Vec<ChunkOverride>,
+    /// 按数据列分组并插入分组标题 slide
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<GroupBy>,
+    /// 按日期列排序并分段，插入时间段分隔 slide，见 [`Timeline`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<Timeline>,
+    /// 按分数列排序并给 slide 加上名次，见 [`Ranking`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ranking: Option<Ranking>,
+    /// 每行数据的两个对象左右并排显示在一个 slide 中，见 [`Versus`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub versus: Option<Versus>,
+    /// 生成 slide 之前对这些数据列脱敏，见 [`Redaction`]。
+    /// 在排行榜排序之后、分组和生成 slide 之前处理，分组标题和行使用脱敏后的数据
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact: Vec<Redaction>,
+    /// 写入输出文件的元数据，例如 `title`、`artist`、`comment`、`creation_time`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// 编码预设，例如 `ultrafast`、`fast`、`slow`
+    #[serde(default = "default_preset")]
+    pub preset: String,
+    /// 把每条 ffmpeg 命令及其工作目录写入 `work_dir/commands.log`
+    #[serde(default)]
+    pub debug_commands: bool,
+    /// 只打印 ffmpeg 命令而不执行，临时文件会被保留以便手动重新执行
+    #[serde(default)]
+    pub print_commands: bool,
+    /// 缓存每段视频，再次运行时只重新生成输入发生变化的片段，缓存保存在 `work_dir/cache`
+    #[serde(default)]
+    pub incremental: bool,
+    /// 相同的输入在任何机器上都生成逐字节相同的视频：ffmpeg 使用 bitexact 输出并固定编码线程数，
+    /// slide 总是在 CPU 上合成
+    #[serde(default)]
+    pub deterministic: bool,
+    /// 按每个 slide 中第一张图片的主要颜色依次设置色块的颜色，见 [`RenderContext::auto_palette`](super::slide::RenderContext::auto_palette)
+    #[serde(default)]
+    pub auto_palette: bool,
+    /// 画面上下边缘可能被裁掉的范围，超出的元素列在报告中，`debug_overlay` 时标出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safe_area: Option<SafeArea>,
+    /// 额外输出的其他分辨率，例如 `[[1280, 720], [1080, 1920]]`。
+    ///
+    /// 由 `save_path` 的视频缩放得到，保存为 `<文件名>_<宽>x<高>.<扩展名>`，
+    /// 画面比例不同时保持比例缩放并用 `back_color` 补齐（letterbox）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renditions: Vec<(u32, u32)>,
+    /// 画面方向。`Vertical` 时 slide 是占满 `screen` 宽度的横条，向上滚动，
+    /// `width_slides` 为横条的高度，见 [`Orientation`]
+    #[serde(default)]
+    pub orientation: Orientation,
+    /// 滚动或幻灯片，见 [`Presentation`]
+    #[serde(default)]
+    pub presentation: Presentation,
+    /// 在各段视频的衔接处混入的音效，幻灯片模式中即每次翻页时，见 [`SoundEffect`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boundary_sound: Option<SoundEffect>,
+    /// 为每个 slide 的文字生成旁白的命令，见 [`CommandNarrator`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narration: Option<CommandNarrator>,
+    /// 与背景音乐的节拍同步滚动，并把音乐混入视频，见 [`BeatSync`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beat_sync: Option<BeatSync>,
+    /// 叠加在视频上的进度条或计数，见 [`ProgressIndicator`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<ProgressIndicator>,
+    /// 叠加在主长条图上的其他行
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<RowConfig>,
 }
 
 impl VideoConfigBuilder {
@@ -60,37 +308,474 @@ impl VideoConfigBuilder {
             work_dir: None,
             back_color: "white".to_string(),
             cover_sec: 10.0,
+            cover_entry: CoverEntry::default(),
             motion_type: MotionType::EaseInOut,
             ending_sec: 4,
+            ending: EndingMode::HoldLastFrame,
             swip_pixels_per_sec: 160,
             width_slides: 480,
             save_path: None,
             step: 20,
+            tile_width: None,
+            rgb8: false,
+            intermediate_format: IntermediateFormat::Png,
+            debug_overlay: false,
             font: None,
+            fallback_fonts: Vec::new(),
             split_line_color: Some(Color([255, 255, 255])),
+            split_line_width: default_split_line_width(),
+            text_quality: TextQuality::Normal,
+            compositor: Compositor::Cpu,
+            min_text_scale: 0.0,
+            text_overflow: TextOverflow::Shrink,
+            text_contrast: TextContrast::Warn,
             clean_temp: true,
+            ffmpeg_path: None,
+            chunk_overrides: Vec::new(),
+            group_by: None,
+            timeline: None,
+            ranking: None,
+            versus: None,
+            redact: Vec::new(),
+            metadata: HashMap::new(),
+            preset: default_preset(),
+            debug_commands: false,
+            print_commands: false,
+            incremental: false,
+            deterministic: false,
+            auto_palette: false,
+            safe_area: None,
+            renditions: Vec::new(),
+            orientation: Orientation::Horizontal,
+            presentation: Presentation::Scroll,
+            boundary_sound: None,
+            narration: None,
+            beat_sync: None,
+            progress: None,
+            rows: Vec::new(),
         }
     }
 
-    pub fn build(self) -> Result<VideoConfig> {
-        if self.screen.0 % self.width_slides != 0 {
-            return Err(format!(
-                "width_screen % width_slides != 0; {} % {} != 0",
-                self.screen.0, self.width_slides
-            )
-            .into());
+    /// 检查配置中的所有问题，不修改文件系统
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let (screen_width, screen_height) = self.screen;
+
+        if screen_width == 0 || screen_height == 0 {
+            issues.push(ConfigIssue::new(
+                "screen",
+                format!("screen size must not be zero, got {screen_width}x{screen_height}"),
+                Some("use a size such as [1920, 1080]".to_string()),
+            ));
+        }
+        if let Some(safe) = self.safe_area
+            && u64::from(safe.top) + u64::from(safe.bottom) >= u64::from(screen_height)
+        {
+            issues.push(ConfigIssue::new(
+                "safe_area",
+                format!(
+                    "safe_area margins {} + {} leave no room in screen height {screen_height}",
+                    safe.top, safe.bottom
+                ),
+                Some(format!("use e.g. 5% of the height: {}", screen_height / 20)),
+            ));
+        }
+        if self.orientation == Orientation::Vertical {
+            // 这些功能按横屏的画面坐标定位，竖屏时不适用
+            let unsupported = [
+                ("rows", !self.rows.is_empty()),
+                ("safe_area", self.safe_area.is_some()),
+                ("debug_overlay", self.debug_overlay),
+            ];
+            for (field, _) in unsupported.into_iter().filter(|(_, used)| *used) {
+                issues.push(ConfigIssue::new(
+                    field,
+                    format!("{field} is not supported with vertical orientation"),
+                    Some(format!("remove {field} or use horizontal orientation")),
+                ));
+            }
+        }
+        if let Presentation::Slideshow {
+            sec_per_slide,
+            per_screen,
+            transition_sec,
+            ..
+        } = self.presentation
+        {
+            if sec_per_slide.is_nan() || sec_per_slide <= 0.0 {
+                issues.push(ConfigIssue::new(
+                    "presentation",
+                    format!("sec_per_slide must be greater than 0, got {sec_per_slide}"),
+                    Some("use e.g. 3".to_string()),
+                ));
+            } else if !(0.0..sec_per_slide).contains(&transition_sec) {
+                issues.push(ConfigIssue::new(
+                    "presentation",
+                    format!(
+                        "transition_sec {transition_sec} must be shorter than sec_per_slide {sec_per_slide}"
+                    ),
+                    Some(format!("use e.g. {}", sec_per_slide / 4.0)),
+                ));
+            }
+            if per_screen == 0 || per_screen > screen_width {
+                issues.push(ConfigIssue::new(
+                    "presentation",
+                    format!("per_screen must be between 1 and the screen width, got {per_screen}"),
+                    Some("use 1 to show one slide at a time".to_string()),
+                ));
+            }
+            // 幻灯片模式不滚动，按滚动位置叠加的行和竖屏的旋转都不适用
+            let unsupported = [
+                ("rows", !self.rows.is_empty(), "remove rows"),
+                (
+                    "orientation",
+                    self.orientation == Orientation::Vertical,
+                    "use a portrait screen with horizontal orientation",
+                ),
+            ];
+            for (field, _, suggestion) in unsupported.into_iter().filter(|(_, used, _)| *used) {
+                issues.push(ConfigIssue::new(
+                    field,
+                    format!("{field} is not supported in slideshow presentation"),
+                    Some(suggestion.to_string()),
+                ));
+            }
+        }
+        if self.timeline.is_some() && self.group_by.is_some() {
+            issues.push(ConfigIssue::new(
+                "timeline",
+                "timeline can not be used together with group_by",
+                Some("remove group_by, timeline already inserts separator slides".to_string()),
+            ));
+        }
+        if let Some(ranking) = &self.ranking {
+            if self.group_by.is_some() || self.timeline.is_some() {
+                issues.push(ConfigIssue::new(
+                    "ranking",
+                    "ranking can not be used together with group_by or timeline",
+                    Some(
+                        "remove group_by and timeline, ranking orders the rows by score"
+                            .to_string(),
+                    ),
+                ));
+            }
+            if ranking
+                .score
+                .as_ref()
+                .is_some_and(|score| score.count_up && score.steps < 2)
+            {
+                issues.push(ConfigIssue::new(
+                    "ranking",
+                    "ranking: score.steps must be at least 2",
+                    Some("use e.g. 12".to_string()),
+                ));
+            }
+        }
+        if let Some(sound) = &self.boundary_sound
+            && !sound.path.exists()
+        {
+            issues.push(ConfigIssue::new(
+                "boundary_sound",
+                format!("boundary_sound does not exist: {}", sound.path.display()),
+                Some("set boundary_sound.path to an audio file".to_string()),
+            ));
+        }
+        if let Some(narration) = &self.narration
+            && !narration.args.iter().any(|arg| arg.contains("{output}"))
+        {
+            issues.push(ConfigIssue::new(
+                "narration",
+                "narration args do not contain {output}",
+                Some("pass {output} as the path the command writes the audio to".to_string()),
+            ));
+        }
+        if let Some(beat_sync) = &self.beat_sync {
+            if !beat_sync.path.exists() {
+                issues.push(ConfigIssue::new(
+                    "beat_sync",
+                    format!(
+                        "beat_sync music does not exist: {}",
+                        beat_sync.path.display()
+                    ),
+                    Some("set beat_sync.path to an audio file".to_string()),
+                ));
+            }
+            if let Beats::Bpm { bpm, .. } = beat_sync.beats
+                && (bpm.is_nan() || bpm <= 0.0)
+            {
+                issues.push(ConfigIssue::new(
+                    "beat_sync",
+                    format!("bpm must be greater than 0, got {bpm}"),
+                    None,
+                ));
+            }
+            let ignored = [
+                ("chunk_overrides", !self.chunk_overrides.is_empty()),
+                ("presentation", self.presentation != Presentation::Scroll),
+            ];
+            issues.extend(
+                ignored
+                    .into_iter()
+                    .filter(|(_, used)| *used)
+                    .map(|(field, _)| {
+                        ConfigIssue::new(
+                            field,
+                            format!("{field} is not supported together with beat_sync"),
+                            Some(format!("remove {field} or beat_sync")),
+                        )
+                    }),
+            );
+        }
+        for &(width, height) in &self.renditions {
+            if width == 0 || height == 0 || width % 2 == 1 || height % 2 == 1 {
+                issues.push(ConfigIssue::new(
+                    "renditions",
+                    format!("renditions: invalid size {width}x{height}"),
+                    Some("sizes must be non-zero and even, e.g. [1280, 720]".to_string()),
+                ));
+            }
+        }
+        if self.fps == 0 {
+            issues.push(ConfigIssue::new(
+                "fps",
+                "fps must be greater than 0",
+                Some("use 30 or 60".to_string()),
+            ));
+        }
+        // 竖屏时 slide 沿屏幕的高度排列
+        let (strip_width, _) = self.orientation.strip_screen(self.screen);
+        if self.width_slides == 0 {
+            issues.push(ConfigIssue::new(
+                "width_slides",
+                "width_slides must be greater than 0",
+                nearest_divisors(strip_width, strip_width / 4),
+            ));
+        } else if !strip_width.is_multiple_of(self.width_slides) {
+            issues.push(ConfigIssue::new(
+                "width_slides",
+                format!(
+                    "width_screen % width_slides != 0; {} % {} != 0",
+                    strip_width, self.width_slides
+                ),
+                nearest_divisors(strip_width, self.width_slides),
+            ));
+        } else {
+            let overlap = strip_width / self.width_slides;
+            if self.step <= overlap {
+                issues.push(ConfigIssue::new(
+                    "step",
+                    format!(
+                        "step is shorter than overlap; step {} <= overlap {overlap}",
+                        self.step
+                    ),
+                    Some(format!(
+                        "set step to at least {}, or increase width_slides",
+                        overlap + 1
+                    )),
+                ));
+            }
+        }
+        if self.tile_width == Some(0) {
+            issues.push(ConfigIssue::new(
+                "tile_width",
+                "tile_width must be greater than 0",
+                Some("remove tile_width to save each strip as one image".to_string()),
+            ));
+        }
+        if self.rgb8 && parse_back_color(&self.back_color).is_none() {
+            issues.push(ConfigIssue::new(
+                "back_color",
+                format!(
+                    "rgb8 requires an opaque back_color (#rrggbb, 0xrrggbb or a basic color name), got {:?}",
+                    self.back_color
+                ),
+                Some("set back_color to e.g. \"#ffffff\", or disable rgb8".to_string()),
+            ));
         }
 
-        let overlap = self.screen.0 / self.width_slides;
+        if let Some(work_dir) = &self.work_dir
+            && !work_dir.exists()
+        {
+            issues.push(ConfigIssue::new(
+                "work_dir",
+                format!("work_dir is set but does not exist: {}", work_dir.display()),
+                Some("create the directory, or remove work_dir to use ./work".to_string()),
+            ));
+        }
+        if let Some(parent) = self
+            .save_path
+            .as_deref()
+            .and_then(|path| path.parent())
+            .filter(|parent| !parent.as_os_str().is_empty() && !parent.exists())
+        {
+            issues.push(ConfigIssue::new(
+                "save_path",
+                format!(
+                    "directory of save_path does not exist: {}",
+                    parent.display()
+                ),
+                Some("create the directory first".to_string()),
+            ));
+        }
 
-        if self.step <= overlap {
-            return Err("step is shorter than overlap".into());
+        match &self.font {
+            Some(font) if !font.exists() => issues.push(ConfigIssue::new(
+                "font",
+                format!("Font is set but does not exist: {}", font.display()),
+                Some("check the path of the font file".to_string()),
+            )),
+            Some(_) => {}
+            None if cfg!(feature = "bundled-font") => {}
+            None => issues.push(ConfigIssue::new(
+                "font",
+                "Font not set",
+                Some(
+                    "set font to a .ttf or .otf file, or enable the bundled-font feature"
+                        .to_string(),
+                ),
+            )),
+        }
+        if self.compositor == Compositor::Gpu && !cfg!(feature = "gpu") {
+            issues.push(ConfigIssue::new(
+                "compositor",
+                "the gpu compositor is not available in this build",
+                Some("rebuild with the gpu feature, or use the cpu compositor".to_string()),
+            ));
+        }
+        for missing in self.fallback_fonts.iter().filter(|font| !font.exists()) {
+            issues.push(ConfigIssue::new(
+                "fallback_fonts",
+                format!("Fallback font {} does not exist", missing.display()),
+                Some("check the path or remove it from fallback_fonts".to_string()),
+            ));
         }
 
-        let work_dir = if let Some(work_dir) = self.work_dir {
-            if !work_dir.exists() {
-                return Err("work_dir is set but does not exist".into());
+        if self.swip_pixels_per_sec == 0 {
+            issues.push(ConfigIssue::new(
+                "swip_pixels_per_sec",
+                "swip_pixels_per_sec must be greater than 0",
+                Some("use e.g. 160".to_string()),
+            ));
+        } else if screen_width > 0 && self.swip_pixels_per_sec > screen_width {
+            issues.push(ConfigIssue::new(
+                "swip_pixels_per_sec",
+                format!(
+                    "swip_pixels_per_sec {} is faster than one screen width ({screen_width}) per second",
+                    self.swip_pixels_per_sec
+                ),
+                Some(format!("use at most {screen_width}")),
+            ));
+        }
+        if self
+            .chunk_overrides
+            .iter()
+            .any(|o| o.swip_pixels_per_sec == Some(0))
+        {
+            issues.push(ConfigIssue::new(
+                "chunk_overrides",
+                "chunk_overrides: swip_pixels_per_sec must be greater than 0",
+                Some("remove swip_pixels_per_sec to use the global speed".to_string()),
+            ));
+        }
+
+        if self.cover_sec.is_nan() || self.cover_sec <= 0.0 {
+            issues.push(ConfigIssue::new(
+                "cover_sec",
+                format!("cover_sec must be greater than 0, got {}", self.cover_sec),
+                Some("use e.g. 10".to_string()),
+            ));
+        }
+        if self
+            .cover_entry
+            .sec
+            .is_some_and(|sec| sec.is_nan() || sec <= 0.0)
+        {
+            issues.push(ConfigIssue::new(
+                "cover_entry",
+                "cover_entry: sec must be greater than 0",
+                Some("remove sec to use the default entry time".to_string()),
+            ));
+        }
+        if self
+            .cover_entry
+            .delays
+            .iter()
+            .any(|d| d.is_nan() || *d < 0.0)
+        {
+            issues.push(ConfigIssue::new(
+                "cover_entry",
+                "cover_entry: delays must not be negative",
+                None,
+            ));
+        }
+
+        if !self.min_text_scale.is_finite() || self.min_text_scale < 0.0 {
+            issues.push(ConfigIssue::new(
+                "min_text_scale",
+                format!(
+                    "min_text_scale must not be negative, got {}",
+                    self.min_text_scale
+                ),
+                Some("use 0 to disable the limit".to_string()),
+            ));
+        }
+
+        match self.ending {
+            EndingMode::FadeToColor { sec, .. } if sec.is_nan() || sec <= 0.0 => {
+                issues.push(ConfigIssue::new(
+                    "ending",
+                    format!("ending: fade sec must be greater than 0, got {sec}"),
+                    None,
+                ));
+            }
+            EndingMode::LoopBackToStart if self.ending_sec == 0 => {
+                issues.push(ConfigIssue::new(
+                    "ending",
+                    "ending: LoopBackToStart requires ending_sec > 0",
+                    Some("set ending_sec to e.g. 4".to_string()),
+                ));
             }
+            _ => {}
+        }
+
+        for key in self
+            .metadata
+            .keys()
+            .filter(|key| key.is_empty() || key.contains('='))
+        {
+            issues.push(ConfigIssue::new(
+                "metadata",
+                format!("metadata: invalid key {key:?}"),
+                Some("keys must be non-empty and must not contain '='".to_string()),
+            ));
+        }
+
+        if let Some(path) = &self.ffmpeg_path
+            && !path.exists()
+        {
+            issues.push(ConfigIssue::new(
+                "ffmpeg_path",
+                format!("ffmpeg not found ({})", path.display()),
+                Some("install ffmpeg or set `ffmpeg_path`".to_string()),
+            ));
+        }
+        issues
+    }
+
+    /// 检查配置并创建 [`VideoConfig`]。
+    ///
+    /// # Errors
+    /// - 配置有问题时返回包含所有问题的 [`ConfigIssues`]，见 [`validate`](Self::validate)。
+    /// - 如果创建默认的 `work_dir` 失败，则返回 `Err`。
+    ///
+    pub fn build(self) -> Result<VideoConfig> {
+        let issues = self.validate();
+        if !issues.is_empty() {
+            return Err(ConfigIssues(issues).into());
+        }
+        let overlap = self.orientation.strip_screen(self.screen).0 / self.width_slides;
+
+        let work_dir = if let Some(work_dir) = self.work_dir {
             work_dir
         } else {
             let default_work_dir = std::env::current_dir()?.join("work");
@@ -100,17 +785,7 @@ impl VideoConfigBuilder {
             }
             default_work_dir
         };
-
-        let font = match self.font {
-            Some(font) => {
-                if font.exists() {
-                    font
-                } else {
-                    return Err("Font is set but does not exist".into());
-                }
-            }
-            None => return Err("Font not set".into()),
-        };
+        let ffmpeg_path = self.ffmpeg_path.unwrap_or_else(|| PathBuf::from("ffmpeg"));
 
         Ok(VideoConfig {
             encoder: self.encoder,
@@ -119,8 +794,10 @@ impl VideoConfigBuilder {
             work_dir: work_dir.clone(),
             back_color: self.back_color,
             cover_sec: self.cover_sec,
+            cover_entry: self.cover_entry,
             motion_type: self.motion_type,
             ending_sec: self.ending_sec,
+            ending: self.ending,
             swip_pixels_per_sec: self.swip_pixels_per_sec,
             width_slides: self.width_slides,
             save_path: self.save_path.unwrap_or_else(|| {
@@ -129,10 +806,45 @@ impl VideoConfigBuilder {
                 default_path
             }),
             step: self.step,
+            tile_width: self.tile_width,
+            rgb8: self.rgb8,
+            intermediate_format: self.intermediate_format,
+            debug_overlay: self.debug_overlay,
             overlap,
-            font,
+            font: self.font,
+            fallback_fonts: self.fallback_fonts,
             split_line_color: self.split_line_color,
+            split_line_width: self.split_line_width,
+            text_quality: self.text_quality,
+            compositor: self.compositor,
+            min_text_scale: self.min_text_scale,
+            text_overflow: self.text_overflow,
+            text_contrast: self.text_contrast,
             clean_temp: self.clean_temp,
+            ffmpeg_path,
+            chunk_overrides: self.chunk_overrides,
+            group_by: self.group_by,
+            timeline: self.timeline,
+            ranking: self.ranking,
+            versus: self.versus,
+            redact: self.redact,
+            metadata: self.metadata,
+            preset: self.preset,
+            preview_scale: None,
+            debug_commands: self.debug_commands,
+            print_commands: self.print_commands,
+            incremental: self.incremental,
+            deterministic: self.deterministic,
+            auto_palette: self.auto_palette,
+            safe_area: self.safe_area,
+            renditions: self.renditions,
+            orientation: self.orientation,
+            presentation: self.presentation,
+            boundary_sound: self.boundary_sound,
+            narration: self.narration,
+            beat_sync: self.beat_sync,
+            progress: self.progress,
+            rows: self.rows,
         })
     }
 }
@@ -168,6 +880,11 @@ impl VideoConfigBuilder {
         self
     }
 
+    pub fn cover_entry(mut self, cover_entry: CoverEntry) -> Self {
+        self.cover_entry = cover_entry;
+        self
+    }
+
     pub fn motion_type(mut self, motion_type: MotionType) -> Self {
         self.motion_type = motion_type;
         self
@@ -178,6 +895,11 @@ impl VideoConfigBuilder {
         self
     }
 
+    pub fn ending(mut self, ending: EndingMode) -> Self {
+        self.ending = ending;
+        self
+    }
+
     pub fn swip_pixels_per_sec(mut self, swip_pixels_per_sec: u32) -> Self {
         self.swip_pixels_per_sec = swip_pixels_per_sec;
         self
@@ -198,20 +920,199 @@ impl VideoConfigBuilder {
         self
     }
 
+    pub fn tile_width(mut self, tile_width: u32) -> Self {
+        self.tile_width = Some(tile_width);
+        self
+    }
+
+    pub fn rgb8(mut self, rgb8: bool) -> Self {
+        self.rgb8 = rgb8;
+        self
+    }
+
+    pub fn intermediate_format(mut self, intermediate_format: IntermediateFormat) -> Self {
+        self.intermediate_format = intermediate_format;
+        self
+    }
+
+    pub fn debug_overlay(mut self, debug_overlay: bool) -> Self {
+        self.debug_overlay = debug_overlay;
+        self
+    }
+
     pub fn font(mut self, font: PathBuf) -> Self {
         self.font = Some(font);
         self
     }
 
+    pub fn fallback_fonts(mut self, fallback_fonts: Vec<PathBuf>) -> Self {
+        self.fallback_fonts = fallback_fonts;
+        self
+    }
+
     pub fn split_line_color(mut self, split_line_color: Option<Color>) -> Self {
         self.split_line_color = split_line_color;
         self
     }
 
+    pub fn split_line_width(mut self, split_line_width: u32) -> Self {
+        self.split_line_width = split_line_width;
+        self
+    }
+
+    pub fn text_quality(mut self, text_quality: TextQuality) -> Self {
+        self.text_quality = text_quality;
+        self
+    }
+
+    pub fn compositor(mut self, compositor: Compositor) -> Self {
+        self.compositor = compositor;
+        self
+    }
+
+    pub fn min_text_scale(mut self, min_text_scale: f32) -> Self {
+        self.min_text_scale = min_text_scale;
+        self
+    }
+
+    pub fn text_overflow(mut self, text_overflow: TextOverflow) -> Self {
+        self.text_overflow = text_overflow;
+        self
+    }
+
+    pub fn text_contrast(mut self, text_contrast: TextContrast) -> Self {
+        self.text_contrast = text_contrast;
+        self
+    }
+
     pub fn clean_temp(mut self, clean_temp: bool) -> Self {
         self.clean_temp = clean_temp;
         self
     }
+
+    pub fn ffmpeg_path(mut self, ffmpeg_path: Option<PathBuf>) -> Self {
+        self.ffmpeg_path = ffmpeg_path;
+        self
+    }
+
+    pub fn chunk_overrides(mut self, chunk_overrides: Vec<ChunkOverride>) -> Self {
+        self.chunk_overrides = chunk_overrides;
+        self
+    }
+
+    pub fn group_by(mut self, group_by: Option<GroupBy>) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    pub fn timeline(mut self, timeline: Option<Timeline>) -> Self {
+        self.timeline = timeline;
+        self
+    }
+
+    pub fn ranking(mut self, ranking: Option<Ranking>) -> Self {
+        self.ranking = ranking;
+        self
+    }
+
+    pub fn versus(mut self, versus: Option<Versus>) -> Self {
+        self.versus = versus;
+        self
+    }
+
+    pub fn redact(mut self, redact: Vec<Redaction>) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    pub fn debug_commands(mut self, debug_commands: bool) -> Self {
+        self.debug_commands = debug_commands;
+        self
+    }
+
+    pub fn print_commands(mut self, print_commands: bool) -> Self {
+        self.print_commands = print_commands;
+        self
+    }
+
+    pub fn rows(mut self, rows: Vec<RowConfig>) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    pub fn auto_palette(mut self, auto_palette: bool) -> Self {
+        self.auto_palette = auto_palette;
+        self
+    }
+
+    pub fn safe_area(mut self, safe_area: Option<SafeArea>) -> Self {
+        self.safe_area = safe_area;
+        self
+    }
+
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    pub fn presentation(mut self, presentation: Presentation) -> Self {
+        self.presentation = presentation;
+        self
+    }
+
+    pub fn boundary_sound(mut self, boundary_sound: Option<SoundEffect>) -> Self {
+        self.boundary_sound = boundary_sound;
+        self
+    }
+
+    pub fn narration(mut self, narration: Option<CommandNarrator>) -> Self {
+        self.narration = narration;
+        self
+    }
+
+    pub fn beat_sync(mut self, beat_sync: Option<BeatSync>) -> Self {
+        self.beat_sync = beat_sync;
+        self
+    }
+
+    pub fn progress(mut self, progress: Option<ProgressIndicator>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// 额外输出一个 `screen` 分辨率的视频，见 [`renditions`](Self::renditions)
+    pub fn rendition(mut self, screen: (u32, u32)) -> Self {
+        self.renditions.push(screen);
+        self
+    }
+
+    pub fn preset(mut self, preset: &str) -> Self {
+        self.preset = preset.to_string();
+        self
+    }
+
+    pub fn metadata<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+const fn default_split_line_width() -> u32 {
+    2
+}
+
+fn default_preset() -> String {
+    "fast".to_string()
 }
 
 impl Default for VideoConfigBuilder {
@@ -219,3 +1120,80 @@ impl Default for VideoConfigBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_collects_issues() {
+        let builder = VideoConfigBuilder::new()
+            .font(PathBuf::from("missing.ttf"))
+            .width_slides(500)
+            .fps(0)
+            .cover_sec(0.0);
+        let issues = builder.validate();
+        let fields: Vec<&str> = issues.iter().map(|issue| issue.field).collect();
+        assert_eq!(fields, ["fps", "width_slides", "font", "cover_sec"]);
+        assert_eq!(issues[1].suggestion.as_deref(), Some("try 480 or 640"));
+
+        let err = builder.build().err().unwrap();
+        let ConfigIssues(issues) = err.downcast_ref::<ConfigIssues>().unwrap();
+        assert_eq!(issues.len(), 4);
+        assert!(err.to_string().starts_with("4 config issues:"));
+    }
+
+    #[test]
+    fn test_validate_vertical() {
+        // 竖屏时 width_slides 是横条的高度，按屏幕高度检查
+        let font = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        let builder = VideoConfigBuilder::new()
+            .font(PathBuf::from(font))
+            .screen((1080, 1920))
+            .width_slides(640)
+            .orientation(Orientation::Vertical);
+        assert_eq!(builder.validate(), []);
+        let fields: Vec<&str> = builder
+            .width_slides(500)
+            .safe_area(Some(SafeArea::vertical(54)))
+            .validate()
+            .iter()
+            .map(|issue| issue.field)
+            .collect();
+        assert_eq!(fields, ["safe_area", "width_slides"]);
+    }
+
+    #[test]
+    fn test_serialize_for_cache_key() {
+        let font = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        let work_dir =
+            std::env::temp_dir().join(format!("to_video_test_config_{}", std::process::id()));
+        std::fs::create_dir_all(&work_dir).unwrap();
+        let builder = || {
+            VideoConfigBuilder::new()
+                .font(PathBuf::from(font))
+                .work_dir(work_dir.clone())
+        };
+        let json = |builder: VideoConfigBuilder| serde_json::to_string(&builder.build().unwrap());
+        let key = json(builder()).unwrap();
+        let moved = json(builder().save_path(work_dir.join("other.mp4"))).unwrap();
+        let safe_area = json(builder().safe_area(Some(SafeArea::vertical(54)))).unwrap();
+        std::fs::remove_dir_all(work_dir).unwrap();
+        // 输出位置不影响片段视频，安全区域在 debug_overlay 时会被绘制
+        assert_eq!(key, moved);
+        assert_ne!(key, safe_area);
+    }
+
+    #[test]
+    fn test_nearest_divisors() {
+        assert_eq!(nearest_divisors(1920, 480).as_deref(), Some("try 480"));
+        assert_eq!(nearest_divisors(1920, 0).as_deref(), Some("try 1"));
+        assert_eq!(nearest_divisors(0, 480), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! 调试叠加层：在长条图上标出 slide、元素区域和相邻 chunk 的重叠部分，
+//! 用于排查各段视频拼接处画面跳动的问题；设置了安全区域时标出其边界和超出的元素
+
+use super::{
+    safe_area::SafeArea,
+    slide::{RenderContext, Slide},
+};
+use crate::imageproc::{
+    drawing::{DrawMut, DrawText},
+    rect::Rect,
+};
+use image::{GenericImage, Rgba};
+use std::ops::Range;
+
+const SLIDE_COLOR: Rgba<u8> = Rgba([255, 0, 255, 255]);
+const ELEMENT_COLOR: Rgba<u8> = Rgba([0, 200, 255, 255]);
+const OVERLAP_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const SAFE_AREA_COLOR: Rgba<u8> = Rgba([0, 255, 0, 255]);
+const OUTSIDE_COLOR: Rgba<u8> = Rgba([255, 160, 0, 255]);
+const LABEL_SCALE: f32 = 32.0;
+
+/// 在长条图（或其中一段）上绘制调试信息。
+///
+/// # Parameters
+/// - `slides`: 这一段中的 slide，第一个 slide 位于 `img` 的左边缘。
+/// - `first_index`: 第一个 slide 在所有 slide 中的序号。
+/// - `x`: 这一段在整个 chunk 长条图中的 x 坐标。
+/// - `overlaps`: chunk 长条图中与相邻 chunk 显示相同内容的范围。
+/// - `safe_area`: 安全区域，超出的元素用另一种颜色标出。
+///
+#[allow(clippy::too_many_arguments)]
+pub fn draw_debug_overlay(
+    img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+    slides: &[Slide],
+    first_index: usize,
+    width_slides: u32,
+    x: u32,
+    overlaps: &[Range<u32>],
+    safe_area: Option<&SafeArea>,
+    ctx: &RenderContext,
+) {
+    let height = img.height();
+    let mut left = 0;
+    for (i, slide) in slides.iter().enumerate() {
+        let width = slide.width().unwrap_or(width_slides);
+        for element in slide.elements() {
+            let rect = element.pos().to_rect((width, height));
+            let color = match safe_area {
+                Some(safe) if safe.is_outside(rect, height) => OUTSIDE_COLOR,
+                _ => ELEMENT_COLOR,
+            };
+            img.draw_hollow_rect_mut(
+                Rect::at(rect.left() + left as i32, rect.top())
+                    .of_size(rect.width(), rect.height()),
+                color,
+            );
+        }
+        // 边框画两层，缩小后仍然可见
+        for inset in 0..2 {
+            if width > 2 * inset && height > 2 * inset {
+                img.draw_hollow_rect_mut(
+                    Rect::at((left + inset) as i32, inset as i32)
+                        .of_size(width - 2 * inset, height - 2 * inset),
+                    SLIDE_COLOR,
+                );
+            }
+        }
+        img.draw_text_mut(
+            SLIDE_COLOR,
+            left as i32 + 8,
+            8,
+            LABEL_SCALE,
+            &ctx.font,
+            &format!("#{}", first_index + i),
+        );
+        left += width;
+    }
+
+    if let Some(safe) = safe_area {
+        let (top, bottom) = safe.bounds(height);
+        for y in [top, bottom - 1] {
+            img.draw_line_segment_with_width_mut(
+                (0.0, y as f32),
+                (img.width() as f32, y as f32),
+                2.0,
+                SAFE_AREA_COLOR,
+            );
+        }
+    }
+
+    let end = x + img.width();
+    for overlap in overlaps {
+        for boundary in [overlap.start, overlap.end] {
+            if (x..end).contains(&boundary) {
+                let line_x = (boundary - x) as f32;
+                img.draw_line_segment_with_width_mut(
+                    (line_x, 0.0),
+                    (line_x, height as f32),
+                    4.0,
+                    OVERLAP_COLOR,
+                );
+            }
+        }
+        if (x..end).contains(&overlap.start) {
+            img.draw_text_mut(
+                OVERLAP_COLOR,
+                (overlap.start - x) as i32 + 8,
+                height as i32 - LABEL_SCALE as i32 - 8,
+                LABEL_SCALE,
+                &ctx.font,
+                &format!("overlap {}..{}", overlap.start, overlap.end),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, slide::Position};
+    use image::RgbaImage;
+
+    #[test]
+    fn test_draw_debug_overlay() {
+        let font = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        ))
+        .unwrap();
+        let ctx = RenderContext::new(ab_glyph::FontArc::try_from_vec(font).unwrap());
+        let mut slide = Slide::new();
+        slide.add_color(Color([0, 0, 0]), Position::new(20, 100, 50));
+        let slides = [slide.clone(), slide];
+
+        // 这一段位于 chunk 长条图的 x = 200 处，重叠部分从 x = 300 开始
+        let overlaps = [Range {
+            start: 300,
+            end: 500,
+        }];
+        let mut img = RgbaImage::new(200, 200);
+        draw_debug_overlay(&mut img, &slides, 5, 100, 200, &overlaps, None, &ctx);
+        assert_eq!(*img.get_pixel(0, 50), SLIDE_COLOR);
+        assert_eq!(*img.get_pixel(198, 50), SLIDE_COLOR);
+        assert_eq!(*img.get_pixel(20, 120), ELEMENT_COLOR);
+        assert_eq!(*img.get_pixel(100, 190), OVERLAP_COLOR);
+        assert_eq!(img.get_pixel(50, 120).0, [0, 0, 0, 0]);
+
+        // 元素的下边缘 149 超出安全区域 (40, 140)
+        let mut img = RgbaImage::new(200, 200);
+        let safe = SafeArea {
+            top: 40,
+            bottom: 60,
+        };
+        draw_debug_overlay(&mut img, &slides, 5, 100, 200, &[], Some(&safe), &ctx);
+        assert_eq!(*img.get_pixel(20, 120), OUTSIDE_COLOR);
+        assert_eq!(*img.get_pixel(50, 40), SAFE_AREA_COLOR);
+        assert_eq!(*img.get_pixel(50, 139), SAFE_AREA_COLOR);
+    }
+}
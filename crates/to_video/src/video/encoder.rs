@@ -0,0 +1,670 @@
+use super::{
+    VideoConfig,
+    animated::AnimatedOverlay,
+    beat::{BeatSync, music_command},
+    ffmpeg::{
+        CoverEntry, Fade, Ffmpeg, FfmpegCommand, MotionType, RowOverlay, StripTile, concat_command,
+        cover_video_command, ffmpeg_version, mid_video_command, page_video_command,
+        rendition_command,
+    },
+    frames::SegmentFrames,
+    ken_burns::KenBurnsOverlay,
+    narration::{NarrationClip, narration_command},
+    orientation::Orientation,
+    probe::{Expected, ffprobe_available, ffprobe_path, run_verified},
+    progress::{ProgressOverlay, progress_command},
+    ranking::CountUpOverlay,
+    scroll::Scroll,
+    slideshow::Transition,
+    sound::{SoundEffect, sound_effects_command},
+};
+use crate::Result;
+use image::RgbaImage;
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    thread::JoinHandle,
+};
+
+/// 一段中间视频的内容，与编码方式无关。
+///
+/// 其中的图片都已保存在本次运行的临时目录中，文件名相对于该目录。
+#[derive(Debug, Clone)]
+pub enum SegmentPlan {
+    /// 封面：各列图片按 `entry` 依次入场，之后静止
+    Cover {
+        /// 图片文件名和 x 坐标
+        images: Vec<(String, u32)>,
+        cover_sec: f32,
+        motion_type: MotionType,
+        entry: CoverEntry,
+        rows: Vec<RowOverlay>,
+    },
+    /// 长条图按 `scroll` 向左滚动
+    Scroll {
+        tiles: Vec<StripTile>,
+        scroll: Scroll,
+        fade: Option<Fade>,
+        rows: Vec<RowOverlay>,
+        ken_burns: Vec<KenBurnsOverlay>,
+        count_ups: Vec<CountUpOverlay>,
+    },
+    /// 幻灯片模式的一页：从上一页切换过来后静止，见 [`Presentation`](super::slideshow::Presentation)
+    Page {
+        /// 这一页的图片文件名
+        image: PathBuf,
+        /// 图片在屏幕上的 x 坐标
+        x: u32,
+        /// 上一页的图片文件名和 x 坐标，第一页为 `None`
+        previous: Option<(PathBuf, u32)>,
+        /// 叠加在这一页上播放的动态图片
+        animated: Vec<AnimatedOverlay>,
+        transition: Transition,
+        transition_sec: f32,
+        fade: Option<Fade>,
+    },
+}
+
+/// 需要编码的一段视频
+#[derive(Debug, Clone)]
+pub struct EncodeSegment {
+    /// 生成的视频文件名，相对于本次运行的临时目录
+    pub name: PathBuf,
+    /// 输出视频的分辨率
+    pub screen: (u32, u32),
+    /// 画面方向，竖屏时 `plan` 中的图片和坐标都在长条图坐标系中
+    pub orientation: Orientation,
+    pub fps: u32,
+    /// 这一段的帧数，见 [`FrameClock`](super::scroll::FrameClock)
+    pub frames: u64,
+    pub back_color: String,
+    pub plan: SegmentPlan,
+}
+
+impl EncodeSegment {
+    /// 这一段视频应有的分辨率、帧率和帧数
+    pub fn expected(&self) -> Expected {
+        Expected {
+            screen: self.screen,
+            fps: self.fps,
+            frames: self.frames,
+        }
+    }
+}
+
+/// 把各段图片编码为视频并合并的后端。
+///
+/// [`Video::run_with`](super::Video::run_with) 依次调用 `begin`、每段一次 `push_segment`、
+/// `flush` 和 `finish`。每段视频必须保存在临时目录中的 [`EncodeSegment::name`]，
+/// 增量生成时从缓存恢复的片段不会再调用 `push_segment`。
+///
+/// 理解 [`SegmentPlan`] 的后端（例如 [`FfmpegCliEncoder`]）实现 `push_segment`，
+/// 直接编码像素的后端只需实现 `push_frame`，由默认的 `push_segment` 逐帧绘制画面。
+pub trait VideoEncoder {
+    /// 开始一次运行，`work_dir` 为本次运行的临时目录
+    ///
+    /// # Errors
+    /// - 后端不可用时返回 `Err`。
+    ///
+    fn begin(&mut self, work_dir: &Path) -> Result<()>;
+
+    /// 编码一段视频，`work_dir` 为本次运行的临时目录。
+    ///
+    /// 默认实现用 [`SegmentFrames`] 依次绘制 `segment` 的每一帧并交给
+    /// [`push_frame`](Self::push_frame)。
+    ///
+    /// # Errors
+    /// - 编码失败时返回 `Err`。
+    ///
+    fn push_segment(&mut self, segment: &EncodeSegment, work_dir: &Path) -> Result<()> {
+        let frames = SegmentFrames::load(segment, work_dir)?;
+        for index in 0..segment.frames {
+            self.push_frame(segment, index, &frames.frame(index))?;
+        }
+        Ok(())
+    }
+
+    /// 编码 `segment` 的第 `index` 帧（从 0 开始），共 [`EncodeSegment::frames`] 帧，
+    /// 最后一帧之后这一段视频应当写完
+    ///
+    /// # Errors
+    /// - 后端不支持或编码失败时返回 `Err`，默认实现总是返回 `Err`。
+    ///
+    fn push_frame(&mut self, segment: &EncodeSegment, index: u64, frame: &RgbaImage) -> Result<()> {
+        let _ = (index, frame);
+        Err(format!(
+            "{}: frame-level encoding is not supported by this encoder",
+            segment.name.display()
+        )
+        .into())
+    }
+
+    /// 等待已经交给 `push_segment` 的各段视频写完，之后才会读取或合并这些视频。
+    /// 默认实现什么都不做，适用于 `push_segment` 返回时已经写完的后端
+    ///
+    /// # Errors
+    /// - 其中一段编码失败时返回 `Err`。
+    ///
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// 按顺序合并 `videos` 并写入 `save_path`
+    ///
+    /// # Errors
+    /// - 合并或写入失败时返回 `Err`。
+    ///
+    fn finish(
+        &mut self,
+        videos: &[PathBuf],
+        save_path: &Path,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()>;
+
+    /// 在 `times`（秒）处把音效 `effect` 混入 `source` 并写入 `output`，
+    /// 见 [`VideoConfigBuilder::boundary_sound`](super::VideoConfigBuilder::boundary_sound)
+    ///
+    /// # Errors
+    /// - 后端不支持或混音失败时返回 `Err`，默认实现总是返回 `Err`。
+    ///
+    fn sound_effects(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        effect: &SoundEffect,
+        times: &[f64],
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        let _ = (source, output, times, metadata);
+        Err(format!(
+            "{}: sound effects are not supported by this encoder",
+            effect.path.display()
+        )
+        .into())
+    }
+
+    /// 在 `source` 上叠加进度指示 `progress` 并写入 `output`，见 [`progress`](super::progress)
+    ///
+    /// # Errors
+    /// - 后端不支持或编码失败时返回 `Err`，默认实现总是返回 `Err`。
+    ///
+    fn progress(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        progress: &ProgressOverlay,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        let _ = (source, output, progress, metadata);
+        Err("progress indicator is not supported by this encoder".into())
+    }
+
+    /// 把旁白 `clips` 混入 `source` 并写入 `output`，`keep_audio` 时保留 `source` 原有的音频，
+    /// 见 [`narration`](super::narration)
+    ///
+    /// # Errors
+    /// - 后端不支持或混音失败时返回 `Err`，默认实现总是返回 `Err`。
+    ///
+    fn narration(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        clips: &[NarrationClip],
+        keep_audio: bool,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        let _ = (source, output, clips, keep_audio, metadata);
+        Err("narration is not supported by this encoder".into())
+    }
+
+    /// 把背景音乐 `music` 混入 `source` 并写入 `output`，`keep_audio` 时保留 `source` 原有的音频，
+    /// 见 [`beat`](super::beat)
+    ///
+    /// # Errors
+    /// - 后端不支持或混音失败时返回 `Err`，默认实现总是返回 `Err`。
+    ///
+    fn music(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        music: &BeatSync,
+        keep_audio: bool,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        let _ = (source, output, keep_audio, metadata);
+        Err(format!(
+            "{}: background music is not supported by this encoder",
+            music.path.display()
+        )
+        .into())
+    }
+
+    /// 把 `finish` 生成的 `source` 缩放为 `screen` 分辨率并写入 `output`，
+    /// 见 [`VideoConfigBuilder::renditions`](super::VideoConfigBuilder::renditions)
+    ///
+    /// # Errors
+    /// - 后端不支持或缩放失败时返回 `Err`，默认实现总是返回 `Err`。
+    ///
+    fn rendition(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        screen: (u32, u32),
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        let _ = (source, metadata);
+        Err(format!(
+            "{} ({}x{}): renditions are not supported by this encoder",
+            output.display(),
+            screen.0,
+            screen.1
+        )
+        .into())
+    }
+}
+
+/// 调用 ffmpeg 命令行的后端，每段视频由一条 `-filter_complex` 命令生成，最后用 concat 合并
+#[derive(Debug)]
+pub struct FfmpegCliEncoder {
+    pub ffmpeg: Ffmpeg,
+    pub encoder: String,
+    pub preset: String,
+    /// 检查中间视频所用的 ffprobe，在 [`begin`](VideoEncoder::begin) 中查找，
+    /// 不可用或只打印命令时为 `None`
+    pub ffprobe: Option<PathBuf>,
+    /// 生成其他分辨率时补齐画面所用的颜色和帧率
+    pub back_color: String,
+    pub fps: u32,
+    /// 同时运行的生成中间视频的 ffmpeg 进程数，大于 1 时 `push_segment` 在后台线程中运行命令，
+    /// 由 [`flush`](VideoEncoder::flush) 等待
+    pub jobs: usize,
+    work_dir: PathBuf,
+    /// 后台运行的命令，按开始的顺序排列
+    running: VecDeque<JoinHandle<Result<()>>>,
+}
+
+impl FfmpegCliEncoder {
+    pub fn new(config: &VideoConfig) -> Self {
+        Self {
+            ffmpeg: Ffmpeg::from_config(config),
+            encoder: config.encoder.clone(),
+            preset: config.preset.clone(),
+            ffprobe: None,
+            back_color: config.back_color.clone(),
+            fps: config.fps,
+            jobs: 1,
+            work_dir: PathBuf::new(),
+            running: VecDeque::new(),
+        }
+    }
+
+    /// 等待最早开始的后台命令结束
+    fn wait_one(&mut self) -> Result<()> {
+        match self.running.pop_front() {
+            Some(handle) => handle.join().map_err(|_| "ffmpeg thread panicked")?,
+            None => Ok(()),
+        }
+    }
+
+    /// 生成 `segment` 的 ffmpeg 命令
+    pub fn command(&self, segment: &EncodeSegment) -> FfmpegCommand {
+        match &segment.plan {
+            SegmentPlan::Cover {
+                images,
+                cover_sec,
+                motion_type,
+                entry,
+                rows,
+            } => cover_video_command(
+                &self.ffmpeg,
+                &self.encoder,
+                &self.preset,
+                images.clone(),
+                *cover_sec,
+                &segment.back_color,
+                segment.screen,
+                segment.orientation,
+                segment.fps,
+                segment.frames,
+                *motion_type,
+                entry,
+                rows,
+                &segment.name,
+            ),
+            SegmentPlan::Scroll {
+                tiles,
+                scroll,
+                fade,
+                rows,
+                ken_burns,
+                count_ups,
+            } => mid_video_command(
+                &self.ffmpeg,
+                &self.encoder,
+                &self.preset,
+                tiles,
+                &segment.name,
+                segment.screen,
+                segment.orientation,
+                &segment.back_color,
+                segment.fps,
+                scroll,
+                segment.frames,
+                fade.as_ref(),
+                rows,
+                ken_burns,
+                count_ups,
+            ),
+            SegmentPlan::Page {
+                image,
+                x,
+                previous,
+                animated,
+                transition,
+                transition_sec,
+                fade,
+            } => page_video_command(
+                &self.ffmpeg,
+                &self.encoder,
+                &self.preset,
+                (image, *x),
+                previous.as_ref().map(|(image, x)| (image.as_path(), *x)),
+                animated,
+                (*transition, *transition_sec),
+                &segment.name,
+                segment.screen,
+                &segment.back_color,
+                segment.fps,
+                segment.frames,
+                fade.as_ref(),
+            ),
+        }
+    }
+}
+
+impl VideoEncoder for FfmpegCliEncoder {
+    fn begin(&mut self, work_dir: &Path) -> Result<()> {
+        self.work_dir = work_dir.to_path_buf();
+        self.ffprobe = None;
+        if !self.ffmpeg.dry_run {
+            // 在渲染任何图片之前确认 ffmpeg 可用
+            println!("Using {}", ffmpeg_version(&self.ffmpeg.path)?);
+            let ffprobe = ffprobe_path(&self.ffmpeg.path);
+            if ffprobe_available(&ffprobe) {
+                self.ffprobe = Some(ffprobe);
+            } else {
+                eprintln!(
+                    "{} not found, intermediate videos will not be verified",
+                    ffprobe.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn push_segment(&mut self, segment: &EncodeSegment, work_dir: &Path) -> Result<()> {
+        let command = self.command(segment);
+        if self.jobs <= 1 {
+            return run_verified(
+                command,
+                work_dir,
+                &segment.name,
+                self.ffprobe.as_deref(),
+                &segment.expected(),
+            );
+        }
+        while self.running.len() >= self.jobs {
+            self.wait_one()?;
+        }
+        let (work_dir, name) = (work_dir.to_path_buf(), segment.name.clone());
+        let (ffprobe, expected) = (self.ffprobe.clone(), segment.expected());
+        self.running.push_back(std::thread::spawn(move || {
+            run_verified(command, &work_dir, &name, ffprobe.as_deref(), &expected)
+        }));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        // 出错时也等待其余的命令结束，避免之后删除临时目录时仍有进程在写入
+        let mut result = Ok(());
+        while !self.running.is_empty() {
+            let done = self.wait_one();
+            if result.is_ok() {
+                result = done;
+            }
+        }
+        result
+    }
+
+    fn finish(
+        &mut self,
+        videos: &[PathBuf],
+        save_path: &Path,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        self.flush()?;
+        let mut videos = videos.to_vec();
+        concat_command(
+            &self.ffmpeg,
+            &mut videos,
+            &self.work_dir,
+            save_path,
+            metadata,
+        )?
+        .run(&self.work_dir)?;
+        println!("{} successed", save_path.display());
+        Ok(())
+    }
+
+    fn sound_effects(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        effect: &SoundEffect,
+        times: &[f64],
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        sound_effects_command(&self.ffmpeg, source, effect, times, output, metadata)
+            .run(&self.work_dir)?;
+        println!("{} successed", output.display());
+        Ok(())
+    }
+
+    fn progress(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        progress: &ProgressOverlay,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        progress_command(
+            &self.ffmpeg,
+            &self.encoder,
+            &self.preset,
+            self.fps,
+            source,
+            &progress.filter()?,
+            output,
+            metadata,
+        )
+        .run(&self.work_dir)?;
+        println!("{} successed", output.display());
+        Ok(())
+    }
+
+    fn narration(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        clips: &[NarrationClip],
+        keep_audio: bool,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        narration_command(&self.ffmpeg, source, clips, keep_audio, output, metadata)
+            .run(&self.work_dir)?;
+        println!("{} successed", output.display());
+        Ok(())
+    }
+
+    fn music(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        music: &BeatSync,
+        keep_audio: bool,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        music_command(&self.ffmpeg, source, music, keep_audio, output, metadata)
+            .run(&self.work_dir)?;
+        println!("{} successed", output.display());
+        Ok(())
+    }
+
+    fn rendition(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        screen: (u32, u32),
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        rendition_command(
+            &self.ffmpeg,
+            &self.encoder,
+            &self.preset,
+            source,
+            output,
+            screen,
+            &self.back_color,
+            self.fps,
+            metadata,
+        )
+        .run(&self.work_dir)?;
+        println!("{} successed", output.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::scroll::Keyframe;
+
+    #[test]
+    fn test_ffmpeg_cli_command() {
+        let font = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        let config = VideoConfig::builder()
+            .font(PathBuf::from(font))
+            .print_commands(true)
+            .build()
+            .unwrap();
+        let encoder = FfmpegCliEncoder::new(&config);
+        assert!(encoder.ffmpeg.dry_run);
+        let scroll = Scroll::linear(vec![
+            Keyframe { t: 0.0, x: 0.0 },
+            Keyframe { t: 1.0, x: 10.0 },
+        ]);
+        let tiles = vec![StripTile {
+            pic_name: PathBuf::from("00.png"),
+            x: 0,
+            raw: None,
+        }];
+        let segment = EncodeSegment {
+            name: PathBuf::from("00.mp4"),
+            screen: (1920, 1080),
+            orientation: Orientation::Horizontal,
+            fps: 30,
+            frames: 30,
+            back_color: "black".to_string(),
+            plan: SegmentPlan::Scroll {
+                tiles: tiles.clone(),
+                scroll: scroll.clone(),
+                fade: None,
+                rows: Vec::new(),
+                ken_burns: Vec::new(),
+                count_ups: Vec::new(),
+            },
+        };
+        let expected = mid_video_command(
+            &encoder.ffmpeg,
+            &encoder.encoder,
+            &encoder.preset,
+            &tiles,
+            &segment.name,
+            segment.screen,
+            segment.orientation,
+            "black",
+            30,
+            &scroll,
+            30,
+            None,
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(encoder.command(&segment).get_args(), expected.get_args());
+        assert_eq!(segment.expected().frames, 30);
+    }
+
+    /// 只实现逐帧编码的后端，记录收到的帧
+    #[derive(Default)]
+    struct FrameRecorder {
+        frames: Vec<(PathBuf, u64, (u32, u32))>,
+    }
+
+    impl VideoEncoder for FrameRecorder {
+        fn begin(&mut self, _: &Path) -> Result<()> {
+            Ok(())
+        }
+
+        fn push_frame(
+            &mut self,
+            segment: &EncodeSegment,
+            index: u64,
+            frame: &RgbaImage,
+        ) -> Result<()> {
+            let entry = (segment.name.clone(), index, frame.dimensions());
+            self.frames.push(entry);
+            Ok(())
+        }
+
+        fn finish(&mut self, _: &[PathBuf], _: &Path, _: &HashMap<String, String>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_push_frames() {
+        let work_dir =
+            std::env::temp_dir().join(format!("to_video_test_push_frames_{}", std::process::id()));
+        std::fs::create_dir_all(&work_dir).unwrap();
+        RgbaImage::new(2, 2).save(work_dir.join("00.png")).unwrap();
+        let segment = EncodeSegment {
+            name: PathBuf::from("00.mp4"),
+            screen: (4, 2),
+            orientation: Orientation::Horizontal,
+            fps: 10,
+            frames: 3,
+            back_color: "black".to_string(),
+            plan: SegmentPlan::Page {
+                image: PathBuf::from("00.png"),
+                x: 1,
+                previous: None,
+                animated: Vec::new(),
+                transition: Transition::Cut,
+                transition_sec: 0.0,
+                fade: None,
+            },
+        };
+        let mut encoder = FrameRecorder::default();
+        encoder.push_segment(&segment, &work_dir).unwrap();
+        let name = PathBuf::from("00.mp4");
+        assert_eq!(
+            encoder.frames,
+            [0, 1, 2].map(|index| (name.clone(), index, (4, 2)))
+        );
+        std::fs::remove_dir_all(work_dir).unwrap();
+    }
+}
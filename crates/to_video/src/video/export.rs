@@ -0,0 +1,158 @@
+use super::{
+    Renderer, Video,
+    ffmpeg::{Direction, IntermediateFormat, MotionType},
+    orientation::Orientation,
+    scroll::{FrameClock, Keyframe},
+};
+use crate::Result;
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// [`Video::export`] 写出的 `manifest.json`，描述各段素材在时间线上的位置
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub fps: u32,
+    pub screen: (u32, u32),
+    /// 竖屏时各图片都是逆时针旋转 90° 后的长条图，`x` 和 `offsets` 沿长条图方向，
+    /// 合成后需要顺时针旋转回竖屏，见 [`Orientation`]
+    pub orientation: Orientation,
+    pub back_color: String,
+    pub total_frames: u64,
+    /// 按播放顺序排列的片段
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Segment {
+    /// 封面：图片依次从 `direction` 方向进入到 `x` 处
+    Cover {
+        images: Vec<CoverImage>,
+        start_frame: u64,
+        frames: u64,
+        motion_type: MotionType,
+        direction: Direction,
+    },
+    /// 长条图向左滚动，`offsets[i]` 为第 `i` 帧时长条图向左移动的像素数
+    Scroll {
+        image: PathBuf,
+        start_frame: u64,
+        frames: u64,
+        keyframes: Vec<Keyframe>,
+        motion_type: MotionType,
+        offsets: Vec<f64>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoverImage {
+    pub file: PathBuf,
+    pub x: u32,
+    /// 开始入场的时间，相对于封面开头
+    pub start_sec: f32,
+    pub duration_sec: f32,
+}
+
+impl Video {
+    /// 不生成视频，把封面图片和每个 chunk 的长条图写入 `dir`，
+    /// 并写出记录各段时长和滚动位置的 `manifest.json`，供其他剪辑软件使用。
+    ///
+    /// 不需要 ffmpeg。[`rows`](super::VideoConfigBuilder::rows) 中各行的图片同样写入 `dir`，
+    /// 但不记录在 `manifest.json` 中。每个 chunk 的长条图总是保存为一张图片，
+    /// 忽略 [`tile_width`](super::VideoConfigBuilder::tile_width) 和
+    /// [`intermediate_format`](super::VideoConfigBuilder::intermediate_format)。
+    ///
+    /// # Results
+    /// `manifest.json` 的路径。
+    pub fn export(self, dir: &Path) -> Result<PathBuf> {
+        self.require_scroll("export")?;
+        fs::create_dir_all(dir)?;
+        let chunks_len = self.chunks.len();
+        let mut renderer = Renderer::in_dir(&self.config, &self.rows, dir.to_path_buf())?;
+        renderer.tile_width = None;
+        renderer.intermediate = IntermediateFormat::Png;
+        let scale = f64::from(renderer.scale);
+        let mut clock = FrameClock::new(self.config.fps);
+        let mut segments = Vec::with_capacity(chunks_len + 1);
+
+        let mut files = Vec::new();
+        let start_frame = clock.elapsed_frames();
+        renderer.cover(&self.chunks[0], &mut clock, &mut files)?;
+        let cover_files: Vec<PathBuf> = files
+            .into_iter()
+            .filter(|file| file.extension().is_some_and(|ext| ext == "png"))
+            .collect();
+        let columns = cover_files.len();
+        let mut x = 0;
+        let images = cover_files
+            .into_iter()
+            .zip(&self.chunks[0].slides)
+            .enumerate()
+            .map(|(i, (file, slide))| {
+                let (start_sec, duration_sec) =
+                    self.config
+                        .cover_entry
+                        .timing(i, columns, self.config.cover_sec);
+                let image = CoverImage {
+                    file,
+                    x: (x as f32 * renderer.scale).round() as u32,
+                    start_sec,
+                    duration_sec,
+                };
+                x += slide.width().unwrap_or(self.config.width_slides);
+                image
+            })
+            .collect();
+        segments.push(Segment::Cover {
+            images,
+            start_frame,
+            frames: clock.elapsed_frames() - start_frame,
+            motion_type: self.config.motion_type,
+            direction: self.config.cover_entry.direction,
+        });
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let start_frame = clock.elapsed_frames();
+            let phase = clock.phase();
+            let video = renderer
+                .chunk(index, chunk, chunks_len, &mut clock, &mut Vec::new())?
+                .name;
+            let frames = clock.elapsed_frames() - start_frame;
+            let (mut scroll, _) = renderer.scroll(index, chunk, chunks_len);
+            scroll.phase = phase;
+            let fps = f64::from(self.config.fps);
+            segments.push(Segment::Scroll {
+                image: video.with_extension("png"),
+                start_frame,
+                frames,
+                keyframes: scroll
+                    .keyframes
+                    .iter()
+                    .map(|k| Keyframe {
+                        x: k.x * scale,
+                        ..*k
+                    })
+                    .collect(),
+                motion_type: scroll.motion_type,
+                offsets: (0..frames)
+                    .map(|frame| scroll.offset(frame as f64 / fps) * scale)
+                    .collect(),
+            });
+        }
+
+        let manifest = Manifest {
+            fps: self.config.fps,
+            screen: renderer.out_screen,
+            orientation: renderer.orientation,
+            back_color: self.config.back_color.clone(),
+            total_frames: clock.elapsed_frames(),
+            segments,
+        };
+        let path = dir.join("manifest.json");
+        fs::write(&path, serde_json::to_string_pretty(&manifest)?)?;
+        Ok(path)
+    }
+}
@@ -1,14 +1,27 @@
-use crate::{Result, color::Color, slide::Slide};
-use ab_glyph::FontArc;
-use image::{DynamicImage, GenericImage};
+use super::{
+    VideoConfig, animated::AnimatedOverlay, ken_burns::KenBurnsOverlay, orientation::Orientation,
+    ranking::CountUpOverlay, scroll::Scroll, slideshow::Transition,
+};
+use crate::{
+    Result,
+    color::Color,
+    slide::{RenderContext, Slide},
+};
+use image::{
+    DynamicImage, GenericImage, Rgb, RgbImage, RgbaImage,
+    codecs::png::{CompressionType, FilterType, PngEncoder},
+};
 use serde::{Deserialize, Serialize};
 use std::{
-    ffi::OsStr,
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fs::{self, File},
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
     process::Command,
 };
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MotionType {
     Linear,    // 匀速运动
     EaseIn,    // 缓入
@@ -25,12 +38,230 @@ impl MotionType {
             MotionType::EaseInOut => format!("(cos({ranges}*3.14)+1)/2"),
         }
     }
+
+    /// 与 [`get_motion_range`](Self::get_motion_range) 相同，但直接计算 `ranges` 处的值
+    #[allow(clippy::approx_constant)]
+    pub fn motion_range(&self, ranges: f64) -> f64 {
+        // 与 ffmpeg 表达式中的 3.14 保持一致
+        match self {
+            MotionType::Linear => 1.0 - ranges,
+            MotionType::EaseIn => (ranges * 3.14 / 2.0).cos(),
+            MotionType::EaseOut => 1.0 - (ranges * 3.14 / 2.0).sin(),
+            MotionType::EaseInOut => ((ranges * 3.14).cos() + 1.0) / 2.0,
+        }
+    }
+}
+
+/// 封面各列的入场方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Direction {
+    #[default]
+    FromBottom,
+    FromTop,
+    FromLeft,
+    FromRight,
+    /// 原地淡入
+    FadeIn,
+}
+
+/// 封面各列的入场方式和时间
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoverEntry {
+    #[serde(default)]
+    pub direction: Direction,
+    /// 每列入场动画的秒数，`None` 时为 `cover_sec` 除以列数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sec: Option<f32>,
+    /// 各列开始入场的时间（秒），缺少的列在前一列开始 `sec` 秒后入场
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delays: Vec<f32>,
+}
+
+impl CoverEntry {
+    /// 共 `columns` 列时第 `index` 列开始入场的时间和入场动画的时长
+    pub fn timing(&self, index: usize, columns: usize, cover_sec: f32) -> (f32, f32) {
+        let sec = self.sec.unwrap_or(cover_sec / columns.max(1) as f32);
+        let start = match self.delays.get(index) {
+            Some(&delay) => delay,
+            None => match self.delays.last() {
+                Some(&last) => last + (index + 1 - self.delays.len()) as f32 * sec,
+                None => index as f32 * sec,
+            },
+        };
+        (start, sec)
+    }
+
+    /// 第 `index` 列叠加到画布上的 `overlay` 选项
+    fn overlay(&self, x_pos: u32, height: u32, ranges: &str) -> String {
+        match self.direction {
+            Direction::FromBottom => format!("x={x_pos}:y='({ranges})*{height}'"),
+            Direction::FromTop => format!("x={x_pos}:y='-({ranges})*{height}'"),
+            Direction::FromLeft => format!("x='{x_pos}-({ranges})*({x_pos}+w)':y=0"),
+            Direction::FromRight => format!("x='{x_pos}+({ranges})*(W-{x_pos})':y=0"),
+            Direction::FadeIn => format!("x={x_pos}:y=0"),
+        }
+    }
+}
+
+/// 叠加在长条图上的一行，见 [`RowConfig`](super::row::RowConfig)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowOverlay {
+    pub pic_name: PathBuf,
+    /// 行的上边缘在屏幕上的 y 坐标
+    pub y: u32,
+    /// 相对于主长条图的滚动速度
+    pub speed: f64,
+    /// 图片左边缘相对于滚动起点额外向左偏移的像素数（不足一像素的部分）
+    pub shift: f64,
+}
+
+/// chunk 长条图交给 ffmpeg 之前的保存格式。各格式都是无损的，生成的视频相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntermediateFormat {
+    /// 默认压缩级别的 PNG
+    #[default]
+    Png,
+    /// 最快压缩级别的 PNG，文件更大，编码更快
+    FastPng,
+    /// 不压缩的原始像素，由 ffmpeg 以 rawvideo 读取。写入最快，但占用的磁盘空间最多
+    Raw,
+}
+
+impl IntermediateFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            IntermediateFormat::Png | IntermediateFormat::FastPng => "png",
+            IntermediateFormat::Raw => "raw",
+        }
+    }
+
+    /// 按此格式保存图片
+    ///
+    /// # Results
+    /// 保存为原始像素时返回 ffmpeg 读取所需的尺寸和像素格式。
+    ///
+    /// # Errors
+    /// - 如果图片编码或文件写入失败，则返回 `Err`。
+    ///
+    pub fn save(&self, img: &DynamicImage, path: &Path) -> Result<Option<RawFrame>> {
+        match self {
+            IntermediateFormat::Png => img.save(path)?,
+            IntermediateFormat::FastPng => {
+                let encoder = PngEncoder::new_with_quality(
+                    BufWriter::new(File::create(path)?),
+                    CompressionType::Fast,
+                    FilterType::Adaptive,
+                );
+                img.write_with_encoder(encoder)?;
+            }
+            IntermediateFormat::Raw => {
+                let size = (img.width(), img.height());
+                let pix_fmt = match img {
+                    DynamicImage::ImageRgb8(img) => {
+                        fs::write(path, img.as_raw())?;
+                        "rgb24"
+                    }
+                    DynamicImage::ImageRgba8(img) => {
+                        fs::write(path, img.as_raw())?;
+                        "rgba"
+                    }
+                    img => {
+                        fs::write(path, img.to_rgba8().as_raw())?;
+                        "rgba"
+                    }
+                };
+                return Ok(Some(RawFrame { size, pix_fmt }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// 以原始像素保存的图片的尺寸和 ffmpeg 像素格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawFrame {
+    pub size: (u32, u32),
+    pub pix_fmt: &'static str,
+}
+
+/// 组成长条图的一段图片
+#[derive(Debug, Clone, PartialEq)]
+pub struct StripTile {
+    pub pic_name: PathBuf,
+    /// 在长条图中的 x 坐标
+    pub x: u32,
+    /// 以 [`IntermediateFormat::Raw`] 保存时的尺寸和像素格式，`None` 时为 PNG 图片
+    pub raw: Option<RawFrame>,
+}
+
+impl StripTile {
+    /// 把图片作为一路无限循环的输入加入命令
+    fn input(&self, command: FfmpegCommand) -> FfmpegCommand {
+        match self.raw {
+            Some(RawFrame {
+                size: (width, height),
+                pix_fmt,
+            }) => command
+                .args(["-f", "rawvideo", "-pix_fmt", pix_fmt])
+                .args(["-video_size", &format!("{width}x{height}")])
+                .args(["-framerate", "1", "-stream_loop", "-1"])
+                .input(&self.pic_name),
+            None => command
+                .args(["-r", "1", "-loop", "1"])
+                .input(&self.pic_name),
+        }
+    }
+}
+
+/// 视频结尾的效果，作用在最后一个 chunk 上
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum EndingMode {
+    /// 在最后一帧停留 `ending_sec` 秒
+    #[default]
+    HoldLastFrame,
+    /// 在视频的最后 `sec` 秒淡出到 `color`
+    FadeToColor { sec: f32, color: Color },
+    /// 在结尾停留的 `ending_sec` 秒内淡出到背景色。
+    ///
+    /// 视频的第一帧是封面的背景，循环播放时结尾与开头无缝衔接。
+    LoopBackToStart,
+}
+
+impl EndingMode {
+    /// 时长为 `duration` 秒的最后一段视频需要添加的淡出效果
+    pub fn fade(&self, duration: f64, ending_sec: u32, back_color: &str) -> Option<Fade> {
+        let (sec, color) = match self {
+            EndingMode::HoldLastFrame => return None,
+            EndingMode::FadeToColor { sec, color } => (
+                f64::from(*sec),
+                format!("0x{:02x}{:02x}{:02x}", color[0], color[1], color[2]),
+            ),
+            EndingMode::LoopBackToStart => (f64::from(ending_sec), back_color.to_string()),
+        };
+        let sec = sec.min(duration);
+        Some(Fade {
+            start: duration - sec,
+            duration: sec,
+            color,
+        })
+    }
+}
+
+/// 从 `start` 秒开始、持续 `duration` 秒的淡出到 `color`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fade {
+    pub start: f64,
+    pub duration: f64,
+    pub color: String,
 }
 
 /// 将多个图像块组合成一个完整的图像。
 ///
 /// # Parameters
 /// - `slides`: 要组合的图像块切片。
+/// - `ctx`: 渲染使用的字体和选项。
+/// - `width_slides`: 没有单独设置宽度的 slide 使用的宽度。
 ///
 /// # Results
 /// 如果成功，则返回组合后的 `DynamicImage`；如果失败，则返回 `Err`。
@@ -41,70 +272,246 @@ impl MotionType {
 ///
 pub fn combain_slides(
     slides: &[Slide],
-    font: &FontArc,
+    ctx: &RenderContext,
+    width_slides: u32,
+    screen: (u32, u32),
+) -> Result<DynamicImage> {
+    combain_rendered_slides(slides, &[], ctx, width_slides, screen, None)
+}
+
+/// 与 [`combain_slides`] 相同，但用 `background` 填充透明部分，得到不带透明通道的 RGB8 图片。
+///
+/// 每个 slide 先单独渲染再填充到长条图中，长条图本身少占用 1/4 的内存，保存 PNG 也更快。
+///
+/// # Errors
+/// - 如果 `slides` 为空，则返回 `Err`。
+/// - 如果图像处理过程中发生错误，则返回 `Err`。
+///
+pub fn combain_slides_opaque(
+    slides: &[Slide],
+    ctx: &RenderContext,
+    width_slides: u32,
+    screen: (u32, u32),
+    background: Color,
+) -> Result<DynamicImage> {
+    combain_rendered_slides(slides, &[], ctx, width_slides, screen, Some(background))
+}
+
+/// 组合长条图，开头的若干 slide 直接使用 `rendered` 中已经渲染好的图片，不再重新渲染。
+///
+/// # Parameters
+/// - `rendered`: 依次对应 `slides` 开头各 slide 的渲染结果，尺寸必须与 slide 一致。
+/// - `background`: 为 `Some` 时同 [`combain_slides_opaque`]，否则同 [`combain_slides`]。
+///
+/// # Errors
+/// - 如果 `slides` 为空，或 `rendered` 比 `slides` 多，则返回 `Err`。
+/// - 如果图像处理过程中发生错误，则返回 `Err`。
+///
+pub fn combain_rendered_slides(
+    slides: &[Slide],
+    rendered: &[RgbaImage],
+    ctx: &RenderContext,
     width_slides: u32,
     screen: (u32, u32),
-    split_line_color: Option<Color>,
+    background: Option<Color>,
 ) -> Result<DynamicImage> {
     if slides.is_empty() {
         return Err("Empty slides".into());
     }
+    if rendered.len() > slides.len() {
+        return Err(format!(
+            "{} rendered images for {} slides",
+            rendered.len(),
+            slides.len()
+        )
+        .into());
+    }
 
-    let len = u32::try_from(slides.len())?;
-    let mut target = DynamicImage::new_rgba8(len * width_slides, screen.1);
+    let widths: Vec<u32> = slides
+        .iter()
+        .map(|slide| slide.width().unwrap_or(width_slides))
+        .collect();
+    let width = widths.iter().sum();
+    let mut x = 0;
+    match background {
+        None => {
+            let mut target = DynamicImage::new_rgba8(width, screen.1);
+            for (i, (item, width)) in slides.iter().zip(widths).enumerate() {
+                match rendered.get(i) {
+                    Some(img) => target.copy_from(img, x, 0)?,
+                    // 将 slide 直接绘制到目标图像中
+                    None => item.render_into(&mut target, x, (width, screen.1), ctx)?,
+                }
+                x += width;
+            }
+            Ok(target)
+        }
+        Some(background) => {
+            let mut target = RgbImage::new(width, screen.1);
+            for (i, (item, width)) in slides.iter().zip(widths).enumerate() {
+                let img = match rendered.get(i) {
+                    Some(img) => flatten(img, background),
+                    None => flatten(
+                        &item.render((width, screen.1), ctx)?.into_rgba8(),
+                        background,
+                    ),
+                };
+                target.copy_from(&img, x, 0)?;
+                x += width;
+            }
+            Ok(DynamicImage::ImageRgb8(target))
+        }
+    }
+}
 
-    // 将每张图片绘制到目标图像中
-    for (i, item) in slides.iter().enumerate() {
-        let img = item.render((width_slides, screen.1), font, split_line_color)?;
-        target.copy_from(&img, u32::try_from(i)? * width_slides, 0)?;
+/// 把图片混合到纯色背景上，去掉透明通道
+pub fn flatten(img: &RgbaImage, background: Color) -> RgbImage {
+    RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let [r, g, b, a] = img.get_pixel(x, y).0;
+        let alpha = u16::from(a);
+        let blend = |c: u8, bg: u8| {
+            ((u16::from(c) * alpha + u16::from(bg) * (255 - alpha) + 127) / 255) as u8
+        };
+        Rgb([
+            blend(r, background[0]),
+            blend(g, background[1]),
+            blend(b, background[2]),
+        ])
+    })
+}
+
+/// 解析 ffmpeg 的背景色，支持 `#rrggbb`、`0xrrggbb` 和常用的颜色名称。
+///
+/// 带透明度（`@0.5`）或无法识别时返回 `None`。
+pub fn parse_back_color(back_color: &str) -> Option<Color> {
+    let back_color = back_color.trim();
+    if let Some(hex) = back_color.strip_prefix("0x") {
+        return Color::try_from(format!("#{hex}").as_str()).ok();
+    }
+    if back_color.starts_with('#') {
+        return Color::try_from(back_color).ok();
     }
-    Ok(target)
+    let rgb = match back_color.to_ascii_lowercase().as_str() {
+        "white" => [255, 255, 255],
+        "black" => [0, 0, 0],
+        "red" => [255, 0, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "gray" | "grey" => [128, 128, 128],
+        _ => return None,
+    };
+    Some(Color(rgb))
 }
 
+/// 生成封面视频，封面中的 slide 依次从上方落下。
+///
+/// # Parameters
+/// - `input_images`: 封面图片名称及其在屏幕上的 x 坐标（长条图坐标系）。
+/// - `screen`: 输出视频的分辨率。
+/// - `orientation`: 画面方向，竖屏时图片已转到长条图坐标系，见 [`Orientation`]。
+/// - `frames`: 视频的总帧数。
+/// - `entry`: 各列的入场方向和时间，方向是输出画面中的方向。
+/// - `rows`: 静止地叠加在封面上的其他行。
+///
+/// # Errors
+/// - 如果 `FFmpeg` 命令执行失败，则返回 `Err`。
+///
+#[allow(clippy::too_many_arguments)]
 pub fn generate_cover_video(
+    ffmpeg: &Ffmpeg,
     encoder: &str,
-    input_images: Vec<String>,
+    preset: &str,
+    input_images: Vec<(String, u32)>,
     cover_sec: f32,
     back_color: &str,
     screen: (u32, u32),
-    width_slides: u32,
+    orientation: Orientation,
     fps: u32,
+    frames: u64,
     motion_type: MotionType,
+    entry: &CoverEntry,
+    rows: &[RowOverlay],
     work_dir: &Path,
     video_name: &Path,
 ) -> Result<()> {
-    let (width, height) = screen;
+    cover_video_command(
+        ffmpeg,
+        encoder,
+        preset,
+        input_images,
+        cover_sec,
+        back_color,
+        screen,
+        orientation,
+        fps,
+        frames,
+        motion_type,
+        entry,
+        rows,
+        video_name,
+    )
+    .run(work_dir)
+}
+
+/// 构建生成封面视频的命令，参数与 [`generate_cover_video`] 相同。
+#[allow(clippy::too_many_arguments)]
+pub fn cover_video_command(
+    ffmpeg: &Ffmpeg,
+    encoder: &str,
+    preset: &str,
+    input_images: Vec<(String, u32)>,
+    cover_sec: f32,
+    back_color: &str,
+    screen: (u32, u32),
+    orientation: Orientation,
+    fps: u32,
+    frames: u64,
+    motion_type: MotionType,
+    entry: &CoverEntry,
+    rows: &[RowOverlay],
+    video_name: &Path,
+) -> FfmpegCommand {
+    let (width, height) = orientation.strip_screen(screen);
     let num_images = input_images.len();
-    let fade_duration = cover_sec / num_images as f32;
+    let entry = &CoverEntry {
+        direction: orientation.strip_direction(entry.direction),
+        ..entry.clone()
+    };
 
+    let mut command = ffmpeg.command();
     // 添加输入图片
-    let inputs: String = input_images
-        .iter()
-        .map(|img| format!("-i {img} "))
-        .collect();
+    for (img, _) in &input_images {
+        command = command.input(img);
+    }
     let mut filters = String::new();
 
     // 创建基础画布
+    let back_color = escape_filter_value(back_color);
     filters.push_str(&format!(
         "color={back_color}:s={width}x{height}:r={fps}[base];"
     ));
 
     // 处理每张图片
-    for i in 0..num_images {
-        let start_time = i as f32 * fade_duration;
+    for (i, &(_, x_pos)) in input_images.iter().enumerate() {
+        let (start_time, fade_duration) = entry.timing(i, num_images, cover_sec);
 
         // 图片输入和格式转换
         filters.push_str(&format!(
-            "[{i}:v]format=yuva420p,setpts=PTS-STARTPTS+{start_time}/TB[v{i}];"
+            "[{i}:v]format=yuva420p,setpts=PTS-STARTPTS+{start_time}/TB"
         ));
+        if entry.direction == Direction::FadeIn {
+            filters.push_str(&format!(
+                ",fade=t=in:st={start_time}:d={fade_duration}:alpha=1"
+            ));
+        }
+        filters.push_str(&format!("[v{i}];"));
 
-        // 计算水平位置（x坐标）和垂直运动（y坐标）
-        let x_pos = i as u32 * width_slides;
-
+        // 入场运动，ranges 从 1 变化到 0
         let ranges = motion_type.get_motion_range(&format!(
             "clip(t-{start_time},0,{fade_duration})/{fade_duration}",
         ));
-        let y_expr = format!("({ranges})*{height}");
+        let overlay = entry.overlay(x_pos, height, &ranges);
 
         // 叠加到画布
         let input = if i == 0 {
@@ -112,57 +519,275 @@ pub fn generate_cover_video(
         } else {
             format!("tmp{}", i - 1)
         };
+        filters.push_str(&format!("[{input}][v{i}]overlay={overlay}[tmp{i}];"));
+    }
+
+    let mut output = format!("tmp{}", num_images - 1);
+    for (i, row) in rows.iter().enumerate() {
+        command = command.input(&row.pic_name);
+        let y = row.y;
         filters.push_str(&format!(
-            "[{input}][v{i}]overlay=x={x_pos}:y='{y_expr}'[tmp{i}];"
+            "[{output}][{}]overlay=x=0:y={y}[row{i}];",
+            num_images + i
         ));
+        output = format!("row{i}");
+    }
+    if let Some(filter) = orientation.filter() {
+        filters.push_str(&format!("[{output}]{filter}[out];"));
+        output = "out".to_string();
     }
 
-    let ffmpeg_args = format!(
-        "{inputs} -filter_complex {} -map [tmp{}] \
-        -c:v {encoder} -r 60 -b:v 4000k -preset fast -movflags +faststart -t {cover_sec} {}",
-        filters.trim_end_matches(';'),
-        num_images - 1,
-        video_name.display()
-    );
-
-    ffmpeg(work_dir, ffmpeg_args.split_ascii_whitespace())
+    command
+        .filter_complex(filters.trim_end_matches(';'))
+        .map(&output)
+        .encoder(encoder, preset, fps)
+        .frames(frames, fps)
+        .output(video_name)
 }
 
 /// 生成中间部分的视频。
 ///
 /// # Parameters
-/// - `len`: 素材图片中 `slides` 数量。
-/// - `pic_name`: 素材图片名称。
+/// - `tiles`: 组成长条图的各段图片，只有一段时即为整张长条图。
 /// - `video_name`: 生成视频名称。
+/// - `screen`: 输出视频的分辨率。
+/// - `orientation`: 画面方向，竖屏时长条图和各坐标都在长条图坐标系中，见 [`Orientation`]。
+/// - `scroll`: 素材图片的滚动方式。
+/// - `frames`: 视频的总帧数，超出滚动时长的部分保持静止。
+/// - `fade`: 结尾的淡出效果。
+/// - `rows`: 按各自速度随长条图滚动的其他行。
+/// - `ken_burns`: 随长条图滚动的动画图片，位于其他行的下方。
+/// - `count_ups`: 随长条图滚动的数字动画，位于动画图片之上、其他行的下方。
 ///
 /// # Errors
 /// - 如果 `FFmpeg` 命令执行失败，则返回 `Err`。
 ///
 #[allow(clippy::too_many_arguments)]
 pub fn generate_mid_video(
+    ffmpeg: &Ffmpeg,
     encoder: &str,
-    pic_name: &Path,
+    preset: &str,
+    tiles: &[StripTile],
     video_name: &Path,
     screen: (u32, u32),
-    swip_pixels_per_sec: u32,
+    orientation: Orientation,
     back_color: &str,
     fps: u32,
-    move_sec: u32,
-    static_sec: u32,
+    scroll: &Scroll,
+    frames: u64,
+    fade: Option<&Fade>,
+    rows: &[RowOverlay],
+    ken_burns: &[KenBurnsOverlay],
+    count_ups: &[CountUpOverlay],
     work_dir: &Path,
 ) -> Result<()> {
-    let (width, height) = screen;
-    let ffmpeg_args = format!(
-        "-r 1 -loop 1 -i {} \
-        -filter_complex \
-        color={back_color}:s={width}x{height}:r={fps}[bg];\
-        [bg][0]overlay=x='-{swip_pixels_per_sec}*clip(t,0,{move_sec})' \
-        -c:v {encoder} -r 60 -b:v 4000k -preset fast -movflags +faststart -t {} {}",
-        pic_name.display(),
-        move_sec + static_sec,
-        video_name.display()
+    mid_video_command(
+        ffmpeg,
+        encoder,
+        preset,
+        tiles,
+        video_name,
+        screen,
+        orientation,
+        back_color,
+        fps,
+        scroll,
+        frames,
+        fade,
+        rows,
+        ken_burns,
+        count_ups,
+    )
+    .run(work_dir)
+}
+
+/// 构建生成中间部分视频的命令，参数与 [`generate_mid_video`] 相同。
+#[allow(clippy::too_many_arguments)]
+pub fn mid_video_command(
+    ffmpeg: &Ffmpeg,
+    encoder: &str,
+    preset: &str,
+    tiles: &[StripTile],
+    video_name: &Path,
+    screen: (u32, u32),
+    orientation: Orientation,
+    back_color: &str,
+    fps: u32,
+    scroll: &Scroll,
+    frames: u64,
+    fade: Option<&Fade>,
+    rows: &[RowOverlay],
+    ken_burns: &[KenBurnsOverlay],
+    count_ups: &[CountUpOverlay],
+) -> FfmpegCommand {
+    let (width, height) = orientation.strip_screen(screen);
+    let back_color = escape_filter_value(back_color);
+    let expr = scroll.expr();
+    let mut filters = format!(
+        "color={back_color}:s={width}x{height}:r={fps}[bg];\
+        [bg][0]overlay=x='-({expr})'"
     );
-    ffmpeg(work_dir, ffmpeg_args.split_ascii_whitespace())
+    // 长条图的其他分段按各自的 x 坐标叠加
+    for (i, StripTile { x, .. }) in tiles.iter().enumerate().skip(1) {
+        filters.push_str(&format!("[tile{i}];[tile{i}][{i}]overlay=x='{x}-({expr})'"));
+    }
+    // 各行的输入排在长条图之后，动画图片的输入排在各行之后
+    let time = format!("(in/{fps})");
+    for (i, layer) in ken_burns.iter().enumerate() {
+        let progress = format!(
+            "clip(({}-{:.3})/{:.3},0,1)",
+            scroll.expr_of(&time),
+            layer.start,
+            layer.len
+        );
+        let mut zoompan = layer.ken_burns.zoompan(&progress, layer.size, fps);
+        if let Some(filter) = orientation.strip_filter() {
+            zoompan = format!("{zoompan},{filter}");
+        }
+        let (x, y) = (layer.x, layer.y);
+        filters.push_str(&format!(
+            "[kbs{i}];[{}]{zoompan}[kb{i}];[kbs{i}][kb{i}]overlay=x='{x:.3}-({expr})':y={y}",
+            tiles.len() + rows.len() + i
+        ));
+    }
+    // 数字动画的各步画面排在动画图片之后，按滚动进度依次显示
+    let mut input = tiles.len() + rows.len() + ken_burns.len();
+    for (i, layer) in count_ups.iter().enumerate() {
+        let progress = format!(
+            "clip(({expr}-{:.3})/{:.3},0,1)*{}",
+            layer.start,
+            layer.len,
+            layer.frames.len().saturating_sub(1)
+        );
+        let (x, y) = (layer.x, layer.y);
+        for k in 0..layer.frames.len() {
+            filters.push_str(&format!(
+                "[cu{i}_{k}];[cu{i}_{k}][{input}]overlay=x='{x:.3}-({expr})':y={y}:\
+                 enable='between({progress},{k}-0.5,{k}+0.5)'"
+            ));
+            input += 1;
+        }
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let (speed, shift, y) = (row.speed, row.shift, row.y);
+        filters.push_str(&format!(
+            "[row{i}];[row{i}][{}]overlay=x='-({speed:.6}*({expr})+{shift:.3})':y={y}",
+            tiles.len() + i
+        ));
+    }
+    if let Some(fade) = fade {
+        filters.push_str(&format!(
+            ",fade=t=out:st={:.3}:d={:.3}:color={}",
+            fade.start,
+            fade.duration,
+            escape_filter_value(&fade.color)
+        ));
+    }
+    if let Some(filter) = orientation.filter() {
+        filters.push_str(&format!(",{filter}"));
+    }
+    let mut command = ffmpeg.command();
+    for tile in tiles {
+        command = tile.input(command);
+    }
+    for row in rows {
+        command = command.args(["-r", "1", "-loop", "1"]).input(&row.pic_name);
+    }
+    for layer in ken_burns {
+        command = command
+            .args(["-loop", "1", "-framerate", &fps.to_string()])
+            .input(&layer.path);
+    }
+    for frame in count_ups.iter().flat_map(|layer| &layer.frames) {
+        command = command
+            .args(["-loop", "1", "-framerate", &fps.to_string()])
+            .input(frame);
+    }
+    command
+        .filter_complex(&filters)
+        .encoder(encoder, preset, fps)
+        .frames(frames, fps)
+        .output(video_name)
+}
+
+/// 构建幻灯片模式中一页的视频命令：从上一页切换到这一页，之后静止。
+///
+/// # Parameters
+/// - `page`: 这一页的图片及其在屏幕上的 x 坐标，透明部分显示 `back_color`。
+/// - `previous`: 上一页的图片及其 x 坐标，为 `None` 时直接显示这一页。
+/// - `animated`: 叠加在这一页上播放的动态图片，见 [`AnimatedOverlay`]。
+/// - `transition`: 切换效果和秒数，见 [`Transition`](super::slideshow::Transition)。
+/// - `frames`: 视频的总帧数。
+/// - `fade`: 结尾的淡出效果。
+///
+#[allow(clippy::too_many_arguments)]
+pub fn page_video_command(
+    ffmpeg: &Ffmpeg,
+    encoder: &str,
+    preset: &str,
+    page: (&Path, u32),
+    previous: Option<(&Path, u32)>,
+    animated: &[AnimatedOverlay],
+    transition: (Transition, f32),
+    video_name: &Path,
+    screen: (u32, u32),
+    back_color: &str,
+    fps: u32,
+    frames: u64,
+    fade: Option<&Fade>,
+) -> FfmpegCommand {
+    let (width, height) = screen;
+    let back_color = escape_filter_value(back_color);
+    let canvas = format!("color={back_color}:s={width}x{height}:r={fps}");
+    let (transition, sec) = transition;
+    let mut command = ffmpeg.command();
+    let previous = previous.zip(transition.xfade());
+    // 动态图片的输入在这一页的图片之后
+    let first_animated = if previous.is_some() { 2 } else { 1 };
+    let page_filter = |bg: &str| {
+        let mut filter = format!("[{bg}][{}]overlay=x={}", first_animated - 1, page.1);
+        for (index, overlay) in animated.iter().enumerate() {
+            let base = format!("anim{index}");
+            filter.push_str(&format!(
+                "[{base}];{}",
+                overlay.filter(first_animated + index, &base)
+            ));
+        }
+        filter
+    };
+    let mut filters = match previous {
+        Some(((previous, previous_x), xfade)) => {
+            command = command.args(["-r", "1", "-loop", "1"]).input(previous);
+            format!(
+                "{canvas},split[bg0][bg1];[bg0][0]overlay=x={previous_x}[prev];\
+                 {}[page];\
+                 [prev][page]xfade=transition={xfade}:duration={sec:.3}:offset=0",
+                page_filter("bg1")
+            )
+        }
+        None => format!("{canvas}[bg];{}", page_filter("bg")),
+    };
+    if let Some(fade) = fade {
+        filters.push_str(&format!(
+            ",fade=t=out:st={:.3}:d={:.3}:color={}",
+            fade.start,
+            fade.duration,
+            escape_filter_value(&fade.color)
+        ));
+    }
+    command = command.args(["-r", "1", "-loop", "1"]).input(page.0);
+    for overlay in animated {
+        command = overlay.input(command);
+    }
+    if !animated.is_empty() {
+        // 动态图片是视频时不带上其中的音轨
+        command = command.arg("-an");
+    }
+    command
+        .filter_complex(&filters)
+        .encoder(encoder, preset, fps)
+        .frames(frames, fps)
+        .output(video_name)
 }
 
 /// 合并多个文件为单个输出文件，使用ffmpeg的concat协议
@@ -170,22 +795,47 @@ pub fn generate_mid_video(
 /// # Parameters
 /// - `results`: 需要合并的源文件路径列表
 /// - `save_name`: 合并后的输出文件路径
+/// - `metadata`: 写入输出文件的元数据，例如 `title`、`artist`、`comment`
 ///
 /// # Errors
 /// - 如果文件写入或 `FFmpeg` 命令执行失败，则返回 `Err`。
 ///
-pub fn combain(results: &mut Vec<PathBuf>, work_dir: &Path, save_path: &Path) -> Result<()> {
+pub fn combain(
+    ffmpeg: &Ffmpeg,
+    results: &mut Vec<PathBuf>,
+    work_dir: &Path,
+    save_path: &Path,
+    metadata: &HashMap<String, String>,
+) -> Result<()> {
+    concat_command(ffmpeg, results, work_dir, save_path, metadata)?.run(work_dir)?;
+
+    println!("{} successed", save_path.display());
+    Ok(())
+}
+
+/// 写入 concat 列表文件并构建合并命令，参数与 [`combain`] 相同。
+///
+/// # Errors
+/// - 如果文件写入失败，则返回 `Err`。
+///
+pub fn concat_command(
+    ffmpeg: &Ffmpeg,
+    results: &mut Vec<PathBuf>,
+    work_dir: &Path,
+    save_path: &Path,
+    metadata: &HashMap<String, String>,
+) -> Result<FfmpegCommand> {
     // 构建ffmpeg concat协议要求的输入文件列表字符串
     // 格式示例：
-    //file /path/to/file1
-    //file /path/to/file2
+    //file '/path/to/file1'
+    //file '/path/to/file2'
+    // 列表中使用绝对路径，不依赖 ffmpeg 按列表文件所在目录解析相对路径的方式
+    let work_dir = std::path::absolute(work_dir)?;
     let result_str: String = results
         .iter()
-        .filter_map(|s| {
-            s.to_str()
-                .and_then(|ss| ss.ends_with("mp4").then(|| format!("file {}\n", ss)))
-        })
-        .collect();
+        .filter(|s| s.extension().is_some_and(|ext| ext == "mp4"))
+        .map(|s| concat_list_entry(&work_dir.join(s)))
+        .collect::<Result<_>>()?;
 
     // 将文件列表写入临时文本文件
     let list_file = "list.txt";
@@ -193,44 +843,933 @@ pub fn combain(results: &mut Vec<PathBuf>, work_dir: &Path, save_path: &Path) ->
     results.push(PathBuf::from(list_file));
 
     // 调用ffmpeg执行合并操作
-    let ffmpeg_args = format!(
-        "-f concat -i {list_file} -c copy -y {}",
-        save_path.display()
+    Ok(ffmpeg
+        .command()
+        // 绝对路径和含空格等字符的路径被 concat 视为不安全，需要 `-safe 0`
+        .args(["-f", "concat", "-safe", "0"])
+        .input(list_file)
+        .args(["-c", "copy"])
+        .metadata(metadata)
+        .output(save_path))
+}
+
+/// 分辨率为 `screen` 的输出文件路径：`<save_path 文件名>_<宽>x<高>.<扩展名>`
+pub fn rendition_path(save_path: &Path, (width, height): (u32, u32)) -> PathBuf {
+    let stem = save_path
+        .file_stem()
+        .map_or_else(|| "output".into(), |s| s.to_string_lossy());
+    let ext = save_path
+        .extension()
+        .map_or_else(|| "mp4".into(), |s| s.to_string_lossy());
+    save_path.with_file_name(format!("{stem}_{width}x{height}.{ext}"))
+}
+
+/// 把 `source` 缩放为 `screen` 分辨率并写入 `output` 的命令。
+///
+/// 保持画面比例缩放到 `screen` 之内，比例不同时在两侧或上下用 `back_color` 补齐。
+/// 音频流（如果有）直接复制。
+#[allow(clippy::too_many_arguments)]
+pub fn rendition_command(
+    ffmpeg: &Ffmpeg,
+    encoder: &str,
+    preset: &str,
+    source: &Path,
+    output: &Path,
+    (width, height): (u32, u32),
+    back_color: &str,
+    fps: u32,
+    metadata: &HashMap<String, String>,
+) -> FfmpegCommand {
+    let back_color = escape_filter_value(back_color);
+    let filter = format!(
+        "scale={width}:{height}:force_original_aspect_ratio=decrease,\
+         pad={width}:{height}:(ow-iw)/2:(oh-ih)/2:color={back_color},setsar=1"
     );
-    ffmpeg(work_dir, ffmpeg_args.split_ascii_whitespace())?;
+    ffmpeg
+        .command()
+        .input(source)
+        .args(["-vf", &filter])
+        .encoder(encoder, preset, fps)
+        .args(["-c:a", "copy"])
+        .metadata(metadata)
+        .output(output)
+}
 
-    println!("{} successed", save_path.display());
+/// ffmpeg 命令行参数构建器。
+///
+/// 每个参数单独传给进程，路径和滤镜表达式中的空格不会被拆开。
+/// 参数按调用顺序排列，输入选项需要在对应的 [`input`](Self::input) 之前添加。
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use to_video::video::ffmpeg::FfmpegCommand;
+/// FfmpegCommand::new(Path::new("ffmpeg"))
+///     .args(["-r", "1", "-loop", "1"])
+///     .input("my slide.png")
+///     .filter_complex("color=black:s=1920x1080[bg];[bg][0]overlay")
+///     .encoder("libx264", "fast", 60)
+///     .frames(600, 60)
+///     .output("my slide.mp4")
+///     .run(Path::new("."))
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FfmpegCommand {
+    program: PathBuf,
+    args: Vec<OsString>,
+    log: Option<PathBuf>,
+    dry_run: bool,
+    bitexact: bool,
+}
+
+impl FfmpegCommand {
+    /// # Parameters
+    /// - `ffmpeg_path` - ffmpeg 可执行文件，可以是 PATH 中的命令名
+    pub fn new(ffmpeg_path: &Path) -> Self {
+        Self {
+            program: ffmpeg_path.to_path_buf(),
+            args: ["-loglevel", "warning", "-y"].map(OsString::from).to_vec(),
+            log: None,
+            dry_run: false,
+            bitexact: false,
+        }
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_os_string()));
+        self
+    }
+
+    /// 添加输入文件 `-i <path>`
+    pub fn input(self, path: impl AsRef<OsStr>) -> Self {
+        self.arg("-i").arg(path)
+    }
+
+    /// 添加滤镜图 `-filter_complex <filters>`
+    pub fn filter_complex(self, filters: &str) -> Self {
+        self.arg("-filter_complex").arg(filters)
+    }
+
+    /// 选择滤镜图中的输出流 `-map [label]`
+    pub fn map(self, label: &str) -> Self {
+        self.arg("-map").arg(format!("[{label}]"))
+    }
+
+    /// 视频编码参数，所有中间视频使用同样的设置以便直接 concat
+    pub fn encoder(self, encoder: &str, preset: &str, fps: u32) -> Self {
+        self.args(["-c:v", encoder])
+            .args(["-r", &fps.to_string()])
+            .args(["-b:v", "4000k", "-preset", preset])
+            .args(["-movflags", "+faststart"])
+    }
+
+    /// 输出时长 `-t`，按帧数换算为秒，保证输出恰好 `frames` 帧
+    pub fn frames(self, frames: u64, fps: u32) -> Self {
+        self.args(["-t", &(frames as f64 / f64::from(fps.max(1))).to_string()])
+    }
+
+    /// 输出文件的元数据 `-metadata key=value`，按键名排序以保证参数顺序稳定
+    pub fn metadata(mut self, metadata: &HashMap<String, String>) -> Self {
+        let mut pairs: Vec<_> = metadata.iter().collect();
+        pairs.sort();
+        for (key, value) in pairs {
+            self = self.arg("-metadata").arg(format!("{key}={value}"));
+        }
+        self
+    }
+
+    /// 添加输出文件，应在所有输出选项之后调用
+    ///
+    /// 设置了 [`Ffmpeg::bitexact`] 时在输出文件之前加上保证输出可重现的选项。
+    pub fn output(self, path: impl AsRef<OsStr>) -> Self {
+        if !self.bitexact {
+            return self.arg(path);
+        }
+        self.args(["-fflags", "+bitexact", "-flags:v", "+bitexact"])
+            .args(["-threads", &BITEXACT_THREADS.to_string()])
+            .arg(path)
+    }
+
+    pub fn get_args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    /// 可以直接粘贴到 shell 中重新执行的命令行，包括切换工作目录
+    pub fn command_line(&self, work_dir: &Path) -> String {
+        let mut line = format!(
+            "cd {} && {}",
+            shell_quote(work_dir.as_os_str()),
+            shell_quote(self.program.as_os_str())
+        );
+        for arg in &self.args {
+            line.push(' ');
+            line.push_str(&shell_quote(arg));
+        }
+        line
+    }
+
+    /// 在 `work_dir` 中执行命令
+    ///
+    /// 设置了日志文件时先把命令追加到日志中；`dry_run` 时只打印命令，不执行。
+    ///
+    /// # Errors
+    /// - 无法执行ffmpeg命令时返回提示安装或设置 `ffmpeg_path` 的错误
+    /// - ffmpeg进程返回非零状态码时返回包含stderr的错误
+    ///
+    pub fn run(self, work_dir: &Path) -> Result<()> {
+        if !self.prepare(work_dir)? {
+            return Ok(());
+        }
+        let output = Command::new(&self.program)
+            .current_dir(work_dir)
+            .args(&self.args)
+            .output()
+            .map_err(|e| not_found(&self.program, e))?;
+        check_output(output)
+    }
+
+    /// 与 [`run`](Self::run) 相同，但通过 `tokio::process` 异步等待 ffmpeg 结束
+    #[cfg(feature = "async")]
+    pub async fn run_async(self, work_dir: &Path) -> Result<()> {
+        if !self.prepare(work_dir)? {
+            return Ok(());
+        }
+        let output = tokio::process::Command::new(&self.program)
+            .current_dir(work_dir)
+            .args(&self.args)
+            .output()
+            .await
+            .map_err(|e| not_found(&self.program, e))?;
+        check_output(output)
+    }
+
+    /// 写日志、处理 `dry_run`，返回是否需要真正执行命令
+    fn prepare(&self, work_dir: &Path) -> Result<bool> {
+        if let Some(log) = &self.log {
+            let mut file = fs::OpenOptions::new().create(true).append(true).open(log)?;
+            writeln!(file, "{}", self.command_line(work_dir))?;
+        }
+        if self.dry_run {
+            println!("{}", self.command_line(work_dir));
+        }
+        Ok(!self.dry_run)
+    }
+}
+
+fn check_output(output: std::process::Output) -> Result<()> {
+    if !output.status.success() {
+        let put = String::from_utf8(output.stderr)?;
+        return Err(format!("FFmpeg command failed: {}", put).into());
+    }
     Ok(())
 }
 
-/// 执行带有指定参数的FFmpeg命令
+/// 可重现输出时编码器使用的线程数。
 ///
-/// # Parameters
-/// - `config: &VideoConfig` - 包含工作路径配置的结构体实例引用
-/// - `args` - 传递给ffmpeg命令行工具的字符串参数切片
+/// x264 等编码器的输出与线程数有关，固定线程数后不同核数的机器也能得到相同的结果。
+const BITEXACT_THREADS: u32 = 4;
+
+/// ffmpeg 可执行文件以及命令的执行方式。
+#[derive(Debug, Clone)]
+pub struct Ffmpeg {
+    /// ffmpeg 可执行文件，可以是 PATH 中的命令名
+    pub path: PathBuf,
+    /// 把每条命令及其工作目录追加写入该文件
+    pub log: Option<PathBuf>,
+    /// 只打印命令，不执行
+    pub dry_run: bool,
+    /// 输出不含编码器版本和创建时间，并固定编码线程数，相同的输入总是得到相同的文件
+    pub bitexact: bool,
+}
+
+impl Ffmpeg {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            log: None,
+            dry_run: false,
+            bitexact: false,
+        }
+    }
+
+    /// 按配置中的 `ffmpeg_path`、`debug_commands`、`print_commands` 和 `deterministic` 创建
+    pub fn from_config(config: &VideoConfig) -> Self {
+        Self {
+            path: config.ffmpeg_path.clone(),
+            // 日志写在 work_dir 下，清理本次运行的临时目录后仍然保留
+            log: config
+                .debug_commands
+                .then(|| config.work_dir.join("commands.log")),
+            dry_run: config.print_commands,
+            bitexact: config.deterministic,
+        }
+    }
+
+    /// 按当前的执行方式创建命令
+    pub fn command(&self) -> FfmpegCommand {
+        FfmpegCommand {
+            log: self.log.clone(),
+            dry_run: self.dry_run,
+            bitexact: self.bitexact,
+            ..FfmpegCommand::new(&self.path)
+        }
+    }
+}
+
+/// 按 POSIX shell 的规则给参数加引号，只含安全字符的参数保持原样
+fn shell_quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:+,@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.into_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// 生成 concat 列表中的一行 `file '<path>'`。
 ///
-/// # Results
-/// - 成功时返回Ok(())，失败时返回包含上下文信息的Err
+/// 路径整体用单引号包裹，路径中的单引号写成 `'\''`，
+/// 因此空格、中文以及 Windows 路径中的反斜杠都能原样传给 ffmpeg。
 ///
 /// # Errors
-/// - 无法执行ffmpeg命令时返回IO错误
-/// - ffmpeg进程返回非零状态码时打印stderr到控制台并返回Other类型错误
-///
-pub fn ffmpeg<I, S>(work_dir: &Path, args: I) -> Result<()>
-where
-    I: IntoIterator<Item = S>,
-    S: AsRef<OsStr>,
-{
-    let command = Command::new("ffmpeg")
-        .current_dir(work_dir)
-        .arg("-loglevel")
-        .arg("warning")
-        .arg("-y")
-        .args(args)
-        .output()?;
-    if !command.status.success() {
-        let put = format!("{}", String::from_utf8(command.stderr)?);
-        return Err(format!("FFmpeg command failed: {}", put).into());
+/// - 路径不是合法的 UTF-8 时返回 `Err`。
+///
+pub fn concat_list_entry(path: &Path) -> Result<String> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| format!("{path:?} is not valid UTF-8"))?;
+    Ok(format!("file '{}'\n", path.replace('\'', r"'\''")))
+}
+
+/// 转义 `-filter_complex` 中作为选项值使用的字符串，例如背景颜色。
+///
+/// 先按选项值规则转义 `\ ' :`，再按滤镜图规则转义 `\ ' [ ] , ;`，
+/// 避免用户输入的值截断或改写整个滤镜表达式。
+pub fn escape_filter_value(value: &str) -> String {
+    let escape = |value: &str, special: &[char]| {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let value = escape(value, &['\\', '\'', ':']);
+    escape(&value, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+/// 运行 `ffmpeg -version` 检查 ffmpeg 是否可用，返回版本信息的第一行，
+/// 例如 `ffmpeg version 7.1 Copyright (c) 2000-2024 the FFmpeg developers`。
+///
+/// # Errors
+/// - 找不到或无法执行 ffmpeg 时返回提示安装或设置 `ffmpeg_path` 的错误。
+///
+pub fn ffmpeg_version(ffmpeg_path: &Path) -> Result<String> {
+    let output = Command::new(ffmpeg_path)
+        .arg("-version")
+        .output()
+        .map_err(|e| not_found(ffmpeg_path, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} -version failed, is it really ffmpeg?",
+            ffmpeg_path.display()
+        )
+        .into());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().unwrap_or_default().to_string())
+}
+
+fn not_found(ffmpeg_path: &Path, e: std::io::Error) -> String {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        format!(
+            "ffmpeg not found ({}), install ffmpeg or set `ffmpeg_path`",
+            ffmpeg_path.display()
+        )
+    } else {
+        format!("failed to run {}: {e}", ffmpeg_path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AnimatedOverlay, Color, CoverEntry, Direction, EndingMode, Ffmpeg, FfmpegCommand,
+        IntermediateFormat, MotionType, Orientation, RawFrame, RenderContext, Slide, StripTile,
+        Transition, combain_rendered_slides, combain_slides, combain_slides_opaque, concat_command,
+        concat_list_entry, cover_video_command, escape_filter_value, ffmpeg_version, flatten,
+        mid_video_command, page_video_command, parse_back_color, rendition_command, rendition_path,
+    };
+    use crate::video::{
+        scroll::{Keyframe, Scroll},
+        slide::Position,
+    };
+    use image::{DynamicImage, Rgb, RgbImage};
+    use std::{
+        collections::HashMap,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    #[test]
+    fn test_command_line_is_quoted() {
+        let command = FfmpegCommand::new(Path::new("ffmpeg"))
+            .input("cover 0.png")
+            .filter_complex("[0:v]overlay=x='-160*clip(t,0,10)'[v0]")
+            .output("out.mp4");
+        assert_eq!(
+            command.command_line(Path::new("/tmp/work dir")),
+            "cd '/tmp/work dir' && ffmpeg -loglevel warning -y -i 'cover 0.png' \
+             -filter_complex '[0:v]overlay=x='\\''-160*clip(t,0,10)'\\''[v0]' out.mp4"
+        );
+    }
+
+    #[test]
+    fn test_bitexact_output_options() {
+        let ffmpeg = Ffmpeg {
+            bitexact: true,
+            ..Ffmpeg::new("ffmpeg")
+        };
+        let command = ffmpeg.command().input("00.png").output("00.mp4");
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            args[args.len() - 7..],
+            [
+                "-fflags",
+                "+bitexact",
+                "-flags:v",
+                "+bitexact",
+                "-threads",
+                "4",
+                "00.mp4"
+            ]
+        );
+        let command = Ffmpeg::new("ffmpeg").command().output("00.mp4");
+        assert!(!command.get_args().iter().any(|a| a == "+bitexact"));
+    }
+
+    #[test]
+    fn test_mid_video_tiles() {
+        let ffmpeg = Ffmpeg {
+            dry_run: true,
+            ..Ffmpeg::new("ffmpeg")
+        };
+        let scroll = Scroll {
+            keyframes: vec![Keyframe { t: 0.0, x: 0.0 }, Keyframe { t: 1.0, x: 10.0 }],
+            motion_type: MotionType::Linear,
+            ease_in: false,
+            ease_out: false,
+            phase: 0.0,
+        };
+        let tiles = [
+            StripTile {
+                pic_name: PathBuf::from("00_tile0.png"),
+                x: 0,
+                raw: None,
+            },
+            StripTile {
+                pic_name: PathBuf::from("00_tile1.raw"),
+                x: 960,
+                raw: Some(RawFrame {
+                    size: (1000, 1080),
+                    pix_fmt: "rgb24",
+                }),
+            },
+        ];
+        let line = mid_video_command(
+            &ffmpeg,
+            "libx264",
+            "fast",
+            &tiles,
+            Path::new("00.mp4"),
+            (1920, 1080),
+            Orientation::Horizontal,
+            "white",
+            30,
+            &scroll,
+            30,
+            None,
+            &[],
+            &[],
+            &[],
+        )
+        .command_line(Path::new("."));
+        assert!(
+            line.contains(
+                "-i 00_tile0.png -f rawvideo -pix_fmt rgb24 -video_size 1000x1080 \
+                 -framerate 1 -stream_loop -1 -i 00_tile1.raw"
+            ),
+            "{line}"
+        );
+        assert!(line.contains("[tile1];[tile1][1]overlay=x="), "{line}");
+        assert!(line.contains("960-("), "{line}");
+    }
+
+    #[test]
+    fn test_vertical_commands() {
+        let ffmpeg = Ffmpeg::new("ffmpeg");
+        let scroll = Scroll::linear(vec![
+            Keyframe { t: 0.0, x: 0.0 },
+            Keyframe { t: 1.0, x: 10.0 },
+        ]);
+        let tiles = [StripTile {
+            pic_name: PathBuf::from("00.png"),
+            x: 0,
+            raw: None,
+        }];
+        let filter_complex = |command: &FfmpegCommand| {
+            let args = command.get_args();
+            let index = args.iter().position(|a| a == "-filter_complex").unwrap();
+            args[index + 1].to_str().unwrap().to_string()
+        };
+        let command = mid_video_command(
+            &ffmpeg,
+            "libx264",
+            "fast",
+            &tiles,
+            Path::new("00.mp4"),
+            (1080, 1920),
+            Orientation::Vertical,
+            "white",
+            30,
+            &scroll,
+            30,
+            None,
+            &[],
+            &[],
+            &[],
+        );
+        let filters = filter_complex(&command);
+        // 在长条图坐标系中滚动，最后旋转回竖屏
+        assert!(
+            filters.starts_with("color=white:s=1920x1080:r=30"),
+            "{filters}"
+        );
+        assert!(filters.ends_with(",transpose=clock"), "{filters}");
+
+        let command = cover_video_command(
+            &ffmpeg,
+            "libx264",
+            "fast",
+            vec![("cover_0.png".to_string(), 0)],
+            2.0,
+            "white",
+            (1080, 1920),
+            Orientation::Vertical,
+            30,
+            60,
+            MotionType::Linear,
+            &CoverEntry::default(),
+            &[],
+            Path::new("cover.mp4"),
+        );
+        let filters = filter_complex(&command);
+        assert!(
+            filters.starts_with("color=white:s=1920x1080:r=30"),
+            "{filters}"
+        );
+        // 从下方进入的横条在长条图坐标系中从右侧进入
+        assert!(filters.contains("overlay=x='0+("), "{filters}");
+        assert!(filters.ends_with("[tmp0]transpose=clock[out]"), "{filters}");
+        let args = command.get_args();
+        assert!(args.windows(2).any(|w| w[0] == "-map" && w[1] == "[out]"));
+    }
+
+    #[test]
+    fn test_page_video_command() {
+        let ffmpeg = Ffmpeg::new("ffmpeg");
+        let filter_complex = |command: &FfmpegCommand| {
+            let args = command.get_args();
+            let index = args.iter().position(|a| a == "-filter_complex").unwrap();
+            args[index + 1].to_str().unwrap().to_string()
+        };
+        let page = |previous, transition| {
+            page_video_command(
+                &ffmpeg,
+                "libx264",
+                "fast",
+                (Path::new("page_001.png"), 480),
+                previous,
+                &[],
+                (transition, 0.5),
+                Path::new("page_001.mp4"),
+                (1920, 1080),
+                "white",
+                30,
+                90,
+                None,
+            )
+        };
+
+        let command = page(Some((Path::new("page_000.png"), 0)), Transition::Fade);
+        let args = command.get_args();
+        // 上一页是第一路输入
+        let inputs: Vec<_> = args
+            .windows(2)
+            .filter(|w| w[0] == "-i")
+            .map(|w| w[1].to_str().unwrap())
+            .collect();
+        assert_eq!(inputs, ["page_000.png", "page_001.png"]);
+        let filters = filter_complex(&command);
+        assert!(filters.contains("[bg0][0]overlay=x=0[prev]"), "{filters}");
+        assert!(filters.contains("[bg1][1]overlay=x=480[page]"), "{filters}");
+        assert!(
+            filters.ends_with("xfade=transition=fade:duration=0.500:offset=0"),
+            "{filters}"
+        );
+
+        // 第一页和直接切换时只有这一页
+        for command in [
+            page(None, Transition::Fade),
+            page(Some((Path::new("page_000.png"), 0)), Transition::Cut),
+        ] {
+            let filters = filter_complex(&command);
+            assert_eq!(
+                filters,
+                "color=white:s=1920x1080:r=30[bg];[bg][0]overlay=x=480"
+            );
+        }
+
+        // 动态图片在这一页之后输入，叠加到切换前的这一页上
+        let animated = [AnimatedOverlay {
+            path: PathBuf::from("/assets/idle.gif"),
+            x: 600,
+            y: 200,
+            size: (320, 240),
+        }];
+        let command = page_video_command(
+            &ffmpeg,
+            "libx264",
+            "fast",
+            (Path::new("page_001.png"), 480),
+            Some((Path::new("page_000.png"), 0)),
+            &animated,
+            (Transition::Fade, 0.5),
+            Path::new("page_001.mp4"),
+            (1920, 1080),
+            "white",
+            30,
+            90,
+            None,
+        );
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+        assert!(args.contains(&"-an"));
+        let inputs: Vec<_> = args
+            .windows(2)
+            .filter(|w| w[0] == "-i")
+            .map(|w| w[1])
+            .collect();
+        assert_eq!(inputs, ["page_000.png", "page_001.png", "/assets/idle.gif"]);
+        let filters = filter_complex(&command);
+        assert!(
+            filters.contains(
+                "[bg1][1]overlay=x=480[anim0];[2]format=rgba,scale=320:240:\
+                 force_original_aspect_ratio=decrease"
+            ),
+            "{filters}"
+        );
+        assert!(
+            filters.contains("[anim0][anim0_anim]overlay=x=600:y=200[page];[prev][page]xfade"),
+            "{filters}"
+        );
+    }
+
+    #[test]
+    fn test_intermediate_format_save() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(3, 2, Rgb([1, 2, 3])));
+        let path =
+            std::env::temp_dir().join(format!("to_video_test_strip_{}.raw", std::process::id()));
+        let raw = IntermediateFormat::Raw.save(&img, &path).unwrap();
+        assert_eq!(
+            raw,
+            Some(RawFrame {
+                size: (3, 2),
+                pix_fmt: "rgb24"
+            })
+        );
+        assert_eq!(fs::read(&path).unwrap().len(), 3 * 2 * 3);
+        fs::remove_file(&path).unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("to_video_test_strip_{}.png", std::process::id()));
+        assert_eq!(IntermediateFormat::FastPng.save(&img, &path).unwrap(), None);
+        assert_eq!(image::open(&path).unwrap().to_rgb8(), img.to_rgb8());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_combain_rendered_slides() {
+        let font = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        ))
+        .unwrap();
+        let ctx = RenderContext::new(ab_glyph::FontArc::try_from_vec(font).unwrap());
+        let mut first = Slide::new();
+        first.add_color(Color([200, 0, 0]), Position::new(10, 10, 80));
+        let mut second = Slide::new();
+        second.add_text("文字", 40.0, Color([0, 0, 0]), Position::new(10, 100, 60));
+        let slides = [first, second];
+        let screen = (240, 200);
+        let rendered = [slides[0].render((120, 200), &ctx).unwrap().into_rgba8()];
+
+        let strip = combain_slides(&slides, &ctx, 120, screen).unwrap();
+        let reused = combain_rendered_slides(&slides, &rendered, &ctx, 120, screen, None).unwrap();
+        assert_eq!(reused, strip);
+
+        let background = Color([10, 20, 30]);
+        let opaque = combain_slides_opaque(&slides, &ctx, 120, screen, background).unwrap();
+        let reused =
+            combain_rendered_slides(&slides, &rendered, &ctx, 120, screen, Some(background))
+                .unwrap();
+        assert_eq!(reused, opaque);
+
+        let too_many = [
+            rendered[0].clone(),
+            rendered[0].clone(),
+            rendered[0].clone(),
+        ];
+        assert!(combain_rendered_slides(&slides, &too_many, &ctx, 120, screen, None).is_err());
+    }
+
+    #[test]
+    fn test_flatten_background() {
+        assert_eq!(parse_back_color("white"), Some(Color([255, 255, 255])));
+        assert_eq!(parse_back_color("0x102030"), Some(Color([16, 32, 48])));
+        assert_eq!(parse_back_color("#102030"), Some(Color([16, 32, 48])));
+        assert_eq!(parse_back_color("white@0.5"), None);
+
+        let mut img = image::RgbaImage::from_pixel(2, 1, image::Rgba([200, 0, 0, 0]));
+        img.put_pixel(1, 0, image::Rgba([200, 100, 0, 255]));
+        let flat = flatten(&img, Color([10, 20, 30]));
+        assert_eq!(flat.get_pixel(0, 0).0, [10, 20, 30]);
+        assert_eq!(flat.get_pixel(1, 0).0, [200, 100, 0]);
+    }
+
+    #[test]
+    fn test_cover_entry_timing() {
+        let entry = CoverEntry::default();
+        assert_eq!(entry.timing(0, 4, 10.0), (0.0, 2.5));
+        assert_eq!(entry.timing(3, 4, 10.0), (7.5, 2.5));
+
+        let entry = CoverEntry {
+            direction: Direction::FadeIn,
+            sec: Some(1.0),
+            delays: vec![0.0, 0.0, 3.0],
+        };
+        assert_eq!(entry.timing(1, 4, 10.0), (0.0, 1.0));
+        assert_eq!(entry.timing(2, 4, 10.0), (3.0, 1.0));
+        assert_eq!(entry.timing(3, 4, 10.0), (4.0, 1.0));
+    }
+
+    #[test]
+    fn test_ending_fade() {
+        assert_eq!(EndingMode::HoldLastFrame.fade(20.0, 4, "white"), None);
+        let fade = EndingMode::FadeToColor {
+            sec: 2.0,
+            color: Color([255, 0, 16]),
+        }
+        .fade(20.0, 4, "white")
+        .unwrap();
+        assert_eq!((fade.start, fade.duration), (18.0, 2.0));
+        assert_eq!(fade.color, "0xff0010");
+
+        // 淡出时间不超过视频时长
+        let fade = EndingMode::LoopBackToStart.fade(3.0, 4, "white").unwrap();
+        assert_eq!((fade.start, fade.duration), (0.0, 3.0));
+        assert_eq!(fade.color, "white");
+    }
+
+    #[test]
+    fn test_command_metadata() {
+        let metadata = HashMap::from([
+            ("title".to_string(), "干员 一览".to_string()),
+            ("artist".to_string(), "hjuy1".to_string()),
+        ]);
+        let command = FfmpegCommand::new(Path::new("ffmpeg")).metadata(&metadata);
+        assert_eq!(
+            &command.get_args()[3..],
+            ["-metadata", "artist=hjuy1", "-metadata", "title=干员 一览"]
+        );
+    }
+
+    #[test]
+    fn test_command_keeps_spaces_in_args() {
+        let command = FfmpegCommand::new(Path::new("ffmpeg"))
+            .input("cover 0.png")
+            .filter_complex("[0:v]format=yuva420p, setpts=PTS-STARTPTS[v0]")
+            .map("v0")
+            .output("out dir/cover.mp4");
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            &args[3..],
+            [
+                "-i",
+                "cover 0.png",
+                "-filter_complex",
+                "[0:v]format=yuva420p, setpts=PTS-STARTPTS[v0]",
+                "-map",
+                "[v0]",
+                "out dir/cover.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_concat_list_entry() {
+        assert_eq!(
+            concat_list_entry(Path::new("00.mp4")).unwrap(),
+            "file '00.mp4'\n"
+        );
+        assert_eq!(
+            concat_list_entry(Path::new("我的 视频/cover.mp4")).unwrap(),
+            "file '我的 视频/cover.mp4'\n"
+        );
+        assert_eq!(
+            concat_list_entry(Path::new("it's.mp4")).unwrap(),
+            "file 'it'\\''s.mp4'\n"
+        );
+
+        // 单引号内的反斜杠不是转义字符
+        assert_eq!(
+            concat_list_entry(Path::new(r"C:\Users\me\My Videos\it's 00.mp4")).unwrap(),
+            "file 'C:\\Users\\me\\My Videos\\it'\\''s 00.mp4'\n"
+        );
+        assert_eq!(
+            concat_list_entry(Path::new(r"\\server\share\a'b'.mp4")).unwrap(),
+            "file '\\\\server\\share\\a'\\''b'\\''.mp4'\n"
+        );
+    }
+
+    #[test]
+    fn test_rendition_command() {
+        let save_path = Path::new("/out/my video.mp4");
+        let output = rendition_path(save_path, (1080, 1920));
+        assert_eq!(output, Path::new("/out/my video_1080x1920.mp4"));
+        let command = rendition_command(
+            &Ffmpeg::new("ffmpeg"),
+            "libx264",
+            "fast",
+            save_path,
+            &output,
+            (1080, 1920),
+            "white",
+            60,
+            &HashMap::new(),
+        );
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert!(args.windows(2).any(|w| w == ["-i", "/out/my video.mp4"]));
+        assert!(args.contains(
+            &"scale=1080:1920:force_original_aspect_ratio=decrease,\
+              pad=1080:1920:(ow-iw)/2:(oh-ih)/2:color=white,setsar=1"
+        ));
+        assert_eq!(args.last(), Some(&"/out/my video_1080x1920.mp4"));
+
+        let command = rendition_command(
+            &Ffmpeg::new("ffmpeg"),
+            "libx264",
+            "fast",
+            save_path,
+            &output,
+            (1080, 1920),
+            "white,drawbox=c=red",
+            60,
+            &HashMap::new(),
+        );
+        // 背景颜色中的逗号不能截断滤镜
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert!(args.contains(
+            &"scale=1080:1920:force_original_aspect_ratio=decrease,\
+              pad=1080:1920:(ow-iw)/2:(oh-ih)/2:color=white\\,drawbox=c=red,setsar=1"
+        ));
+    }
+
+    #[test]
+    fn test_concat_command_uses_absolute_paths() {
+        let work_dir =
+            std::env::temp_dir().join(format!("to_video_test_concat dir_{}", std::process::id()));
+        fs::create_dir_all(&work_dir).unwrap();
+        let ffmpeg = Ffmpeg::new("ffmpeg");
+        let mut results = vec![
+            PathBuf::from("cover.mp4"),
+            PathBuf::from("00.png"),
+            PathBuf::from("00.mp4"),
+        ];
+        let command = concat_command(
+            &ffmpeg,
+            &mut results,
+            &work_dir,
+            Path::new("/out/my video.mp4"),
+            &HashMap::new(),
+        )
+        .unwrap();
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert!(args.windows(4).any(|w| w == ["-f", "concat", "-safe", "0"]));
+        assert_eq!(results.last(), Some(&PathBuf::from("list.txt")));
+        let list = fs::read_to_string(work_dir.join("list.txt")).unwrap();
+        assert_eq!(
+            list,
+            format!(
+                "file '{}'\nfile '{}'\n",
+                work_dir.join("cover.mp4").display(),
+                work_dir.join("00.mp4").display()
+            )
+        );
+        fs::remove_dir_all(&work_dir).unwrap();
+    }
+
+    #[test]
+    fn test_escape_filter_value() {
+        assert_eq!(escape_filter_value("black"), "black");
+        assert_eq!(escape_filter_value("#1e1e1e@0.5"), "#1e1e1e@0.5");
+        assert_eq!(escape_filter_value("red:s=1x1"), r"red\\:s=1x1");
+        assert_eq!(escape_filter_value("a;[b]"), r"a\;\[b\]");
+        assert_eq!(escape_filter_value("it's"), r"it\\\'s");
+    }
+
+    #[test]
+    fn test_missing_ffmpeg_is_reported() {
+        let err = ffmpeg_version(Path::new("/nonexistent/ffmpeg"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("ffmpeg not found"), "{err}");
+        assert!(err.contains("ffmpeg_path"), "{err}");
     }
-    Ok(())
 }
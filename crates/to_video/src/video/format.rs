@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+
+/// 日期等格式使用的语言
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Locale {
+    /// 2024年3月15日
+    #[default]
+    Zh,
+    /// Mar 15, 2024
+    En,
+}
+
+/// 文本数据在绘制前的格式转换，无法解析的数据按原样显示
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum Format {
+    /// 日期，数据中依次包含年、月、日三个数字，例如 `2024-03-15` 或 `[2024,3,15]`
+    Date {
+        #[serde(default)]
+        locale: Locale,
+    },
+    /// 千位分隔，例如 `1234567.5` → `1,234,567.5`。`decimals` 为 `None` 时保留原有的小数位
+    Thousands {
+        #[serde(default)]
+        decimals: Option<u8>,
+    },
+    /// 把比例显示为百分比，例如 `0.125` → `12.5%`，已经带 `%` 的数据不变
+    Percent {
+        #[serde(default)]
+        decimals: u8,
+    },
+}
+
+/// [`Operation::Timestamp`](super::slide::Operation::Timestamp) 的显示方式
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateDisplay {
+    /// 2021年3月15日
+    #[default]
+    Absolute,
+    /// 3年前
+    Relative,
+    /// 2021年3月15日（3年前）
+    Both,
+}
+
+pub(super) const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+impl Format {
+    pub fn apply(&self, cell: &str) -> String {
+        let formatted = match *self {
+            Format::Date { locale } => format_date(cell, locale),
+            Format::Thousands { decimals } => format_thousands(cell, decimals),
+            Format::Percent { decimals } => format_percent(cell, decimals),
+        };
+        formatted.unwrap_or_else(|| cell.to_string())
+    }
+}
+
+/// 从数据中依次读取年、月、日
+pub(super) fn parse_date(cell: &str) -> Option<(u16, u8, u8)> {
+    let numbers: Vec<&str> = cell
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let [year, month, day] = numbers[..] else {
+        return None;
+    };
+    let (year, month, day) = (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((year, month, day))
+}
+
+fn format_date(cell: &str, locale: Locale) -> Option<String> {
+    let (year, month, day) = parse_date(cell)?;
+    Some(match locale {
+        Locale::Zh => format!("{year}年{month}月{day}日"),
+        Locale::En => format!("{} {day}, {year}", MONTHS[usize::from(month) - 1]),
+    })
+}
+
+/// 按 `display` 显示数据中的日期，相对日期以 `reference` 为基准，无法解析的数据按原样显示
+pub fn format_timestamp(
+    cell: &str,
+    display: DateDisplay,
+    locale: Locale,
+    reference: (u16, u8, u8),
+) -> String {
+    let Some(date) = parse_date(cell) else {
+        return cell.to_string();
+    };
+    let absolute = || format_date(cell, locale).unwrap_or_else(|| cell.to_string());
+    match (display, locale) {
+        (DateDisplay::Absolute, _) => absolute(),
+        (DateDisplay::Relative, _) => relative_date(date, reference, locale),
+        (DateDisplay::Both, Locale::Zh) => {
+            format!(
+                "{}（{}）",
+                absolute(),
+                relative_date(date, reference, locale)
+            )
+        }
+        (DateDisplay::Both, Locale::En) => {
+            format!(
+                "{} ({})",
+                absolute(),
+                relative_date(date, reference, locale)
+            )
+        }
+    }
+}
+
+/// `date` 相对于 `reference` 的描述，例如 “3年前”、“in 2 months”。
+///
+/// 相差一年以上时按整年，一个月以上时按整月，否则按天数。
+pub fn relative_date(date: (u16, u8, u8), reference: (u16, u8, u8), locale: Locale) -> String {
+    let (early, late) = if date <= reference {
+        (date, reference)
+    } else {
+        (reference, date)
+    };
+    let mut months =
+        (i32::from(late.0) - i32::from(early.0)) * 12 + (i32::from(late.1) - i32::from(early.1));
+    if late.2 < early.2 {
+        months -= 1;
+    }
+    let (amount, unit) = if months >= 12 {
+        (months / 12, 0)
+    } else if months >= 1 {
+        (months, 1)
+    } else {
+        (days_from_civil(late) - days_from_civil(early), 2)
+    };
+    let past = date < reference;
+    match locale {
+        Locale::Zh => {
+            if amount == 0 {
+                return "今天".to_string();
+            }
+            let unit = ["年", "个月", "天"][unit];
+            format!("{amount}{unit}{}", if past { "前" } else { "后" })
+        }
+        Locale::En => {
+            if amount == 0 {
+                return "today".to_string();
+            }
+            let unit = ["year", "month", "day"][unit];
+            let plural = if amount == 1 { "" } else { "s" };
+            if past {
+                format!("{amount} {unit}{plural} ago")
+            } else {
+                format!("in {amount} {unit}{plural}")
+            }
+        }
+    }
+}
+
+/// 公历日期距 1970-01-01 的天数
+fn days_from_civil((year, month, day): (u16, u8, u8)) -> i32 {
+    let (month, day) = (i32::from(month), i32::from(day));
+    let year = i32::from(year) - i32::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// 1970-01-01 之后第 `days` 天的公历日期，[`days_from_civil`] 的逆运算
+fn civil_from_days(days: i32) -> (u16, u8, u8) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i32::from(month <= 2);
+    (year as u16, month as u8, day as u8)
+}
+
+/// 今天的日期（UTC）
+pub fn today() -> (u16, u8, u8) {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    civil_from_days((secs / 86400) as i32)
+}
+
+fn format_thousands(cell: &str, decimals: Option<u8>) -> Option<String> {
+    let cell = cell.trim();
+    // 不处理科学计数法等写法
+    if !cell
+        .trim_start_matches(['+', '-'])
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '.')
+    {
+        return None;
+    }
+    let value: f64 = cell.parse().ok().filter(|v: &f64| v.is_finite())?;
+    let text = match decimals {
+        Some(decimals) => format!("{value:.*}", usize::from(decimals)),
+        None => cell.trim_start_matches('+').to_string(),
+    };
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text.as_str()),
+    };
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+    let mut grouped = String::with_capacity(integer.len() * 4 / 3 + 1);
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    Some(match fraction {
+        Some(fraction) => format!("{sign}{grouped}.{fraction}"),
+        None => format!("{sign}{grouped}"),
+    })
+}
+
+fn format_percent(cell: &str, decimals: u8) -> Option<String> {
+    let cell = cell.trim();
+    if cell.ends_with('%') {
+        return Some(cell.to_string());
+    }
+    let value: f64 = cell.parse().ok().filter(|v: &f64| v.is_finite())?;
+    Some(format!("{:.*}%", usize::from(decimals), value * 100.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_date() {
+        let zh = Format::Date { locale: Locale::Zh };
+        let en = Format::Date { locale: Locale::En };
+        assert_eq!(zh.apply("[2024,3,15]"), "2024年3月15日");
+        assert_eq!(zh.apply("2024-03-15"), "2024年3月15日");
+        assert_eq!(en.apply("(2024, 3, 15)"), "Mar 15, 2024");
+        assert_eq!(en.apply("2024-13-01"), "2024-13-01");
+        assert_eq!(en.apply("未知"), "未知");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let reference = (2024, 6, 1);
+        let relative =
+            |cell, locale| format_timestamp(cell, DateDisplay::Relative, locale, reference);
+        assert_eq!(relative("2021-03-15", Locale::Zh), "3年前");
+        assert_eq!(relative("2021-06-02", Locale::En), "2 years ago");
+        assert_eq!(relative("2024-01-01", Locale::En), "5 months ago");
+        assert_eq!(relative("2024-05-31", Locale::En), "1 day ago");
+        assert_eq!(relative("2024-06-01", Locale::Zh), "今天");
+        assert_eq!(relative("2024-08-01", Locale::En), "in 2 months");
+        assert_eq!(relative("未知", Locale::Zh), "未知");
+        assert_eq!(
+            format_timestamp("[2021,3,15]", DateDisplay::Both, Locale::Zh, reference),
+            "2021年3月15日（3年前）"
+        );
+        assert_eq!(
+            civil_from_days(days_from_civil((2024, 2, 29))),
+            (2024, 2, 29)
+        );
+        assert_eq!(days_from_civil((1970, 1, 1)), 0);
+    }
+
+    #[test]
+    fn test_format_numbers() {
+        let thousands = Format::Thousands { decimals: None };
+        assert_eq!(thousands.apply("1234567"), "1,234,567");
+        assert_eq!(thousands.apply("-1234.50"), "-1,234.50");
+        assert_eq!(thousands.apply("999"), "999");
+        assert_eq!(thousands.apply("abc"), "abc");
+        let rounded = Format::Thousands { decimals: Some(1) };
+        assert_eq!(rounded.apply("12345.67"), "12,345.7");
+
+        let percent = Format::Percent { decimals: 1 };
+        assert_eq!(percent.apply("0.125"), "12.5%");
+        assert_eq!(percent.apply("30%"), "30%");
+        assert_eq!(Format::Percent { decimals: 0 }.apply("1"), "100%");
+    }
+}
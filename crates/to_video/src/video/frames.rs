@@ -0,0 +1,371 @@
+//! 在内存中逐帧绘制一段视频，供逐帧编码的后端使用，见 [`VideoEncoder::push_frame`](super::encoder::VideoEncoder::push_frame)。
+//!
+//! 画面与 ffmpeg 命令行后端的滤镜一致：封面各列的入场、长条图和其他行的滚动、
+//! 幻灯片的翻页效果以及结尾的淡出。动画图片和数字动画只有 ffmpeg 命令行后端支持。
+
+use super::{
+    encoder::{EncodeSegment, SegmentPlan},
+    ffmpeg::{Direction, RawFrame, parse_back_color},
+    orientation::Orientation,
+    slideshow::Transition,
+};
+use crate::{Result, color::Color};
+use image::{DynamicImage, RgbImage, Rgba, RgbaImage, imageops};
+use std::{fs, path::Path};
+
+/// 一段视频的所有图片，按 `frame` 绘制其中任意一帧
+pub struct SegmentFrames<'a> {
+    segment: &'a EncodeSegment,
+    back_color: Rgba<u8>,
+    /// 淡出的颜色，没有淡出时为 `None`
+    fade_color: Option<Rgba<u8>>,
+    /// 按 ffmpeg 命令的输入顺序排列的图片：封面为各列和各行，
+    /// 滚动为长条图的各段和各行，幻灯片为这一页和上一页
+    images: Vec<RgbaImage>,
+}
+
+impl<'a> SegmentFrames<'a> {
+    /// 从临时目录 `work_dir` 读取 `segment` 用到的图片。
+    ///
+    /// # Errors
+    /// - 图片无法读取，颜色不是 `#rrggbb`、`0xrrggbb` 或常用颜色名，
+    ///   或者这一段用到了只有 ffmpeg 命令行后端支持的效果时返回 `Err`。
+    ///
+    pub fn load(segment: &'a EncodeSegment, work_dir: &Path) -> Result<Self> {
+        let back_color = color(&segment.back_color)?;
+        let load = |name: &Path, raw| load_image(&work_dir.join(name), raw);
+        let (images, fade) = match &segment.plan {
+            SegmentPlan::Cover { images, rows, .. } => {
+                let images = images
+                    .iter()
+                    .map(|(name, _)| load(Path::new(name), None))
+                    .chain(rows.iter().map(|row| load(&row.pic_name, None)))
+                    .collect::<Result<_>>()?;
+                (images, None)
+            }
+            SegmentPlan::Scroll {
+                tiles,
+                fade,
+                rows,
+                ken_burns,
+                count_ups,
+                ..
+            } => {
+                if !ken_burns.is_empty() || !count_ups.is_empty() {
+                    return Err(unsupported(segment, "animated images and count-ups"));
+                }
+                let images = tiles
+                    .iter()
+                    .map(|tile| load(&tile.pic_name, tile.raw))
+                    .chain(rows.iter().map(|row| load(&row.pic_name, None)))
+                    .collect::<Result<_>>()?;
+                (images, fade.as_ref())
+            }
+            SegmentPlan::Page {
+                image,
+                previous,
+                animated,
+                fade,
+                ..
+            } => {
+                if !animated.is_empty() {
+                    return Err(unsupported(segment, "animated images"));
+                }
+                let mut images = vec![load(image, None)?];
+                if let Some((previous, _)) = previous {
+                    images.push(load(previous, None)?);
+                }
+                (images, fade.as_ref())
+            }
+        };
+        Ok(Self {
+            segment,
+            back_color,
+            fade_color: fade.map(|fade| color(&fade.color)).transpose()?,
+            images,
+        })
+    }
+
+    /// 第 `index` 帧（从 0 开始）的画面，尺寸为 [`EncodeSegment::screen`]
+    pub fn frame(&self, index: u64) -> RgbaImage {
+        let segment = self.segment;
+        let t = index as f64 / f64::from(segment.fps.max(1));
+        // 封面和滚动画面在长条图坐标系中绘制，竖屏时最后转回输出画面
+        let (width, height) = match segment.plan {
+            SegmentPlan::Page { .. } => segment.screen,
+            _ => segment.orientation.strip_screen(segment.screen),
+        };
+        let canvas = || RgbaImage::from_pixel(width, height, self.back_color);
+        let mut frame = match &segment.plan {
+            SegmentPlan::Cover {
+                images,
+                cover_sec,
+                motion_type,
+                entry,
+                rows,
+            } => {
+                let mut frame = canvas();
+                let direction = segment.orientation.strip_direction(entry.direction);
+                for (i, &(_, x_pos)) in images.iter().enumerate() {
+                    let (start, sec) = entry.timing(i, images.len(), *cover_sec);
+                    let (start, sec) = (f64::from(start), f64::from(sec));
+                    // 开始入场之前这一列还没有出现
+                    if t < start {
+                        continue;
+                    }
+                    let r = if sec > 0.0 {
+                        (t - start).clamp(0.0, sec) / sec
+                    } else {
+                        1.0
+                    };
+                    let ranges = motion_type.motion_range(r);
+                    let image = &self.images[i];
+                    let (x, y) = (f64::from(x_pos), f64::from(height));
+                    let (x, y) = match direction {
+                        Direction::FromBottom => (x, ranges * y),
+                        Direction::FromTop => (x, -ranges * y),
+                        Direction::FromLeft => (x - ranges * (x + f64::from(image.width())), 0.0),
+                        Direction::FromRight => (x + ranges * (f64::from(width) - x), 0.0),
+                        Direction::FadeIn => (x, 0.0),
+                    };
+                    if direction == Direction::FadeIn && r < 1.0 {
+                        overlay(&mut frame, &with_opacity(image, r), x, y);
+                    } else {
+                        overlay(&mut frame, image, x, y);
+                    }
+                }
+                for (row, image) in rows.iter().zip(&self.images[images.len()..]) {
+                    overlay(&mut frame, image, 0.0, f64::from(row.y));
+                }
+                frame
+            }
+            SegmentPlan::Scroll {
+                tiles,
+                scroll,
+                rows,
+                ..
+            } => {
+                let mut frame = canvas();
+                let offset = scroll.offset(t);
+                for (tile, image) in tiles.iter().zip(&self.images) {
+                    overlay(&mut frame, image, f64::from(tile.x) - offset, 0.0);
+                }
+                for (row, image) in rows.iter().zip(&self.images[tiles.len()..]) {
+                    let x = -(row.speed * offset + row.shift);
+                    overlay(&mut frame, image, x, f64::from(row.y));
+                }
+                frame
+            }
+            SegmentPlan::Page {
+                x,
+                previous,
+                transition,
+                transition_sec,
+                ..
+            } => {
+                let mut page = canvas();
+                overlay(&mut page, &self.images[0], f64::from(*x), 0.0);
+                match previous.as_ref().filter(|_| *transition != Transition::Cut) {
+                    Some(&(_, previous_x)) => {
+                        let mut previous = canvas();
+                        overlay(&mut previous, &self.images[1], f64::from(previous_x), 0.0);
+                        let sec = f64::from(*transition_sec);
+                        let progress = if sec > 0.0 {
+                            1.0 - (t / sec).clamp(0.0, 1.0)
+                        } else {
+                            0.0
+                        };
+                        xfade(*transition, &previous, &page, progress as f32)
+                    }
+                    None => page,
+                }
+            }
+        };
+        let fade = match &segment.plan {
+            SegmentPlan::Cover { .. } => None,
+            SegmentPlan::Scroll { fade, .. } | SegmentPlan::Page { fade, .. } => fade.as_ref(),
+        };
+        if let (Some(fade), Some(color)) = (fade, self.fade_color)
+            && t > fade.start
+        {
+            let level = if fade.duration > 0.0 {
+                ((t - fade.start) / fade.duration).min(1.0)
+            } else {
+                1.0
+            };
+            for pixel in frame.pixels_mut() {
+                *pixel = mix(color, *pixel, level as f32);
+            }
+        }
+        match (&segment.plan, segment.orientation) {
+            (SegmentPlan::Page { .. }, _) | (_, Orientation::Horizontal) => frame,
+            (_, Orientation::Vertical) => imageops::rotate90(&frame),
+        }
+    }
+}
+
+fn unsupported(segment: &EncodeSegment, feature: &str) -> crate::Error {
+    format!(
+        "{}: {feature} are only supported by the ffmpeg command line encoder",
+        segment.name.display()
+    )
+    .into()
+}
+
+/// 不透明的 ffmpeg 颜色，带透明度等其他写法无法逐帧绘制
+fn color(color: &str) -> Result<Rgba<u8>> {
+    let Color([r, g, b]) = parse_back_color(color)
+        .ok_or_else(|| format!("color {color} is not supported by frame-level encoders"))?;
+    Ok(Rgba([r, g, b, 255]))
+}
+
+/// 读取 PNG 图片，或者按 `raw` 读取以原始像素保存的图片
+fn load_image(path: &Path, raw: Option<RawFrame>) -> Result<RgbaImage> {
+    let Some(RawFrame {
+        size: (width, height),
+        pix_fmt,
+    }) = raw
+    else {
+        return Ok(image::open(path)?.into_rgba8());
+    };
+    let bytes = fs::read(path)?;
+    let image = match pix_fmt {
+        "rgb24" => RgbImage::from_raw(width, height, bytes)
+            .map(|image| DynamicImage::ImageRgb8(image).into_rgba8()),
+        "rgba" => RgbaImage::from_raw(width, height, bytes),
+        _ => None,
+    };
+    image.ok_or_else(|| format!("{}: invalid {pix_fmt} image", path.display()).into())
+}
+
+/// 与 ffmpeg `overlay` 滤镜相同，坐标向零取整
+fn overlay(frame: &mut RgbaImage, image: &RgbaImage, x: f64, y: f64) {
+    imageops::overlay(frame, image, x as i64, y as i64);
+}
+
+/// 透明度乘以 `opacity` 的图片
+fn with_opacity(image: &RgbaImage, opacity: f64) -> RgbaImage {
+    let mut image = image.clone();
+    for pixel in image.pixels_mut() {
+        pixel[3] = (f64::from(pixel[3]) * opacity).round() as u8;
+    }
+    image
+}
+
+/// `a * m + b * (1 - m)`，与 ffmpeg `xfade` 滤镜的 `mix` 相同
+fn mix(a: Rgba<u8>, b: Rgba<u8>, m: f32) -> Rgba<u8> {
+    Rgba(std::array::from_fn(|i| {
+        (f32::from(a[i]) * m + f32::from(b[i]) * (1.0 - m)).round() as u8
+    }))
+}
+
+/// 与 ffmpeg `xfade` 滤镜相同的切换效果，`progress` 从 1（上一页）变化到 0（这一页）
+fn xfade(transition: Transition, a: &RgbaImage, b: &RgbaImage, progress: f32) -> RgbaImage {
+    let (width, height) = a.dimensions();
+    RgbaImage::from_fn(width, height, |x, y| match transition {
+        Transition::Cut => *b.get_pixel(x, y),
+        Transition::Fade => mix(*a.get_pixel(x, y), *b.get_pixel(x, y), progress),
+        Transition::Dissolve => {
+            let r = (x as f32 * 12.9898 + y as f32 * 78.233).sin() * 43758.547;
+            let smooth = (r - r.floor()) * 2.0 + progress * 2.0 - 1.5;
+            if smooth >= 0.5 {
+                *a.get_pixel(x, y)
+            } else {
+                *b.get_pixel(x, y)
+            }
+        }
+        Transition::SlideLeft => {
+            let z = x as i64 - (progress * width as f32) as i64;
+            let from = if z < 0 { a } else { b };
+            *from.get_pixel(z.rem_euclid(i64::from(width)) as u32, y)
+        }
+        Transition::SlideUp => {
+            let z = y as i64 - (progress * height as f32) as i64;
+            let from = if z < 0 { a } else { b };
+            *from.get_pixel(x, z.rem_euclid(i64::from(height)) as u32)
+        }
+        Transition::WipeLeft => {
+            if x as f32 > width as f32 * progress {
+                *b.get_pixel(x, y)
+            } else {
+                *a.get_pixel(x, y)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::{
+        ffmpeg::{Fade, StripTile},
+        scroll::{Keyframe, Scroll},
+    };
+    use std::path::PathBuf;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    #[test]
+    fn test_scroll_frames() {
+        let work_dir =
+            std::env::temp_dir().join(format!("to_video_test_frames_{}", std::process::id()));
+        fs::create_dir_all(&work_dir).unwrap();
+        RgbaImage::from_pixel(2, 2, RED)
+            .save(work_dir.join("00.png"))
+            .unwrap();
+        let segment = EncodeSegment {
+            name: PathBuf::from("00.mp4"),
+            screen: (4, 2),
+            orientation: Orientation::Horizontal,
+            fps: 1,
+            frames: 4,
+            back_color: "black".to_string(),
+            plan: SegmentPlan::Scroll {
+                tiles: vec![StripTile {
+                    pic_name: PathBuf::from("00.png"),
+                    x: 2,
+                    raw: None,
+                }],
+                scroll: Scroll::linear(vec![
+                    Keyframe { t: 0.0, x: 0.0 },
+                    Keyframe { t: 2.0, x: 2.0 },
+                ]),
+                fade: Some(Fade {
+                    start: 2.0,
+                    duration: 1.0,
+                    color: "white".to_string(),
+                }),
+                rows: Vec::new(),
+                ken_burns: Vec::new(),
+                count_ups: Vec::new(),
+            },
+        };
+        let frames = SegmentFrames::load(&segment, &work_dir).unwrap();
+        let row = |index| -> Vec<Rgba<u8>> {
+            let frame = frames.frame(index);
+            (0..4).map(|x| *frame.get_pixel(x, 0)).collect()
+        };
+        assert_eq!(row(0), [BLACK, BLACK, RED, RED]);
+        assert_eq!(row(1), [BLACK, RED, RED, BLACK]);
+        assert_eq!(row(2), [RED, RED, BLACK, BLACK]);
+        assert_eq!(row(3), [Rgba([255; 4]); 4]);
+        fs::remove_dir_all(work_dir).unwrap();
+    }
+
+    #[test]
+    fn test_xfade() {
+        let a = RgbaImage::from_pixel(4, 1, RED);
+        let b = RgbaImage::from_pixel(4, 1, BLACK);
+        let row = |transition, progress| -> Vec<Rgba<u8>> {
+            xfade(transition, &a, &b, progress)
+                .pixels()
+                .copied()
+                .collect()
+        };
+        assert_eq!(row(Transition::Fade, 1.0), [RED; 4]);
+        assert_eq!(row(Transition::Fade, 0.0), [BLACK; 4]);
+        assert_eq!(row(Transition::WipeLeft, 0.5), [RED, RED, RED, BLACK]);
+        assert_eq!(row(Transition::SlideLeft, 0.5), [RED, RED, BLACK, BLACK]);
+    }
+}
@@ -0,0 +1,791 @@
+use super::slide::{Element, RenderContext, Slide};
+use crate::{Result, color::Color, font::GlyphKey, imageproc::drawing::PlacedGlyph};
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use wgpu::util::DeviceExt;
+
+/// 绘制矩形的着色器：按 `rect` 生成两个三角形，图片采样后、色块按圆角、
+/// 字形按图集中的覆盖率计算颜色，输出预乘 alpha 的颜色，与目标按 source-over 混合
+const SHADER: &str = r"
+struct Quad {
+    rect: vec4<f32>,
+    canvas: vec4<f32>,
+    color: vec4<f32>,
+    // x: 0 为图片，1 为色块，2 为字形；y: 圆角半径
+    params: vec4<f32>,
+    // 字形在图集中的位置和大小，按图集的边长归一化
+    atlas: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> quad: Quad;
+@group(0) @binding(1) var tex: texture_2d<f32>;
+@group(0) @binding(2) var samp: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0),
+        vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0),
+    );
+    let corner = corners[index];
+    let pixel = quad.rect.xy + corner * quad.rect.zw;
+    let ndc = pixel / quad.canvas.xy * vec2(2.0, -2.0) + vec2(-1.0, 1.0);
+    var out: VertexOutput;
+    out.position = vec4(ndc, 0.0, 1.0);
+    out.local = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if quad.params.x < 0.5 {
+        let c = textureSample(tex, samp, in.local);
+        return vec4(c.rgb * c.a, c.a);
+    }
+    if quad.params.x > 1.5 {
+        let coverage = textureSample(tex, samp, quad.atlas.xy + in.local * quad.atlas.zw).r;
+        let a = quad.color.a * coverage;
+        return vec4(quad.color.rgb * a, a);
+    }
+    // 圆角矩形的有向距离，边缘一个像素内线性过渡
+    let size = quad.rect.zw;
+    let radius = min(quad.params.y, min(size.x, size.y) / 2.0);
+    let p = abs(in.local * size - size / 2.0) - (size / 2.0 - vec2(radius));
+    let dist = length(max(p, vec2(0.0))) + min(max(p.x, p.y), 0.0) - radius;
+    let coverage = clamp(0.5 - dist, 0.0, 1.0);
+    let a = quad.color.a * coverage;
+    return vec4(quad.color.rgb * a, a);
+}
+";
+
+/// GPU 上绘制的一个矩形
+#[derive(Debug, Clone)]
+pub enum Quad {
+    /// 图片按原尺寸绘制在 `(x, y)`
+    Image {
+        image: Arc<RgbaImage>,
+        x: i32,
+        y: i32,
+    },
+    /// 圆角色块
+    Color {
+        color: Color,
+        opacity: f32,
+        /// (x, y, 宽, 高)
+        rect: (i32, i32, u32, u32),
+        radius: f32,
+    },
+    /// 排版好的文字，字形放入图集后逐个绘制
+    Text {
+        color: Color,
+        glyphs: Vec<PlacedGlyph>,
+    },
+}
+
+/// 字形图集的边长
+const ATLAS_SIZE: u32 = 2048;
+
+/// 文字的字形图集。
+///
+/// 字形第一次出现时把覆盖率写入图集纹理，之后的 slide 直接引用图集中的位置。
+/// 字形按行从左到右放置，放满后在下一次合成之前清空。
+/// 键中不包含字体，一个合成器只用于同一个字体。
+struct GlyphAtlas {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    /// 字形在图集中的左上角
+    glyphs: HashMap<GlyphKey, (u32, u32)>,
+    /// 当前行的下一个位置和行高
+    cursor: (u32, u32),
+    row_height: u32,
+    /// 有字形没能放入图集
+    full: bool,
+}
+
+impl GlyphAtlas {
+    fn new(device: &wgpu::Device) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("glyph atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            glyphs: HashMap::new(),
+            cursor: (0, 0),
+            row_height: 0,
+            full: false,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        self.cursor = (0, 0);
+        self.row_height = 0;
+        self.full = false;
+    }
+
+    /// 查找或写入字形，返回它在图集中的左上角，图集已满时返回 `None`
+    fn insert(&mut self, queue: &wgpu::Queue, glyph: &PlacedGlyph) -> Option<(u32, u32)> {
+        let key = glyph.coverage.key();
+        if let Some(&pos) = self.glyphs.get(&key) {
+            return Some(pos);
+        }
+        let (width, height) = glyph.coverage.size();
+        // 字形之间留一个像素的空隙，线性采样时不会取到相邻字形的覆盖率
+        if self.cursor.0 + width > ATLAS_SIZE {
+            self.cursor = (0, self.cursor.1 + self.row_height + 1);
+            self.row_height = 0;
+        }
+        if width > ATLAS_SIZE || self.cursor.1 + height > ATLAS_SIZE {
+            self.full = true;
+            return None;
+        }
+        let mut data = vec![0u8; (width * height) as usize];
+        glyph.coverage.draw(|x, y, v| {
+            data[(y * width + x) as usize] = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        });
+        let pos = self.cursor;
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: pos.0,
+                    y: pos.1,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.glyphs.insert(key, pos);
+        self.cursor.0 += width + 1;
+        self.row_height = self.row_height.max(height);
+        Some(pos)
+    }
+}
+
+/// 图集放不下的文字在 CPU 上绘制到刚好容纳所有字形的图层，返回图层和它的左上角
+fn text_layer(color: Color, glyphs: &[PlacedGlyph]) -> Option<(RgbaImage, i32, i32)> {
+    let left = glyphs.iter().map(|glyph| glyph.x).min()?;
+    let top = glyphs.iter().map(|glyph| glyph.y).min()?;
+    let right = glyphs
+        .iter()
+        .map(|glyph| glyph.x + glyph.coverage.size().0 as i32)
+        .max()?;
+    let bottom = glyphs
+        .iter()
+        .map(|glyph| glyph.y + glyph.coverage.size().1 as i32)
+        .max()?;
+    let Color([r, g, b]) = color;
+    let mut layer = RgbaImage::new((right - left) as u32, (bottom - top) as u32);
+    for glyph in glyphs {
+        let (x, y) = ((glyph.x - left) as u32, (glyph.y - top) as u32);
+        glyph.coverage.draw(|gx, gy, v| {
+            let pixel = layer.get_pixel_mut(x + gx, y + gy);
+            // 同一种颜色叠加，只需要合并覆盖率
+            let a = f32::from(pixel[3]) / 255.0;
+            let a = 1.0 - (1.0 - a) * (1.0 - v.clamp(0.0, 1.0));
+            *pixel = Rgba([r, g, b, (a * 255.0).round() as u8]);
+        });
+    }
+    Some((layer, left, top))
+}
+
+/// 使用 wgpu 合成 slide：图片和色块作为矩形在 GPU 上绘制和混合，
+/// 文字的字形放入图集后作为矩形绘制，表格等其他元素仍在 CPU 上栅格化为图层后再上传。
+pub struct GpuCompositor {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// 色块不采样纹理，绑定一个 1x1 的占位纹理
+    blank: wgpu::TextureView,
+    /// 合成期间一直锁住，其他线程清空图集时不会覆盖正在使用的字形
+    atlas: Mutex<GlyphAtlas>,
+}
+
+impl GpuCompositor {
+    /// 选择一个 GPU 并创建绘制管线。
+    ///
+    /// # Errors
+    /// - 没有可用的 GPU 或创建设备失败时返回 `Err`。
+    ///
+    pub fn new() -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+        .ok_or("no GPU adapter available")?;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("to_video compositor"),
+                required_features: wgpu::Features::empty(),
+                required_limits:
+                    wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))?;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("quad"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("quad"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("quad"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("quad"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let blank = upload(&device, &queue, &RgbaImage::new(1, 1));
+        let atlas = Mutex::new(GlyphAtlas::new(&device));
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            layout,
+            sampler,
+            blank,
+            atlas,
+        })
+    }
+
+    /// 在 `size` 大小的透明画布上依次绘制 `quads`
+    ///
+    /// # Errors
+    /// - 读取渲染结果失败时返回 `Err`。
+    ///
+    pub fn composite(&self, size: (u32, u32), quads: &[Quad]) -> Result<RgbaImage> {
+        let (width, height) = (size.0.max(1), size.1.max(1));
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let target = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("slide"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut atlas = self.atlas.lock().unwrap_or_else(|e| e.into_inner());
+        if atlas.full {
+            atlas.clear();
+        }
+        let bind_groups: Vec<wgpu::BindGroup> = quads
+            .iter()
+            .flat_map(|quad| self.bind_groups(quad, (width, height), &mut atlas))
+            .collect();
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("slide"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            for bind_group in &bind_groups {
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.draw(0..6, 0..1);
+            }
+        }
+
+        // 复制到缓冲区时每行需要按 256 字节对齐
+        let row_bytes = width * 4;
+        let padded = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: u64::from(padded) * u64::from(height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded),
+                    rows_per_image: Some(height),
+                },
+            },
+            extent,
+        );
+        self.queue.submit([encoder.finish()]);
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+
+        let data = slice.get_mapped_range();
+        let mut img = RgbaImage::new(width, height);
+        for (row, pixels) in data
+            .chunks(padded as usize)
+            .zip(img.chunks_mut(row_bytes as usize))
+        {
+            pixels.copy_from_slice(&row[..row_bytes as usize]);
+        }
+        drop(data);
+        buffer.unmap();
+        // 着色器输出的是预乘 alpha 的颜色
+        for pixel in img.pixels_mut() {
+            let a = u16::from(pixel[3]);
+            if a > 0 && a < 255 {
+                for c in &mut pixel.0[..3] {
+                    *c = ((u16::from(*c) * 255 + a / 2) / a).min(255) as u8;
+                }
+            }
+        }
+        Ok(img)
+    }
+
+    /// 在 GPU 上渲染 slide，结果与 [`Slide::render`] 相近，图片缩放的插值方式略有不同。
+    ///
+    /// 不旋转、不带阴影的图片和色块直接作为矩形绘制，不带标记的文字通过字形图集绘制，
+    /// 其他元素在 CPU 上绘制到透明图层，相邻的这类元素共用一个图层。
+    ///
+    /// # Errors
+    /// - 元素渲染失败时返回 `Err`。
+    ///
+    pub fn render_slide(
+        &self,
+        slide: &Slide,
+        size: (u32, u32),
+        ctx: &RenderContext,
+    ) -> Result<DynamicImage> {
+        let mut quads = Vec::new();
+        let mut layer: Option<RgbaImage> = None;
+        let flush = |quads: &mut Vec<Quad>, layer: &mut Option<RgbaImage>| {
+            if let Some(image) = layer.take() {
+                quads.push(Quad::Image {
+                    image: Arc::new(image),
+                    x: 0,
+                    y: 0,
+                });
+            }
+        };
+        for element in slide.resolved_elements(size, ctx)?.iter() {
+            match element {
+                Element::Image {
+                    path,
+                    pos,
+                    rotation_deg,
+                    shadow: None,
+                    ken_burns: None,
+                    animate: false,
+                } if *rotation_deg % 360.0 == 0.0 => {
+                    flush(&mut quads, &mut layer);
+                    let rect = pos.to_rect(size);
+                    let image = ctx.images.load(path, (rect.width(), rect.height()))?;
+                    let (w, h) = (image.width(), image.height());
+                    quads.push(Quad::Image {
+                        image: Arc::new(image.to_rgba8()),
+                        x: rect.left() + (w.abs_diff(rect.width()) / 2) as i32,
+                        y: rect.top() + (h.abs_diff(rect.height()) / 2) as i32,
+                    });
+                }
+                Element::Color {
+                    color,
+                    pos,
+                    shadow: None,
+                    opacity,
+                } => {
+                    flush(&mut quads, &mut layer);
+                    let rect = pos.to_rect(size);
+                    quads.push(Quad::Color {
+                        color: *color,
+                        opacity: *opacity,
+                        rect: (rect.left(), rect.top(), rect.width(), rect.height()),
+                        radius: 10.0,
+                    });
+                }
+                element => match element.text_glyphs(size, ctx)? {
+                    Some((color, glyphs)) => {
+                        flush(&mut quads, &mut layer);
+                        quads.push(Quad::Text { color, glyphs });
+                    }
+                    None => {
+                        let layer = layer.get_or_insert_with(|| RgbaImage::new(size.0, size.1));
+                        element.render(layer, size, ctx)?;
+                    }
+                },
+            }
+        }
+        flush(&mut quads, &mut layer);
+        if let Some(color) = ctx.split_line_color {
+            quads.push(Quad::Color {
+                color,
+                opacity: 1.0,
+                rect: (0, 0, ctx.split_line_width, size.1),
+                radius: 0.0,
+            });
+        }
+        Ok(DynamicImage::ImageRgba8(self.composite(size, &quads)?))
+    }
+
+    /// 绘制 `quad` 所需的绑定组，每个字形一个；图集放不下的文字在 CPU 上绘制为图层
+    fn bind_groups(
+        &self,
+        quad: &Quad,
+        target: (u32, u32),
+        atlas: &mut GlyphAtlas,
+    ) -> Vec<wgpu::BindGroup> {
+        let canvas = [target.0 as f32, target.1 as f32, 0.0, 0.0];
+        match quad {
+            Quad::Image { image, x, y } => {
+                let rect = [
+                    *x as f32,
+                    *y as f32,
+                    image.width() as f32,
+                    image.height() as f32,
+                ];
+                let texture = upload(&self.device, &self.queue, image);
+                vec![self.bind_group([rect, canvas, [0.0; 4], [0.0; 4], [0.0; 4]], &texture)]
+            }
+            Quad::Color {
+                color,
+                opacity,
+                rect: (x, y, w, h),
+                radius,
+            } => {
+                let rect = [*x as f32, *y as f32, *w as f32, *h as f32];
+                let color = rgba(*color, *opacity);
+                let params = [1.0, *radius, 0.0, 0.0];
+                vec![self.bind_group([rect, canvas, color, params, [0.0; 4]], &self.blank)]
+            }
+            Quad::Text { color, glyphs } => {
+                let glyphs: Vec<&PlacedGlyph> = glyphs
+                    .iter()
+                    .filter(|glyph| glyph.coverage.size().0 * glyph.coverage.size().1 > 0)
+                    .collect();
+                let placed: Option<Vec<(u32, u32)>> = glyphs
+                    .iter()
+                    .map(|glyph| atlas.insert(&self.queue, glyph))
+                    .collect();
+                let Some(placed) = placed else {
+                    let glyphs: Vec<PlacedGlyph> = glyphs.into_iter().cloned().collect();
+                    let Some((image, x, y)) = text_layer(*color, &glyphs) else {
+                        return vec![];
+                    };
+                    let image = Arc::new(image);
+                    return self.bind_groups(&Quad::Image { image, x, y }, target, atlas);
+                };
+                let color = rgba(*color, 1.0);
+                let size = ATLAS_SIZE as f32;
+                glyphs
+                    .into_iter()
+                    .zip(placed)
+                    .map(|(glyph, (u, v))| {
+                        let (w, h) = glyph.coverage.size();
+                        let (w, h) = (w as f32, h as f32);
+                        let rect = [glyph.x as f32, glyph.y as f32, w, h];
+                        let uv = [u as f32 / size, v as f32 / size, w / size, h / size];
+                        let params = [2.0, 0.0, 0.0, 0.0];
+                        self.bind_group([rect, canvas, color, params, uv], &atlas.view)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// 以着色器中 `Quad` 的各字段和要采样的纹理创建绑定组
+    fn bind_group(&self, uniform: [[f32; 4]; 5], view: &wgpu::TextureView) -> wgpu::BindGroup {
+        let buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("quad"),
+                contents: bytemuck::cast_slice(&uniform),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("quad"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
+
+/// 着色器中的颜色，各分量在 0 到 1 之间
+fn rgba(Color([r, g, b]): Color, opacity: f32) -> [f32; 4] {
+    [
+        f32::from(r) / 255.0,
+        f32::from(g) / 255.0,
+        f32::from(b) / 255.0,
+        opacity.clamp(0.0, 1.0),
+    ]
+}
+
+/// 把图片上传为纹理
+fn upload(device: &wgpu::Device, queue: &wgpu::Queue, image: &RgbaImage) -> wgpu::TextureView {
+    let texture = device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("image"),
+            size: wgpu::Extent3d {
+                width: image.width().max(1),
+                height: image.height().max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        image.as_raw(),
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::slide::Position;
+
+    #[test]
+    fn test_shader_is_valid() {
+        use wgpu::naga::{front::wgsl, valid};
+        let module = wgsl::parse_str(SHADER).unwrap();
+        valid::Validator::new(valid::ValidationFlags::all(), valid::Capabilities::empty())
+            .validate(&module)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_composite_color_quads() {
+        // 没有 GPU 的环境中跳过
+        let Ok(gpu) = GpuCompositor::new() else {
+            return;
+        };
+        let quads = [
+            Quad::Color {
+                color: Color([255, 0, 0]),
+                opacity: 1.0,
+                rect: (0, 0, 40, 40),
+                radius: 0.0,
+            },
+            Quad::Color {
+                color: Color([0, 0, 255]),
+                opacity: 0.5,
+                rect: (20, 0, 20, 40),
+                radius: 0.0,
+            },
+        ];
+        let img = gpu.composite((40, 40), &quads).unwrap();
+        assert_eq!(img.get_pixel(5, 20).0, [255, 0, 0, 255]);
+        let [r, g, b, a] = img.get_pixel(30, 20).0;
+        assert!(r.abs_diff(128) <= 1 && g == 0 && b.abs_diff(128) <= 1 && a == 255);
+    }
+
+    #[test]
+    fn test_text_layer() {
+        use crate::imageproc::{
+            drawing::{DrawText, layout_text_center},
+            rect::Rect,
+        };
+        use image::{GrayImage, Luma};
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        let font = ab_glyph::FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap();
+        let rect = Rect::at(10, 20).of_size(100, 60);
+        let glyphs = layout_text_center(rect, 40.0, &font, "文字").unwrap();
+        let (layer, x, y) = text_layer(Color([0, 0, 255]), &glyphs).unwrap();
+        let mut gray = GrayImage::new(120, 80);
+        gray.draw_text_center_mut(Luma([255]), rect, 40.0, &font, "文字");
+        // 图层的不透明度与直接绘制的覆盖率相同
+        for (px, py, pixel) in layer.enumerate_pixels() {
+            let coverage = gray.get_pixel(px + x as u32, py + y as u32)[0];
+            assert!(pixel[3].abs_diff(coverage) <= 1);
+            assert!(pixel[3] == 0 || pixel.0[..3] == [0, 0, 255]);
+        }
+        assert!(text_layer(Color([0, 0, 0]), &[]).is_none());
+    }
+
+    #[test]
+    fn test_glyph_atlas_reuse() {
+        let Ok(gpu) = GpuCompositor::new() else {
+            return;
+        };
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        let font = ab_glyph::FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap();
+        let ctx = RenderContext::new(font);
+        let mut slide = Slide::new();
+        slide.add_text("文字文字", 40.0, Color([0, 0, 0]), Position::new(0, 0, 100));
+        let first = gpu.render_slide(&slide, (200, 100), &ctx).unwrap();
+        let glyphs = gpu.atlas.lock().unwrap().glyphs.len();
+        assert!(glyphs > 0);
+        // 第二次渲染直接使用图集中的字形
+        let second = gpu.render_slide(&slide, (200, 100), &ctx).unwrap();
+        assert_eq!(gpu.atlas.lock().unwrap().glyphs.len(), glyphs);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_render_slide_matches_cpu() {
+        let Ok(gpu) = GpuCompositor::new() else {
+            return;
+        };
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        let font = ab_glyph::FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap();
+        let ctx = RenderContext {
+            split_line_color: Some(Color([255, 255, 255])),
+            ..RenderContext::new(font)
+        };
+        let mut slide = Slide::new();
+        slide.add_color(Color([200, 0, 0]), Position::new(10, 10, 80));
+        slide.add_text("文字", 40.0, Color([0, 0, 0]), Position::new(10, 100, 60));
+        let size = (120, 200);
+        let cpu = slide.render(size, &ctx).unwrap().into_rgba8();
+        let gpu = gpu.render_slide(&slide, size, &ctx).unwrap().into_rgba8();
+        // 只有圆角和分割线边缘的抗锯齿略有差别
+        let differs = cpu
+            .pixels()
+            .zip(gpu.pixels())
+            .filter(|(a, b)| a.0.iter().zip(b.0).any(|(x, y)| x.abs_diff(y) > 8))
+            .count();
+        assert!(
+            differs * 100 < cpu.pixels().len(),
+            "{differs} pixels differ"
+        );
+        assert_eq!(gpu.get_pixel(50, 50), cpu.get_pixel(50, 50));
+    }
+}
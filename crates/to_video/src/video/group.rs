@@ -0,0 +1,226 @@
+use super::slide::{Position, Slide};
+use crate::{BLACK, Result, WHITE, color::Color};
+use serde::{Deserialize, Serialize};
+use structs::{Mastery, Profession};
+
+/// 按某一数据列分组，在每组的第一行之前插入一张分组标题 slide。
+///
+/// 数据需要事先按该列排好序，相邻且取值相同的行属于同一组，
+/// 例如按品牌排序的皮肤数据以品牌名称所在的列分组。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GroupBy {
+    /// 用作分组键的数据列序号，从 0 开始
+    pub column: usize,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default = "default_color")]
+    pub color: Color,
+    #[serde(default = "default_background")]
+    pub background: Color,
+    /// 标题 slide 的宽度，`None` 时使用 `width_slides`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+}
+
+impl GroupBy {
+    pub fn new(column: usize) -> Self {
+        Self {
+            column,
+            scale: default_scale(),
+            color: default_color(),
+            background: default_background(),
+            width: None,
+        }
+    }
+
+    /// 生成显示 `title` 的分组标题 slide：整页背景色加上居中的大号文字
+    pub fn header_slide(&self, title: &str) -> Slide {
+        let mut slide = Slide::new();
+        slide.add_color(self.background, Position::relative(0.0, 0.0, 1.0));
+        slide.add_text(
+            title,
+            self.scale,
+            self.color,
+            Position::relative(0.05, 0.35, 0.3),
+        );
+        match self.width {
+            Some(width) => slide.with_width(width),
+            None => slide,
+        }
+    }
+}
+
+/// 分组后的一行：分组标题或原始数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupRow {
+    Header(String),
+    Data(Vec<String>),
+}
+
+/// 在分组键 `column` 的取值变化处插入分组标题。
+///
+/// # Errors
+/// - 某一行缺少 `column` 列时返回 `Err`。
+///
+pub fn group_rows(datas: Vec<Vec<String>>, column: usize) -> Result<Vec<GroupRow>> {
+    let mut rows = Vec::with_capacity(datas.len());
+    let mut current: Option<String> = None;
+    for (index, data) in datas.into_iter().enumerate() {
+        let key = data
+            .get(column)
+            .ok_or_else(|| format!("group_by: row {index} has no column {column}"))?;
+        if current.as_ref() != Some(key) {
+            current = Some(key.clone());
+            rows.push(GroupRow::Header(key.clone()));
+        }
+        rows.push(GroupRow::Data(data));
+    }
+    Ok(rows)
+}
+
+/// 按第 0 列分组的数据及对应的 [`GroupBy`]，用于常见的 “分组列表” 视频：
+/// 把 `group_by` 写入配置，用 `datas` 创建视频即可，每组之前自动插入分组标题 slide
+#[derive(Debug, Clone)]
+pub struct GroupedData {
+    pub datas: Vec<Vec<String>>,
+    pub group_by: GroupBy,
+}
+
+impl GroupedData {
+    /// 按职能分组的专精数据，每行为 `[职能, 干员, 专精]`，按职能和干员排序
+    pub fn masteries(masteries: &[Mastery]) -> Self {
+        let mut datas: Vec<Vec<String>> = masteries
+            .iter()
+            .map(|mastery| {
+                vec![
+                    mastery.职能.clone(),
+                    mastery.name.clone(),
+                    mastery.专精.clone(),
+                ]
+            })
+            .collect();
+        datas.sort_by(|a, b| a[..2].cmp(&b[..2]));
+        Self {
+            datas,
+            group_by: GroupBy::new(0),
+        }
+    }
+
+    /// 按职业分组的分支数据，每行为 `[职业, 分支, 分支说明]`，
+    /// 职业保持原有的顺序，同一职业的分支按名称排序
+    pub fn professions(professions: &[Profession]) -> Self {
+        let datas = professions
+            .iter()
+            .flat_map(|profession| {
+                let mut subprofessions: Vec<_> = profession.subprofession.iter().collect();
+                subprofessions.sort();
+                subprofessions.into_iter().map(|(name, description)| {
+                    vec![
+                        profession.profession.clone(),
+                        name.clone(),
+                        description.clone(),
+                    ]
+                })
+            })
+            .collect();
+        Self {
+            datas,
+            group_by: GroupBy::new(0),
+        }
+    }
+}
+
+const fn default_scale() -> f32 {
+    160.0
+}
+
+const fn default_color() -> Color {
+    WHITE
+}
+
+const fn default_background() -> Color {
+    BLACK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(brand: &str, skin: &str) -> Vec<String> {
+        vec![skin.to_string(), brand.to_string()]
+    }
+
+    #[test]
+    fn test_group_rows() {
+        let datas = vec![
+            row("A", "a1"),
+            row("A", "a2"),
+            row("B", "b1"),
+            row("A", "a3"),
+        ];
+        let rows = group_rows(datas, 1).unwrap();
+        assert_eq!(
+            rows,
+            [
+                GroupRow::Header("A".into()),
+                GroupRow::Data(row("A", "a1")),
+                GroupRow::Data(row("A", "a2")),
+                GroupRow::Header("B".into()),
+                GroupRow::Data(row("B", "b1")),
+                GroupRow::Header("A".into()),
+                GroupRow::Data(row("A", "a3")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grouped_data() {
+        let masteries: Vec<Mastery> = serde_json::from_str(
+            r#"[
+                {"name": "史尔特尔", "专精": "熔核巨影", "职能": "输出"},
+                {"name": "能天使", "专精": "过载模式", "职能": "输出"},
+                {"name": "闪灵", "专精": "圣域", "职能": "治疗"},
+                {"name": "艾雅法拉", "专精": "火山", "职能": "输出"}
+            ]"#,
+        )
+        .unwrap();
+        let grouped = GroupedData::masteries(&masteries);
+        let keys: Vec<_> = grouped
+            .datas
+            .iter()
+            .map(|row| (row[0].as_str(), row[1].as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("治疗", "闪灵"),
+                ("输出", "史尔特尔"),
+                ("输出", "能天使"),
+                ("输出", "艾雅法拉"),
+            ]
+        );
+        let headers = group_rows(grouped.datas, grouped.group_by.column)
+            .unwrap()
+            .into_iter()
+            .filter(|row| matches!(row, GroupRow::Header(_)))
+            .count();
+        assert_eq!(headers, 2);
+
+        let professions: Vec<Profession> = serde_json::from_str(
+            r#"[
+                {"profession": "近卫", "subprofession": {"武者": "b", "剑豪": "a"}},
+                {"profession": "先锋", "subprofession": {"尖兵": "c"}}
+            ]"#,
+        )
+        .unwrap();
+        let grouped = GroupedData::professions(&professions);
+        assert_eq!(grouped.datas[0], ["近卫", "剑豪", "a"]);
+        assert_eq!(grouped.datas[1], ["近卫", "武者", "b"]);
+        assert_eq!(grouped.datas[2], ["先锋", "尖兵", "c"]);
+    }
+
+    #[test]
+    fn test_missing_column() {
+        assert!(group_rows(vec![row("A", "a1")], 2).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use image::{DynamicImage, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 图片的平移缩放动画：在图片出现在屏幕上的期间从 `from` 变化到 `to`。
+///
+/// 进度按长条图的滚动位置计算：图片进入屏幕（或视频开始）时为 0，完全离开屏幕时为 1，
+/// 因此跨越多个 chunk 的图片动画是连续的。
+/// 动画画面叠加在整个 slide 之上，会遮住 z_index 更高的元素，并且不支持旋转。
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct KenBurns {
+    pub from: ViewRect,
+    pub to: ViewRect,
+}
+
+/// 图片的可见区域：中心点 (`x`, `y`) 为相对于图片宽高的比例，`zoom` 不小于 1
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct ViewRect {
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
+impl ViewRect {
+    /// 原图，不缩放
+    pub const FULL: Self = Self {
+        x: 0.5,
+        y: 0.5,
+        zoom: 1.0,
+    };
+
+    /// 截取可见区域并放大回原尺寸
+    pub fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        let (width, height) = (img.width() as f32, img.height() as f32);
+        let zoom = self.zoom.max(1.0);
+        let (w, h) = (width / zoom, height / zoom);
+        let left = (self.x * width - w / 2.0).clamp(0.0, width - w);
+        let top = (self.y * height - h / 2.0).clamp(0.0, height - h);
+        img.crop_imm(
+            left.round() as u32,
+            top.round() as u32,
+            (w.round() as u32).max(1),
+            (h.round() as u32).max(1),
+        )
+        .resize_exact(img.width(), img.height(), FilterType::Triangle)
+    }
+}
+
+impl KenBurns {
+    /// 生成 ffmpeg 的 `zoompan` 滤镜，`progress` 为 0 到 1 的进度表达式，
+    /// 输出 `size` 大小、帧率为 `fps` 的画面
+    pub fn zoompan(&self, progress: &str, size: (u32, u32), fps: u32) -> String {
+        let (from, to) = (self.from, self.to);
+        let lerp = |a: f32, b: f32| format!("({a}+({b}-{a})*{progress})");
+        let (z0, z1) = (from.zoom.max(1.0), to.zoom.max(1.0));
+        let (w, h) = size;
+        // 先放大两倍再截取，避免缩放动画抖动
+        format!(
+            "scale={}:{},zoompan=z='{}':x='clip({}*iw-iw/zoom/2,0,iw-iw/zoom)':y='clip({}*ih-ih/zoom/2,0,ih-ih/zoom)':d=1:s={w}x{h}:fps={fps}",
+            w * 2,
+            h * 2,
+            lerp(z0, z1),
+            lerp(from.x, to.x),
+            lerp(from.y, to.y),
+        )
+    }
+}
+
+/// slide 中带有动画的图片，见 [`Slide::ken_burns_images`](super::slide::Slide::ken_burns_images)
+#[derive(Debug, Clone, PartialEq)]
+pub struct KenBurnsImage {
+    pub path: PathBuf,
+    pub ken_burns: KenBurns,
+    /// 图片左上角在 slide 中的位置
+    pub pos: (u32, u32),
+    pub size: (u32, u32),
+}
+
+/// 叠加在长条图上的动画图片，坐标和尺寸都已按预览比例缩放
+#[derive(Debug, Clone, PartialEq)]
+pub struct KenBurnsOverlay {
+    /// 图片的绝对路径
+    pub path: PathBuf,
+    pub ken_burns: KenBurns,
+    /// 图片左上角在长条图中的位置
+    pub x: f64,
+    pub y: u32,
+    pub size: (u32, u32),
+    /// 长条图滚动到 `start` 像素时进度为 0，再滚动 `len` 像素时进度为 1
+    pub start: f64,
+    pub len: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgba};
+
+    #[test]
+    fn test_view_rect_apply() {
+        let mut img = DynamicImage::new_rgba8(100, 50);
+        img.as_mut_rgba8()
+            .unwrap()
+            .put_pixel(99, 49, Rgba([255, 0, 0, 255]));
+
+        assert_eq!(
+            ViewRect::FULL.apply(&img).get_pixel(99, 49),
+            img.get_pixel(99, 49)
+        );
+        // 放大两倍并对准右下角：可见区域被限制在图片内
+        let zoomed = ViewRect {
+            x: 1.0,
+            y: 1.0,
+            zoom: 2.0,
+        }
+        .apply(&img);
+        assert_eq!(zoomed.dimensions(), (100, 50));
+        assert_eq!(zoomed.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(zoomed.get_pixel(99, 49)[0], 255);
+    }
+
+    #[test]
+    fn test_zoompan() {
+        let ken_burns = KenBurns {
+            from: ViewRect::FULL,
+            to: ViewRect {
+                x: 0.25,
+                y: 0.5,
+                zoom: 1.5,
+            },
+        };
+        let filter = ken_burns.zoompan("P", (480, 600), 30);
+        assert!(filter.starts_with("scale=960:1200,zoompan=z='(1+(1.5-1)*P)'"));
+        assert!(filter.contains("x='clip((0.5+(0.25-0.5)*P)*iw-iw/zoom/2,0,iw-iw/zoom)'"));
+        assert!(filter.ends_with(":d=1:s=480x600:fps=30"));
+    }
+}
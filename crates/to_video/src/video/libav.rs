@@ -0,0 +1,243 @@
+//! 通过 ffmpeg-next 直接调用 libavcodec 和 libavformat 的后端。
+//!
+//! 每一帧由 [`SegmentFrames`](super::frames::SegmentFrames) 绘制后直接交给编码器，
+//! 不启动 ffmpeg 进程，也不把中间图片交给命令行，出错时返回 libav 的错误。
+//! 各段视频仍写入临时目录，以便增量生成时缓存，合并时直接复制各段的数据包。
+//!
+//! 进度指示、音效、旁白、背景音乐和其他分辨率依赖 ffmpeg 滤镜，
+//! 只有 [`FfmpegCliEncoder`](super::encoder::FfmpegCliEncoder) 支持。
+
+use super::{
+    VideoConfig,
+    encoder::{EncodeSegment, VideoEncoder},
+};
+use crate::Result;
+use ffmpeg_next::{
+    Dictionary, Packet, Rational, codec, encoder,
+    format::{self, Pixel},
+    frame, media,
+    software::scaling,
+};
+use image::RgbaImage;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// 调用 libav 编码的后端，只实现逐帧的 [`push_frame`](VideoEncoder::push_frame)
+pub struct LibavEncoder {
+    /// 编码器名称，例如 `libx264`
+    pub encoder: String,
+    pub preset: String,
+    work_dir: PathBuf,
+    /// 正在写入的一段视频
+    current: Option<SegmentOutput>,
+}
+
+impl LibavEncoder {
+    pub fn new(config: &VideoConfig) -> Self {
+        Self {
+            encoder: config.encoder.clone(),
+            preset: config.preset.clone(),
+            work_dir: PathBuf::new(),
+            current: None,
+        }
+    }
+}
+
+impl VideoEncoder for LibavEncoder {
+    fn begin(&mut self, work_dir: &Path) -> Result<()> {
+        ffmpeg_next::init()?;
+        if encoder::find_by_name(&self.encoder).is_none() {
+            return Err(format!("encoder {} not found in libav", self.encoder).into());
+        }
+        self.work_dir = work_dir.to_path_buf();
+        self.current = None;
+        Ok(())
+    }
+
+    fn push_frame(&mut self, segment: &EncodeSegment, index: u64, frame: &RgbaImage) -> Result<()> {
+        if index == 0 {
+            let path = self.work_dir.join(&segment.name);
+            let output = SegmentOutput::open(&path, &self.encoder, &self.preset, segment)?;
+            self.current = Some(output);
+        }
+        let output = self.current.as_mut().ok_or_else(|| {
+            format!(
+                "{}: frame {index} pushed before frame 0",
+                segment.name.display()
+            )
+        })?;
+        output.push(index, frame)?;
+        if index + 1 == segment.frames
+            && let Some(output) = self.current.take()
+        {
+            output.finish()?;
+        }
+        Ok(())
+    }
+
+    fn finish(
+        &mut self,
+        videos: &[PathBuf],
+        save_path: &Path,
+        metadata: &HashMap<String, String>,
+    ) -> Result<()> {
+        let first = videos.first().ok_or("no videos to concatenate")?;
+        let first = format::input(&self.work_dir.join(first))?;
+        let stream = first
+            .streams()
+            .best(media::Type::Video)
+            .ok_or("no video stream")?;
+
+        let mut octx = format::output(save_path)?;
+        let mut dictionary = Dictionary::new();
+        for (key, value) in metadata {
+            dictionary.set(key, value);
+        }
+        octx.set_metadata(dictionary);
+        // 各段由同一个编码器以相同的参数生成，直接使用第一段的参数
+        octx.add_stream(encoder::find(codec::Id::None))?
+            .set_parameters(stream.parameters());
+        octx.write_header()?;
+        let time_base = octx.stream(0).ok_or("no output stream")?.time_base();
+
+        // 每段的时间戳接在前一段的结尾之后
+        let mut offset = 0;
+        for video in videos {
+            let mut input = format::input(&self.work_dir.join(video))?;
+            let stream = input
+                .streams()
+                .best(media::Type::Video)
+                .ok_or_else(|| format!("{}: no video stream", video.display()))?;
+            let (index, input_time_base) = (stream.index(), stream.time_base());
+            let mut end = offset;
+            for (stream, mut packet) in input.packets() {
+                if stream.index() != index {
+                    continue;
+                }
+                packet.rescale_ts(input_time_base, time_base);
+                packet.set_pts(packet.pts().map(|pts| pts + offset));
+                packet.set_dts(packet.dts().map(|dts| dts + offset));
+                if let Some(pts) = packet.pts() {
+                    end = end.max(pts + packet.duration());
+                }
+                packet.set_position(-1);
+                packet.set_stream(0);
+                packet.write_interleaved(&mut octx)?;
+            }
+            offset = end;
+        }
+        octx.write_trailer()?;
+        println!("{} successed", save_path.display());
+        Ok(())
+    }
+}
+
+/// 正在写入的一段视频
+struct SegmentOutput {
+    octx: format::context::Output,
+    encoder: encoder::Video,
+    /// RGBA 转换为编码器像素格式
+    scaler: scaling::Context,
+    rgba: frame::Video,
+    yuv: frame::Video,
+    /// 编码器的时间基，即一帧
+    time_base: Rational,
+}
+
+impl SegmentOutput {
+    /// 以名为 `name` 的编码器创建 `path`，分辨率和帧率与 `segment` 相同
+    fn open(path: &Path, name: &str, preset: &str, segment: &EncodeSegment) -> Result<Self> {
+        let codec = encoder::find_by_name(name)
+            .ok_or_else(|| format!("encoder {name} not found in libav"))?;
+        let (width, height) = segment.screen;
+        let fps = i32::try_from(segment.fps.max(1))?;
+        let time_base = Rational(1, fps);
+
+        let mut octx = format::output(path)?;
+        let global_header = octx.format().flags().contains(format::Flags::GLOBAL_HEADER);
+        let mut video = codec::context::Context::new_with_codec(codec)
+            .encoder()
+            .video()?;
+        video.set_width(width);
+        video.set_height(height);
+        video.set_format(Pixel::YUV420P);
+        video.set_time_base(time_base);
+        video.set_frame_rate(Some(Rational(fps, 1)));
+        if global_header {
+            video.set_flags(codec::Flags::GLOBAL_HEADER);
+        }
+        let mut options = Dictionary::new();
+        options.set("preset", preset);
+        let encoder = video.open_with(options)?;
+
+        let mut stream = octx.add_stream(codec)?;
+        stream.set_time_base(time_base);
+        stream.set_parameters(&encoder);
+        octx.write_header()?;
+
+        let scaler = scaling::Context::get(
+            Pixel::RGBA,
+            width,
+            height,
+            Pixel::YUV420P,
+            width,
+            height,
+            scaling::Flags::BILINEAR,
+        )?;
+        Ok(Self {
+            octx,
+            encoder,
+            scaler,
+            rgba: frame::Video::new(Pixel::RGBA, width, height),
+            yuv: frame::Video::new(Pixel::YUV420P, width, height),
+            time_base,
+        })
+    }
+
+    /// 编码第 `index` 帧
+    fn push(&mut self, index: u64, image: &RgbaImage) -> Result<()> {
+        if image.dimensions() != (self.rgba.width(), self.rgba.height()) {
+            return Err(format!(
+                "frame {index} is {}x{}, expected {}x{}",
+                image.width(),
+                image.height(),
+                self.rgba.width(),
+                self.rgba.height()
+            )
+            .into());
+        }
+        // libav 的每行可能有额外的对齐字节
+        let stride = self.rgba.stride(0);
+        let row = image.width() as usize * 4;
+        let data = self.rgba.data_mut(0);
+        for (y, pixels) in image.as_raw().chunks_exact(row).enumerate() {
+            data[y * stride..y * stride + row].copy_from_slice(pixels);
+        }
+        self.scaler.run(&self.rgba, &mut self.yuv)?;
+        self.yuv.set_pts(Some(i64::try_from(index)?));
+        self.encoder.send_frame(&self.yuv)?;
+        self.write_packets()
+    }
+
+    /// 写入编码器已经输出的数据包
+    fn write_packets(&mut self) -> Result<()> {
+        let stream_time_base = self.octx.stream(0).ok_or("no output stream")?.time_base();
+        let mut packet = Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(0);
+            packet.rescale_ts(self.time_base, stream_time_base);
+            packet.write_interleaved(&mut self.octx)?;
+        }
+        Ok(())
+    }
+
+    /// 取出编码器中剩余的帧并写完文件
+    fn finish(mut self) -> Result<()> {
+        self.encoder.send_eof()?;
+        self.write_packets()?;
+        self.octx.write_trailer()?;
+        Ok(())
+    }
+}
@@ -1,46 +1,246 @@
+pub mod animated;
+pub mod assets;
+pub mod beat;
+mod cache;
+pub mod chart;
+pub mod chunk;
+pub mod collation;
+pub mod component;
 pub mod config;
+pub mod debug;
+pub mod encoder;
+pub mod export;
 pub mod ffmpeg;
+pub mod format;
+pub mod frames;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod group;
+pub mod ken_burns;
+#[cfg(feature = "libav")]
+pub mod libav;
+pub mod narration;
+pub mod orientation;
+pub mod preflight;
+pub mod probe;
+pub mod progress;
+pub mod ranking;
+pub mod redact;
+pub mod renderable;
+pub mod repeat;
+pub mod report;
+pub mod row;
+pub mod safe_area;
+pub mod scroll;
 pub mod slide;
+pub mod slideshow;
+pub mod sound;
+pub mod table;
+pub mod timeline;
+pub mod transform;
+pub mod versus;
+pub mod voice;
 
-use crate::{Result, video::ffmpeg::generate_cover_video};
-use ab_glyph::FontArc;
-use ffmpeg::{combain, combain_slides, generate_mid_video};
-use slide::{Operation, Slide};
+use crate::{
+    Result,
+    color::Color,
+    font::{FontChain, load_font_chain},
+};
+use beat::BeatSync;
+use cache::{RenderCache, inputs_hash, modified};
+use chunk::{Chunk, chunk_ranges};
+use debug::draw_debug_overlay;
+use encoder::{EncodeSegment, FfmpegCliEncoder, SegmentPlan, VideoEncoder};
+use ffmpeg::{
+    Ffmpeg, IntermediateFormat, RowOverlay, StripTile, combain_rendered_slides, flatten,
+    parse_back_color,
+};
+use group::{GroupRow, group_rows};
+use image::{DynamicImage, RgbaImage, imageops::FilterType};
+use ken_burns::KenBurnsOverlay;
+use narration::{NarrationClip, Narrator};
+use orientation::Orientation;
+use progress::ProgressOverlay;
+use redact::redact;
+use report::{ContrastReport, RenderReport, SafeAreaReport, TextFitReport};
+use row::Row;
+use scroll::{FrameClock, Keyframe, Scroll, scroll_keyframes};
+use slide::{Operation, RenderContext, Slide, TextOverflow};
+use slideshow::Presentation;
+use sound::SoundEffect;
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     fs,
+    ops::Range,
     path::{Path, PathBuf},
+    sync::Mutex,
 };
+use timeline::{TimelineRow, timeline_rows};
 
-pub use config::{VideoConfig, VideoConfigBuilder};
+pub use config::{ConfigIssue, ConfigIssues, VideoConfig, VideoConfigBuilder};
 
 pub struct Video {
-    chunks: Vec<Vec<Slide>>,
+    chunks: Vec<Chunk>,
+    rows: Vec<Row>,
     config: VideoConfig,
+    narrator: Option<Box<dyn Narrator + Send>>,
 }
 
 impl Video {
     pub fn builder(
         operations: &mut [Operation],
-        datas: Vec<Vec<String>>,
-        config: VideoConfig,
+        mut datas: Vec<Vec<String>>,
+        mut config: VideoConfig,
     ) -> Result<VideoBuilder> {
         operations.sort();
-        Ok(VideoBuilder {
-            slides: datas
+        if !config.rows.is_empty() && config.group_by.is_some() {
+            return Err("rows can not be used together with group_by".into());
+        }
+        if !config.rows.is_empty() && config.timeline.is_some() {
+            return Err("rows can not be used together with timeline".into());
+        }
+        if config.group_by.is_some() && config.timeline.is_some() {
+            return Err("timeline can not be used together with group_by".into());
+        }
+        if config.ranking.is_some() && (config.group_by.is_some() || config.timeline.is_some()) {
+            return Err("ranking can not be used together with group_by or timeline".into());
+        }
+        // 排行榜先排序和截取数据，其他行也使用排序后的数据
+        let ranks = match &config.ranking {
+            Some(ranking) => {
+                let (sorted, ranks) = ranking.rank(datas)?;
+                datas = sorted;
+                ranks
+            }
+            None => Vec::new(),
+        };
+        redact(&mut datas, &config.redact);
+        let rows = std::mem::take(&mut config.rows)
+            .into_iter()
+            .map(|row| Row::new(row, &datas, config.width_slides))
+            .collect::<Result<Vec<Row>>>()?;
+        let operations = &*operations;
+        let generation = |data| match &config.versus {
+            Some(versus) => versus.generation(operations, data, config.width_slides),
+            None => Slide::generation(operations, data),
+        };
+        let slides = match (&config.group_by, &config.timeline) {
+            (Some(group_by), _) => group_rows(datas, group_by.column)?
+                .into_iter()
+                .map(|row| match row {
+                    GroupRow::Header(title) => Ok(group_by.header_slide(&title)),
+                    GroupRow::Data(data) => generation(data),
+                })
+                .collect::<Result<Vec<Slide>>>()?,
+            (None, Some(timeline)) => timeline_rows(datas, timeline)?
+                .into_iter()
+                .map(|row| match row {
+                    TimelineRow::Separator { label, gap } => {
+                        Ok(timeline.separator_slide(&label, gap, config.width_slides))
+                    }
+                    TimelineRow::Data(data) => generation(data),
+                })
+                .collect::<Result<Vec<Slide>>>()?,
+            (None, None) => datas
                 .into_iter()
-                .map(|data| Slide::generation(operations, data))
+                .map(generation)
                 .collect::<Result<Vec<Slide>>>()?,
-            config: config,
+        };
+        let slides = match &config.ranking {
+            Some(ranking) => slides
+                .into_iter()
+                .zip(ranks)
+                .map(|(slide, (rank, score))| ranking.decorate(slide, rank, score))
+                .collect(),
+            None => slides,
+        };
+        Ok(VideoBuilder {
+            slides,
+            rows,
+            config,
         })
     }
 
-    pub fn chunks(&self) -> &Vec<Vec<Slide>> {
+    pub fn chunks(&self) -> &Vec<Chunk> {
         &self.chunks
     }
 
     pub fn config(&self) -> &VideoConfig {
         &self.config
     }
+
+    /// 检查主长条图中各文字元素的排版，不渲染图片，也不需要 ffmpeg。
+    ///
+    /// 报告中包含每个文字元素最终使用的字号，是否缩小到了 `min_text_scale` 以下，
+    /// 与下方色块对比度不足的文字，以及超出 `safe_area` 的元素。
+    /// `text_overflow` 为 `Error` 时也返回报告，由调用者决定如何处理。
+    pub fn validate(&self) -> Result<RenderReport> {
+        let font = load_font_chain(self.config.font.as_deref(), &self.config.fallback_fonts)?;
+        self.text_report(&render_context(&self.config, font))
+    }
+
+    fn text_report(&self, ctx: &RenderContext) -> Result<RenderReport> {
+        let mut report = RenderReport::default();
+        let (mut index, mut end) = (0, 0);
+        for chunk in &self.chunks {
+            let mut x = chunk.offset;
+            for slide in &chunk.slides {
+                let width = slide.width().unwrap_or(self.config.width_slides);
+                // 上一个 chunk 末尾的 slide 会在下一个 chunk 开头重复出现
+                if x >= end {
+                    let size = self
+                        .config
+                        .orientation
+                        .slide_size(width, self.config.screen);
+                    report
+                        .texts
+                        .extend(
+                            slide
+                                .text_fits(size, ctx)
+                                .into_iter()
+                                .map(|(element, text)| TextFitReport {
+                                    slide: index,
+                                    element,
+                                    text,
+                                }),
+                        );
+                    report.contrast.extend(
+                        slide
+                            .low_contrast_texts(size, ctx)?
+                            .into_iter()
+                            .map(|text| ContrastReport { slide: index, text }),
+                    );
+                    if let Some(safe) = &self.config.safe_area {
+                        report.outside_safe_area.extend(
+                            safe.outside_elements(slide, size)
+                                .into_iter()
+                                .map(|element| SafeAreaReport {
+                                    slide: index,
+                                    element,
+                                }),
+                        );
+                    }
+                    index += 1;
+                    end = x + width;
+                }
+                x += width;
+            }
+        }
+        Ok(report)
+    }
+
+    /// 与 [`text_report`](Self::text_report) 相同，`text_overflow` 为 `Error` 时
+    /// 在渲染任何图片之前检查文字是否过小
+    fn checked_text_report(&self, ctx: &RenderContext) -> Result<RenderReport> {
+        let report = self.text_report(ctx)?;
+        if self.config.text_overflow == TextOverflow::Error
+            && let Some(text) = report.below_min().next()
+        {
+            return Err(format!("text below min_text_scale {}: {text}", ctx.min_text_scale).into());
+        }
+        Ok(report)
+    }
 }
 
 impl Video {
@@ -48,118 +248,889 @@ impl Video {
     ///
     /// # Parameters
     /// - `handle_progress`: 处理进度的回调函数，参数为处理文件名、已处理数量和总数量。
-    pub fn run<F>(self, handle_progress: F) -> Result<()>
+    ///
+    /// # Results
+    /// 各文字元素的排版报告，见 [`validate`](Self::validate)。
+    pub fn run<F>(self, handle_progress: F) -> Result<RenderReport>
+    where
+        F: Fn(&Path, usize, usize) -> std::result::Result<(), String>,
+    {
+        let mut encoder = FfmpegCliEncoder::new(&self.config);
+        self.run_with(&mut encoder, handle_progress)
+    }
+
+    /// 与 [`run`](Self::run) 相同，但由 `encoder` 编码和合并各段视频。
+    ///
+    /// # Parameters
+    /// - `encoder`: 编码后端，见 [`VideoEncoder`]。
+    /// - `handle_progress`: 处理进度的回调函数，参数为处理文件名、已处理数量和总数量。
+    ///
+    /// # Results
+    /// 各文字元素的排版报告，见 [`validate`](Self::validate)。
+    pub fn run_with<F>(
+        mut self,
+        encoder: &mut dyn VideoEncoder,
+        handle_progress: F,
+    ) -> Result<RenderReport>
     where
         F: Fn(&Path, usize, usize) -> std::result::Result<(), String>,
     {
+        if self.config.presentation != Presentation::Scroll {
+            return self.run_slideshow(encoder, handle_progress);
+        }
         let chunks_len = self.chunks.len();
+        let mut narrator = self.take_narrator();
+        let renderer = Renderer::new(&self.config, &self.rows)?;
+        let mut report = self.checked_text_report(&renderer.ctx)?;
+        renderer.preflight(&self.chunks)?;
+        let work_dir = &renderer.work_dir;
+        encoder.begin(work_dir)?;
+        let mut results = Vec::with_capacity(chunks_len * 2 + 1 + self.config.overlap as usize);
+        let mut videos = Vec::with_capacity(chunks_len + 1);
+        // 各个 chunk 开始的帧，即与前一段视频衔接的位置
+        let mut boundaries = Vec::with_capacity(chunks_len);
+        // 所有片段的时长都按帧计算，避免舍入误差在拼接后累积
+        let mut clock = FrameClock::new(self.config.fps);
+        let mut cache = (self.config.incremental && !renderer.ffmpeg.dry_run)
+            .then(|| RenderCache::open(&self.config.work_dir))
+            .transpose()?;
+        // 新生成的片段及其缓存键，等编码器写完之后再存入缓存
+        let mut encoded = Vec::with_capacity(chunks_len + 1);
+
+        let cover_video_name = PathBuf::from("cover.mp4");
+        let key = renderer.cover_key(&self.chunks[0], &clock);
+        if let Some(cache) = cache.as_mut()
+            && cache.restore(&cover_video_name, key, &work_dir.join(&cover_video_name))?
+        {
+            clock.advance(f64::from(self.config.cover_sec));
+            results.push(cover_video_name.clone());
+        } else {
+            let segment = renderer.cover(&self.chunks[0], &mut clock, &mut results)?;
+            encoder.push_segment(&segment, work_dir)?;
+            encoded.push((segment.name, key));
+        }
+        videos.push(cover_video_name.clone());
+        handle_progress(&cover_video_name, 1, chunks_len + 1)?;
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            boundaries.push(clock.elapsed_frames());
+            let mid_video_name = PathBuf::from(format!("{index:0>2}.mp4"));
+            let key = renderer.chunk_key(index, chunk, chunks_len, &clock);
+            if let Some(cache) = cache.as_mut()
+                && cache.restore(&mid_video_name, key, &work_dir.join(&mid_video_name))?
+            {
+                let (scroll, static_sec) = renderer.scroll(index, chunk, chunks_len);
+                clock.advance(scroll.duration() + f64::from(static_sec));
+                results.push(mid_video_name.clone());
+            } else {
+                let segment = renderer.chunk(index, chunk, chunks_len, &mut clock, &mut results)?;
+                encoder.push_segment(&segment, work_dir)?;
+                encoded.push((segment.name, key));
+            }
+            videos.push(mid_video_name.clone());
+            handle_progress(&mid_video_name, index + 2, chunks_len + 1)?;
+        }
+        encoder.flush()?;
+        if let Some(cache) = cache.as_mut() {
+            for (name, key) in encoded {
+                cache.store(&name, key, &work_dir.join(&name))?;
+            }
+        }
+
+        let narration = match narrator.as_deref_mut() {
+            Some(narrator) => self.narrate(narrator, &renderer)?,
+            None => Vec::new(),
+        };
+        let duration = clock.elapsed_frames() as f64 / f64::from(clock.fps());
+        let progress = self.progress_overlay(&renderer, duration);
+        let steps = self.post_steps(&boundaries, &narration, progress.as_ref());
+        self.finish(encoder, &videos, work_dir, &steps)?;
+        if let Some(cache) = cache {
+            cache.prune()?;
+        }
+
+        if self.config.clean_temp && !renderer.ffmpeg.dry_run {
+            // 只删除本次运行的子目录，不影响同一 work_dir 下的其他任务
+            fs::remove_dir_all(work_dir)?;
+            println!("cleanup successed");
+        }
+        report.glyph_cache = renderer.ctx.font.glyph_cache().stats();
+        Ok(report)
+    }
 
-        let font_buf = fs::read(&self.config.font)?;
-        let font = FontArc::try_from_vec(font_buf).map_err(|_| "Invalid font file")?;
+    /// 按顺序合并 `videos` 并写入 `save_path`，之后生成 `renditions` 中的其他分辨率。
+    ///
+    /// 有后续处理 `steps`（见 [`post_steps`](Self::post_steps)）时先合并到 `work_dir` 中，
+    /// 再依次处理，最后一步写入 `save_path`。
+    fn finish(
+        &self,
+        encoder: &mut dyn VideoEncoder,
+        videos: &[PathBuf],
+        work_dir: &Path,
+        steps: &[PostStep],
+    ) -> Result<()> {
         let VideoConfig {
-            encoder,
-            screen,
-            fps,
-            ref work_dir,
-            ref back_color,
-            cover_sec,
-            motion_type,
-            ending_sec,
-            swip_pixels_per_sec,
-            width_slides,
             ref save_path,
-            overlap,
-            split_line_color,
-            clean_temp,
+            ref metadata,
             ..
         } = self.config;
-        let mut results = Vec::with_capacity(chunks_len * 2 + 1 + overlap as usize);
+        // ffmpeg 的工作目录是本次运行的临时目录，相对的 save_path 需要先转换为绝对路径
+        let save_path = &std::path::absolute(save_path)?;
+        if steps.is_empty() {
+            encoder.finish(videos, save_path, metadata)?;
+        } else {
+            let mut source = work_dir.join(SILENT_VIDEO);
+            encoder.finish(videos, &source, &Default::default())?;
+            for (i, step) in steps.iter().enumerate() {
+                let (output, metadata) = if i + 1 == steps.len() {
+                    (save_path.clone(), metadata.clone())
+                } else {
+                    (work_dir.join(format!("post_{i}.mp4")), HashMap::new())
+                };
+                let keep_audio = steps[..i].iter().any(PostStep::adds_audio);
+                match step {
+                    PostStep::Progress(progress) => {
+                        encoder.progress(&source, &output, progress, &metadata)?
+                    }
+                    PostStep::Effects(effect, times) => {
+                        encoder.sound_effects(&source, &output, effect, times, &metadata)?
+                    }
+                    PostStep::Narration(clips) => {
+                        encoder.narration(&source, &output, clips, keep_audio, &metadata)?
+                    }
+                    PostStep::Music(music) => {
+                        encoder.music(&source, &output, music, keep_audio, &metadata)?
+                    }
+                }
+                source = output;
+            }
+        }
+        // 其他分辨率由合并后的视频缩放得到，不再重新渲染
+        for &screen in &self.config.renditions {
+            let output = ffmpeg::rendition_path(save_path, screen);
+            encoder.rendition(save_path, &output, screen, metadata)?;
+        }
+        Ok(())
+    }
 
+    /// 合并后依次进行的处理：叠加进度指示 `progress`，
+    /// 再混入 `boundaries`（帧）处的音效、旁白和背景音乐
+    fn post_steps<'a>(
+        &'a self,
+        boundaries: &[u64],
+        narration: &'a [NarrationClip],
+        progress: Option<&'a ProgressOverlay>,
+    ) -> Vec<PostStep<'a>> {
+        let mut steps = Vec::new();
+        if let Some(progress) = progress {
+            steps.push(PostStep::Progress(progress));
+        }
+        if let Some(effect) = &self.config.boundary_sound
+            && !boundaries.is_empty()
         {
-            let cover_imgs = (0..overlap as usize)
-                .map(|i| {
-                    let img = self.chunks[0][i].render(
-                        (width_slides, screen.1),
-                        &font,
-                        split_line_color,
-                    )?;
-                    let cover_pic_name = format!("cover_{i}.png");
-                    img.save(work_dir.join(&cover_pic_name))?;
-                    results.push(PathBuf::from(&cover_pic_name));
-                    Ok(cover_pic_name)
-                })
-                .collect::<Result<Vec<_>>>()?;
-
-            let cover_video_name = PathBuf::from("cover.mp4");
-
-            generate_cover_video(
-                &encoder,
-                cover_imgs,
-                cover_sec,
-                back_color,
-                screen,
-                width_slides,
-                fps,
-                motion_type,
-                work_dir,
-                &cover_video_name,
+            let fps = f64::from(self.config.fps.max(1));
+            let times = boundaries.iter().map(|&frame| frame as f64 / fps).collect();
+            steps.push(PostStep::Effects(effect, times));
+        }
+        if !narration.is_empty() {
+            steps.push(PostStep::Narration(narration));
+        }
+        if let Some(music) = &self.config.beat_sync {
+            steps.push(PostStep::Music(music));
+        }
+        steps
+    }
+
+    /// 只在滚动模式下可用的功能，幻灯片模式时返回 `Err`
+    fn require_scroll(&self, feature: &str) -> Result<()> {
+        match self.config.presentation {
+            Presentation::Scroll => Ok(()),
+            Presentation::Slideshow { .. } => {
+                Err(format!("{feature} is not supported in slideshow presentation").into())
+            }
+        }
+    }
+
+    /// 只生成 `range` 范围内的 chunk 视频，不生成封面，也不合并。
+    ///
+    /// 每段视频保存在 `save_path` 所在目录，命名为 `<save_path 文件名>_<chunk 序号>.mp4`，
+    /// 时长与完整运行时对应的片段完全相同，可以直接替换。
+    ///
+    /// # Parameters
+    /// - `range`: chunk 序号的范围，超出 chunk 数量时返回 `Err`。
+    /// - `handle_progress`: 处理进度的回调函数，参数为处理文件名、已处理数量和总数量。
+    ///
+    /// # Results
+    /// 生成的视频文件路径。
+    pub fn run_range<F>(self, range: Range<usize>, handle_progress: F) -> Result<Vec<PathBuf>>
+    where
+        F: Fn(&Path, usize, usize) -> std::result::Result<(), String>,
+    {
+        let mut encoder = FfmpegCliEncoder::new(&self.config);
+        self.run_range_with(&mut encoder, range, handle_progress)
+    }
+
+    /// 与 [`run_range`](Self::run_range) 相同，但由 `encoder` 编码各段视频，见 [`VideoEncoder`]。
+    pub fn run_range_with<F>(
+        self,
+        encoder: &mut dyn VideoEncoder,
+        range: Range<usize>,
+        handle_progress: F,
+    ) -> Result<Vec<PathBuf>>
+    where
+        F: Fn(&Path, usize, usize) -> std::result::Result<(), String>,
+    {
+        self.require_scroll("run_range")?;
+        let chunks_len = self.chunks.len();
+        if range.is_empty() || range.end > chunks_len {
+            return Err(format!("chunk range {range:?} out of 0..{chunks_len}").into());
+        }
+        let renderer = Renderer::new(&self.config, &self.rows)?;
+        renderer.preflight(&self.chunks[range.clone()])?;
+        encoder.begin(&renderer.work_dir)?;
+        let save_path = std::path::absolute(&self.config.save_path)?;
+        let stem = save_path
+            .file_stem()
+            .map_or_else(|| "output".into(), |s| s.to_string_lossy());
+        let mut results = Vec::new();
+        let mut segments = Vec::with_capacity(range.len());
+        let mut outputs = Vec::with_capacity(range.len());
+
+        // 跳过的片段只推进时钟，保证每段的帧数与完整运行时一致
+        let mut clock = FrameClock::new(self.config.fps);
+        clock.advance(f64::from(self.config.cover_sec));
+        for (index, chunk) in self.chunks.iter().enumerate().take(range.start) {
+            let (scroll, static_sec) = renderer.scroll(index, chunk, chunks_len);
+            clock.advance(scroll.duration() + f64::from(static_sec));
+        }
+
+        for (done, index) in range.clone().enumerate() {
+            let segment = renderer.chunk(
+                index,
+                &self.chunks[index],
+                chunks_len,
+                &mut clock,
+                &mut results,
             )?;
+            encoder.push_segment(&segment, &renderer.work_dir)?;
+            let output = save_path.with_file_name(format!("{stem}_{index:0>2}.mp4"));
+            handle_progress(&output, done + 1, range.len())?;
+            segments.push(segment.name);
+            outputs.push(output);
+        }
+        // 编码器写完之后再复制到 save_path 旁边
+        encoder.flush()?;
+        if !renderer.ffmpeg.dry_run {
+            for (name, output) in segments.iter().zip(&outputs) {
+                fs::copy(renderer.work_dir.join(name), output)?;
+            }
+        }
 
-            handle_progress(&cover_video_name, 1, chunks_len + 1)?;
-            results.push(cover_video_name);
+        if self.config.clean_temp && !renderer.ffmpeg.dry_run {
+            fs::remove_dir_all(&renderer.work_dir)?;
         }
+        Ok(outputs)
+    }
+}
 
-        for (index, slides) in self.chunks.into_iter().enumerate() {
-            let slides_len = slides.len();
+/// [`Video::run_async`] 的进度：已渲染 `done` 段视频并交给编码器，共 `total` 段
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub file: PathBuf,
+    pub done: usize,
+    pub total: usize,
+}
 
-            let target = combain_slides(&slides, &font, width_slides, screen, split_line_color)?;
+#[cfg(feature = "async")]
+impl Video {
+    /// 与 [`run`](Self::run) 相同，但在 tokio 的阻塞线程中运行，不占用异步任务，
+    /// 各段视频的 ffmpeg 进程并发运行，同时运行的进程数不超过 CPU 核数，进度通过 `progress` 发送。
+    ///
+    /// 必须在 tokio 运行时中调用。
+    pub async fn run_async(
+        self,
+        progress: tokio::sync::mpsc::UnboundedSender<Progress>,
+    ) -> Result<RenderReport> {
+        let mut encoder = FfmpegCliEncoder::new(&self.config);
+        encoder.jobs = std::thread::available_parallelism().map_or(2, |n| n.get());
+        self.run_async_with(Box::new(encoder), progress).await
+    }
 
-            // 保存组合后的图像
-            let mid_pic_name = format!("{index:0>2}.png");
-            let mid_pic_name = Path::new(&mid_pic_name);
-            target.save(work_dir.join(mid_pic_name))?;
+    /// 与 [`run_async`](Self::run_async) 相同，但由 `encoder` 编码和合并各段视频，见 [`VideoEncoder`]。
+    pub async fn run_async_with(
+        self,
+        mut encoder: Box<dyn VideoEncoder + Send>,
+        progress: tokio::sync::mpsc::UnboundedSender<Progress>,
+    ) -> Result<RenderReport> {
+        tokio::task::spawn_blocking(move || {
+            self.run_with(encoder.as_mut(), |file, done, total| {
+                // 接收端已经关闭时不再报告进度，但继续生成视频
+                let _ = progress.send(Progress {
+                    file: file.to_path_buf(),
+                    done,
+                    total,
+                });
+                Ok(())
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
 
-            let mid_video_name = mid_pic_name.with_extension("mp4");
-            let image_width = slides_len as u32 * width_slides;
-            let move_sec = (image_width - screen.0) / swip_pixels_per_sec;
-            let static_sec = if index == chunks_len - 1 {
-                ending_sec
+/// 一次运行中生成封面和各个 chunk 视频所需的状态
+struct Renderer<'a> {
+    config: &'a VideoConfig,
+    rows: &'a [Row],
+    /// 各行的配置和内容的哈希，只在增量生成时计算
+    rows_key: u64,
+    ffmpeg: Ffmpeg,
+    ctx: RenderContext,
+    /// 本次运行的临时目录，也是 ffmpeg 的工作目录
+    work_dir: PathBuf,
+    /// 预览模式的缩放比例
+    scale: f32,
+    /// 输出视频的分辨率
+    out_screen: (u32, u32),
+    /// 画面方向，见 [`Orientation`]
+    orientation: Orientation,
+    /// 长条图坐标系中的屏幕尺寸（原始分辨率），横屏时即为 `screen`
+    strip_screen: (u32, u32),
+    /// 长条图分段保存的宽度，见 [`VideoConfigBuilder::tile_width`]
+    tile_width: Option<u32>,
+    /// 保存为 RGB8 图片时填充透明部分的背景色，见 [`VideoConfigBuilder::rgb8`]
+    background: Option<Color>,
+    /// chunk 长条图的保存格式，见 [`VideoConfigBuilder::intermediate_format`]
+    intermediate: IntermediateFormat,
+    /// 封面中渲染好的 slide，组合第一个 chunk 时取出复用，每个 slide 只渲染一次
+    cover_slides: Mutex<Vec<RgbaImage>>,
+    /// [`Compositor::Gpu`](slide::Compositor::Gpu) 时使用的 GPU，没有可用的 GPU 时为 `None`
+    #[cfg(feature = "gpu")]
+    gpu: Option<gpu::GpuCompositor>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(config: &'a VideoConfig, rows: &'a [Row]) -> Result<Self> {
+        // 临时文件都写入本次运行独立的子目录
+        let work_dir = create_run_dir(&config.work_dir).map_err(|e| {
+            format!(
+                "cannot create temporary files in work_dir {}: {e}",
+                config.work_dir.display()
+            )
+        })?;
+        Self::in_dir(config, rows, work_dir)
+    }
+
+    /// 把图片写入 `work_dir`，不检查 ffmpeg 是否可用
+    fn in_dir(config: &'a VideoConfig, rows: &'a [Row], work_dir: PathBuf) -> Result<Self> {
+        let font = load_font_chain(config.font.as_deref(), &config.fallback_fonts)?;
+        let ffmpeg = Ffmpeg::from_config(config);
+        let ctx = render_context(config, font);
+        // 预览模式下 slide 仍按原尺寸渲染，只在交给 ffmpeg 之前缩小图片
+        let scale = config.preview_scale.unwrap_or(1.0);
+        let screen = config.screen;
+        let rows_key = if config.incremental && !rows.is_empty() {
+            let params: Vec<_> = rows.iter().map(|row| (&row.config, row.speed)).collect();
+            let slides: Vec<Slide> = rows.iter().flat_map(|row| row.slides.clone()).collect();
+            inputs_hash(&params, &slides)
+        } else {
+            0
+        };
+        Ok(Self {
+            config,
+            rows,
+            rows_key,
+            ffmpeg,
+            ctx,
+            work_dir,
+            scale,
+            out_screen: (scale_even(screen.0, scale), scale_even(screen.1, scale)),
+            orientation: config.orientation,
+            strip_screen: config.orientation.strip_screen(screen),
+            tile_width: config.tile_width,
+            background: config
+                .rgb8
+                .then(|| parse_back_color(&config.back_color))
+                .flatten(),
+            intermediate: config.intermediate_format,
+            cover_slides: Mutex::new(Vec::new()),
+            #[cfg(feature = "gpu")]
+            // GPU 的插值和舍入因驱动而异，可重现输出时不使用
+            gpu: (config.compositor == slide::Compositor::Gpu && !config.deterministic)
+                .then(|| {
+                    gpu::GpuCompositor::new()
+                        .inspect_err(|e| eprintln!("{e}, slides will be rendered on the CPU"))
+                        .ok()
+                })
+                .flatten(),
+        })
+    }
+
+    /// 渲染一个 slide，选择了 GPU 合成且 GPU 可用时在 GPU 上渲染
+    fn render_slide(&self, slide: &Slide, size: (u32, u32)) -> Result<DynamicImage> {
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = &self.gpu {
+            return gpu.render_slide(slide, size, &self.ctx);
+        }
+        slide.render(size, &self.ctx)
+    }
+
+    /// 渲染一个 slide 并转到长条图坐标系，竖屏时为逆时针旋转 90° 后的一列
+    fn render_strip_slide(&self, slide: &Slide) -> Result<DynamicImage> {
+        let along = slide.width().unwrap_or(self.config.width_slides);
+        let size = self.orientation.slide_size(along, self.config.screen);
+        Ok(self.orientation.to_strip(self.render_slide(slide, size)?))
+    }
+
+    /// 长条图坐标系中输出视频的高度
+    fn strip_height(&self) -> u32 {
+        self.orientation.strip_screen(self.out_screen).1
+    }
+
+    /// GPU 可用或竖屏时单独渲染 `slides` 中尚未渲染的 slide，接在 `rendered` 之后。
+    ///
+    /// 否则原样返回 `rendered`，剩余的 slide 在组合长条图时直接绘制到长条图上。
+    fn prerender<'r>(
+        &self,
+        slides: &[Slide],
+        rendered: &'r [RgbaImage],
+    ) -> Result<Cow<'r, [RgbaImage]>> {
+        // 竖屏时 slide 需要旋转，不能直接绘制到长条图上
+        #[cfg(feature = "gpu")]
+        let separate = self.gpu.is_some() || self.orientation == Orientation::Vertical;
+        #[cfg(not(feature = "gpu"))]
+        let separate = self.orientation == Orientation::Vertical;
+        if !separate {
+            return Ok(Cow::Borrowed(rendered));
+        }
+        let mut rendered = rendered.to_vec();
+        for slide in slides.iter().skip(rendered.len()) {
+            rendered.push(self.render_strip_slide(slide)?.into_rgba8());
+        }
+        Ok(Cow::Owned(rendered))
+    }
+
+    /// 渲染封面图片，返回需要编码的封面视频
+    fn cover(
+        &self,
+        first: &Chunk,
+        clock: &mut FrameClock,
+        results: &mut Vec<PathBuf>,
+    ) -> Result<EncodeSegment> {
+        let config = self.config;
+        let screen = self.strip_screen;
+        // 封面由第一个 chunk 开头铺满屏幕的若干 slide 组成
+        let mut cover_imgs = Vec::new();
+        let mut cover_slides = Vec::new();
+        let mut x = 0;
+        for (i, slide) in first.slides.iter().enumerate() {
+            if x >= screen.0 {
+                break;
+            }
+            let width = slide.width().unwrap_or(config.width_slides);
+            let mut img = self.render_strip_slide(slide)?;
+            cover_slides.push(img.to_rgba8());
+            if let Some(background) = self.background {
+                img = DynamicImage::ImageRgb8(flatten(&img.into_rgba8(), background));
+            }
+            let img = scale_image(img, self.scale, self.strip_height());
+            let cover_pic_name = format!("cover_{i}.png");
+            img.save(self.work_dir.join(&cover_pic_name))?;
+            results.push(PathBuf::from(&cover_pic_name));
+            cover_imgs.push((cover_pic_name, (x as f32 * self.scale).round() as u32));
+            x += width;
+        }
+        // 这些 slide 也位于第一个 chunk 的开头，组合长条图时直接复用
+        *self.cover_slides.lock().map_err(|e| e.to_string())? = cover_slides;
+
+        let rows = self.row_overlays("cover", 0.0, 0.0)?;
+        results.extend(rows.iter().map(|row| row.pic_name.clone()));
+
+        let cover_video_name = PathBuf::from("cover.mp4");
+        results.push(cover_video_name.clone());
+        Ok(EncodeSegment {
+            name: cover_video_name,
+            screen: self.out_screen,
+            orientation: self.orientation,
+            fps: config.fps,
+            frames: clock.advance(f64::from(config.cover_sec)),
+            back_color: config.back_color.clone(),
+            plan: SegmentPlan::Cover {
+                images: cover_imgs,
+                cover_sec: config.cover_sec,
+                motion_type: config.motion_type,
+                entry: config.cover_entry.clone(),
+                rows,
+            },
+        })
+    }
+
+    /// 渲染各行在主长条图从 `offset` 滚动 `distance` 像素期间经过的部分
+    fn row_overlays(&self, name: &str, offset: f64, distance: f64) -> Result<Vec<RowOverlay>> {
+        let screen_width = self.strip_screen.0;
+        let mut overlays = Vec::with_capacity(self.rows.len());
+        for (i, row) in self.rows.iter().enumerate() {
+            let start = row.speed * offset;
+            let x = start.floor();
+            let width = (row.speed * distance + start - x).ceil() as u32 + screen_width;
+            let img = row.window(x as u32, width, &self.ctx)?;
+            let height = ((row.config.height as f32 * self.scale).round() as u32).max(1);
+            let img = scale_image(img, self.scale, height);
+            let pic_name = PathBuf::from(format!("{name}_row{i}.png"));
+            img.save(self.work_dir.join(&pic_name))?;
+            overlays.push(RowOverlay {
+                pic_name,
+                y: (row.config.y as f32 * self.scale).round() as u32,
+                speed: row.speed,
+                shift: (start - x) * f64::from(self.scale),
+            });
+        }
+        Ok(overlays)
+    }
+
+    /// chunk 滚动期间出现在屏幕上的动画图片
+    fn ken_burns_overlays(&self, chunk: &Chunk) -> Result<Vec<KenBurnsOverlay>> {
+        let config = self.config;
+        let screen_width = self.strip_screen.0;
+        let scale = f64::from(self.scale);
+        let mut overlays = Vec::new();
+        let mut x = 0;
+        for slide in &chunk.slides {
+            let width = slide.width().unwrap_or(config.width_slides);
+            let size = self.orientation.slide_size(width, config.screen);
+            for image in slide.ken_burns_images(size, &self.ctx)? {
+                // zoompan 按图片原本的方向生成，叠加前再转到长条图坐标系
+                let (w, h) = image.size;
+                let zoompan_size = (scale_even(w, self.scale), scale_even(h, self.scale));
+                let ((left, top), (w, _)) = self
+                    .orientation
+                    .rect_to_strip(image.pos, image.size, size.0);
+                let left = x + left;
+                let global = chunk.offset + left;
+                // 图片从进入屏幕（或视频开始）到完全离开屏幕的滚动位置
+                let start = global.saturating_sub(screen_width);
+                let end = global + w;
+                if global >= chunk.offset + chunk.distance + screen_width || end <= chunk.offset {
+                    continue;
+                }
+                overlays.push(KenBurnsOverlay {
+                    // ffmpeg 的工作目录是本次运行的临时目录
+                    path: std::path::absolute(&image.path)?,
+                    ken_burns: image.ken_burns,
+                    x: f64::from(left) * scale,
+                    y: (f64::from(top) * scale).round() as u32,
+                    size: zoompan_size,
+                    start: (f64::from(start) - f64::from(chunk.offset)) * scale,
+                    len: f64::from(end - start) * scale,
+                });
+            }
+            x += width;
+        }
+        Ok(overlays)
+    }
+
+    /// 影响所有片段的参数，作为增量生成时输入哈希的一部分：
+    /// 序列化的配置（见 [`VideoConfig`]）、字体的修改时间和各行的输入哈希
+    fn render_params(&self) -> impl std::fmt::Debug {
+        let config = self.config;
+        let fonts: Vec<_> = config
+            .font
+            .iter()
+            .chain(&config.fallback_fonts)
+            .map(|font| modified(font))
+            .collect();
+        let config = serde_json::to_string(config).expect("VideoConfig serializes to JSON");
+        (config, fonts, self.rows_key)
+    }
+
+    /// 封面视频的输入哈希，`clock` 为生成封面之前的时钟
+    fn cover_key(&self, first: &Chunk, clock: &FrameClock) -> u64 {
+        let config = self.config;
+        let frames = clock.clone().advance(f64::from(config.cover_sec));
+        let params = (
+            self.render_params(),
+            config.cover_sec,
+            config.motion_type,
+            &config.cover_entry,
+            frames,
+        );
+        inputs_hash(&params, &first.slides)
+    }
+
+    /// 第 `index` 个 chunk 视频的输入哈希，`clock` 为生成该片段之前的时钟
+    fn chunk_key(&self, index: usize, chunk: &Chunk, chunks_len: usize, clock: &FrameClock) -> u64 {
+        let config = self.config;
+        let (scroll, static_sec) = self.scroll(index, chunk, chunks_len);
+        let frames = clock
+            .clone()
+            .advance(scroll.duration() + f64::from(static_sec));
+        let ending = (index == chunks_len - 1).then_some(config.ending);
+        let params = (self.render_params(), chunk.distance, scroll, frames, ending);
+        inputs_hash(&params, &chunk.slides)
+    }
+
+    /// 第 `index` 个 chunk 的滚动方式（原始分辨率）和结尾停留的秒数
+    fn scroll(&self, index: usize, chunk: &Chunk, chunks_len: usize) -> (Scroll, u32) {
+        let config = self.config;
+        let chunk_override = config.chunk_overrides.iter().find(|o| o.chunk == index);
+        let speed = chunk_override
+            .and_then(|o| o.swip_pixels_per_sec)
+            .unwrap_or(config.swip_pixels_per_sec);
+        let slide_widths: Vec<u32> = chunk
+            .slides
+            .iter()
+            .map(|slide| slide.width().unwrap_or(config.width_slides))
+            .collect();
+        let keyframes = chunk.keyframes.clone().unwrap_or_else(|| {
+            scroll_keyframes(
+                chunk.distance,
+                speed,
+                chunk_override.map_or(&[], |o| &o.pauses),
+                &slide_widths,
+                self.strip_screen.0,
+            )
+        });
+        let scroll = Scroll {
+            keyframes,
+            motion_type: config.motion_type,
+            // 只在整段滚动的开头缓入、进入结尾停留时缓出
+            ease_in: index == 0,
+            ease_out: index == chunks_len - 1,
+            phase: 0.0,
+        };
+        let static_sec = if index == chunks_len - 1 {
+            config.ending_sec
+        } else {
+            0
+        };
+        (scroll, static_sec)
+    }
+
+    /// 组合第 `index` 个 chunk 的长条图，返回需要编码的视频
+    fn chunk(
+        &self,
+        index: usize,
+        chunk: &Chunk,
+        chunks_len: usize,
+        clock: &mut FrameClock,
+        results: &mut Vec<PathBuf>,
+    ) -> Result<EncodeSegment> {
+        let config = self.config;
+        // 第一个 chunk 开头的 slide 已经在渲染封面时渲染过
+        let rendered = if index == 0 {
+            std::mem::take(&mut *self.cover_slides.lock().map_err(|e| e.to_string())?)
+        } else {
+            Vec::new()
+        };
+        // 与前后 chunk 显示相同内容的范围，只在调试叠加层中使用
+        let mut overlaps = Vec::new();
+        let screen_width = self.strip_screen.0;
+        if index > 0 {
+            overlaps.push(0..screen_width);
+        }
+        if index + 1 < chunks_len {
+            overlaps.push(chunk.distance..chunk.distance + screen_width);
+        }
+        // 保存组合后的图像，分段时每段单独渲染和保存，同一时间只有一段在内存中
+        let mut tiles = Vec::new();
+        for (i, (range, x)) in tile_ranges(&chunk.slides, config.width_slides, self.tile_width)
+            .into_iter()
+            .enumerate()
+        {
+            let reused = &rendered[range.start.min(rendered.len())..range.end.min(rendered.len())];
+            let reused = self.prerender(&chunk.slides[range.clone()], reused)?;
+            let first = range.start;
+            let mut target = combain_rendered_slides(
+                &chunk.slides[range.clone()],
+                &reused,
+                &self.ctx,
+                config.width_slides,
+                self.strip_screen,
+                self.background,
+            )?;
+            if config.debug_overlay {
+                draw_debug_overlay(
+                    &mut target,
+                    &chunk.slides[range],
+                    chunk.first_slide + first,
+                    config.width_slides,
+                    x,
+                    &overlaps,
+                    config.safe_area.as_ref(),
+                    &self.ctx,
+                );
+            }
+            let target = scale_image(target, self.scale, self.strip_height());
+            let extension = self.intermediate.extension();
+            let pic_name = if self.tile_width.is_some() {
+                PathBuf::from(format!("{index:0>2}_tile{i}.{extension}"))
             } else {
-                0
+                PathBuf::from(format!("{index:0>2}.{extension}"))
             };
+            let raw = self
+                .intermediate
+                .save(&target, &self.work_dir.join(&pic_name))?;
+            tiles.push(StripTile {
+                pic_name,
+                x: (x as f32 * self.scale).round() as u32,
+                raw,
+            });
+        }
 
-            generate_mid_video(
-                &encoder,
-                mid_pic_name,
-                &mid_video_name,
-                screen,
-                swip_pixels_per_sec,
-                back_color,
-                fps,
-                move_sec,
-                static_sec,
-                work_dir,
-            )?;
-            handle_progress(&mid_video_name, index + 2, chunks_len + 1)?;
-            results.push(mid_pic_name.to_path_buf());
-            results.push(mid_video_name);
+        let mid_video_name = PathBuf::from(format!("{index:0>2}.mp4"));
+        let rows = self.row_overlays(
+            &format!("{index:0>2}"),
+            f64::from(chunk.offset),
+            f64::from(chunk.distance),
+        )?;
+        results.extend(rows.iter().map(|row| row.pic_name.clone()));
+        let (mut scroll, static_sec) = self.scroll(index, chunk, chunks_len);
+        // 从上一段结束的位置继续滚动，衔接处不会出现不足一帧的跳动
+        scroll.phase = clock.phase();
+        let frames = clock.advance(scroll.duration() + f64::from(static_sec));
+        let fade = (index == chunks_len - 1)
+            .then(|| {
+                let duration = frames as f64 / f64::from(config.fps);
+                config
+                    .ending
+                    .fade(duration, config.ending_sec, &config.back_color)
+            })
+            .flatten();
+        scroll.keyframes = scroll
+            .keyframes
+            .into_iter()
+            .map(|k| Keyframe {
+                x: k.x * f64::from(self.scale),
+                ..k
+            })
+            .collect();
+
+        let ken_burns = self.ken_burns_overlays(chunk)?;
+        let count_ups = self.count_up_overlays(chunk)?;
+        results.extend(tiles.iter().map(|tile| tile.pic_name.clone()));
+        results.push(mid_video_name.clone());
+        Ok(EncodeSegment {
+            name: mid_video_name,
+            screen: self.out_screen,
+            orientation: self.orientation,
+            fps: config.fps,
+            frames,
+            back_color: config.back_color.clone(),
+            plan: SegmentPlan::Scroll {
+                tiles,
+                scroll,
+                fade,
+                rows,
+                ken_burns,
+                count_ups,
+            },
+        })
+    }
+}
+
+/// 按 `tile_width` 把 chunk 的 slide 分成若干段，返回每段的 slide 范围和 x 坐标。
+///
+/// 每段至少包含一个 slide，`tile_width` 为 `None` 时只有一段。
+fn tile_ranges(
+    slides: &[Slide],
+    width_slides: u32,
+    tile_width: Option<u32>,
+) -> Vec<(Range<usize>, u32)> {
+    let Some(tile_width) = tile_width else {
+        return vec![(0..slides.len(), 0)];
+    };
+    let mut tiles = Vec::new();
+    let (mut start, mut start_x, mut x) = (0, 0, 0);
+    for (i, slide) in slides.iter().enumerate() {
+        let width = slide.width().unwrap_or(width_slides);
+        if i > start && x + width - start_x > tile_width {
+            tiles.push((start..i, start_x));
+            (start, start_x) = (i, x);
         }
+        x += width;
+    }
+    tiles.push((start..slides.len(), start_x));
+    tiles
+}
 
-        combain(&mut results, work_dir, save_path)?;
+/// 按比例缩放尺寸并取偶数，yuv420p 要求视频的宽高都是偶数
+fn scale_even(value: u32, scale: f32) -> u32 {
+    ((value as f32 * scale / 2.0).round() as u32 * 2).max(2)
+}
 
-        if clean_temp {
-            // 清理临时文件：
-            for result in results.iter() {
-                let _ = std::fs::remove_file(work_dir.join(result));
-            }
-            println!("cleanup successed");
+/// 预览模式下把图片缩小到 `height` 高，宽度按比例缩放
+fn scale_image(img: DynamicImage, scale: f32, height: u32) -> DynamicImage {
+    if scale == 1.0 {
+        return img;
+    }
+    let width = ((img.width() as f32 * scale).round() as u32).max(1);
+    img.resize_exact(width, height, FilterType::Triangle)
+}
+
+/// 按配置创建渲染 slide 所需的上下文
+fn render_context(config: &VideoConfig, font: FontChain) -> RenderContext {
+    RenderContext {
+        split_line_color: config.split_line_color,
+        split_line_width: config.split_line_width,
+        text_quality: config.text_quality,
+        min_text_scale: config.min_text_scale,
+        text_overflow: config.text_overflow,
+        auto_palette: config.auto_palette,
+        text_contrast: config.text_contrast,
+        ..RenderContext::new(font)
+    }
+}
+
+/// 在 `work_dir` 下为本次运行创建独立的子目录 `run_<时间戳>_<进程号>`，
+/// 多个任务共用同一个 work_dir 时不会互相覆盖临时文件。
+fn create_run_dir(work_dir: &Path) -> Result<PathBuf> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis();
+    let pid = std::process::id();
+    for attempt in 0u32.. {
+        let name = match attempt {
+            0 => format!("run_{timestamp}_{pid}"),
+            n => format!("run_{timestamp}_{pid}_{n}"),
+        };
+        let dir = work_dir.join(name);
+        // create_dir 在目录已存在时失败，保证目录只属于本次运行
+        match fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
         }
-        Ok(())
+    }
+    unreachable!()
+}
+
+/// 预览视频的帧率
+const PREVIEW_FPS: u32 = 15;
+
+/// 后续处理之前合并得到的视频，保存在本次运行的临时目录中
+const SILENT_VIDEO: &str = "silent.mp4";
+
+/// 合并后对整个视频的一步处理，见 [`Video::post_steps`]
+enum PostStep<'a> {
+    /// 叠加进度指示，见 [`progress`]
+    Progress(&'a ProgressOverlay),
+    /// 音效及其播放时间（秒）
+    Effects(&'a SoundEffect, Vec<f64>),
+    Narration(&'a [NarrationClip]),
+    Music(&'a BeatSync),
+}
+
+impl PostStep<'_> {
+    /// 这一步之后视频是否带有音频
+    fn adds_audio(&self) -> bool {
+        !matches!(self, PostStep::Progress(_))
     }
 }
 
 pub struct VideoBuilder {
     slides: Vec<Slide>,
+    rows: Vec<Row>,
     config: VideoConfig,
 }
 
@@ -170,36 +1141,181 @@ impl VideoBuilder {
         self
     }
 
+    /// 按 slide 的顺序（包括分组标题）设置每个 slide 的宽度，
+    /// 多余的宽度被忽略，缺少宽度的 slide 使用配置中的 `width_slides`。
+    pub fn slide_widths(mut self, widths: &[u32]) -> Result<Self> {
+        if widths.contains(&0) {
+            return Err("slide width must be greater than 0".into());
+        }
+        for (slide, &width) in self.slides.iter_mut().zip(widths) {
+            *slide = std::mem::take(slide).with_width(width);
+        }
+        Ok(self)
+    }
+
     pub fn len(&self) -> usize {
         self.slides.len()
     }
 
+    /// 绑定数据后的所有 slide（包括分组标题），可以序列化保存，
+    /// 之后通过 [`add_slides`](Self::add_slides) 重新读取
+    pub fn slides(&self) -> &[Slide] {
+        &self.slides
+    }
+
     pub fn is_empty(&self) -> bool {
         self.slides.is_empty()
     }
 
+    /// 生成预览视频：分辨率按 `scale` 缩小，帧率降为 15，
+    /// 并使用 `ultrafast` 编码预设，用于快速检查完整视频的效果。
+    ///
+    /// # Errors
+    /// - `scale` 不在 (0, 1] 范围内时返回 `Err`。
+    ///
+    pub fn build_preview(mut self, scale: f32) -> Result<Video> {
+        if !(scale > 0.0 && scale <= 1.0) {
+            return Err(format!("preview scale must be in (0, 1], got {scale}").into());
+        }
+        self.config.preview_scale = Some(scale);
+        self.config.fps = PREVIEW_FPS;
+        self.config.preset = "ultrafast".to_string();
+        self.build()
+    }
+
     pub fn build(self) -> Result<Video> {
         if self.slides.is_empty() {
             return Err("slides data is empty".into());
         }
-
-        let (step, overlap, len) = (
-            self.config.step as usize,
-            self.config.overlap as usize,
-            self.len(),
-        );
-
-        if len < overlap {
-            return Err("slides data is shorter than overlap".into());
+        if let Presentation::Slideshow { per_screen, .. } = self.config.presentation {
+            let page_width = self.config.screen.0 / per_screen.max(1);
+            return Ok(Video {
+                chunks: slideshow::pages(self.slides, per_screen, page_width),
+                rows: self.rows,
+                config: self.config,
+                narrator: None,
+            });
         }
 
-        let chunks = (0..len - overlap)
-            .step_by(step - overlap)
-            .map(|i| self.slides[i..(i + step).min(len)].to_vec())
+        let widths: Vec<u32> = self
+            .slides
+            .iter()
+            .map(|slide| slide.width().unwrap_or(self.config.width_slides))
             .collect();
+        // 长条图最宽为 step 个默认宽度的 slide
+        let max_width = self.config.step * self.config.width_slides;
+        // 竖屏时沿滚动方向的屏幕长度是屏幕的高度
+        let (screen_width, _) = self.config.orientation.strip_screen(self.config.screen);
+        let mut chunks: Vec<Chunk> = chunk_ranges(&widths, max_width, screen_width)?
+            .into_iter()
+            .map(|(range, distance)| Chunk {
+                offset: widths[..range.start].iter().sum(),
+                first_slide: range.start,
+                slides: self.slides[range].to_vec(),
+                distance,
+                keyframes: None,
+            })
+            .collect();
+
+        for chunk_override in &self.config.chunk_overrides {
+            let Some(Chunk { slides, .. }) = chunks.get(chunk_override.chunk) else {
+                return Err(format!(
+                    "chunk_overrides: chunk {} out of range, only {} chunks",
+                    chunk_override.chunk,
+                    chunks.len()
+                )
+                .into());
+            };
+            if let Some(pause) = chunk_override
+                .pauses
+                .iter()
+                .find(|p| p.slide >= slides.len())
+            {
+                return Err(format!(
+                    "chunk_overrides: slide {} out of range in chunk {}",
+                    pause.slide, chunk_override.chunk
+                )
+                .into());
+            }
+        }
+        let main_distance = widths.iter().sum::<u32>() - screen_width;
+        if let Some(beat_sync) = &self.config.beat_sync {
+            // 各 slide 的左边缘依次经过屏幕左边缘，最后滚动到长条图的结尾
+            let boundaries: Vec<u32> = widths
+                .iter()
+                .scan(0, |x, w| {
+                    *x += w;
+                    Some(*x)
+                })
+                .take_while(|&x| x < main_distance)
+                .chain([main_distance])
+                .collect();
+            let start = f64::from(self.config.cover_sec);
+            let speed = self.config.swip_pixels_per_sec;
+            // 滚动最慢为名义速度的一半，只需要这段时间内的节拍
+            let until = start + 2.0 * f64::from(main_distance) / f64::from(speed.max(1));
+            let beats = beat_sync.beat_times(&self.config.ffmpeg_path, until)?;
+            let global = beat::beat_keyframes(&boundaries, start, speed, &beats);
+            for chunk in &mut chunks {
+                chunk.keyframes = Some(beat::chunk_keyframes(&global, chunk));
+            }
+        }
+        let mut rows = self.rows;
+        for row in &mut rows {
+            row.set_speed(main_distance, screen_width);
+        }
         Ok(Video {
             chunks,
+            rows,
             config: self.config,
+            narrator: None,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Slide, create_run_dir, scale_even, tile_ranges};
+
+    #[test]
+    fn test_scale_even() {
+        assert_eq!(scale_even(1920, 1.0), 1920);
+        assert_eq!(scale_even(1920, 0.25), 480);
+        assert_eq!(scale_even(1080, 0.25), 270);
+        assert_eq!(scale_even(1080, 0.3), 324);
+        assert_eq!(scale_even(1, 0.1), 2);
+    }
+
+    #[test]
+    fn test_tile_ranges() {
+        let slides = [
+            Slide::new(),
+            Slide::new().with_width(300),
+            Slide::new(),
+            Slide::new().with_width(1200),
+            Slide::new(),
+        ];
+        assert_eq!(tile_ranges(&slides, 480, None), [(0..5, 0)]);
+        assert_eq!(
+            tile_ranges(&slides, 480, Some(1000)),
+            [(0..2, 0), (2..3, 780), (3..4, 1260), (4..5, 2460)]
+        );
+        assert_eq!(
+            tile_ranges(&slides, 480, Some(1300)),
+            [(0..3, 0), (3..4, 1260), (4..5, 2460)]
+        );
+    }
+
+    #[test]
+    fn test_create_run_dir_is_unique() {
+        let work_dir =
+            std::env::temp_dir().join(format!("to_video_test_run_dir_{}", std::process::id()));
+        std::fs::create_dir_all(&work_dir).unwrap();
+        let first = create_run_dir(&work_dir).unwrap();
+        let second = create_run_dir(&work_dir).unwrap();
+        assert_ne!(first, second);
+        assert!(first.is_dir() && second.is_dir());
+        assert!(first.starts_with(&work_dir));
+        std::fs::remove_dir_all(work_dir).unwrap();
+    }
+}
@@ -0,0 +1,375 @@
+//! 旁白：把每个 slide 的文字转换为语音，在 slide 显示在屏幕上的时间段内播放。
+//!
+//! 语音由 [`Narrator`] 生成，默认的 [`CommandNarrator`] 对每段文字执行一次用户配置的命令
+//! （例如 `espeak-ng`、`piper` 或自己的脚本）。所有语音片段在视频合并后由 ffmpeg 延迟到
+//! 各自的开始时间、截断到 slide 的显示时长，再混合为视频的音轨。
+
+use super::{
+    Renderer, Video,
+    chunk::Chunk,
+    ffmpeg::{Ffmpeg, FfmpegCommand},
+    slideshow::Presentation,
+};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// 文字转语音的后端
+pub trait Narrator {
+    /// 把 `text` 转换为语音，保存到 `output`
+    ///
+    /// # Errors
+    /// - 转换失败时返回 `Err`。
+    ///
+    fn narrate(&mut self, text: &str, output: &Path) -> Result<()>;
+
+    /// 生成的音频文件的扩展名，决定 `narrate` 的 `output`
+    fn extension(&self) -> &str {
+        "wav"
+    }
+}
+
+/// 对每段文字执行一次外部命令的 [`Narrator`]。
+///
+/// `args` 中的 `{text}` 替换为文字，`{output}` 替换为音频文件的路径，
+/// 例如 `espeak-ng` 的参数为 `["-v", "zh", "-w", "{output}", "{text}"]`。
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CommandNarrator {
+    pub program: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// 命令生成的音频格式
+    #[serde(default = "default_extension")]
+    pub extension: String,
+}
+
+fn default_extension() -> String {
+    "wav".to_string()
+}
+
+impl CommandNarrator {
+    pub fn new(program: impl Into<PathBuf>, args: &[&str]) -> Self {
+        Self {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            extension: default_extension(),
+        }
+    }
+
+    /// 替换占位符后的命令
+    pub fn command(&self, text: &str, output: &Path) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(self.args.iter().map(|arg| {
+            arg.replace("{text}", text)
+                .replace("{output}", &output.to_string_lossy())
+        }));
+        command
+    }
+}
+
+impl Narrator for CommandNarrator {
+    fn narrate(&mut self, text: &str, output: &Path) -> Result<()> {
+        let result = self.command(text, output).output()?;
+        if !result.status.success() {
+            return Err(format!(
+                "{} failed: {}",
+                self.program.display(),
+                String::from_utf8_lossy(&result.stderr).trim()
+            )
+            .into());
+        }
+        if !output.exists() {
+            return Err(format!(
+                "{} did not write {}",
+                self.program.display(),
+                output.display()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+}
+
+/// 一段旁白：`start` 秒开始播放，超过 `duration` 秒的部分被截掉
+#[derive(Debug, Clone, PartialEq)]
+pub struct NarrationClip {
+    pub path: PathBuf,
+    pub start: f64,
+    pub duration: f64,
+}
+
+/// 把 `clips` 混入 `source` 的命令，视频流直接复制，音频编码为 AAC。
+///
+/// `keep_audio` 为 `true` 时与 `source` 原有的音频（例如 [`sound`](super::sound) 的音效）混合，
+/// 否则替换。`clips` 不能为空。
+pub fn narration_command(
+    ffmpeg: &Ffmpeg,
+    source: &Path,
+    clips: &[NarrationClip],
+    keep_audio: bool,
+    output: &Path,
+    metadata: &HashMap<String, String>,
+) -> FfmpegCommand {
+    let mut command = ffmpeg.command().input(source);
+    let mut filters = String::new();
+    let mut mix = String::new();
+    if keep_audio {
+        mix.push_str("[0:a]");
+    }
+    for (i, clip) in clips.iter().enumerate() {
+        command = command.input(&clip.path);
+        let ms = (clip.start * 1000.0).round() as u64;
+        filters.push_str(&format!(
+            "[{}]atrim=duration={:.3},adelay=delays={ms}:all=1[n{i}];",
+            i + 1,
+            clip.duration
+        ));
+        mix.push_str(&format!("[n{i}]"));
+    }
+    let inputs = clips.len() + usize::from(keep_audio);
+    filters.push_str(&format!(
+        "{mix}amix=inputs={inputs}:duration=longest:normalize=0[narration]"
+    ));
+    command
+        .filter_complex(&filters)
+        .args(["-map", "0:v"])
+        .map("narration")
+        .args(["-c:v", "copy", "-c:a", "aac"])
+        .metadata(metadata)
+        .output(output)
+}
+
+/// `samples` 为 `(时间, 长条图向左移动的像素数)`，按时间排序且移动距离不减少，
+/// 返回移动距离第一次达到 `x` 的时间，始终达不到时返回 `None`
+fn time_at(samples: &[(f64, f64)], x: f64) -> Option<f64> {
+    let i = samples.partition_point(|&(_, offset)| offset < x);
+    samples.get(i).map(|&(t, _)| t)
+}
+
+/// 各 slide 在屏幕上的时间段（秒），`samples` 的含义与 [`time_at`] 相同。
+///
+/// 比屏幕窄的 slide 从完全进入屏幕开始，到开始离开屏幕结束；
+/// 比屏幕宽的 slide 从铺满屏幕开始，到右边缘到达屏幕右侧结束。
+fn slide_windows(
+    widths: &[u32],
+    screen_width: u32,
+    samples: &[(f64, f64)],
+    end: f64,
+) -> Vec<(f64, f64)> {
+    let mut left = 0.0;
+    widths
+        .iter()
+        .map(|&width| {
+            let right_aligned = left + f64::from(width) - f64::from(screen_width);
+            let (from, to) = (left.min(right_aligned), left.max(right_aligned));
+            left += f64::from(width);
+            let start = time_at(samples, from).unwrap_or(end);
+            // 到达 `to` 之后的下一个位置才算离开
+            let stop = time_at(samples, to + 0.5).unwrap_or(end).max(start);
+            (start, stop)
+        })
+        .collect()
+}
+
+/// 让同时在屏幕上的 slide 依次播放：每个时间段都不早于上一个时间段的结束
+fn one_at_a_time(windows: &mut [(f64, f64)]) {
+    let mut previous_stop = 0.0;
+    for (start, stop) in windows {
+        *start = start.max(previous_stop);
+        *stop = stop.max(*start);
+        previous_stop = *stop;
+    }
+}
+
+impl Renderer<'_> {
+    /// 滚动模式中各 slide 在屏幕上的时间段（秒），按整个视频中 slide 的顺序排列
+    pub(super) fn scroll_windows(&self, chunks: &[Chunk]) -> Vec<(f64, f64)> {
+        let config = self.config;
+        let step = 1.0 / f64::from(config.fps.max(1));
+        let chunks_len = chunks.len();
+        // 封面期间长条图不动
+        let mut samples = vec![(0.0, 0.0)];
+        let mut start = f64::from(config.cover_sec);
+        let mut widths = Vec::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let (scroll, static_sec) = self.scroll(index, chunk, chunks_len);
+            let duration = scroll.duration();
+            let mut t = 0.0;
+            while t < duration {
+                samples.push((start + t, f64::from(chunk.offset) + scroll.offset(t)));
+                t += step;
+            }
+            samples.push((
+                start + duration,
+                f64::from(chunk.offset) + scroll.offset(duration),
+            ));
+            start += duration + f64::from(static_sec);
+            // 下一个 chunk 从这个 chunk 中的某个 slide 开始，只记录之前的部分
+            let next_first = chunks.get(index + 1).map_or(usize::MAX, |c| c.first_slide);
+            widths.extend(
+                chunk
+                    .slides
+                    .iter()
+                    .take(next_first.saturating_sub(chunk.first_slide))
+                    .map(|slide| slide.width().unwrap_or(config.width_slides)),
+            );
+        }
+        slide_windows(&widths, self.strip_screen.0, &samples, start)
+    }
+}
+
+impl Video {
+    /// 使用 `narrator` 生成旁白，覆盖配置中的 `narration` 命令
+    pub fn narrator(mut self, narrator: impl Narrator + Send + 'static) -> Self {
+        self.narrator = Some(Box::new(narrator));
+        self
+    }
+
+    /// 取出设置的旁白后端，没有设置时使用配置中的 `narration` 命令
+    pub(super) fn take_narrator(&mut self) -> Option<Box<dyn Narrator + Send>> {
+        self.narrator.take().or_else(|| {
+            let command = self.config.narration.clone()?;
+            Some(Box::new(command) as Box<dyn Narrator + Send>)
+        })
+    }
+
+    /// 由 `narrator` 为每个有文字的 slide（幻灯片模式中为每页）生成旁白
+    pub(super) fn narrate(
+        &self,
+        narrator: &mut dyn Narrator,
+        renderer: &Renderer,
+    ) -> Result<Vec<NarrationClip>> {
+        let (texts, windows): (Vec<String>, Vec<(f64, f64)>) = match self.config.presentation {
+            Presentation::Scroll => {
+                let texts = self
+                    .chunks
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, chunk)| {
+                        let next_first = self
+                            .chunks
+                            .get(index + 1)
+                            .map_or(usize::MAX, |c| c.first_slide);
+                        chunk
+                            .slides
+                            .iter()
+                            .take(next_first.saturating_sub(chunk.first_slide))
+                    })
+                    .map(|slide| slide.plain_text())
+                    .collect();
+                let mut windows = renderer.scroll_windows(&self.chunks);
+                one_at_a_time(&mut windows);
+                (texts, windows)
+            }
+            Presentation::Slideshow { sec_per_slide, .. } => {
+                let sec = f64::from(sec_per_slide);
+                self.chunks
+                    .iter()
+                    .enumerate()
+                    .map(|(index, page)| {
+                        let texts: Vec<String> =
+                            page.slides.iter().map(|slide| slide.plain_text()).collect();
+                        let start = index as f64 * sec;
+                        (texts.join("\n"), (start, start + sec))
+                    })
+                    .unzip()
+            }
+        };
+
+        let mut clips = Vec::new();
+        for (index, (text, (start, end))) in texts.iter().zip(windows).enumerate() {
+            if text.trim().is_empty() || end <= start {
+                continue;
+            }
+            let path = renderer
+                .work_dir
+                .join(format!("narration_{index:0>3}.{}", narrator.extension()));
+            // 预演时只输出 ffmpeg 命令，不生成语音
+            if !renderer.ffmpeg.dry_run {
+                narrator.narrate(text.trim(), &path)?;
+            }
+            clips.push(NarrationClip {
+                path,
+                start,
+                duration: end - start,
+            });
+        }
+        Ok(clips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slide_windows() {
+        // 匀速每秒移动 100 像素，共移动 1100 像素
+        let samples: Vec<_> = (0..=110)
+            .map(|i| (f64::from(i) / 10.0, f64::from(i) * 10.0))
+            .collect();
+        let mut windows = slide_windows(&[400, 400, 600, 500], 800, &samples, 12.0);
+        // 前两个 slide 一开始就在屏幕上
+        assert_eq!(windows[0], (0.0, 0.1));
+        assert_eq!(windows[1], (0.0, 4.1));
+        // 第三个 slide 在移动 600 像素后完全进入屏幕
+        assert_eq!(windows[2], (6.0, 8.1));
+        // 最后一个 slide 直到视频结束
+        assert_eq!(windows[3], (11.0, 12.0));
+        // 旁白依次播放
+        one_at_a_time(&mut windows);
+        assert_eq!(windows[1], (0.1, 4.1));
+    }
+
+    #[test]
+    fn test_narration_command() {
+        let clips = [
+            NarrationClip {
+                path: PathBuf::from("a.wav"),
+                start: 0.0,
+                duration: 2.5,
+            },
+            NarrationClip {
+                path: PathBuf::from("b.wav"),
+                start: 3.25,
+                duration: 4.0,
+            },
+        ];
+        let command = narration_command(
+            &Ffmpeg::new("ffmpeg"),
+            Path::new("effects.mp4"),
+            &clips,
+            true,
+            Path::new("out.mp4"),
+            &HashMap::new(),
+        );
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert!(args.contains(
+            &"[1]atrim=duration=2.500,adelay=delays=0:all=1[n0];\
+              [2]atrim=duration=4.000,adelay=delays=3250:all=1[n1];\
+              [0:a][n0][n1]amix=inputs=3:duration=longest:normalize=0[narration]"
+        ));
+        assert!(args.windows(2).any(|w| w == ["-map", "[narration]"]));
+        assert_eq!(args.last(), Some(&"out.mp4"));
+    }
+
+    #[test]
+    fn test_command_narrator() {
+        let narrator = CommandNarrator::new("espeak-ng", &["-w", "{output}", "{text}"]);
+        let command = narrator.command("第一行", Path::new("clip.wav"));
+        let args: Vec<_> = command.get_args().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(args, ["-w", "clip.wav", "第一行"]);
+    }
+}
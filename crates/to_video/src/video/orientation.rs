@@ -0,0 +1,150 @@
+//! 画面方向：横屏时各 slide 是占满高度的竖条，长条图向左滚动；
+//! 竖屏时各 slide 是占满宽度的横条，长条图向上滚动，适合手机上的短视频。
+//!
+//! 竖屏视频在“长条图坐标系”中生成：每个 slide 渲染后逆时针旋转 90° 成为一列，
+//! 之后与横屏完全相同地组合、分段和滚动，最后由 ffmpeg 顺时针旋转回竖屏。
+//! 因此 `width_slides` 和 slide 的宽度在竖屏时是横条的高度，chunk 也按高度划分。
+
+use super::ffmpeg::Direction;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+/// 竖屏时把长条图坐标系旋转回输出画面的 ffmpeg 滤镜
+const TRANSPOSE_FILTER: &str = "transpose=clock";
+/// 竖屏时把输出画面方向的视频转到长条图坐标系的 ffmpeg 滤镜
+const TRANSPOSE_BACK_FILTER: &str = "transpose=cclock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    /// slide 从左到右排列，向左滚动
+    #[default]
+    Horizontal,
+    /// slide 从上到下排列，向上滚动
+    Vertical,
+}
+
+impl Orientation {
+    /// 输出画面 `screen` 在长条图坐标系中的尺寸，长条图总是沿 x 方向滚动
+    pub fn strip_screen(self, (width, height): (u32, u32)) -> (u32, u32) {
+        match self {
+            Orientation::Horizontal => (width, height),
+            Orientation::Vertical => (height, width),
+        }
+    }
+
+    /// 沿滚动方向长度为 `along` 的 slide 在输出画面 `screen` 中的渲染尺寸
+    pub fn slide_size(self, along: u32, (width, height): (u32, u32)) -> (u32, u32) {
+        match self {
+            Orientation::Horizontal => (along, height),
+            Orientation::Vertical => (width, along),
+        }
+    }
+
+    /// 把按 [`slide_size`](Self::slide_size) 渲染的图片转到长条图坐标系
+    pub fn to_strip(self, img: DynamicImage) -> DynamicImage {
+        match self {
+            Orientation::Horizontal => img,
+            Orientation::Vertical => img.rotate270(),
+        }
+    }
+
+    /// slide 中位于 `pos`、大小为 `size` 的矩形在长条图坐标系中的位置和大小，
+    /// `slide_width` 为 slide 渲染时的宽度
+    pub fn rect_to_strip(
+        self,
+        (left, top): (u32, u32),
+        (width, height): (u32, u32),
+        slide_width: u32,
+    ) -> ((u32, u32), (u32, u32)) {
+        match self {
+            Orientation::Horizontal => ((left, top), (width, height)),
+            Orientation::Vertical => (
+                (top, slide_width.saturating_sub(left + width)),
+                (height, width),
+            ),
+        }
+    }
+
+    /// 输出画面中的入场方向在长条图坐标系中对应的方向
+    pub fn strip_direction(self, direction: Direction) -> Direction {
+        match (self, direction) {
+            (Orientation::Horizontal, direction) | (_, direction @ Direction::FadeIn) => direction,
+            (Orientation::Vertical, Direction::FromBottom) => Direction::FromRight,
+            (Orientation::Vertical, Direction::FromTop) => Direction::FromLeft,
+            (Orientation::Vertical, Direction::FromLeft) => Direction::FromBottom,
+            (Orientation::Vertical, Direction::FromRight) => Direction::FromTop,
+        }
+    }
+
+    /// 把长条图坐标系转回输出画面的滤镜，横屏时为 `None`
+    pub fn filter(self) -> Option<&'static str> {
+        match self {
+            Orientation::Horizontal => None,
+            Orientation::Vertical => Some(TRANSPOSE_FILTER),
+        }
+    }
+
+    /// 与 [`filter`](Self::filter) 相反，用于叠加到长条图上的动画图片
+    pub fn strip_filter(self) -> Option<&'static str> {
+        match self {
+            Orientation::Horizontal => None,
+            Orientation::Vertical => Some(TRANSPOSE_BACK_FILTER),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_vertical_round_trip() {
+        let vertical = Orientation::Vertical;
+        assert_eq!(vertical.strip_screen((1080, 1920)), (1920, 1080));
+        assert_eq!(vertical.slide_size(480, (1080, 1920)), (1080, 480));
+
+        // 横条中 (10, 20) 处 4x2 的色块
+        let band = RgbaImage::from_fn(100, 40, |x, y| {
+            if (10..14).contains(&x) && (20..22).contains(&y) {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        });
+        let strip = vertical
+            .to_strip(DynamicImage::ImageRgba8(band.clone()))
+            .into_rgba8();
+        assert_eq!(strip.dimensions(), (40, 100));
+        let ((left, top), (width, height)) = vertical.rect_to_strip((10, 20), (4, 2), 100);
+        assert_eq!(((left, top), (width, height)), ((20, 86), (2, 4)));
+        for (x, y, pixel) in strip.enumerate_pixels() {
+            let inside = (left..left + width).contains(&x) && (top..top + height).contains(&y);
+            assert_eq!(pixel[0] == 255, inside, "({x}, {y})");
+        }
+        // ffmpeg 顺时针旋转后与渲染的横条相同
+        assert_eq!(
+            DynamicImage::ImageRgba8(strip).rotate90().into_rgba8(),
+            band
+        );
+    }
+
+    #[test]
+    fn test_strip_direction() {
+        let vertical = Orientation::Vertical;
+        assert_eq!(
+            vertical.strip_direction(Direction::FromBottom),
+            Direction::FromRight
+        );
+        assert_eq!(
+            vertical.strip_direction(Direction::FadeIn),
+            Direction::FadeIn
+        );
+        assert_eq!(
+            Orientation::Horizontal.strip_direction(Direction::FromLeft),
+            Direction::FromLeft
+        );
+        assert_eq!(Orientation::Horizontal.filter(), None);
+    }
+}
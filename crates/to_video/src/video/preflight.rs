@@ -0,0 +1,166 @@
+use super::{Renderer, chunk::Chunk, ffmpeg::IntermediateFormat, scroll::FrameClock};
+use crate::Result;
+use std::{path::Path, process::Command};
+
+/// 每帧每像素估算的视频大小（字节），约为 1080p30 下 25 Mbps，高于常见编码设置的实际码率
+const VIDEO_BYTES_PER_PIXEL: f64 = 0.05;
+
+/// PNG 相对于原始像素的估算压缩比，slide 大多是纯色背景和文字，实际通常更小
+const PNG_RATIO: f64 = 0.5;
+
+/// 运行前估算的磁盘占用，单位为字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiskEstimate {
+    /// 写入 `work_dir` 的封面图片、长条图和各段视频
+    pub temp: u64,
+    /// 合并后写入 `save_path` 的视频
+    pub output: u64,
+}
+
+/// 文件系统的剩余空间
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeSpace {
+    pub available: u64,
+    /// 文件系统的挂载点，用于判断两个目录是否位于同一个文件系统
+    pub mount: String,
+}
+
+/// 查询 `path` 所在文件系统的剩余空间。
+///
+/// 通过 `df` 命令查询，没有 `df` 的平台（如 Windows）或查询失败时返回 `None`。
+pub fn free_space(path: &Path) -> Option<FreeSpace> {
+    let output = Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_df_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// 解析 `df -Pk` 的输出：第二行依次为文件系统、总块数、已用、可用、使用率和挂载点
+fn parse_df_output(output: &str) -> Option<FreeSpace> {
+    let fields: Vec<&str> = output.lines().nth(1)?.split_whitespace().collect();
+    let available: u64 = fields.get(3)?.parse().ok()?;
+    Some(FreeSpace {
+        available: available * 1024,
+        mount: fields.get(5..).filter(|f| !f.is_empty())?.join(" "),
+    })
+}
+
+fn megabytes(bytes: u64) -> u64 {
+    bytes.div_ceil(1024 * 1024)
+}
+
+impl Renderer<'_> {
+    /// 按配置和 slide 数量估算本次运行的磁盘占用，不计入 rows 和动画图片
+    pub(super) fn estimate_disk(&self, chunks: &[Chunk]) -> DiskEstimate {
+        let config = self.config;
+        let bytes_per_pixel = if self.background.is_some() { 3.0 } else { 4.0 };
+        let image_bytes = |width: u32, ratio: f64| {
+            let width = f64::from(width) * f64::from(self.scale);
+            (width * f64::from(self.strip_height()) * bytes_per_pixel * ratio) as u64
+        };
+        let strip_ratio = match self.intermediate {
+            IntermediateFormat::Png | IntermediateFormat::FastPng => PNG_RATIO,
+            IntermediateFormat::Raw => 1.0,
+        };
+        let strips: u64 = chunks
+            .iter()
+            .map(|chunk| {
+                let width = chunk
+                    .slides
+                    .iter()
+                    .map(|slide| slide.width().unwrap_or(config.width_slides))
+                    .sum();
+                image_bytes(width, strip_ratio)
+            })
+            .sum();
+        let cover = image_bytes(self.strip_screen.0, PNG_RATIO);
+
+        let mut clock = FrameClock::new(config.fps);
+        clock.advance(f64::from(config.cover_sec));
+        for (index, chunk) in chunks.iter().enumerate() {
+            let (scroll, static_sec) = self.scroll(index, chunk, chunks.len());
+            clock.advance(scroll.duration() + f64::from(static_sec));
+        }
+        let (width, height) = self.out_screen;
+        let videos = (clock.elapsed_frames() as f64
+            * f64::from(width)
+            * f64::from(height)
+            * VIDEO_BYTES_PER_PIXEL) as u64;
+
+        DiskEstimate {
+            temp: strips + cover + videos,
+            output: videos,
+        }
+    }
+
+    /// 确认 `work_dir` 和 `save_path` 所在的文件系统有足够的剩余空间，避免运行到一半才失败。
+    ///
+    /// 无法查询剩余空间时跳过检查。
+    ///
+    /// # Errors
+    /// - 如果剩余空间少于估算的占用，则返回 `Err`。
+    ///
+    pub(super) fn preflight(&self, chunks: &[Chunk]) -> Result<()> {
+        // 只打印命令时不生成视频
+        if self.ffmpeg.dry_run {
+            return Ok(());
+        }
+        let estimate = self.estimate_disk(chunks);
+        let work_dir = &self.work_dir;
+        let save_dir = std::path::absolute(&self.config.save_path)?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let work_space = free_space(work_dir);
+        let save_space = free_space(&save_dir);
+
+        let mut checks = Vec::with_capacity(2);
+        match (&work_space, &save_space) {
+            // 合并时临时文件和输出视频同时存在于同一个文件系统
+            (Some(work), Some(save)) if work.mount == save.mount => {
+                checks.push((work_dir.as_path(), work, estimate.temp + estimate.output));
+            }
+            _ => {
+                if let Some(work) = &work_space {
+                    checks.push((work_dir.as_path(), work, estimate.temp));
+                }
+                if let Some(save) = &save_space {
+                    checks.push((save_dir.as_path(), save, estimate.output));
+                }
+            }
+        }
+        for (dir, space, needed) in checks {
+            if space.available < needed {
+                return Err(format!(
+                    "not enough disk space in {}: about {} MB needed, {} MB available",
+                    dir.display(),
+                    megabytes(needed),
+                    megabytes(space.available)
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df_output() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/sda1        102400000  51200000  51200000      50% /mnt/my disk\n";
+        assert_eq!(
+            parse_df_output(output),
+            Some(FreeSpace {
+                available: 51200000 * 1024,
+                mount: "/mnt/my disk".to_string()
+            })
+        );
+        assert_eq!(parse_df_output("Filesystem 1024-blocks\n"), None);
+        assert_eq!(megabytes(1), 1);
+    }
+}
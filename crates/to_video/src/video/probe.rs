@@ -0,0 +1,245 @@
+use super::ffmpeg::FfmpegCommand;
+use crate::Result;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// ffprobe 读出的视频流信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeInfo {
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    /// 时长（秒）
+    pub duration: f64,
+}
+
+/// 一段中间视频应有的分辨率、帧率和帧数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expected {
+    pub screen: (u32, u32),
+    pub fps: u32,
+    pub frames: u64,
+}
+
+/// 与 `ffmpeg_path` 位于同一目录的 ffprobe，`ffmpeg_path` 只是命令名时使用 PATH 中的 `ffprobe`。
+///
+/// 文件名中的 `ffmpeg` 替换为 `ffprobe`，保留扩展名，例如 `C:\ffmpeg\bin\ffmpeg.exe`
+/// 对应 `C:\ffmpeg\bin\ffprobe.exe`。
+pub fn ffprobe_path(ffmpeg_path: &Path) -> PathBuf {
+    let name = ffmpeg_path
+        .file_name()
+        .map(|name| name.to_string_lossy().replacen("ffmpeg", "ffprobe", 1))
+        .filter(|name| name.contains("ffprobe"))
+        .unwrap_or_else(|| "ffprobe".to_string());
+    ffmpeg_path.with_file_name(name)
+}
+
+/// 运行 `ffprobe -version` 检查 ffprobe 是否可用
+pub fn ffprobe_available(ffprobe_path: &Path) -> bool {
+    Command::new(ffprobe_path)
+        .arg("-version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// 读取 `path` 第一个视频流的分辨率、帧率和文件时长。
+///
+/// # Errors
+/// - 文件不存在、ffprobe 执行失败或输出无法解析时返回 `Err`。
+///
+pub fn probe(ffprobe_path: &Path, path: &Path) -> Result<ProbeInfo> {
+    if !path.is_file() {
+        return Err(format!("{} was not generated", path.display()).into());
+    }
+    let output = Command::new(ffprobe_path)
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args([
+            "-show_entries",
+            "stream=width,height,r_frame_rate:format=duration",
+        ])
+        .args(["-of", "default=noprint_wrappers=1"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("failed to run {}: {e}", ffprobe_path.display()))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffprobe cannot read {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    parse_probe_output(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| format!("{} has no readable video stream", path.display()).into())
+}
+
+/// 解析 `-of default=noprint_wrappers=1` 格式的 `key=value` 输出
+fn parse_probe_output(output: &str) -> Option<ProbeInfo> {
+    let value = |key: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(str::trim)
+    };
+    // 帧率形如 `30/1` 或 `30000/1001`
+    let fps = match value("r_frame_rate")?.split_once('/') {
+        Some((num, den)) => num.parse::<f64>().ok()? / den.parse::<f64>().ok()?,
+        None => value("r_frame_rate")?.parse().ok()?,
+    };
+    Some(ProbeInfo {
+        width: value("width")?.parse().ok()?,
+        height: value("height")?.parse().ok()?,
+        fps,
+        // 无法确定时长时 ffprobe 输出 `N/A`
+        duration: value("duration")?.parse().unwrap_or(0.0),
+    })
+}
+
+/// 检查视频流是否与预期一致。
+///
+/// 时长允许比预期短两帧以内，编码器和容器在结尾的取整可能使时长略有出入。
+///
+/// # Errors
+/// - 时长为 0、分辨率或帧率不符、时长明显短于预期时返回 `Err`。
+///
+pub fn verify(info: &ProbeInfo, expected: &Expected) -> Result<()> {
+    let mut problems = Vec::new();
+    if info.duration <= 0.0 {
+        problems.push("duration is zero".to_string());
+    }
+    if (info.width, info.height) != expected.screen {
+        problems.push(format!(
+            "resolution is {}x{}, expected {}x{}",
+            info.width, info.height, expected.screen.0, expected.screen.1
+        ));
+    }
+    let fps = f64::from(expected.fps);
+    if (info.fps - fps).abs() > 0.01 {
+        problems.push(format!("fps is {:.3}, expected {}", info.fps, expected.fps));
+    }
+    let duration = expected.frames as f64 / fps;
+    if info.duration > 0.0 && info.duration < duration - 2.0 / fps {
+        problems.push(format!(
+            "duration is {:.3}s, expected {duration:.3}s",
+            info.duration
+        ));
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join(", ").into())
+    }
+}
+
+/// 执行生成中间视频的命令并检查结果，检查失败时重新执行一次。
+///
+/// `ffprobe_path` 为 `None`（ffprobe 不可用或只打印命令）时只执行命令。
+///
+/// # Errors
+/// - 命令执行失败，或重新执行后仍未通过检查时返回 `Err`。
+///
+pub fn run_verified(
+    command: FfmpegCommand,
+    work_dir: &Path,
+    video_name: &Path,
+    ffprobe_path: Option<&Path>,
+    expected: &Expected,
+) -> Result<()> {
+    let Some(ffprobe_path) = ffprobe_path else {
+        return command.run(work_dir);
+    };
+    let check =
+        || probe(ffprobe_path, &work_dir.join(video_name)).and_then(|i| verify(&i, expected));
+    command.clone().run(work_dir)?;
+    if let Err(e) = check() {
+        eprintln!(
+            "{} failed verification ({e}), retrying",
+            video_name.display()
+        );
+        command.run(work_dir)?;
+        check().map_err(|e| format!("{} failed verification: {e}", video_name.display()))?;
+    }
+    Ok(())
+}
+
+/// 与 [`run_verified`] 相同，但通过 `tokio::process` 异步执行命令
+#[cfg(feature = "async")]
+pub async fn run_verified_async(
+    command: FfmpegCommand,
+    work_dir: &Path,
+    video_name: &Path,
+    ffprobe_path: Option<&Path>,
+    expected: &Expected,
+) -> Result<()> {
+    let Some(ffprobe_path) = ffprobe_path else {
+        return command.run_async(work_dir).await;
+    };
+    let check =
+        || probe(ffprobe_path, &work_dir.join(video_name)).and_then(|i| verify(&i, expected));
+    command.clone().run_async(work_dir).await?;
+    if let Err(e) = check() {
+        eprintln!(
+            "{} failed verification ({e}), retrying",
+            video_name.display()
+        );
+        command.run_async(work_dir).await?;
+        check().map_err(|e| format!("{} failed verification: {e}", video_name.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_verify() {
+        let output = "width=1920\nheight=1080\nr_frame_rate=30/1\nduration=10.033333\n";
+        let info = parse_probe_output(output).unwrap();
+        assert_eq!(
+            info,
+            ProbeInfo {
+                width: 1920,
+                height: 1080,
+                fps: 30.0,
+                duration: 10.033333,
+            }
+        );
+        let expected = Expected {
+            screen: (1920, 1080),
+            fps: 30,
+            frames: 301,
+        };
+        assert!(verify(&info, &expected).is_ok());
+
+        let short = ProbeInfo {
+            duration: 5.0,
+            ..info.clone()
+        };
+        assert!(verify(&short, &expected).is_err());
+        let empty =
+            parse_probe_output("width=1920\nheight=1080\nr_frame_rate=30/1\nduration=N/A\n");
+        assert!(verify(&empty.unwrap(), &expected).is_err());
+        let wrong = Expected {
+            screen: (1280, 720),
+            ..expected
+        };
+        assert!(verify(&info, &wrong).is_err());
+        assert_eq!(parse_probe_output("width=1920\n"), None);
+    }
+
+    #[test]
+    fn test_ffprobe_path() {
+        assert_eq!(ffprobe_path(Path::new("ffmpeg")), PathBuf::from("ffprobe"));
+        assert_eq!(
+            ffprobe_path(Path::new("/opt/ffmpeg/bin/ffmpeg.exe")),
+            PathBuf::from("/opt/ffmpeg/bin/ffprobe.exe")
+        );
+        assert_eq!(
+            ffprobe_path(Path::new("/usr/bin/my-encoder")),
+            PathBuf::from("/usr/bin/ffprobe")
+        );
+    }
+}
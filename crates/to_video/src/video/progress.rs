@@ -0,0 +1,254 @@
+//! 进度指示：在合并后的视频上叠加底部的进度条或 “12/87” 计数，显示当前看到列表的哪里。
+//!
+//! 叠加需要重新编码整个视频一次，因此在混入音频之前进行。
+
+use super::{
+    Renderer, Video,
+    ffmpeg::{Ffmpeg, FfmpegCommand, escape_filter_value},
+    slideshow::Presentation,
+};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// 进度指示的样式，尺寸都是原始分辨率下的像素，预览时按比例缩小
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum ProgressIndicator {
+    /// 底部的细条，从左向右随时间增长，视频结束时正好占满屏幕宽度
+    Bar {
+        #[serde(default = "default_bar_height")]
+        height: u32,
+        /// ffmpeg 颜色，例如 `white@0.8`
+        #[serde(default = "default_color")]
+        color: String,
+    },
+    /// 右下角的计数，分子为已经完整出现在屏幕上的 slide 数，分母为 slide 总数
+    Counter {
+        #[serde(default = "default_font_size")]
+        font_size: u32,
+        #[serde(default = "default_color")]
+        color: String,
+        /// 与屏幕右边缘和下边缘的距离
+        #[serde(default = "default_margin")]
+        margin: u32,
+    },
+}
+
+fn default_bar_height() -> u32 {
+    6
+}
+
+fn default_color() -> String {
+    "white".to_string()
+}
+
+fn default_font_size() -> u32 {
+    36
+}
+
+fn default_margin() -> u32 {
+    24
+}
+
+/// 在合并后的视频上叠加进度指示所需的信息，与编码方式无关，
+/// 见 [`VideoEncoder::progress`](super::encoder::VideoEncoder::progress)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressOverlay {
+    pub indicator: ProgressIndicator,
+    /// 视频的总时长（秒）
+    pub duration: f64,
+    /// 每个 slide 完整出现在屏幕上的时间（秒），长度即 slide 总数
+    pub starts: Vec<f64>,
+    /// 输出视频的分辨率
+    pub screen: (u32, u32),
+    /// 计数使用的字体文件
+    pub font: Option<PathBuf>,
+    /// 预览时的缩放比例
+    pub scale: f32,
+}
+
+impl ProgressOverlay {
+    /// 叠加进度指示的 ffmpeg 滤镜，见 [`progress_filter`]
+    ///
+    /// # Errors
+    /// - 计数样式没有设置字体时返回 `Err`。
+    ///
+    pub fn filter(&self) -> Result<String> {
+        progress_filter(
+            &self.indicator,
+            self.duration,
+            &self.starts,
+            self.screen,
+            self.font.as_deref(),
+            self.scale,
+        )
+    }
+}
+
+/// 缩放像素尺寸，至少为 1
+fn scaled(value: u32, scale: f32) -> u32 {
+    ((value as f32 * scale).round() as u32).max(1)
+}
+
+/// 叠加进度指示的滤镜，输入为 `[0]`，输出为 `[progress]`。
+///
+/// # Parameters
+/// - `duration`: 视频的总时长（秒）。
+/// - `starts`: 每个 slide 完整出现在屏幕上的时间（秒），长度即 slide 总数。
+/// - `screen`: 输出视频的分辨率。
+/// - `font`: 计数使用的字体文件。
+/// - `scale`: 预览时的缩放比例。
+///
+/// # Errors
+/// - 计数样式没有设置字体时返回 `Err`。
+///
+pub fn progress_filter(
+    indicator: &ProgressIndicator,
+    duration: f64,
+    starts: &[f64],
+    screen: (u32, u32),
+    font: Option<&Path>,
+    scale: f32,
+) -> Result<String> {
+    match indicator {
+        ProgressIndicator::Bar { height, color } => Ok(format!(
+            "color=c={}:s={}x{}[bar];[0][bar]overlay=x=W*t/{:.6}-w:y=H-h:shortest=1[progress]",
+            escape_filter_value(color),
+            screen.0,
+            scaled(*height, scale),
+            duration.max(0.001),
+        )),
+        ProgressIndicator::Counter {
+            font_size,
+            color,
+            margin,
+        } => {
+            let font = font.ok_or("progress counter requires a font")?;
+            // 开头就完整显示的 slide 直接计入，其余的到达各自的时间后加一
+            let shown = starts.iter().filter(|&&t| t <= 0.0).count();
+            let terms: String = starts
+                .iter()
+                .filter(|&&t| t > 0.0)
+                .map(|t| format!("+gte(t,{t:.3})"))
+                .collect();
+            let count = if terms.is_empty() {
+                shown.to_string()
+            } else {
+                format!("%{{eif:{shown}{terms}:d}}")
+            };
+            let margin = scaled(*margin, scale);
+            Ok(format!(
+                "[0]drawtext=fontfile={}:text={}:fontsize={}:fontcolor={}:\
+                 x=w-tw-{margin}:y=h-th-{margin}[progress]",
+                escape_filter_value(&font.to_string_lossy()),
+                escape_filter_value(&format!("{count}/{}", starts.len())),
+                scaled(*font_size, scale),
+                escape_filter_value(color),
+            ))
+        }
+    }
+}
+
+/// 用 `filter`（见 [`progress_filter`]）重新编码 `source` 并写入 `output`
+#[allow(clippy::too_many_arguments)]
+pub fn progress_command(
+    ffmpeg: &Ffmpeg,
+    encoder: &str,
+    preset: &str,
+    fps: u32,
+    source: &Path,
+    filter: &str,
+    output: &Path,
+    metadata: &HashMap<String, String>,
+) -> FfmpegCommand {
+    ffmpeg
+        .command()
+        .input(source)
+        .filter_complex(filter)
+        .map("progress")
+        .encoder(encoder, preset, fps)
+        .metadata(metadata)
+        .output(output)
+}
+
+impl Video {
+    /// 需要叠加的进度指示，没有设置 `progress` 时为 `None`，`duration` 为视频的总时长（秒）
+    pub(super) fn progress_overlay(
+        &self,
+        renderer: &Renderer,
+        duration: f64,
+    ) -> Option<ProgressOverlay> {
+        let indicator = self.config.progress.clone()?;
+        let starts: Vec<f64> = match self.config.presentation {
+            Presentation::Scroll => renderer
+                .scroll_windows(&self.chunks)
+                .into_iter()
+                .map(|(start, _)| start)
+                .collect(),
+            // 每页的 slide 在这一页开始时同时出现
+            Presentation::Slideshow { sec_per_slide, .. } => self
+                .chunks
+                .iter()
+                .enumerate()
+                .flat_map(|(index, page)| {
+                    let start = index as f64 * f64::from(sec_per_slide);
+                    std::iter::repeat_n(start, page.slides.len())
+                })
+                .collect(),
+        };
+        Some(ProgressOverlay {
+            indicator,
+            duration,
+            starts,
+            screen: renderer.out_screen,
+            font: self.config.font.clone(),
+            scale: renderer.scale,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_bar() {
+        let bar = ProgressIndicator::Bar {
+            height: 6,
+            color: "white@0.8".to_string(),
+        };
+        let filter = progress_filter(&bar, 12.5, &[], (1920, 1080), None, 0.5).unwrap();
+        assert_eq!(
+            filter,
+            "color=c=white@0.8:s=1920x3[bar];\
+             [0][bar]overlay=x=W*t/12.500000-w:y=H-h:shortest=1[progress]"
+        );
+    }
+
+    #[test]
+    fn test_progress_counter() {
+        let counter = ProgressIndicator::Counter {
+            font_size: 36,
+            color: "white".to_string(),
+            margin: 24,
+        };
+        let filter = progress_filter(
+            &counter,
+            10.0,
+            &[0.0, 0.0, 2.5, 4.0],
+            (1920, 1080),
+            Some(Path::new("font.ttf")),
+            1.0,
+        )
+        .unwrap();
+        assert_eq!(
+            filter,
+            r"[0]drawtext=fontfile=font.ttf:text=%{eif\\:2+gte(t\,2.500)+gte(t\,4.000)\\:d}/4:fontsize=36:fontcolor=white:x=w-tw-24:y=h-th-24[progress]"
+        );
+        assert!(progress_filter(&counter, 10.0, &[0.0], (1920, 1080), None, 1.0).is_err());
+    }
+}
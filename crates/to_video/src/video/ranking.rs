@@ -0,0 +1,442 @@
+//! 排行榜：按分数列排序数据，给每个 slide 加上名次（#1、#2……）和分数，适合 “top N” 视频。
+//!
+//! 分数可以在 slide 滚入屏幕时从 0 增加到最终的值。每一步的数字预先渲染为
+//! 与分数区域同样大小的短序列图片，编码时按长条图的滚动位置依次叠加，
+//! 因此静态的长条图、封面和导出的图片中显示的都是最终的分数。
+
+use super::{
+    Renderer,
+    chunk::Chunk,
+    slide::{Element, Position, Slide},
+};
+use crate::{Result, WHITE, color::Color};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 名次和分数
+pub type RankScore = (u32, f64);
+
+/// 按分数列排序并给 slide 编号，与 `group_by`、`timeline` 不能同时使用
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Ranking {
+    /// 分数所在的数据列序号，从 0 开始，分数中的千位分隔符和 `%` 会被忽略
+    pub column: usize,
+    /// 分数低的排在前面，默认分数高的排在前面
+    #[serde(default)]
+    pub ascending: bool,
+    /// 只保留前 `limit` 行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// 名次文字的样式
+    #[serde(default = "default_rank_label")]
+    pub rank: Label,
+    /// 分数文字的样式，`None` 时不显示分数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<ScoreLabel>,
+}
+
+/// 名次文字的样式
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Label {
+    pub scale: f32,
+    pub color: Color,
+    pub pos: Position,
+}
+
+/// 分数文字的样式
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct ScoreLabel {
+    pub scale: f32,
+    pub color: Color,
+    pub pos: Position,
+    /// 小数位数
+    #[serde(default)]
+    pub decimals: u8,
+    /// slide 滚入屏幕时分数从 0 增加到最终的值，只在滚动模式中生效
+    #[serde(default)]
+    pub count_up: bool,
+    /// 数字动画的步数（包括 0 和最终的值），至少为 2
+    #[serde(default = "default_steps")]
+    pub steps: u32,
+}
+
+/// slide 中从 0 增加到 `to` 的数字，见 [`Slide::count_up_frames`]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct CountUp {
+    /// 显示数字的文字元素在 slide 中的序号
+    pub element: usize,
+    pub to: f64,
+    pub decimals: u8,
+    pub steps: u32,
+}
+
+impl CountUp {
+    /// 各步显示的数字
+    pub fn values(&self) -> impl Iterator<Item = String> + '_ {
+        let steps = self.steps.max(2);
+        (0..steps).map(move |k| {
+            let value = self.to * f64::from(k) / f64::from(steps - 1);
+            format!("{value:.*}", usize::from(self.decimals))
+        })
+    }
+}
+
+/// 数字动画各步的画面，见 [`Slide::count_up_frames`]
+#[derive(Debug, Clone)]
+pub struct CountUpFrames {
+    /// 画面左上角在 slide 中的位置
+    pub pos: (u32, u32),
+    pub frames: Vec<DynamicImage>,
+}
+
+/// 叠加在长条图上的数字动画，坐标已按预览比例缩放
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountUpOverlay {
+    /// 各步画面的文件名，相对于本次运行的临时目录
+    pub frames: Vec<PathBuf>,
+    /// 画面左上角在长条图中的位置
+    pub x: f64,
+    pub y: u32,
+    /// 长条图滚动到 `start` 像素时显示第一步，再滚动 `len` 像素时显示最后一步
+    pub start: f64,
+    pub len: f64,
+}
+
+impl Ranking {
+    pub fn new(column: usize) -> Self {
+        Self {
+            column,
+            ascending: false,
+            limit: None,
+            rank: default_rank_label(),
+            score: None,
+        }
+    }
+
+    pub fn ascending(mut self, ascending: bool) -> Self {
+        self.ascending = ascending;
+        self
+    }
+
+    pub fn limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn score(mut self, score: Option<ScoreLabel>) -> Self {
+        self.score = score;
+        self
+    }
+
+    /// 按分数排序（分数相同的行保持原有顺序）并截取前 `limit` 行，
+    /// 同时返回每行的名次和分数。分数相同的行名次相同，之后的名次跳过相应的数量，例如 1、2、2、4。
+    ///
+    /// # Errors
+    /// - 某一行缺少分数列，或分数不是数字时返回 `Err`。
+    ///
+    pub fn rank(&self, datas: Vec<Vec<String>>) -> Result<(Vec<Vec<String>>, Vec<RankScore>)> {
+        let column = self.column;
+        let mut scored = datas
+            .into_iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let score = data
+                    .get(column)
+                    .and_then(|cell| parse_score(cell))
+                    .ok_or_else(|| {
+                        format!("ranking: row {index} has no numeric score in column {column}")
+                    })?;
+                Ok((score, data))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if self.ascending {
+            scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        } else {
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        }
+        if let Some(limit) = self.limit {
+            scored.truncate(limit);
+        }
+
+        let mut ranks = Vec::with_capacity(scored.len());
+        for (index, (score, _)) in scored.iter().enumerate() {
+            let rank = match ranks.last() {
+                Some(&(rank, previous)) if previous == *score => rank,
+                _ => index as u32 + 1,
+            };
+            ranks.push((rank, *score));
+        }
+        let datas = scored.into_iter().map(|(_, data)| data).collect();
+        Ok((datas, ranks))
+    }
+
+    /// 在 slide 上加上名次和分数
+    pub fn decorate(&self, mut slide: Slide, rank: u32, score: f64) -> Slide {
+        let Label { scale, color, pos } = self.rank;
+        slide.add_text(&format!("#{rank}"), scale, color, pos);
+        if let Some(label) = &self.score {
+            let content = format!("{score:.*}", usize::from(label.decimals));
+            slide.add_text(&content, label.scale, label.color, label.pos);
+            if label.count_up {
+                slide.set_count_up(CountUp {
+                    element: slide.elements().len() - 1,
+                    to: score,
+                    decimals: label.decimals,
+                    steps: label.steps,
+                });
+            }
+        }
+        slide
+    }
+}
+
+/// 解析分数，忽略千位分隔符和百分号
+fn parse_score(cell: &str) -> Option<f64> {
+    let cleaned: String = cell
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ',' | '%'))
+        .collect();
+    cleaned.parse().ok().filter(|score: &f64| score.is_finite())
+}
+
+impl Renderer<'_> {
+    /// chunk 滚动期间需要播放的数字动画，各步画面保存在临时目录中。
+    ///
+    /// 数字在分数区域滚入屏幕后、再滚动半个屏幕宽度的期间从 0 增加到最终的值；
+    /// 视频开始时已经在屏幕上的 slide 不播放动画。
+    pub(super) fn count_up_overlays(&self, chunk: &Chunk) -> Result<Vec<CountUpOverlay>> {
+        let config = self.config;
+        let screen_width = self.strip_screen.0;
+        let scale = f64::from(self.scale);
+        let len = f64::from(screen_width / 2);
+        let mut overlays = Vec::new();
+        let mut x = 0;
+        for (index, slide) in chunk.slides.iter().enumerate() {
+            let width = slide.width().unwrap_or(config.width_slides);
+            let size = self.orientation.slide_size(width, config.screen);
+            let slide_x = x;
+            x += width;
+            let Some(count_up) = slide.count_up() else {
+                continue;
+            };
+            let Some(Element::Text { pos, .. }) = slide.elements().get(count_up.element) else {
+                continue;
+            };
+            // 数字区域在长条图中的左边缘
+            let rect = pos.to_rect(size);
+            let rect_pos = (rect.left().max(0) as u32, rect.top().max(0) as u32);
+            let ((left, _), _) =
+                self.orientation
+                    .rect_to_strip(rect_pos, (rect.width(), rect.height()), size.0);
+            let global = chunk.offset + slide_x + left;
+            let start = f64::from(global) - f64::from(screen_width);
+            let chunk_end = f64::from(chunk.offset + chunk.distance);
+            if global < screen_width || start + len <= f64::from(chunk.offset) || start >= chunk_end
+            {
+                continue;
+            }
+            let Some(CountUpFrames { pos, frames }) = slide.count_up_frames(size, &self.ctx)?
+            else {
+                continue;
+            };
+            let frame_size = frames
+                .first()
+                .map_or((1, 1), |frame| (frame.width(), frame.height()));
+            let ((left, top), _) = self.orientation.rect_to_strip(pos, frame_size, size.0);
+            let slide_index = chunk.first_slide + index;
+            let mut names = Vec::with_capacity(frames.len());
+            for (step, frame) in frames.into_iter().enumerate() {
+                let name = PathBuf::from(format!("count_{slide_index:0>3}_{step:0>2}.png"));
+                // 跨越两个 chunk 的 slide 只保存一次
+                if !self.work_dir.join(&name).exists() {
+                    let frame = self.orientation.to_strip(frame);
+                    let (w, h) = (frame.width(), frame.height());
+                    let frame = if self.scale == 1.0 {
+                        frame
+                    } else {
+                        frame.resize_exact(
+                            ((w as f32 * self.scale).round() as u32).max(1),
+                            ((h as f32 * self.scale).round() as u32).max(1),
+                            image::imageops::FilterType::Triangle,
+                        )
+                    };
+                    frame.save(self.work_dir.join(&name))?;
+                }
+                names.push(name);
+            }
+            overlays.push(CountUpOverlay {
+                frames: names,
+                x: f64::from(slide_x + left) * scale,
+                y: (f64::from(top) * scale).round() as u32,
+                start: (start - f64::from(chunk.offset)) * scale,
+                len: len * scale,
+            });
+        }
+        Ok(overlays)
+    }
+}
+
+fn default_rank_label() -> Label {
+    Label {
+        scale: 120.0,
+        color: WHITE,
+        pos: Position::relative(0.05, 0.02, 0.1),
+    }
+}
+
+fn default_steps() -> u32 {
+    12
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BLACK,
+        font::FontChain,
+        video::{
+            ffmpeg::{Ffmpeg, StripTile, mid_video_command},
+            orientation::Orientation,
+            scroll::{Keyframe, Scroll},
+            slide::RenderContext,
+        },
+    };
+    use std::path::Path;
+
+    fn row(name: &str, score: &str) -> Vec<String> {
+        vec![name.to_string(), score.to_string()]
+    }
+
+    #[test]
+    fn test_rank() {
+        let datas = vec![
+            row("a", "80"),
+            row("b", "1,200"),
+            row("c", "95.5"),
+            row("d", "95.5"),
+            row("e", "10"),
+        ];
+        let (sorted, ranks) = Ranking::new(1).limit(Some(4)).rank(datas).unwrap();
+        let names: Vec<_> = sorted.iter().map(|data| data[0].as_str()).collect();
+        assert_eq!(names, ["b", "c", "d", "a"]);
+        assert_eq!(ranks, [(1, 1200.0), (2, 95.5), (2, 95.5), (4, 80.0)]);
+
+        let (sorted, _) = Ranking::new(1)
+            .ascending(true)
+            .rank(vec![row("a", "3"), row("b", "1")])
+            .unwrap();
+        assert_eq!(sorted[0][0], "b");
+
+        assert!(Ranking::new(1).rank(vec![row("a", "n/a")]).is_err());
+    }
+
+    #[test]
+    fn test_count_up_values() {
+        let count_up = CountUp {
+            element: 0,
+            to: 90.0,
+            decimals: 1,
+            steps: 4,
+        };
+        let values: Vec<_> = count_up.values().collect();
+        assert_eq!(values, ["0.0", "30.0", "60.0", "90.0"]);
+    }
+
+    #[test]
+    fn test_count_up_frames() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        let font = ab_glyph::FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap();
+        let ctx = RenderContext::new(FontChain::from(font));
+        let mut slide = Slide::new();
+        slide.add_color(BLACK, Position::relative(0.0, 0.0, 1.0));
+        let ranking = Ranking::new(1).score(Some(ScoreLabel {
+            scale: 60.0,
+            color: WHITE,
+            pos: Position::new(20, 200, 80),
+            decimals: 0,
+            count_up: true,
+            steps: 3,
+        }));
+        let slide = ranking.decorate(slide, 1, 300.0);
+        assert_eq!(slide.count_up().unwrap().element, 2);
+
+        let CountUpFrames { pos, frames } =
+            slide.count_up_frames((400, 600), &ctx).unwrap().unwrap();
+        assert_eq!(pos, (20, 200));
+        assert_eq!(frames.len(), 3);
+        assert_eq!((frames[0].width(), frames[0].height()), (360, 80));
+        // 第一步为 0，最后一步与静态画面中的数字相同
+        let rendered = slide.render((400, 600), &ctx).unwrap();
+        assert_eq!(
+            frames[2].to_rgba8(),
+            rendered.crop_imm(20, 200, 360, 80).to_rgba8()
+        );
+        assert_ne!(frames[0].to_rgba8(), frames[2].to_rgba8());
+    }
+
+    #[test]
+    fn test_count_up_command() {
+        let scroll = Scroll::linear(vec![
+            Keyframe { t: 0.0, x: 0.0 },
+            Keyframe { t: 1.0, x: 10.0 },
+        ]);
+        let tiles = [StripTile {
+            pic_name: PathBuf::from("00.png"),
+            x: 0,
+            raw: None,
+        }];
+        let count_up = CountUpOverlay {
+            frames: vec![
+                PathBuf::from("count_000_00.png"),
+                PathBuf::from("count_000_01.png"),
+            ],
+            x: 100.0,
+            y: 200,
+            start: -50.0,
+            len: 960.0,
+        };
+        let command = mid_video_command(
+            &Ffmpeg::new("ffmpeg"),
+            "libx264",
+            "fast",
+            &tiles,
+            Path::new("00.mp4"),
+            (1920, 1080),
+            Orientation::Horizontal,
+            "white",
+            30,
+            &scroll,
+            30,
+            None,
+            &[],
+            &[],
+            &[count_up],
+        );
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert!(args.contains(&"count_000_01.png"));
+        let index = args.iter().position(|a| *a == "-filter_complex").unwrap();
+        let filters = args[index + 1];
+        assert!(
+            filters.contains("[cu0_0];[cu0_0][1]overlay=x='100.000-("),
+            "{filters}"
+        );
+        assert!(filters.contains("[cu0_1];[cu0_1][2]overlay="), "{filters}");
+        assert!(
+            filters.contains(":y=200:enable='between(clip(("),
+            "{filters}"
+        );
+        assert!(
+            filters.contains("-50.000)/960.000,0,1)*1,1-0.5,1+0.5)'"),
+            "{filters}"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! 数据脱敏：生成 slide 之前清空、散列或替换指定的数据列，
+//! 例如公开分享预览时隐藏 `RealName` 中的真名等剧透内容。
+
+use serde::{Deserialize, Serialize};
+
+/// 脱敏方式
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RedactMode {
+    /// 清空
+    Blank,
+    /// 替换为内容的散列，例如 `#3f2a9c01`。相同的内容得到相同的散列，仍然可以看出哪些行相同
+    Hash,
+    /// 替换为固定的文字，其中的 `{n}` 替换为行号（从 1 开始），例如 `干员{n}`
+    Placeholder { text: String },
+}
+
+/// 对一个数据列脱敏，空白的单元格保持为空
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Redaction {
+    pub column: usize,
+    #[serde(flatten)]
+    pub mode: RedactMode,
+}
+
+impl Redaction {
+    pub fn new(column: usize, mode: RedactMode) -> Self {
+        Self { column, mode }
+    }
+
+    fn redact(&self, cell: &str, row: usize) -> String {
+        match &self.mode {
+            RedactMode::Blank => String::new(),
+            RedactMode::Hash => format!("#{:08x}", fnv1a(cell) as u32),
+            RedactMode::Placeholder { text } => text.replace("{n}", &(row + 1).to_string()),
+        }
+    }
+}
+
+/// 按 `redactions` 依次处理每一行数据，缺少的列不处理
+pub fn redact(datas: &mut [Vec<String>], redactions: &[Redaction]) {
+    for (row, data) in datas.iter_mut().enumerate() {
+        for redaction in redactions {
+            if let Some(cell) = data.get_mut(redaction.column)
+                && !cell.trim().is_empty()
+            {
+                *cell = redaction.redact(cell, row);
+            }
+        }
+    }
+}
+
+/// 64 位 FNV-1a 散列，结果不随 Rust 版本和运行次数变化
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let mut datas = vec![
+            vec![
+                "阿米娅".to_string(),
+                "真名A".to_string(),
+                "罗德岛".to_string(),
+            ],
+            vec!["凯尔希".to_string(), String::new(), "罗德岛".to_string()],
+        ];
+        let redactions = [
+            Redaction::new(
+                0,
+                RedactMode::Placeholder {
+                    text: "干员{n}".to_string(),
+                },
+            ),
+            Redaction::new(1, RedactMode::Blank),
+            Redaction::new(2, RedactMode::Hash),
+            Redaction::new(9, RedactMode::Blank),
+        ];
+        redact(&mut datas, &redactions);
+        assert_eq!(datas[0][..2], ["干员1", ""]);
+        assert_eq!(datas[1][..2], ["干员2", ""]);
+        assert_eq!(datas[0][2], datas[1][2]);
+        assert!(datas[0][2].starts_with('#') && datas[0][2].len() == 9);
+
+        let redaction: Redaction =
+            serde_json::from_str(r#"{"column": 3, "mode": "placeholder", "text": "???"}"#).unwrap();
+        assert_eq!(
+            redaction,
+            Redaction::new(
+                3,
+                RedactMode::Placeholder {
+                    text: "???".to_string()
+                }
+            )
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! 把 `#[derive(ToSlide)]` 声明的布局（见 `structs::slide`）转换为操作和数据行。
+//!
+//! 未指定位置的图片列在左侧从上到下平分高度；未指定位置的文字列从上到下排列，
+//! 高度与字号成正比，有自动排列的图片时排在右半边。
+
+use super::slide::{Condition, Operation, Position};
+use crate::{BLACK, WHITE};
+use structs::slide::{SlideField, SlideKind, ToSlide};
+
+/// 图片列的左边距和上下边距
+const MARGIN: f32 = 0.05;
+/// 一行文字最多占用的高度
+const MAX_TEXT_HEIGHT: f32 = 0.2;
+
+/// 按布局生成操作：黑色背景加上每列一个元素，空白的单元格不绘制
+pub fn slide_operations(fields: &[SlideField]) -> Vec<Operation> {
+    let auto_images = fields
+        .iter()
+        .filter(|field| field.kind == SlideKind::Image && field.pos.is_none())
+        .count();
+    let auto_scale: f32 = fields
+        .iter()
+        .filter(|field| field.pos.is_none())
+        .filter_map(|field| match field.kind {
+            SlideKind::Text { scale } => Some(scale),
+            SlideKind::Image => None,
+        })
+        .sum();
+    let text_left = if auto_images > 0 { 0.5 } else { MARGIN };
+
+    let mut operations = vec![Operation::Color {
+        color: BLACK,
+        pos: Position::relative(0.0, 0.0, 1.0),
+        z_index: 0,
+        condition: None,
+        shadow: None,
+        opacity: 1.0,
+    }];
+    let (mut image_top, mut text_top) = (MARGIN, 0.1);
+    for (column, field) in fields.iter().enumerate() {
+        let z_index = u8::try_from(column + 1).unwrap_or(u8::MAX);
+        let condition = Some(Condition::NonEmpty { column });
+        let operation = match field.kind {
+            SlideKind::Image => {
+                let pos = field.pos.unwrap_or_else(|| {
+                    let height = (1.0 - 2.0 * MARGIN) / auto_images as f32;
+                    image_top += height;
+                    (MARGIN, image_top - height, height)
+                });
+                Operation::Image {
+                    pos: Position::relative(pos.0, pos.1, pos.2),
+                    z_index,
+                    condition,
+                    rotation_deg: 0.0,
+                    shadow: None,
+                    ken_burns: None,
+                    animate: false,
+                }
+            }
+            SlideKind::Text { scale } => {
+                let pos = field.pos.unwrap_or_else(|| {
+                    let height = (0.8 * scale / auto_scale).min(MAX_TEXT_HEIGHT);
+                    text_top += height;
+                    (text_left, text_top - height, height)
+                });
+                Operation::Text {
+                    scale,
+                    color: WHITE,
+                    pos: Position::relative(pos.0, pos.1, pos.2),
+                    z_index,
+                    condition,
+                    format: None,
+                }
+            }
+        };
+        operations.push(operation);
+    }
+    operations
+}
+
+/// 生成 `T` 的布局对应的操作和每一项的数据行
+pub fn slide_data<T: ToSlide>(items: &[T]) -> (Vec<Operation>, Vec<Vec<String>>) {
+    let operations = slide_operations(&T::slide_fields());
+    (operations, items.iter().map(ToSlide::slide_row).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slide::Slide;
+    use structs::Skin;
+
+    #[test]
+    fn test_skin_slides() {
+        let skin: Skin = serde_json::from_str(
+            r#"{
+                "name": "夏日", "is_animated": false, "has_intro_animation": false,
+                "has_exclusive_voice": false, "has_multiple_actions": false,
+                "owner": "阿米娅", "essay": "", "painter": "某画师", "access": "",
+                "brand_group": "", "date_launch": null, "description": ""
+            }"#,
+        )
+        .unwrap();
+        let (mut operations, datas) = slide_data(&[skin]);
+        assert_eq!(datas[0], ["skins/夏日.png", "夏日", "阿米娅", "某画师", ""]);
+        assert_eq!(operations.len(), 6);
+
+        operations.sort();
+        let slide = Slide::generation(&operations, datas[0].clone()).unwrap();
+        // 背景、立绘和三行文字，空白的获得方式不绘制
+        assert_eq!(slide.elements().len(), 5);
+        assert_eq!(slide.plain_text(), "夏日\n阿米娅\n某画师");
+    }
+}
@@ -0,0 +1,263 @@
+use super::slide::RenderContext;
+use crate::{
+    Result,
+    color::Color,
+    imageproc::{drawing::DrawText, overlay_over, rect::Rect},
+};
+use image::{GenericImage, GenericImageView, Rgba};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 列表中每一项的绘制方式，每项占据一个格子
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum RepeatItem {
+    /// 文字居中，放不下时自动缩小
+    Text { scale: f32, color: Color },
+    /// 每一项为图片路径，保持比例缩放到格子内并居中
+    Image,
+}
+
+/// 列表的排列方向
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum RepeatDirection {
+    /// 从上到下
+    #[default]
+    Vertical,
+    /// 从左到右
+    Horizontal,
+    /// 按实际的项数自动排成网格，从左到右、从上到下，格子尽量大，最后一行居中。
+    ///
+    /// 与其他方向不同，格子的大小随项数变化，例如一组缩略图
+    Grid,
+}
+
+/// 列表项超过 `max` 时，最后一个格子改为显示未显示的项数，例如 “+3”
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MoreLabel {
+    /// `{n}` 替换为未显示的项数
+    #[serde(default = "default_more_text")]
+    pub text: String,
+    pub scale: f32,
+    pub color: Color,
+}
+
+/// 生成后的 [`MoreLabel`]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MoreText {
+    pub content: String,
+    pub scale: f32,
+    pub color: Color,
+}
+
+fn default_more_text() -> String {
+    "+{n}".to_string()
+}
+
+impl MoreLabel {
+    /// 从全部列表项中取出显示的项，超过 `max` 项时最后一个格子显示剩余的项数
+    pub fn truncate(&self, mut items: Vec<String>, max: u32) -> (Vec<String>, Option<MoreText>) {
+        let max = max as usize;
+        if items.len() <= max || max == 0 {
+            return (items, None);
+        }
+        let hidden = items.len() - (max - 1);
+        items.truncate(max - 1);
+        let more = MoreText {
+            content: self.text.replace("{n}", &hidden.to_string()),
+            scale: self.scale,
+            color: self.color,
+        };
+        (items, Some(more))
+    }
+}
+
+pub fn default_separator() -> String {
+    "\n".to_string()
+}
+
+/// 把一个单元格按 `separator` 拆分为列表项，去掉首尾空白和空项，最多保留 `max` 项
+pub fn split_items(cell: &str, separator: &str, max: u32) -> Vec<String> {
+    cell.split(separator)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .take(max as usize)
+        .map(str::to_string)
+        .collect()
+}
+
+/// 把 `rect` 沿 `direction` 等分为 `max` 个格子，格子之间间隔 `gap` 像素。
+///
+/// 格子的数量固定为 `max`，因此不同 slide 中列表项的大小相同，项数不足时后面的格子留空。
+/// [`RepeatDirection::Grid`] 时 `max` 为实际的项数，见 [`grid_slots`]。
+pub fn slots(rect: Rect, direction: RepeatDirection, gap: u32, max: u32) -> Vec<Rect> {
+    let len = match direction {
+        RepeatDirection::Vertical => rect.height(),
+        RepeatDirection::Horizontal => rect.width(),
+        RepeatDirection::Grid => return grid_slots(rect, gap, max),
+    };
+    let gaps = gap.saturating_mul(max.saturating_sub(1));
+    if max == 0 || len <= gaps {
+        return vec![];
+    }
+    let slot = ((len - gaps) / max).max(1);
+    (0..max)
+        .map(|i| {
+            let offset = (i * (slot + gap)) as i32;
+            match direction {
+                RepeatDirection::Vertical => {
+                    Rect::at(rect.left(), rect.top() + offset).of_size(rect.width(), slot)
+                }
+                RepeatDirection::Horizontal => {
+                    Rect::at(rect.left() + offset, rect.top()).of_size(slot, rect.height())
+                }
+                RepeatDirection::Grid => unreachable!(),
+            }
+        })
+        .collect()
+}
+
+/// 把 `rect` 分为 `count` 个格子的网格，选择使格子较短一边最长的列数，
+/// 格子之间间隔 `gap` 像素，最后一行不满时居中
+pub fn grid_slots(rect: Rect, gap: u32, count: u32) -> Vec<Rect> {
+    let size = |columns: u32| {
+        let rows = count.div_ceil(columns);
+        let width = rect.width().checked_sub(gap * (columns - 1))? / columns;
+        let height = rect.height().checked_sub(gap * (rows - 1))? / rows;
+        (width > 0 && height > 0).then_some((rows, width, height))
+    };
+    let Some((columns, (rows, width, height))) = (1..=count)
+        .filter_map(|columns| Some((columns, size(columns)?)))
+        .max_by_key(|&(columns, (_, width, height))| (width.min(height), u32::MAX - columns))
+    else {
+        return vec![];
+    };
+    (0..count)
+        .map(|i| {
+            let (row, column) = (i / columns, i % columns);
+            // 最后一行居中
+            let in_row = if row == rows - 1 {
+                count - row * columns
+            } else {
+                columns
+            };
+            let shift = (columns - in_row) * (width + gap) / 2;
+            Rect::at(
+                rect.left() + (shift + column * (width + gap)) as i32,
+                rect.top() + (row * (height + gap)) as i32,
+            )
+            .of_size(width, height)
+        })
+        .collect()
+}
+
+/// 在 `rect` 内按 [`slots`] 依次绘制 `items`，`more` 绘制在最后一项之后的格子中
+#[allow(clippy::too_many_arguments)]
+pub fn draw_repeat(
+    img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+    rect: Rect,
+    items: &[String],
+    item: &RepeatItem,
+    direction: RepeatDirection,
+    gap: u32,
+    max: u32,
+    more: Option<&MoreText>,
+    ctx: &RenderContext,
+) -> Result<()> {
+    let count = match direction {
+        RepeatDirection::Grid => (items.len() + usize::from(more.is_some())) as u32,
+        _ => max,
+    };
+    let slots = slots(rect, direction, gap, count);
+    if let Some(more) = more
+        && let Some(&slot) = slots.get(items.len())
+    {
+        img.draw_text_center_supersampled_mut(
+            Into::into(more.color),
+            slot,
+            more.scale,
+            &ctx.font,
+            &more.content,
+            ctx.text_quality.factor(),
+        );
+    }
+    for (slot, content) in slots.into_iter().zip(items) {
+        match item {
+            RepeatItem::Text { scale, color } => img.draw_text_center_supersampled_mut(
+                Into::into(*color),
+                slot,
+                *scale,
+                &ctx.font,
+                content,
+                ctx.text_quality.factor(),
+            ),
+            RepeatItem::Image => {
+                let image = ctx
+                    .images
+                    .load(Path::new(content), (slot.width(), slot.height()))?;
+                let (w, h) = image.dimensions();
+                let x = slot.left() + (w.abs_diff(slot.width()) / 2) as i32;
+                let y = slot.top() + (h.abs_diff(slot.height()) / 2) as i32;
+                overlay_over(img, image.as_ref(), x.into(), y.into());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_items() {
+        assert_eq!(
+            split_items("作品 A\n\n 作品 B \n作品 C", "\n", 2),
+            vec!["作品 A", "作品 B"]
+        );
+        assert!(split_items("  ", "、", 5).is_empty());
+    }
+
+    #[test]
+    fn test_slots() {
+        let rect = Rect::at(10, 100).of_size(400, 320);
+        let vertical = slots(rect, RepeatDirection::Vertical, 10, 3);
+        assert_eq!(vertical.len(), 3);
+        assert_eq!((vertical[1].top(), vertical[1].height()), (210, 100));
+        assert_eq!(vertical[2].width(), 400);
+
+        let horizontal = slots(rect, RepeatDirection::Horizontal, 0, 4);
+        assert_eq!((horizontal[3].left(), horizontal[3].width()), (310, 100));
+        assert!(slots(rect, RepeatDirection::Vertical, 200, 3).is_empty());
+    }
+
+    #[test]
+    fn test_grid_slots() {
+        // 400x300 中的 5 项：3 列 2 行，格子 130x147，最后一行的 2 项居中
+        let rect = Rect::at(0, 0).of_size(400, 300);
+        let grid = grid_slots(rect, 5, 5);
+        assert_eq!(grid.len(), 5);
+        assert_eq!((grid[0].width(), grid[0].height()), (130, 147));
+        assert_eq!((grid[2].left(), grid[2].top()), (270, 0));
+        assert_eq!((grid[3].left(), grid[3].top()), (67, 152));
+        assert_eq!(grid[4].left(), 202);
+        // 一项时占满整个区域
+        assert_eq!(grid_slots(rect, 5, 1)[0], rect);
+        assert!(grid_slots(rect, 5, 0).is_empty());
+    }
+
+    #[test]
+    fn test_more_label() {
+        let more = MoreLabel {
+            text: default_more_text(),
+            scale: 40.0,
+            color: Color([255, 255, 255]),
+        };
+        let items: Vec<String> = (0..7).map(|i| i.to_string()).collect();
+        let (shown, label) = more.truncate(items.clone(), 4);
+        assert_eq!(shown, ["0", "1", "2"]);
+        assert_eq!(label.unwrap().content, "+4");
+        let (shown, label) = more.truncate(items, 7);
+        assert_eq!(shown.len(), 7);
+        assert!(label.is_none());
+    }
+}
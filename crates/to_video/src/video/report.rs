@@ -0,0 +1,106 @@
+//! 渲染报告：主长条图中各文字元素最终使用的字号、对比度不足的文字、超出安全区域的元素，
+//! 以及字形缓存的命中率
+
+use super::slide::{FittedText, LowContrast};
+use crate::font::GlyphCacheStats;
+use std::fmt;
+
+/// 一个文字元素的排版结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextFitReport {
+    /// slide 在主长条图中的序号
+    pub slide: usize,
+    /// 元素在 slide 中的序号，不计被条件隐藏的元素
+    pub element: usize,
+    pub text: FittedText,
+}
+
+impl TextFitReport {
+    /// 文字是否被缩小
+    pub fn shrunk(&self) -> bool {
+        self.text.scale < self.text.max_scale
+    }
+}
+
+impl fmt::Display for TextFitReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slide {} element {}: {:?} scale {:.1} / {}",
+            self.slide, self.element, self.text.content, self.text.scale, self.text.max_scale
+        )?;
+        if self.text.wrapped {
+            write!(f, " (wrapped)")?;
+        }
+        if self.text.below_min {
+            write!(f, " (below min_text_scale)")?;
+        }
+        Ok(())
+    }
+}
+
+/// 与下方色块对比度不足的文字
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastReport {
+    /// slide 在主长条图中的序号
+    pub slide: usize,
+    pub text: LowContrast,
+}
+
+impl fmt::Display for ContrastReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slide {} element {}: text {} on {} contrast {:.2}",
+            self.slide,
+            self.text.element,
+            self.text.text.hex(),
+            self.text.background.hex(),
+            self.text.ratio
+        )
+    }
+}
+
+/// 超出 [`SafeArea`](super::safe_area::SafeArea) 的元素
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeAreaReport {
+    /// slide 在主长条图中的序号
+    pub slide: usize,
+    /// 元素在 slide 中的序号
+    pub element: usize,
+}
+
+impl fmt::Display for SafeAreaReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slide {} element {}: outside safe area",
+            self.slide, self.element
+        )
+    }
+}
+
+/// [`Video::run`](super::Video::run) 和 [`Video::validate`](super::Video::validate) 的报告
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderReport {
+    pub texts: Vec<TextFitReport>,
+    /// 对比度低于 [`MIN_TEXT_CONTRAST`](super::slide::MIN_TEXT_CONTRAST) 的文字，
+    /// `text_contrast` 为 `Auto` 或 `Ignore` 时为空
+    pub contrast: Vec<ContrastReport>,
+    /// 超出 `safe_area` 的元素，没有设置 `safe_area` 时为空
+    pub outside_safe_area: Vec<SafeAreaReport>,
+    /// 字形缓存的命中率，只检查排版时为 0
+    pub glyph_cache: GlyphCacheStats,
+}
+
+impl RenderReport {
+    /// 被自动缩小的文字
+    pub fn shrunk(&self) -> impl Iterator<Item = &TextFitReport> {
+        self.texts.iter().filter(|text| text.shrunk())
+    }
+
+    /// 缩小到 `min_text_scale` 以下的文字
+    pub fn below_min(&self) -> impl Iterator<Item = &TextFitReport> {
+        self.texts.iter().filter(|text| text.text.below_min)
+    }
+}
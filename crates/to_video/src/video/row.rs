@@ -0,0 +1,122 @@
+use super::slide::{Operation, RenderContext, Slide};
+use crate::Result;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+/// 叠加在主长条图上的另一行长条图。
+///
+/// 每行使用同一份数据，但有自己的操作和尺寸，例如上方一行立绘、下方一行数据卡片。
+/// 主长条图仍然占满整个屏幕，各行按顺序叠加在它上面。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RowConfig {
+    pub operations: Vec<Operation>,
+    /// 行的上边缘在屏幕上的 y 坐标
+    pub y: u32,
+    /// 行的高度，即这一行 slide 的高度
+    pub height: u32,
+    /// 这一行 slide 的宽度，`None` 时使用 `width_slides`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slide_width: Option<u32>,
+    /// 相对于主长条图的滚动速度，`None` 时自动计算，使各行同时滚动到结尾
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    /// 生成这一行时跳过每行数据的前 `skip` 列
+    #[serde(default)]
+    pub skip: usize,
+}
+
+/// 生成好 slide 的一行
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub config: RowConfig,
+    pub slides: Vec<Slide>,
+    /// 实际的滚动速度，见 [`RowConfig::speed`]
+    pub speed: f64,
+}
+
+impl Row {
+    /// 用每行数据生成这一行的 slide，`speed` 在 [`set_speed`](Self::set_speed) 中确定
+    ///
+    /// # Errors
+    /// - 高度或宽度为 0、数据列数不足时返回 `Err`。
+    ///
+    pub fn new(mut config: RowConfig, datas: &[Vec<String>], width_slides: u32) -> Result<Self> {
+        if config.height == 0 || config.slide_width == Some(0) {
+            return Err("rows: height and slide_width must be greater than 0".into());
+        }
+        config.operations.sort();
+        let width = config.slide_width.unwrap_or(width_slides);
+        let slides = datas
+            .iter()
+            .map(|data| {
+                let data = data.get(config.skip..).unwrap_or_default().to_vec();
+                Ok(Slide::generation(&config.operations, data)?.with_width(width))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            config,
+            slides,
+            speed: 0.0,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.slides.iter().filter_map(Slide::width).sum()
+    }
+
+    /// 确定滚动速度：主长条图滚动 `main_distance` 像素时，
+    /// 自动速度的行正好滚动到自己的结尾
+    pub fn set_speed(&mut self, main_distance: u32, screen_width: u32) {
+        self.speed = match self.config.speed {
+            Some(speed) => f64::from(speed),
+            None if main_distance == 0 => 0.0,
+            None => f64::from(self.width().saturating_sub(screen_width)) / f64::from(main_distance),
+        };
+    }
+
+    /// 渲染这一行从 `x` 开始、宽 `width` 的一段，超出这一行的部分是透明的
+    pub fn window(&self, x: u32, width: u32, ctx: &RenderContext) -> Result<DynamicImage> {
+        let height = self.config.height;
+        let mut target = DynamicImage::new_rgba8(width, height);
+        let mut left = 0;
+        for slide in &self.slides {
+            let slide_width = slide.width().unwrap_or_default();
+            if left + slide_width > x && left < x + width {
+                let img = slide.render((slide_width, height), ctx)?;
+                image::imageops::overlay(&mut target, &img, i64::from(left) - i64::from(x), 0);
+            }
+            left += slide_width;
+        }
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(speed: Option<f32>) -> Row {
+        let config = RowConfig {
+            operations: Vec::new(),
+            y: 540,
+            height: 540,
+            slide_width: Some(960),
+            speed,
+            skip: 0,
+        };
+        Row::new(config, &vec![Vec::new(); 10], 480).unwrap()
+    }
+
+    #[test]
+    fn test_row_speed() {
+        let mut auto = row(None);
+        assert_eq!(auto.width(), 9600);
+        // 主长条图 4800 像素、滚动 2880 像素；这一行需要滚动 9600 - 1920 像素
+        auto.set_speed(4800 - 1920, 1920);
+        assert!((auto.speed - 7680.0 / 2880.0).abs() < 1e-9);
+
+        let mut fixed = row(Some(0.5));
+        fixed.set_speed(2880, 1920);
+        assert_eq!(fixed.speed, 0.5);
+    }
+}
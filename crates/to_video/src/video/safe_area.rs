@@ -0,0 +1,72 @@
+//! 安全区域：电视和手机播放时画面边缘可能被裁掉，重要的内容应放在安全区域内
+
+use super::slide::Slide;
+use crate::imageproc::rect::Rect;
+use serde::{Deserialize, Serialize};
+
+/// 画面上下边缘不安全的范围（像素，按 `screen` 的分辨率）。
+///
+/// 长条图横向滚动，元素会经过画面的左右边缘，因此只检查上下边缘。
+/// 常用的设置为画面高度的 5%（动作安全）或 10%（字幕安全），1080p 下为 54 或 108。
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct SafeArea {
+    #[serde(default)]
+    pub top: u32,
+    #[serde(default)]
+    pub bottom: u32,
+}
+
+impl SafeArea {
+    /// 上下各留出 `margin` 像素
+    pub const fn vertical(margin: u32) -> Self {
+        Self {
+            top: margin,
+            bottom: margin,
+        }
+    }
+
+    /// 高度为 `height` 的画面中安全区域的上下边界 `(top, bottom)`，`bottom` 不包含在内
+    pub fn bounds(&self, height: u32) -> (i32, i32) {
+        (self.top as i32, height.saturating_sub(self.bottom) as i32)
+    }
+
+    /// `rect` 是否有一部分位于高度为 `height` 的画面的安全区域之外
+    pub fn is_outside(&self, rect: Rect, height: u32) -> bool {
+        let (top, bottom) = self.bounds(height);
+        rect.top() < top || rect.bottom() >= bottom
+    }
+
+    /// slide 尺寸为 `size` 时超出安全区域的元素序号
+    pub fn outside_elements(&self, slide: &Slide, size: (u32, u32)) -> Vec<usize> {
+        slide
+            .elements()
+            .iter()
+            .enumerate()
+            .filter(|(_, element)| self.is_outside(element.pos().to_rect(size), size.1))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, slide::Position};
+
+    #[test]
+    fn test_outside_elements() {
+        let mut slide = Slide::new();
+        slide.add_color(Color([0, 0, 0]), Position::new(0, 0, 200));
+        slide.add_color(Color([0, 0, 0]), Position::new(0, 100, 800));
+        slide.add_color(Color([0, 0, 0]), Position::new(0, 900, 180));
+        slide.add_color(Color([0, 0, 0]), Position::relative(0.0, 0.1, 0.8));
+        let safe = SafeArea::vertical(54);
+        assert_eq!(safe.bounds(1080), (54, 1026));
+        assert_eq!(safe.outside_elements(&slide, (480, 1080)), [0, 2]);
+        assert!(
+            SafeArea::default()
+                .outside_elements(&slide, (480, 1080))
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use super::ffmpeg::MotionType;
+use serde::{Deserialize, Serialize};
+
+/// 长条图滚动的关键帧：`t` 秒时长条图已经向左移动了 `x` 像素。
+///
+/// 相邻关键帧之间按 [`Scroll`] 的缓动方式移动，最后一个关键帧之后保持不动。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Keyframe {
+    pub t: f64,
+    pub x: f64,
+}
+
+/// 单个 chunk 的滚动设置，覆盖全局的 `swip_pixels_per_sec`。
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ChunkOverride {
+    /// chunk 序号，从 0 开始
+    pub chunk: usize,
+    /// 滚动速度（像素/秒），`None` 时使用全局设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swip_pixels_per_sec: Option<u32>,
+    /// 滚动途中的停顿
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pauses: Vec<Pause>,
+}
+
+/// 滚动到 `slide` 位于屏幕中央时停顿 `sec` 秒。
+///
+/// 靠近两端、无法居中的 slide 会在滚动的起点或终点停顿。
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct Pause {
+    /// chunk 内 slide 的序号，从 0 开始
+    pub slide: usize,
+    pub sec: f32,
+}
+
+/// 计算一个 chunk 的滚动关键帧。
+///
+/// # Parameters
+/// - `distance`: 长条图需要移动的总像素数。
+/// - `swip_pixels_per_sec`: 滚动速度（像素/秒）。
+/// - `pauses`: 停顿位置，不要求有序。
+/// - `slide_widths`: chunk 内每个 slide 的宽度。
+/// - `screen_width`: 屏幕宽度。
+///
+pub fn scroll_keyframes(
+    distance: u32,
+    swip_pixels_per_sec: u32,
+    pauses: &[Pause],
+    slide_widths: &[u32],
+    screen_width: u32,
+) -> Vec<Keyframe> {
+    let distance = f64::from(distance);
+    let speed = f64::from(swip_pixels_per_sec.max(1));
+
+    let mut stops: Vec<(f64, f64)> = pauses
+        .iter()
+        .map(|pause| {
+            let left: u32 = slide_widths.iter().take(pause.slide).sum();
+            let width = slide_widths.get(pause.slide).copied().unwrap_or(0);
+            let center = f64::from(left) + f64::from(width) / 2.0;
+            let x = (center - f64::from(screen_width) / 2.0).clamp(0.0, distance);
+            (x, f64::from(pause.sec.max(0.0)))
+        })
+        .collect();
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut keyframes = vec![Keyframe { t: 0.0, x: 0.0 }];
+    let mut last = keyframes[0];
+    for (x, sec) in stops.into_iter().chain([(distance, 0.0)]) {
+        if x > last.x {
+            last = Keyframe {
+                t: last.t + (x - last.x) / speed,
+                x,
+            };
+            keyframes.push(last);
+        }
+        if sec > 0.0 {
+            last.t += sec;
+            keyframes.push(last);
+        }
+    }
+    keyframes
+}
+
+/// 一个 chunk 的滚动方式：关键帧加上缓动设置。
+///
+/// 缓动只作用在与静止相邻的移动区间：`ease_in` 时从静止开始的区间缓入，
+/// `ease_out` 时进入静止的区间缓出，chunk 内的停顿前后总是视为静止。
+/// chunk 之间的衔接处保持匀速，避免每个 chunk 都减速一次。
+#[derive(Debug, Clone)]
+pub struct Scroll {
+    pub keyframes: Vec<Keyframe>,
+    pub motion_type: MotionType,
+    /// 第一个关键帧之前是静止的（视频开头）
+    pub ease_in: bool,
+    /// 最后一个关键帧之后是静止的（结尾停留）
+    pub ease_out: bool,
+    /// 视频第一帧对应的滚动时间（秒）。
+    ///
+    /// chunk 的精确起点通常落在两帧之间，第一帧比起点晚 `phase` 秒，
+    /// 按这个时间计算移动距离，各段视频的帧就与整个视频的时间轴对齐。
+    pub phase: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ease {
+    Linear,
+    In,
+    Out,
+    InOut,
+}
+
+impl Ease {
+    /// 进度 `r`（0 到 1）对应的移动比例
+    fn progress(self, r: f64) -> f64 {
+        use std::f64::consts::PI;
+        match self {
+            Ease::Linear => r,
+            Ease::In => 1.0 - (r * PI / 2.0).cos(),
+            Ease::Out => (r * PI / 2.0).sin(),
+            Ease::InOut => (1.0 - (r * PI).cos()) / 2.0,
+        }
+    }
+
+    /// 与 [`progress`](Self::progress) 相同的 ffmpeg 表达式
+    fn expr(self, r: &str) -> String {
+        match self {
+            Ease::Linear => r.to_string(),
+            Ease::In => format!("(1-cos({r}*PI/2))"),
+            Ease::Out => format!("sin({r}*PI/2)"),
+            Ease::InOut => format!("(1-cos({r}*PI))/2"),
+        }
+    }
+}
+
+impl Scroll {
+    /// 不使用缓动的滚动
+    pub fn linear(keyframes: Vec<Keyframe>) -> Self {
+        Self {
+            keyframes,
+            motion_type: MotionType::Linear,
+            ease_in: false,
+            ease_out: false,
+            phase: 0.0,
+        }
+    }
+
+    /// 最后一次移动或停顿结束的时间
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |k| k.t)
+    }
+
+    /// 所有移动的区间及其缓动方式
+    fn segments(&self) -> impl Iterator<Item = (Keyframe, Keyframe, Ease)> + '_ {
+        let k = &self.keyframes;
+        let (allow_in, allow_out) = match self.motion_type {
+            MotionType::Linear => (false, false),
+            MotionType::EaseIn => (true, false),
+            MotionType::EaseOut => (false, true),
+            MotionType::EaseInOut => (true, true),
+        };
+        (0..k.len().saturating_sub(1))
+            .filter(|&i| k[i + 1].x != k[i].x && k[i + 1].t > k[i].t)
+            .map(move |i| {
+                let rest_before = if i == 0 {
+                    self.ease_in
+                } else {
+                    k[i - 1].x == k[i].x
+                };
+                let rest_after = if i + 2 == k.len() {
+                    self.ease_out
+                } else {
+                    k[i + 2].x == k[i + 1].x
+                };
+                let ease = match (allow_in && rest_before, allow_out && rest_after) {
+                    (false, false) => Ease::Linear,
+                    (true, false) => Ease::In,
+                    (false, true) => Ease::Out,
+                    (true, true) => Ease::InOut,
+                };
+                (k[i], k[i + 1], ease)
+            })
+    }
+
+    /// `t` 时刻长条图向左移动的像素数，与 [`expr`](Self::expr) 的结果一致
+    pub fn offset(&self, t: f64) -> f64 {
+        let t = t + self.phase;
+        self.segments()
+            .map(|(k0, k1, ease)| {
+                let r = (t.clamp(k0.t, k1.t) - k0.t) / (k1.t - k0.t);
+                (k1.x - k0.x) * ease.progress(r)
+            })
+            .sum()
+    }
+
+    /// 生成 ffmpeg 表达式，计算 `t` 时刻长条图向左移动的像素数。
+    ///
+    /// 每个移动的区间贡献一项，停顿的区间不产生任何项，
+    /// 例如匀速且没有停顿时结果为 `160*clip(t,0,10)`。
+    pub fn expr(&self) -> String {
+        self.expr_of("t")
+    }
+
+    /// 与 [`expr`](Self::expr) 相同，但时间变量为 `t`，例如 `zoompan` 滤镜中的 `(in/30)`
+    pub fn expr_of(&self, t: &str) -> String {
+        let t = if self.phase > 0.0 {
+            format!("({t}+{:.6})", self.phase)
+        } else {
+            t.to_string()
+        };
+        let terms: Vec<String> = self
+            .segments()
+            .map(|(k0, k1, ease)| {
+                let clip = if k0.t == 0.0 {
+                    format!("clip({t},0,{})", k1.t)
+                } else {
+                    format!("(clip({t},{},{})-{})", k0.t, k1.t, k0.t)
+                };
+                if ease == Ease::Linear {
+                    format!("{}*{clip}", (k1.x - k0.x) / (k1.t - k0.t))
+                } else {
+                    let r = format!("{clip}/{}", k1.t - k0.t);
+                    format!("{}*{}", k1.x - k0.x, ease.expr(&r))
+                }
+            })
+            .collect();
+        if terms.is_empty() {
+            "0".to_string()
+        } else {
+            terms.join("+")
+        }
+    }
+}
+
+/// 把各段视频的时长换算为整数帧。
+///
+/// 每段的帧数由累计时长向上取整后相减得到，舍入误差不会随段数累积，
+/// 整个视频的帧数与精确的总时长相差不超过一帧。
+/// 每段的第一帧总是不早于这一段的精确起点，早于起点的帧属于上一段，
+/// 差值由 [`phase`](Self::phase) 给出。
+#[derive(Debug, Clone)]
+pub struct FrameClock {
+    fps: u32,
+    elapsed_sec: f64,
+    elapsed_frames: u64,
+}
+
+impl FrameClock {
+    pub fn new(fps: u32) -> Self {
+        Self {
+            fps: fps.max(1),
+            elapsed_sec: 0.0,
+            elapsed_frames: 0,
+        }
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    /// 追加一段 `sec` 秒的视频，返回这一段的帧数
+    pub fn advance(&mut self, sec: f64) -> u64 {
+        self.elapsed_sec += sec.max(0.0);
+        // 减去一个很小的值，避免浮点误差使整数帧多出一帧
+        let total = (self.elapsed_sec * f64::from(self.fps) - 1e-6)
+            .ceil()
+            .max(0.0) as u64;
+        let frames = total - self.elapsed_frames;
+        self.elapsed_frames = total;
+        frames
+    }
+
+    /// 目前为止的总帧数
+    pub fn elapsed_frames(&self) -> u64 {
+        self.elapsed_frames
+    }
+
+    /// 下一段的第一帧比这一段的精确起点晚的秒数，在 `0` 到一帧的时长之间
+    pub fn phase(&self) -> f64 {
+        (self.elapsed_frames as f64 / f64::from(self.fps) - self.elapsed_sec).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_scroll() {
+        let keyframes = scroll_keyframes(1600, 160, &[], &[480; 10], 1920);
+        assert_eq!(
+            keyframes,
+            [Keyframe { t: 0.0, x: 0.0 }, Keyframe { t: 10.0, x: 1600.0 }]
+        );
+        let scroll = Scroll::linear(keyframes);
+        assert_eq!(scroll.expr(), "160*clip(t,0,10)");
+        assert_eq!(scroll.offset(5.0), 800.0);
+    }
+
+    #[test]
+    fn test_pause_centers_slide() {
+        // slide 5 的中心在 2640，居中时移动了 2640 - 960 = 1680
+        let pauses = [Pause { slide: 5, sec: 3.0 }];
+        let keyframes = scroll_keyframes(3200, 160, &pauses, &[480; 10], 1920);
+        assert_eq!(
+            keyframes,
+            [
+                Keyframe { t: 0.0, x: 0.0 },
+                Keyframe { t: 10.5, x: 1680.0 },
+                Keyframe { t: 13.5, x: 1680.0 },
+                Keyframe { t: 23.0, x: 3200.0 },
+            ]
+        );
+        let scroll = Scroll::linear(keyframes);
+        assert_eq!(scroll.duration(), 23.0);
+        assert_eq!(
+            scroll.expr(),
+            "160*clip(t,0,10.5)+160*(clip(t,13.5,23)-13.5)"
+        );
+        assert_eq!(scroll.offset(12.0), 1680.0);
+    }
+
+    #[test]
+    fn test_uneven_distance_is_not_truncated() {
+        let keyframes = scroll_keyframes(1234, 160, &[], &[480; 10], 1920);
+        let scroll = Scroll::linear(keyframes);
+        assert_eq!(scroll.duration(), 7.7125);
+        assert_eq!(scroll.offset(scroll.duration()), 1234.0);
+    }
+
+    #[test]
+    fn test_total_frames_within_one_frame() {
+        let (fps, cover_sec, ending_sec) = (30, 10.0, 4.0);
+        let distances = [1600, 1234, 999, 7, 1481];
+        let mut clock = FrameClock::new(fps);
+        let mut frames = clock.advance(cover_sec);
+        let mut exact = cover_sec;
+        for (i, distance) in distances.into_iter().enumerate() {
+            let scroll = Scroll::linear(scroll_keyframes(distance, 160, &[], &[480; 10], 1920));
+            let static_sec = if i + 1 == distances.len() {
+                ending_sec
+            } else {
+                0.0
+            };
+            frames += clock.advance(scroll.duration() + static_sec);
+            exact += scroll.duration() + static_sec;
+        }
+        assert_eq!(frames, clock.elapsed_frames());
+        assert!((frames as f64 / f64::from(fps) - exact).abs() <= 1.0 / f64::from(fps));
+    }
+
+    #[test]
+    fn test_pause_at_edges_is_clamped() {
+        let pauses = [Pause { slide: 0, sec: 2.0 }, Pause { slide: 9, sec: 1.0 }];
+        let keyframes = scroll_keyframes(960, 96, &pauses, &[480; 6], 1920);
+        assert_eq!(
+            keyframes,
+            [
+                Keyframe { t: 0.0, x: 0.0 },
+                Keyframe { t: 2.0, x: 0.0 },
+                Keyframe { t: 12.0, x: 960.0 },
+                Keyframe { t: 13.0, x: 960.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_easing_only_next_to_rest() {
+        let keyframes = scroll_keyframes(1600, 160, &[], &[480; 10], 1920);
+        let mut scroll = Scroll {
+            keyframes,
+            motion_type: MotionType::EaseInOut,
+            ease_in: true,
+            ease_out: false,
+            phase: 0.0,
+        };
+        assert_eq!(scroll.expr(), "1600*(1-cos(clip(t,0,10)/10*PI/2))");
+        assert_eq!(scroll.offset(0.0), 0.0);
+        assert!(scroll.offset(5.0) < 800.0);
+        assert!((scroll.offset(10.0) - 1600.0).abs() < 1e-9);
+
+        scroll.ease_in = false;
+        scroll.ease_out = true;
+        assert_eq!(scroll.expr(), "1600*sin(clip(t,0,10)/10*PI/2)");
+        assert!(scroll.offset(5.0) > 800.0);
+
+        // 缓入的设置不影响结尾
+        scroll.motion_type = MotionType::EaseIn;
+        assert_eq!(scroll.expr(), "160*clip(t,0,10)");
+    }
+
+    #[test]
+    fn test_easing_around_pause() {
+        let pauses = [Pause { slide: 5, sec: 3.0 }];
+        let scroll = Scroll {
+            keyframes: scroll_keyframes(3200, 160, &pauses, &[480; 10], 1920),
+            motion_type: MotionType::EaseInOut,
+            ease_in: false,
+            ease_out: false,
+            phase: 0.0,
+        };
+        assert_eq!(
+            scroll.expr(),
+            "1680*sin(clip(t,0,10.5)/10.5*PI/2)+1520*(1-cos((clip(t,13.5,23)-13.5)/9.5*PI/2))"
+        );
+        assert_eq!(scroll.offset(11.0), 1680.0);
+        assert!((scroll.offset(23.0) - 3200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_chunks_continue_across_seams() {
+        // 每个 chunk 的时长都不是整数帧，相邻两帧的移动距离仍然保持一致
+        let fps = 30;
+        let mut clock = FrameClock::new(fps);
+        let mut offsets = Vec::new();
+        let mut x = 0.0;
+        for distance in [1234, 999, 1481] {
+            let mut scroll = Scroll::linear(scroll_keyframes(distance, 160, &[], &[480; 10], 1920));
+            scroll.phase = clock.phase();
+            assert!(scroll.phase < 1.0 / f64::from(fps));
+            let frames = clock.advance(scroll.duration());
+            offsets
+                .extend((0..frames).map(|frame| x + scroll.offset(frame as f64 / f64::from(fps))));
+            x += f64::from(distance);
+        }
+        for pair in offsets.windows(2) {
+            assert!((pair[1] - pair[0] - 160.0 / f64::from(fps)).abs() < 1e-6);
+        }
+
+        let mut scroll = Scroll::linear(scroll_keyframes(1600, 160, &[], &[480; 10], 1920));
+        scroll.phase = 0.0125;
+        assert_eq!(scroll.expr(), "160*clip((t+0.012500),0,10)");
+        assert_eq!(scroll.offset(0.0), 2.0);
+    }
+}
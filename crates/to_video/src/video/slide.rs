@@ -1,24 +1,63 @@
+use super::{
+    assets::{ImageCache, is_video, open_image},
+    chart::{ChartStyle, draw_bar_chart, draw_radar_chart, parse_chart_value},
+    format::{DateDisplay, Format, Locale, format_timestamp, today},
+    ken_burns::{KenBurns, KenBurnsImage},
+    ranking::{CountUp, CountUpFrames},
+    repeat::{
+        MoreLabel, MoreText, RepeatDirection, RepeatItem, default_separator, draw_repeat,
+        split_items,
+    },
+    table::{TableStyle, draw_table},
+    transform::parse_number,
+};
+#[cfg(feature = "gpu")]
+use crate::imageproc::drawing::{PlacedGlyph, layout_text_center};
 use crate::{
-    Result,
+    BLACK, Result, WHITE,
     color::Color,
+    font::FontChain,
     imageproc::{
-        drawing::{DrawMut, DrawText},
+        drawing::{DrawMut, DrawText, NinePatch, fit_rich_text_scale, fit_text_scale, wrap_text},
+        filters::drop_shadow,
+        geometric_transformations::{Interpolation, rotate_about_center},
+        overlay_over,
+        palette::dominant_colors,
         rect::Rect,
+        weighted_sum,
     },
+    rich_text::{has_markup, parse_markup},
 };
-use ab_glyph::FontArc;
-use image::{DynamicImage, GenericImage, GenericImageView};
+use image::{DynamicImage, GenericImage, GenericImageView, Rgba, RgbaImage};
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+/// 绑定数据后的元素，可以序列化为 JSON 保存，之后不需要原始数据即可重新读取
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Element {
     Image {
         path: PathBuf,
         pos: Position,
+        /// 顺时针旋转角度（度），绕图片中心旋转，超出图片自身范围的部分被裁掉
+        #[serde(default)]
+        rotation_deg: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shadow: Option<Shadow>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ken_burns: Option<KenBurns>,
+        /// 动态图片，见 [`Operation::Image`] 的 `animate`
+        #[serde(default)]
+        animate: bool,
     },
     Text {
+        /// 可以包含 `<b>`、`<color=#rrggbb>` 等标记，见 [`rich_text`](crate::rich_text)
         content: String,
+        /// 与 [`Operation::Text`] 的 `scale` 相同，两种写法都可以读取
+        #[serde(alias = "scale")]
         max_scale: f32,
         color: Color,
         pos: Position,
@@ -26,23 +65,467 @@ pub enum Element {
     Color {
         color: Color,
         pos: Position,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shadow: Option<Shadow>,
+        /// 不透明度，0.0 ~ 1.0
+        #[serde(default = "default_opacity", skip_serializing_if = "is_opaque")]
+        opacity: f32,
+    },
+    /// 九宫格边框图片，拉伸到 `pos` 的大小
+    Frame {
+        path: PathBuf,
+        /// (左, 上, 右, 下) 不拉伸部分的宽度（像素）
+        insets: [u32; 4],
+        pos: Position,
+    },
+    /// 表格，`header` 为空时不绘制表头
+    Table {
+        header: Vec<String>,
+        cells: Vec<Vec<String>>,
+        /// 各列宽度的比例
+        column_widths: Vec<f32>,
+        style: TableStyle,
+        pos: Position,
+    },
+    /// 横向柱状图，`labels` 与 `values` 一一对应
+    BarChart {
+        labels: Vec<String>,
+        values: Vec<f32>,
+        style: ChartStyle,
+        pos: Position,
+    },
+    /// 雷达图，至少 3 个维度
+    RadarChart {
+        labels: Vec<String>,
+        values: Vec<f32>,
+        style: ChartStyle,
+        pos: Position,
+    },
+    /// 长度可变的列表，`pos` 的区域等分为 `max` 个格子，依次绘制 `items`
+    Repeat {
+        items: Vec<String>,
+        item: RepeatItem,
+        direction: RepeatDirection,
+        /// 格子之间的间隔（像素）
+        gap: u32,
+        max: u32,
+        pos: Position,
+        /// 列表项超过 `max` 时显示在最后一个格子中的剩余项数
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        more: Option<MoreText>,
+    },
+    /// 占满 slide 高度的一栏，`elements` 按这一栏的宽度布局，见 [`Versus`](super::versus::Versus)
+    Panel {
+        elements: Vec<Element>,
+        /// 左边缘和宽度，相对于 slide 的宽度
+        rel_left: f32,
+        rel_width: f32,
+        /// 水平翻转其中的图片，文字不翻转
+        #[serde(default)]
+        mirror_images: bool,
+    },
+}
+
+/// 占满整个 slide 的位置，用作 [`Element::Panel`] 的位置
+const FULL_SLIDE: Position = Position::relative(0.0, 0.0, 1.0);
+
+/// 元素的显示条件，按 slide 的数据判断，`column` 为数据中的列号（从 0 开始）。
+///
+/// 不满足条件时元素不绘制，但仍然消耗数据列，不影响后续元素读取的数据。
+/// 列号超出数据范围时视为空字符串。
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "test", rename_all = "snake_case")]
+pub enum Condition {
+    /// 去掉首尾空白后非空
+    NonEmpty {
+        column: usize,
+    },
+    /// 去掉首尾空白后为空
+    Empty {
+        column: usize,
     },
+    Equals {
+        column: usize,
+        value: String,
+    },
+    NotEquals {
+        column: usize,
+        value: String,
+    },
+    /// 包含 `value`
+    Contains {
+        column: usize,
+        value: String,
+    },
+    /// 数值大于 `value`，不是数字时不满足
+    GreaterThan {
+        column: usize,
+        value: f64,
+    },
+    /// 数值小于 `value`，不是数字时不满足
+    LessThan {
+        column: usize,
+        value: f64,
+    },
+}
+
+impl Condition {
+    pub fn eval(&self, data: &[String]) -> bool {
+        let cell = |column: usize| data.get(column).map_or("", |s| s.trim());
+        match self {
+            Condition::NonEmpty { column } => !cell(*column).is_empty(),
+            Condition::Empty { column } => cell(*column).is_empty(),
+            Condition::Equals { column, value } => cell(*column) == value,
+            Condition::NotEquals { column, value } => cell(*column) != value,
+            Condition::Contains { column, value } => cell(*column).contains(value.as_str()),
+            Condition::GreaterThan { column, value } => {
+                parse_number(cell(*column)).is_some_and(|number| number > *value)
+            }
+            Condition::LessThan { column, value } => {
+                parse_number(cell(*column)).is_some_and(|number| number < *value)
+            }
+        }
+    }
+}
+
+/// 图片、色块下方的投影
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct Shadow {
+    /// 阴影相对元素的偏移（像素）
+    pub offset: (i32, i32),
+    /// 模糊程度（高斯标准差，像素）
+    pub blur: f32,
+    pub color: Color,
+    /// 不透明度，0.0 ~ 1.0
+    pub opacity: f32,
+}
+
+impl Shadow {
+    /// 为位于 `(x, y)` 的图层 `layer` 绘制投影，需在绘制图层本身之前调用
+    fn draw(
+        &self,
+        img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+        layer: &RgbaImage,
+        x: i64,
+        y: i64,
+    ) {
+        let Color([r, g, b]) = self.color;
+        let alpha = (self.opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let (shadow, padding) = drop_shadow(layer, self.blur, Rgba([r, g, b, alpha]));
+        let padding = i64::from(padding);
+        overlay_over(
+            img,
+            &shadow,
+            x + i64::from(self.offset.0) - padding,
+            y + i64::from(self.offset.1) - padding,
+        );
+    }
+}
+
+const fn default_opacity() -> f32 {
+    1.0
+}
+
+fn is_opaque(opacity: &f32) -> bool {
+    *opacity >= 1.0
+}
+
+/// 文字渲染质量
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum TextQuality {
+    /// 直接按字形覆盖率绘制
+    #[default]
+    Normal,
+    /// 2 倍超采样
+    Supersample2x,
+    /// 4 倍超采样，最平滑也最慢
+    Supersample4x,
+}
+
+impl TextQuality {
+    /// 超采样倍数
+    pub const fn factor(self) -> u32 {
+        match self {
+            TextQuality::Normal => 1,
+            TextQuality::Supersample2x => 2,
+            TextQuality::Supersample4x => 4,
+        }
+    }
+}
+
+/// 合成 slide 的方式
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Compositor {
+    /// 全部在 CPU 上绘制
+    #[default]
+    Cpu,
+    /// 图片、色块和文字在 GPU 上绘制，需要 `gpu` feature，没有可用的 GPU 时退回 CPU
+    Gpu,
+}
+
+/// 文字缩小到 `min_text_scale` 以下时的处理方式
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextOverflow {
+    /// 继续缩小，只在报告中标记
+    #[default]
+    Shrink,
+    /// 渲染失败
+    Error,
+    /// 按 `min_text_scale` 自动换行，换行后仍放不下时再缩小。带标记的文字不换行
+    Wrap,
+}
+
+/// 文字与下方色块对比度不足时的处理方式，见 [`MIN_TEXT_CONTRAST`]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextContrast {
+    /// 只在报告中列出
+    #[default]
+    Warn,
+    /// 文字改为黑色或白色中对比度较高的一种
+    Auto,
+    /// 不检查
+    Ignore,
+}
+
+/// 文字与下方色块的最低对比度，WCAG AA 对大号文字的要求
+pub const MIN_TEXT_CONTRAST: f32 = 3.0;
+
+/// 对比度不足的文字
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LowContrast {
+    /// 文字元素在 slide 中的序号
+    pub element: usize,
+    pub text: Color,
+    pub background: Color,
+    pub ratio: f32,
+}
+
+/// 文字元素排版后的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FittedText {
+    /// 实际绘制的文字，自动换行时包含插入的换行
+    pub content: String,
+    /// 元素设置的字号
+    pub max_scale: f32,
+    /// 最终使用的字号
+    pub scale: f32,
+    /// 是否缩小到了 `min_text_scale` 以下
+    pub below_min: bool,
+    /// 是否自动换行
+    pub wrapped: bool,
+}
+
+/// 计算文字在 `size` 内完整显示时的字号，按 `ctx.text_overflow` 处理过小的字号
+pub fn fit_text(
+    content: &str,
+    max_scale: f32,
+    size: (u32, u32),
+    ctx: &RenderContext,
+) -> FittedText {
+    let markup = has_markup(content);
+    let fit = |content: &str| {
+        if markup {
+            fit_rich_text_scale(size, max_scale, &ctx.font, &parse_markup(content)).y
+        } else {
+            fit_text_scale(size, max_scale, &ctx.font, content).y
+        }
+    };
+    let scale = fit(content);
+    let below_min = |scale: f32| scale < ctx.min_text_scale;
+    if below_min(scale) && ctx.text_overflow == TextOverflow::Wrap && !markup {
+        let wrapped = wrap_text(ctx.min_text_scale, &ctx.font, content, size.0);
+        let scale = fit(&wrapped);
+        return FittedText {
+            below_min: below_min(scale),
+            wrapped: wrapped.lines().count() > content.lines().count(),
+            content: wrapped,
+            max_scale,
+            scale,
+        };
+    }
+    FittedText {
+        content: content.to_string(),
+        max_scale,
+        scale,
+        below_min: below_min(scale),
+        wrapped: false,
+    }
+}
+
+/// 按 [`fit_text`] 适配文字元素，`text_overflow` 为 `Error` 时检查最小字号
+fn fit_element_text(
+    content: &str,
+    max_scale: f32,
+    rect: Rect,
+    ctx: &RenderContext,
+) -> Result<FittedText> {
+    let fitted = fit_text(content, max_scale, (rect.width(), rect.height()), ctx);
+    if fitted.below_min && ctx.text_overflow == TextOverflow::Error {
+        return Err(format!(
+            "文字 \"{content}\" 需要缩小到 {:.1}，小于 min_text_scale {}",
+            fitted.scale, ctx.min_text_scale
+        )
+        .into());
+    }
+    Ok(fitted)
+}
+
+/// 渲染 slide 所需的字体和绘制选项
+#[derive(Clone)]
+pub struct RenderContext {
+    /// 字体及其后备字体
+    pub font: FontChain,
+    /// slide 左侧分割线的颜色，`None` 时不绘制
+    pub split_line_color: Option<Color>,
+    /// 分割线宽度（像素），过细的线在视频压缩后几乎不可见
+    pub split_line_width: u32,
+    pub text_quality: TextQuality,
+    /// 文字自动缩小的下限，0 表示不限制
+    pub min_text_scale: f32,
+    pub text_overflow: TextOverflow,
+    /// 按 slide 中第一张图片的主要颜色依次设置各色块的颜色，
+    /// 让卡片的配色与图片一致。没有图片的 slide 保持原来的颜色
+    pub auto_palette: bool,
+    pub text_contrast: TextContrast,
+    /// 图片缓存，克隆的 `RenderContext` 共享同一个缓存
+    pub images: Arc<ImageCache>,
+}
+
+impl RenderContext {
+    pub fn new(font: impl Into<FontChain>) -> Self {
+        Self {
+            font: font.into(),
+            split_line_color: None,
+            split_line_width: 2,
+            text_quality: TextQuality::Normal,
+            min_text_scale: 0.0,
+            text_overflow: TextOverflow::Shrink,
+            auto_palette: false,
+            text_contrast: TextContrast::Warn,
+            images: Arc::default(),
+        }
+    }
 }
 
 impl Element {
-    pub fn render(&self, img: &mut DynamicImage, width: u32, font: &FontArc) -> Result<()> {
+    /// 元素在 slide 中的位置
+    pub fn pos(&self) -> &Position {
+        match self {
+            Element::Image { pos, .. }
+            | Element::Text { pos, .. }
+            | Element::Color { pos, .. }
+            | Element::Frame { pos, .. }
+            | Element::Table { pos, .. }
+            | Element::BarChart { pos, .. }
+            | Element::RadarChart { pos, .. }
+            | Element::Repeat { pos, .. } => pos,
+            Element::Panel { .. } => &FULL_SLIDE,
+        }
+    }
+
+    /// 元素引用的图片文件，包括栏中的元素
+    fn asset_paths(&self) -> Vec<&Path> {
+        match self {
+            Element::Image { path, .. } | Element::Frame { path, .. } => vec![path.as_path()],
+            Element::Repeat {
+                items,
+                item: RepeatItem::Image,
+                ..
+            } => items.iter().map(Path::new).collect(),
+            Element::Panel { elements, .. } => {
+                elements.iter().flat_map(Element::asset_paths).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// 不带标记的文字排版后的颜色和字形，供 GPU 通过字形图集绘制。
+    ///
+    /// 不是文字、带标记、使用超采样或含有彩色字形时返回 `None`，这些文字仍在 CPU 上绘制。
+    ///
+    /// # Errors
+    /// - 文字需要缩小到 `min_text_scale` 以下且 `text_overflow` 为 `Error` 时返回 `Err`。
+    ///
+    #[cfg(feature = "gpu")]
+    pub fn text_glyphs(
+        &self,
+        size: (u32, u32),
+        ctx: &RenderContext,
+    ) -> Result<Option<(Color, Vec<PlacedGlyph>)>> {
+        let Element::Text {
+            content,
+            max_scale,
+            color,
+            pos,
+        } = self
+        else {
+            return Ok(None);
+        };
+        if ctx.text_quality.factor() > 1 {
+            return Ok(None);
+        }
+        let rect = pos.to_rect(size);
+        let fitted = fit_element_text(content, *max_scale, rect, ctx)?;
+        if has_markup(&fitted.content) {
+            return Ok(None);
+        }
+        Ok(
+            layout_text_center(rect, *max_scale, &ctx.font, &fitted.content)
+                .map(|glyphs| (*color, glyphs)),
+        )
+    }
+
+    pub fn render(
+        &self,
+        img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+        size: (u32, u32),
+        ctx: &RenderContext,
+    ) -> Result<()> {
         match self {
-            Element::Image { path, pos } => {
-                let rect = pos.to_rect(width);
-                let img_element = image::open(path)
-                    .map_err(|e| format!("{path:?}: {e}"))?
-                    .thumbnail(rect.width(), rect.height());
+            Element::Image {
+                path,
+                pos,
+                rotation_deg,
+                shadow,
+                ken_burns,
+                animate,
+            } => {
+                if *animate && is_video(path) {
+                    return Ok(());
+                }
+                let rect = pos.to_rect(size);
+                let mut img_element = ctx.images.load(path, (rect.width(), rect.height()))?;
+                // 静态画面使用动画的第一帧
+                if let Some(ken_burns) = ken_burns {
+                    img_element = Arc::new(ken_burns.from.apply(&img_element));
+                }
                 let (img_w, img_h) = img_element.dimensions();
-                img.copy_from(
-                    &img_element,
-                    rect.left() as u32 + (rect.width() - img_w) / 2,
-                    rect.top() as u32 + (rect.height() - img_h) / 2,
-                )?;
+                let (x, y) = (
+                    rect.left() as u32 + (img_w.abs_diff(rect.width())) / 2,
+                    rect.top() as u32 + (img_h.abs_diff(rect.height())) / 2,
+                );
+                let rotated = *rotation_deg % 360.0 != 0.0;
+                // 图片按 source-over 混合，透明部分露出下层的元素
+                if !rotated && shadow.is_none() {
+                    overlay_over(img, img_element.as_ref(), x.into(), y.into());
+                    return Ok(());
+                }
+                let mut layer = img_element.to_rgba8();
+                if rotated {
+                    layer = rotate_about_center(
+                        &layer,
+                        rotation_deg.to_radians(),
+                        Interpolation::Bilinear,
+                        Rgba([0, 0, 0, 0]),
+                    );
+                }
+                if let Some(shadow) = shadow {
+                    shadow.draw(img, &layer, x.into(), y.into());
+                }
+                overlay_over(img, &layer, x.into(), y.into());
             }
             Element::Text {
                 content,
@@ -50,59 +533,649 @@ impl Element {
                 color,
                 pos,
             } => {
-                let rect = pos.to_rect(width);
-                img.draw_text_center_mut(Into::into(*color), rect, *max_scale, font, content);
+                let rect = pos.to_rect(size);
+                let fitted = fit_element_text(content, *max_scale, rect, ctx)?;
+                let content = &fitted.content;
+                // 带标记的文字不使用超采样
+                if has_markup(content) {
+                    img.draw_rich_text_center_mut(
+                        Into::into(*color),
+                        rect,
+                        *max_scale,
+                        &ctx.font,
+                        &parse_markup(content),
+                    );
+                    return Ok(());
+                }
+                img.draw_text_center_supersampled_mut(
+                    Into::into(*color),
+                    rect,
+                    *max_scale,
+                    &ctx.font,
+                    content,
+                    ctx.text_quality.factor(),
+                );
             }
-            Element::Color { color, pos } => {
-                let rect = pos.to_rect(width);
-                img.draw_filled_rounded_rect_mut(rect, 10, Into::into(*color));
+            Element::Color {
+                color,
+                pos,
+                shadow,
+                opacity,
+            } => {
+                let rect = pos.to_rect(size);
+                if let Some(shadow) = shadow {
+                    let mut layer = RgbaImage::new(rect.width(), rect.height());
+                    layer.draw_filled_rounded_rect_mut(
+                        Rect::at(0, 0).of_size(rect.width(), rect.height()),
+                        10,
+                        Rgba([0, 0, 0, 255]),
+                    );
+                    shadow.draw(img, &layer, rect.left().into(), rect.top().into());
+                }
+                if *opacity >= 1.0 {
+                    img.draw_antialiased_filled_rounded_rect_mut(
+                        rect,
+                        10,
+                        Into::into(*color),
+                        |line, original, weight| weighted_sum(original, line, 1.0 - weight, weight),
+                    );
+                    return Ok(());
+                }
+                // 半透明的色块先绘制到单独的图层，再按 source-over 与下层混合
+                let Color([r, g, b]) = *color;
+                let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+                let mut layer = RgbaImage::new(rect.width(), rect.height());
+                layer.draw_antialiased_filled_rounded_rect_mut(
+                    Rect::at(0, 0).of_size(rect.width(), rect.height()),
+                    10,
+                    Rgba([r, g, b, alpha]),
+                    |line, _, weight| {
+                        Rgba([
+                            line[0],
+                            line[1],
+                            line[2],
+                            (f32::from(line[3]) * weight).round() as u8,
+                        ])
+                    },
+                );
+                overlay_over(img, &layer, rect.left().into(), rect.top().into());
+            }
+            Element::Frame { path, insets, pos } => {
+                let rect = pos.to_rect(size);
+                let patch = NinePatch::new(open_image(path)?.into_rgba8(), *insets);
+                // 边框素材通常带透明部分，先绘制到单独的图层再混合
+                let mut layer = RgbaImage::new(rect.width(), rect.height());
+                layer.draw_nine_patch_mut(
+                    &patch,
+                    Rect::at(0, 0).of_size(rect.width(), rect.height()),
+                );
+                overlay_over(img, &layer, rect.left().into(), rect.top().into());
+            }
+            Element::Table {
+                header,
+                cells,
+                column_widths,
+                style,
+                pos,
+            } => {
+                draw_table(
+                    img,
+                    pos.to_rect(size),
+                    header,
+                    cells,
+                    column_widths,
+                    style,
+                    ctx,
+                );
+            }
+            Element::BarChart {
+                labels,
+                values,
+                style,
+                pos,
+            } => draw_bar_chart(img, pos.to_rect(size), labels, values, style, ctx),
+            Element::RadarChart {
+                labels,
+                values,
+                style,
+                pos,
+            } => draw_radar_chart(img, pos.to_rect(size), labels, values, style, ctx),
+            Element::Repeat {
+                items,
+                item,
+                direction,
+                gap,
+                max,
+                pos,
+                more,
+            } => draw_repeat(
+                img,
+                pos.to_rect(size),
+                items,
+                item,
+                *direction,
+                *gap,
+                *max,
+                more.as_ref(),
+                ctx,
+            )?,
+            Element::Panel {
+                elements,
+                rel_left,
+                rel_width,
+                mirror_images,
+            } => {
+                let x = ((rel_left * size.0 as f32).round() as u32).min(size.0 - 1);
+                let width = ((rel_width * size.0 as f32).round() as u32).clamp(1, size.0 - x);
+                let panel_size = (width, size.1);
+                // 先绘制到单独的图层再混合，嵌套的栏不需要再实例化新的图片类型
+                let mut layer = RgbaImage::new(width, size.1);
+                for element in elements {
+                    if *mirror_images && matches!(element, Element::Image { .. }) {
+                        let mut image = RgbaImage::new(width, size.1);
+                        element.render(&mut image, panel_size, ctx)?;
+                        image::imageops::flip_horizontal_in_place(&mut image);
+                        overlay_over(&mut layer, &image, 0, 0);
+                    } else {
+                        element.render(&mut layer, panel_size, ctx)?;
+                    }
+                }
+                overlay_over(img, &layer, x.into(), 0);
             }
         }
         Ok(())
     }
 }
 
-#[derive(Clone, Debug, Copy, Deserialize, Serialize, PartialEq, Eq)]
-pub struct Position {
-    left: i32,
-    top: i32,
-    height: u32,
+/// 元素在 slide 中的位置，左右边距相同，宽度由 slide 宽度减去两侧边距得到。
+///
+/// - `Absolute`: 以像素为单位，与 `width_slides` 和 `screen` 绑定。
+/// - `Relative`: 以 slide 宽、高的比例（0.0 ~ 1.0）为单位，`screen` 或 `width_slides`
+///   改变时无需重新布局。`rel_left` 相对于宽度，`rel_top` 和 `rel_height` 相对于高度。
+#[derive(Clone, Debug, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum Position {
+    Absolute {
+        left: i32,
+        top: i32,
+        height: u32,
+    },
+    Relative {
+        rel_left: f32,
+        rel_top: f32,
+        rel_height: f32,
+    },
 }
 
 impl Position {
     pub const fn new(left: i32, top: i32, height: u32) -> Self {
-        Self { left, top, height }
+        Self::Absolute { left, top, height }
     }
-    pub fn to_rect(&self, width: u32) -> Rect {
-        Rect::at(self.left, self.top).of_size(width - self.left as u32 * 2, self.height)
+
+    pub const fn relative(rel_left: f32, rel_top: f32, rel_height: f32) -> Self {
+        Self::Relative {
+            rel_left,
+            rel_top,
+            rel_height,
+        }
+    }
+
+    /// 将位置换算为 `size` (slide 宽, slide 高) 下的像素矩形。
+    pub fn to_rect(&self, size: (u32, u32)) -> Rect {
+        let (width, height) = size;
+        let (left, top, h) = match *self {
+            Position::Absolute { left, top, height } => (left, top, height),
+            Position::Relative {
+                rel_left,
+                rel_top,
+                rel_height,
+            } => (
+                (rel_left * width as f32).round() as i32,
+                (rel_top * height as f32).round() as i32,
+                (rel_height * height as f32).round() as u32,
+            ),
+        };
+        let w = (width as i32 - left * 2).max(1) as u32;
+        Rect::at(left, top).of_size(w, h.max(1))
     }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Operation {
     Image {
         pos: Position,
         z_index: u8,
+        /// 满足条件时才绘制，见 [`Condition`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
+        /// 顺时针旋转角度（度）
+        #[serde(default)]
+        rotation_deg: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shadow: Option<Shadow>,
+        /// 平移缩放动画
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ken_burns: Option<KenBurns>,
+        /// 按动态图片（GIF、APNG，或 webm、mp4 等视频）播放，见 [`animated`](super::animated)。
+        ///
+        /// 幻灯片模式中播放整个动画，滚动模式和静态图片中 GIF、APNG 显示第一帧，视频不显示
+        #[serde(default)]
+        animate: bool,
     },
     Text {
+        /// 最大字号，放不下时自动缩小。也可以写作 [`Element::Text`] 的 `max_scale`
+        #[serde(alias = "max_scale")]
         scale: f32,
         color: Color,
         pos: Position,
         z_index: u8,
+        /// 满足条件时才绘制，见 [`Condition`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
+        /// 绘制前转换数据的格式，例如日期、千位分隔
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<Format>,
     },
     Color {
         color: Color,
         pos: Position,
         z_index: u8,
+        /// 满足条件时才绘制，见 [`Condition`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shadow: Option<Shadow>,
+        /// 不透明度，0.0 ~ 1.0。半透明的色块与下层的元素混合，例如给图片蒙上一层颜色
+        #[serde(default = "default_opacity", skip_serializing_if = "is_opaque")]
+        opacity: f32,
+    },
+    /// 九宫格边框，所有 slide 使用同一张图片，不消耗数据列
+    Frame {
+        path: PathBuf,
+        insets: [u32; 4],
+        pos: Position,
+        z_index: u8,
+        /// 满足条件时才绘制，见 [`Condition`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
+    },
+    /// 表格，按行依次从数据中读取 `rows * column_widths.len()` 个单元格
+    Table {
+        #[serde(default)]
+        header: Vec<String>,
+        column_widths: Vec<f32>,
+        rows: u32,
+        style: TableStyle,
+        pos: Position,
+        z_index: u8,
+        /// 满足条件时才绘制，见 [`Condition`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
+    },
+    /// 柱状图，每个标签从数据中读取一个数值（见 `parse_chart_value`）
+    BarChart {
+        labels: Vec<String>,
+        style: ChartStyle,
+        pos: Position,
+        z_index: u8,
+        /// 满足条件时才绘制，见 [`Condition`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
+    },
+    /// 雷达图，每个标签从数据中读取一个数值（见 `parse_chart_value`）
+    RadarChart {
+        labels: Vec<String>,
+        style: ChartStyle,
+        pos: Position,
+        z_index: u8,
+        /// 满足条件时才绘制，见 [`Condition`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
+    },
+    /// 列表，从数据中读取一个单元格，按 `separator` 拆分后依次排列，最多 `max` 项，
+    /// 用于作品、曾用名等长度不固定的数据
+    Repeat {
+        item_template: RepeatItem,
+        #[serde(default)]
+        direction: RepeatDirection,
+        #[serde(default)]
+        gap: u32,
+        max: u32,
+        #[serde(default = "default_separator")]
+        separator: String,
+        /// 列表项超过 `max` 时最后一个格子显示剩余的项数，`None` 时直接截断
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        more: Option<MoreLabel>,
+        pos: Position,
+        z_index: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
+    },
+    /// 日期，从数据中读取一个单元格（写法见 [`Format::Date`]），统一显示为日期或 “3年前”，
+    /// 用于按时间排列的视频
+    Timestamp {
+        #[serde(default)]
+        display: DateDisplay,
+        #[serde(default)]
+        locale: Locale,
+        /// 相对日期的基准日期（年、月、日），`None` 时为生成视频的当天
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reference: Option<(u16, u8, u8)>,
+        scale: f32,
+        color: Color,
+        pos: Position,
+        z_index: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<Condition>,
     },
 }
 
 impl Operation {
-    fn z_index(&self) -> u8 {
+    pub fn z_index(&self) -> u8 {
         match self {
             Operation::Image { z_index, .. } => *z_index,
             Operation::Text { z_index, .. } => *z_index,
             Operation::Color { z_index, .. } => *z_index,
+            Operation::Frame { z_index, .. } => *z_index,
+            Operation::Table { z_index, .. } => *z_index,
+            Operation::BarChart { z_index, .. } => *z_index,
+            Operation::RadarChart { z_index, .. } => *z_index,
+            Operation::Repeat { z_index, .. } => *z_index,
+            Operation::Timestamp { z_index, .. } => *z_index,
+        }
+    }
+
+    pub fn z_index_mut(&mut self) -> &mut u8 {
+        match self {
+            Operation::Image { z_index, .. } => z_index,
+            Operation::Text { z_index, .. } => z_index,
+            Operation::Color { z_index, .. } => z_index,
+            Operation::Frame { z_index, .. } => z_index,
+            Operation::Table { z_index, .. } => z_index,
+            Operation::BarChart { z_index, .. } => z_index,
+            Operation::RadarChart { z_index, .. } => z_index,
+            Operation::Repeat { z_index, .. } => z_index,
+            Operation::Timestamp { z_index, .. } => z_index,
+        }
+    }
+
+    pub fn pos(&self) -> &Position {
+        match self {
+            Operation::Image { pos, .. } => pos,
+            Operation::Text { pos, .. } => pos,
+            Operation::Color { pos, .. } => pos,
+            Operation::Frame { pos, .. } => pos,
+            Operation::Table { pos, .. } => pos,
+            Operation::BarChart { pos, .. } => pos,
+            Operation::RadarChart { pos, .. } => pos,
+            Operation::Repeat { pos, .. } => pos,
+            Operation::Timestamp { pos, .. } => pos,
+        }
+    }
+
+    pub fn pos_mut(&mut self) -> &mut Position {
+        match self {
+            Operation::Image { pos, .. } => pos,
+            Operation::Text { pos, .. } => pos,
+            Operation::Color { pos, .. } => pos,
+            Operation::Frame { pos, .. } => pos,
+            Operation::Table { pos, .. } => pos,
+            Operation::BarChart { pos, .. } => pos,
+            Operation::RadarChart { pos, .. } => pos,
+            Operation::Repeat { pos, .. } => pos,
+            Operation::Timestamp { pos, .. } => pos,
+        }
+    }
+
+    pub fn condition(&self) -> Option<&Condition> {
+        match self {
+            Operation::Image { condition, .. } => condition.as_ref(),
+            Operation::Text { condition, .. } => condition.as_ref(),
+            Operation::Color { condition, .. } => condition.as_ref(),
+            Operation::Frame { condition, .. } => condition.as_ref(),
+            Operation::Table { condition, .. } => condition.as_ref(),
+            Operation::BarChart { condition, .. } => condition.as_ref(),
+            Operation::RadarChart { condition, .. } => condition.as_ref(),
+            Operation::Repeat { condition, .. } => condition.as_ref(),
+            Operation::Timestamp { condition, .. } => condition.as_ref(),
+        }
+    }
+
+    /// 比较所有字段是否相同（`PartialEq` 只比较 `z_index`，用于排序）
+    pub fn same_as(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Operation::Image {
+                    pos,
+                    z_index,
+                    condition,
+                    rotation_deg,
+                    shadow,
+                    ken_burns,
+                    animate,
+                },
+                Operation::Image {
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                    rotation_deg: rotation_deg2,
+                    shadow: shadow2,
+                    ken_burns: ken_burns2,
+                    animate: animate2,
+                },
+            ) => {
+                pos == pos2
+                    && z_index == z_index2
+                    && condition == condition2
+                    && rotation_deg == rotation_deg2
+                    && shadow == shadow2
+                    && ken_burns == ken_burns2
+                    && animate == animate2
+            }
+            (
+                Operation::Text {
+                    scale,
+                    color,
+                    pos,
+                    z_index,
+                    condition,
+                    format,
+                },
+                Operation::Text {
+                    scale: scale2,
+                    color: color2,
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                    format: format2,
+                },
+            ) => {
+                scale == scale2
+                    && color == color2
+                    && pos == pos2
+                    && z_index == z_index2
+                    && condition == condition2
+                    && format == format2
+            }
+            (
+                Operation::Color {
+                    color,
+                    pos,
+                    z_index,
+                    condition,
+                    shadow,
+                    opacity,
+                },
+                Operation::Color {
+                    color: color2,
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                    shadow: shadow2,
+                    opacity: opacity2,
+                },
+            ) => {
+                color == color2
+                    && pos == pos2
+                    && z_index == z_index2
+                    && condition == condition2
+                    && shadow == shadow2
+                    && opacity == opacity2
+            }
+            (
+                Operation::Frame {
+                    path,
+                    insets,
+                    pos,
+                    z_index,
+                    condition,
+                },
+                Operation::Frame {
+                    path: path2,
+                    insets: insets2,
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                },
+            ) => {
+                path == path2
+                    && insets == insets2
+                    && pos == pos2
+                    && z_index == z_index2
+                    && condition == condition2
+            }
+            (
+                Operation::Table {
+                    header,
+                    column_widths,
+                    rows,
+                    style,
+                    pos,
+                    z_index,
+                    condition,
+                },
+                Operation::Table {
+                    header: header2,
+                    column_widths: column_widths2,
+                    rows: rows2,
+                    style: style2,
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                },
+            ) => {
+                header == header2
+                    && column_widths == column_widths2
+                    && rows == rows2
+                    && style == style2
+                    && pos == pos2
+                    && z_index == z_index2
+                    && condition == condition2
+            }
+            (
+                Operation::BarChart {
+                    labels,
+                    style,
+                    pos,
+                    z_index,
+                    condition,
+                },
+                Operation::BarChart {
+                    labels: labels2,
+                    style: style2,
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                },
+            )
+            | (
+                Operation::RadarChart {
+                    labels,
+                    style,
+                    pos,
+                    z_index,
+                    condition,
+                },
+                Operation::RadarChart {
+                    labels: labels2,
+                    style: style2,
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                },
+            ) => {
+                labels == labels2
+                    && style == style2
+                    && pos == pos2
+                    && z_index == z_index2
+                    && condition == condition2
+            }
+            (
+                Operation::Repeat {
+                    item_template,
+                    direction,
+                    gap,
+                    max,
+                    separator,
+                    more,
+                    pos,
+                    z_index,
+                    condition,
+                },
+                Operation::Repeat {
+                    item_template: item_template2,
+                    direction: direction2,
+                    gap: gap2,
+                    max: max2,
+                    separator: separator2,
+                    more: more2,
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                },
+            ) => {
+                item_template == item_template2
+                    && direction == direction2
+                    && gap == gap2
+                    && max == max2
+                    && separator == separator2
+                    && more == more2
+                    && pos == pos2
+                    && z_index == z_index2
+                    && condition == condition2
+            }
+            (
+                Operation::Timestamp {
+                    display,
+                    locale,
+                    reference,
+                    scale,
+                    color,
+                    pos,
+                    z_index,
+                    condition,
+                },
+                Operation::Timestamp {
+                    display: display2,
+                    locale: locale2,
+                    reference: reference2,
+                    scale: scale2,
+                    color: color2,
+                    pos: pos2,
+                    z_index: z_index2,
+                    condition: condition2,
+                },
+            ) => {
+                display == display2
+                    && locale == locale2
+                    && reference == reference2
+                    && scale == scale2
+                    && color == color2
+                    && pos == pos2
+                    && z_index == z_index2
+                    && condition == condition2
+            }
+            _ => false,
         }
     }
 }
@@ -117,7 +1190,7 @@ impl Eq for Operation {}
 
 impl PartialOrd for Operation {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.z_index().partial_cmp(&other.z_index())
+        Some(self.cmp(other))
     }
 }
 
@@ -127,40 +1200,380 @@ impl Ord for Operation {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct Slide(Vec<Element>);
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Slide {
+    elements: Vec<Element>,
+    /// slide 的宽度，`None` 时使用配置中的 `width_slides`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+    /// 从 0 增加到最终值的数字，见 [`Ranking`](super::ranking::Ranking)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    count_up: Option<CountUp>,
+}
 
 impl Slide {
     pub fn new() -> Self {
-        Self(Vec::with_capacity(8))
+        Self {
+            elements: Vec::with_capacity(8),
+            width: None,
+            count_up: None,
+        }
+    }
+
+    pub fn width(&self) -> Option<u32> {
+        self.width
+    }
+
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    /// 按动态图片播放的图片元素（不包括 [`Element::Panel`] 中的元素），返回路径和 `size` 下的像素矩形
+    pub(super) fn animated_images(&self, size: (u32, u32)) -> Vec<(&Path, Rect)> {
+        self.elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Image {
+                    path,
+                    pos,
+                    animate: true,
+                    ..
+                } => Some((path.as_path(), pos.to_rect(size))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 去掉动态图片元素后的 slide，动态图片由视频叠加，不再绘制第一帧
+    pub(super) fn without_animated(&self) -> Slide {
+        let mut slide = self.clone();
+        slide
+            .elements
+            .retain(|element| !matches!(element, Element::Image { animate: true, .. }));
+        slide
+    }
+
+    /// 所有文字元素去掉标记后的内容，每个元素一行
+    pub fn plain_text(&self) -> String {
+        let texts: Vec<String> = self
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Text { content, .. } => Some(
+                    parse_markup(content)
+                        .into_iter()
+                        .map(|span| span.text)
+                        .collect(),
+                ),
+                Element::Panel { elements, .. } => Some(
+                    Slide {
+                        elements: elements.clone(),
+                        width: None,
+                        count_up: None,
+                    }
+                    .plain_text(),
+                ),
+                _ => None,
+            })
+            .collect();
+        texts.join("\n")
+    }
+
+    pub fn with_width(mut self, width: u32) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn count_up(&self) -> Option<&CountUp> {
+        self.count_up.as_ref()
+    }
+
+    pub(super) fn set_count_up(&mut self, count_up: CountUp) {
+        self.count_up = Some(count_up);
+    }
+
+    /// 数字动画各步的画面：不含数字的 slide 在数字区域中的部分，再绘制该步的数字。
+    ///
+    /// 画面是不透明的（slide 在该区域不透明时），叠加后遮住静态画面中的最终数字，
+    /// 也会遮住在数字之后绘制的元素。没有数字动画时为 `None`。
+    ///
+    /// # Errors
+    /// - `count_up` 指向的元素不是文字，或渲染失败时返回 `Err`。
+    ///
+    pub fn count_up_frames(
+        &self,
+        size: (u32, u32),
+        ctx: &RenderContext,
+    ) -> Result<Option<CountUpFrames>> {
+        let Some(count_up) = &self.count_up else {
+            return Ok(None);
+        };
+        let Some(Element::Text {
+            max_scale,
+            color,
+            pos,
+            ..
+        }) = self
+            .resolved_elements(size, ctx)?
+            .get(count_up.element)
+            .cloned()
+        else {
+            return Err(format!("count_up element {} is not a text", count_up.element).into());
+        };
+        let mut base = self.clone();
+        base.elements.remove(count_up.element);
+        base.count_up = None;
+        let background = base.render(size, ctx)?;
+
+        let rect = pos.to_rect(size);
+        let (x, y) = (rect.left().max(0) as u32, rect.top().max(0) as u32);
+        let (w, h) = (
+            rect.width().min(size.0.saturating_sub(x)).max(1),
+            rect.height().min(size.1.saturating_sub(y)).max(1),
+        );
+        let frames = count_up
+            .values()
+            .map(|content| {
+                let mut img = background.clone();
+                Element::Text {
+                    content,
+                    max_scale,
+                    color,
+                    pos,
+                }
+                .render(&mut img, size, ctx)?;
+                Ok(img.crop_imm(x, y, w, h))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(CountUpFrames {
+            pos: (x, y),
+            frames,
+        }))
+    }
+
+    /// slide 引用的图片文件
+    pub fn asset_paths(&self) -> impl Iterator<Item = &Path> {
+        self.elements.iter().flat_map(Element::asset_paths)
+    }
+
+    /// slide 中各文字元素在 slide 尺寸为 `size` 时的排版结果，以及元素在 slide 中的序号
+    pub fn text_fits(&self, size: (u32, u32), ctx: &RenderContext) -> Vec<(usize, FittedText)> {
+        self.elements
+            .iter()
+            .enumerate()
+            .filter_map(|(index, element)| match element {
+                Element::Text {
+                    content,
+                    max_scale,
+                    pos,
+                    ..
+                } => {
+                    let rect = pos.to_rect(size);
+                    let size = (rect.width(), rect.height());
+                    Some((index, fit_text(content, *max_scale, size, ctx)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// slide 中带有平移缩放动画的图片在 slide 尺寸为 `size` 时的位置和大小
+    pub fn ken_burns_images(
+        &self,
+        size: (u32, u32),
+        ctx: &RenderContext,
+    ) -> Result<Vec<KenBurnsImage>> {
+        let mut images = Vec::new();
+        for element in &self.elements {
+            let Element::Image {
+                path,
+                pos,
+                ken_burns: Some(ken_burns),
+                ..
+            } = element
+            else {
+                continue;
+            };
+            // 与静态渲染时的位置相同
+            let rect = pos.to_rect(size);
+            let (img_w, img_h) = ctx
+                .images
+                .load(path, (rect.width(), rect.height()))?
+                .dimensions();
+            let (x, y) = (
+                rect.left() as u32 + (img_w.abs_diff(rect.width())) / 2,
+                rect.top() as u32 + (img_h.abs_diff(rect.height())) / 2,
+            );
+            images.push(KenBurnsImage {
+                path: path.clone(),
+                ken_burns: *ken_burns,
+                pos: (x, y),
+                size: (img_w, img_h),
+            });
+        }
+        Ok(images)
     }
+
     pub fn generation(operations: &[Operation], datas: Vec<String>) -> Result<Self> {
+        let visible: Vec<bool> = operations
+            .iter()
+            .map(|op| op.condition().is_none_or(|c| c.eval(&datas)))
+            .collect();
         let mut data = datas.into_iter();
         let elements = operations
             .iter()
             .map(|op| match op {
-                Operation::Image { pos, .. } => Ok(Element::Image {
-                    path: PathBuf::from(data.next().ok_or(format!("图片数据不足"))?),
+                Operation::Image {
+                    pos,
+                    rotation_deg,
+                    shadow,
+                    ken_burns,
+                    animate,
+                    ..
+                } => Ok(Element::Image {
+                    path: PathBuf::from(data.next().ok_or("图片数据不足")?),
                     pos: *pos,
+                    rotation_deg: *rotation_deg,
+                    shadow: *shadow,
+                    ken_burns: *ken_burns,
+                    animate: *animate,
                 }),
                 Operation::Text {
-                    scale, color, pos, ..
+                    scale,
+                    color,
+                    pos,
+                    format,
+                    ..
                 } => Ok(Element::Text {
-                    content: data.next().take().ok_or(format!("文本数据不足"))?,
+                    content: data
+                        .next()
+                        .map(|cell| match format {
+                            Some(format) => format.apply(&cell),
+                            None => cell,
+                        })
+                        .ok_or("文本数据不足")?,
                     max_scale: *scale,
                     color: *color,
                     pos: *pos,
                 }),
-                Operation::Color { color, pos, .. } => Ok(Element::Color {
+                Operation::Color {
+                    color,
+                    pos,
+                    shadow,
+                    opacity,
+                    ..
+                } => Ok(Element::Color {
+                    color: *color,
+                    pos: *pos,
+                    shadow: *shadow,
+                    opacity: *opacity,
+                }),
+                Operation::Frame {
+                    path, insets, pos, ..
+                } => Ok(Element::Frame {
+                    path: path.clone(),
+                    insets: *insets,
+                    pos: *pos,
+                }),
+                Operation::Table {
+                    header,
+                    column_widths,
+                    rows,
+                    style,
+                    pos,
+                    ..
+                } => Ok(Element::Table {
+                    header: header.clone(),
+                    cells: (0..*rows)
+                        .map(|_| {
+                            (0..column_widths.len())
+                                .map(|_| data.next().ok_or("表格数据不足"))
+                                .collect()
+                        })
+                        .collect::<std::result::Result<_, _>>()?,
+                    column_widths: column_widths.clone(),
+                    style: *style,
+                    pos: *pos,
+                }),
+                Operation::BarChart {
+                    labels, style, pos, ..
+                } => Ok(Element::BarChart {
+                    labels: labels.clone(),
+                    values: chart_values(&mut data, labels.len())?,
+                    style: *style,
+                    pos: *pos,
+                }),
+                Operation::RadarChart {
+                    labels, style, pos, ..
+                } => Ok(Element::RadarChart {
+                    labels: labels.clone(),
+                    values: chart_values(&mut data, labels.len())?,
+                    style: *style,
+                    pos: *pos,
+                }),
+                Operation::Repeat {
+                    item_template,
+                    direction,
+                    gap,
+                    max,
+                    separator,
+                    more,
+                    pos,
+                    ..
+                } => {
+                    if *max == 0 || separator.is_empty() {
+                        return Err("列表的 max 必须大于 0，separator 不能为空".into());
+                    }
+                    let cell = data.next().ok_or("列表数据不足")?;
+                    let (items, more) = match more {
+                        Some(label) => {
+                            label.truncate(split_items(&cell, separator, u32::MAX), *max)
+                        }
+                        None => (split_items(&cell, separator, *max), None),
+                    };
+                    Ok(Element::Repeat {
+                        items,
+                        item: *item_template,
+                        direction: *direction,
+                        gap: *gap,
+                        max: *max,
+                        pos: *pos,
+                        more,
+                    })
+                }
+                Operation::Timestamp {
+                    display,
+                    locale,
+                    reference,
+                    scale,
+                    color,
+                    pos,
+                    ..
+                } => Ok(Element::Text {
+                    content: format_timestamp(
+                        &data.next().ok_or("日期数据不足")?,
+                        *display,
+                        *locale,
+                        reference.unwrap_or_else(today),
+                    ),
+                    max_scale: *scale,
                     color: *color,
                     pos: *pos,
                 }),
             })
-            .collect::<Result<Vec<Element>>>()?;
-        Ok(Self(elements))
+            .collect::<Result<Vec<Element>>>()?
+            .into_iter()
+            .zip(visible)
+            .filter_map(|(element, visible)| visible.then_some(element))
+            .collect();
+        Ok(Self {
+            elements,
+            width: None,
+            count_up: None,
+        })
     }
     pub fn add_text(&mut self, str: &str, max_scale: f32, color: Color, pos: Position) {
-        self.0.push(Element::Text {
+        self.elements.push(Element::Text {
             content: str.to_string(),
             max_scale,
             color,
@@ -168,34 +1581,223 @@ impl Slide {
         });
     }
     pub fn add_image(&mut self, image_path: impl AsRef<Path>, pos: Position) {
-        self.0.push(Element::Image {
+        self.elements.push(Element::Image {
             path: image_path.as_ref().to_path_buf(),
             pos,
+            rotation_deg: 0.0,
+            shadow: None,
+            ken_burns: None,
+            animate: false,
         });
     }
+    /// 把 `panel` 的元素作为一栏加入 slide，见 [`Element::Panel`]
+    pub fn add_panel(&mut self, panel: Slide, rel_left: f32, rel_width: f32, mirror_images: bool) {
+        self.elements.push(Element::Panel {
+            elements: panel.elements,
+            rel_left,
+            rel_width,
+            mirror_images,
+        });
+    }
+
     pub fn add_color(&mut self, color: Color, pos: Position) {
-        self.0.push(Element::Color { color, pos });
+        self.elements.push(Element::Color {
+            color,
+            pos,
+            shadow: None,
+            opacity: 1.0,
+        });
     }
 }
 
+/// 提取图片主要颜色时使用的缩略图大小
+const PALETTE_SAMPLE_SIZE: (u32, u32) = (64, 64);
+
+/// 最多提取的主要颜色数，色块更多时循环使用
+const MAX_PALETTE_COLORS: usize = 8;
+
 impl Slide {
-    pub fn render(
+    /// 按 `ctx` 的选项调整后实际绘制的元素。
+    ///
+    /// 启用 [`RenderContext::auto_palette`] 时，各色块依次使用第一张图片中按像素数排列的主要颜色；
+    /// [`RenderContext::text_contrast`] 为 `Auto` 时，与下方色块对比度不足的文字改为黑色或白色。
+    ///
+    /// # Errors
+    /// - 启用 `auto_palette` 且图片无法读取时返回 `Err`。
+    ///
+    pub fn resolved_elements(
         &self,
         size: (u32, u32),
-        font: &FontArc,
-        split_line_color: Option<Color>,
-    ) -> Result<DynamicImage> {
+        ctx: &RenderContext,
+    ) -> Result<Cow<'_, [Element]>> {
+        let mut elements = Cow::Borrowed(self.elements.as_slice());
+        if let Some(palette) = self.palette(ctx)? {
+            let cards = elements
+                .to_mut()
+                .iter_mut()
+                .filter_map(|element| match element {
+                    Element::Color { color, .. } => Some(color),
+                    _ => None,
+                });
+            for (color, rgb) in cards.zip(palette.iter().cycle()) {
+                *color = Color(*rgb);
+            }
+        }
+        if ctx.text_contrast == TextContrast::Auto {
+            for low in low_contrast(&elements, size) {
+                if let Element::Text { color, .. } = &mut elements.to_mut()[low.element] {
+                    let background = low.background;
+                    *color =
+                        if background.contrast_ratio(&BLACK) >= background.contrast_ratio(&WHITE) {
+                            BLACK
+                        } else {
+                            WHITE
+                        };
+                }
+            }
+        }
+        Ok(elements)
+    }
+
+    /// 启用 `auto_palette` 时第一张图片的主要颜色，没有图片或色块时为 `None`
+    fn palette(&self, ctx: &RenderContext) -> Result<Option<Vec<[u8; 3]>>> {
+        let image = self.elements.iter().find_map(|element| match element {
+            Element::Image { path, .. } if !is_video(path) => Some(path),
+            _ => None,
+        });
+        let colors = self
+            .elements
+            .iter()
+            .filter(|element| matches!(element, Element::Color { .. }))
+            .count();
+        let (true, Some(path), 1..) = (ctx.auto_palette, image, colors) else {
+            return Ok(None);
+        };
+        let sample = ctx.images.load(path, PALETTE_SAMPLE_SIZE)?.to_rgba8();
+        let palette = dominant_colors(&sample, colors.min(MAX_PALETTE_COLORS));
+        Ok((!palette.is_empty()).then_some(palette))
+    }
+
+    /// slide 尺寸为 `size` 时与下方色块对比度低于 [`MIN_TEXT_CONTRAST`] 的文字，
+    /// 按实际绘制的元素（见 [`resolved_elements`](Self::resolved_elements)）检查。
+    ///
+    /// # Errors
+    /// - 启用 `auto_palette` 且图片无法读取时返回 `Err`。
+    ///
+    pub fn low_contrast_texts(
+        &self,
+        size: (u32, u32),
+        ctx: &RenderContext,
+    ) -> Result<Vec<LowContrast>> {
+        if ctx.text_contrast == TextContrast::Ignore {
+            return Ok(Vec::new());
+        }
+        Ok(low_contrast(&self.resolved_elements(size, ctx)?, size))
+    }
+
+    pub fn render(&self, size: (u32, u32), ctx: &RenderContext) -> Result<DynamicImage> {
+        let mut img = DynamicImage::new_rgba8(size.0, size.1);
+        self.render_into(&mut img, 0, size, ctx)?;
+        Ok(img)
+    }
+
+    /// 把 slide 直接绘制到 `target` 中左上角为 `(x, 0)`、大小为 `size` 的区域，
+    /// 不另外分配整张 slide 的图片。`target` 中该区域应为透明，超出区域的部分被裁掉。
+    ///
+    /// # Errors
+    /// - 区域超出 `target` 时返回 `Err`。
+    pub fn render_into(
+        &self,
+        target: &mut impl GenericImage<Pixel = Rgba<u8>>,
+        x: u32,
+        size: (u32, u32),
+        ctx: &RenderContext,
+    ) -> Result<()> {
         let (width, height) = size;
-        let mut img = DynamicImage::new_rgba8(width, height);
-        for element in &self.0 {
-            element.render(&mut img, width, font)?;
+        if u64::from(x) + u64::from(width) > u64::from(target.width()) || height > target.height() {
+            return Err(format!(
+                "slide region {width}x{height} at x={x} out of target {}x{}",
+                target.width(),
+                target.height()
+            )
+            .into());
+        }
+        let mut view = target.sub_image(x, 0, width, height);
+        let img = &mut *view;
+        for element in self.resolved_elements(size, ctx)?.iter() {
+            element.render(img, size, ctx)?;
         }
         // 绘制分割线
-        if let Some(color) = split_line_color {
-            img.draw_line_segment_mut((0.0, 0.0), (0.0, height as f32), color.into());
+        if let Some(color) = ctx.split_line_color {
+            // 线条位于 slide 左边缘内侧，不会被相邻 slide 覆盖
+            let x = (ctx.split_line_width as f32 - 1.0).max(0.0) / 2.0;
+            img.draw_line_segment_with_width_mut(
+                (x, 0.0),
+                (x, height as f32),
+                ctx.split_line_width as f32,
+                color.into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 与下方色块对比度不足的文字。
+///
+/// 文字的背景为在它之前绘制、覆盖文字区域中心的最后一个色块，没有这样的色块时不检查；
+/// 半透明的色块按不透明计算。
+fn low_contrast(elements: &[Element], size: (u32, u32)) -> Vec<LowContrast> {
+    let center = |rect: Rect| {
+        (
+            rect.left() + rect.width() as i32 / 2,
+            rect.top() + rect.height() as i32 / 2,
+        )
+    };
+    let mut lows = Vec::new();
+    for (index, element) in elements.iter().enumerate() {
+        let Element::Text {
+            color: text, pos, ..
+        } = element
+        else {
+            continue;
+        };
+        let (x, y) = center(pos.to_rect(size));
+        let background = elements[..index]
+            .iter()
+            .rev()
+            .find_map(|below| match below {
+                Element::Color { color, pos, .. } => {
+                    let rect = pos.to_rect(size);
+                    ((rect.left()..=rect.right()).contains(&x)
+                        && (rect.top()..=rect.bottom()).contains(&y))
+                    .then_some(*color)
+                }
+                _ => None,
+            });
+        if let Some(background) = background {
+            let ratio = text.contrast_ratio(&background);
+            if ratio < MIN_TEXT_CONTRAST {
+                lows.push(LowContrast {
+                    element: index,
+                    text: *text,
+                    background,
+                    ratio,
+                });
+            }
         }
-        Ok(img)
     }
+    lows
+}
+
+/// 从数据中读取 `count` 个图表数值
+fn chart_values(data: &mut impl Iterator<Item = String>, count: usize) -> Result<Vec<f32>> {
+    (0..count)
+        .map(|_| {
+            data.next()
+                .map(|text| parse_chart_value(&text))
+                .ok_or_else(|| "图表数据不足".into())
+        })
+        .collect()
 }
 
 impl Default for Slide {
@@ -203,3 +1805,326 @@ impl Default for Slide {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_absolute_position_to_rect() {
+        let rect = Position::new(10, 20, 100).to_rect((480, 1080));
+        assert_eq!((rect.left(), rect.top()), (10, 20));
+        assert_eq!((rect.width(), rect.height()), (460, 100));
+    }
+
+    #[test]
+    fn test_relative_position_scales_with_size() {
+        let pos = Position::relative(0.1, 0.5, 0.25);
+        let rect = pos.to_rect((480, 1080));
+        assert_eq!((rect.left(), rect.top()), (48, 540));
+        assert_eq!((rect.width(), rect.height()), (384, 270));
+
+        let rect = pos.to_rect((240, 540));
+        assert_eq!((rect.left(), rect.top()), (24, 270));
+        assert_eq!((rect.width(), rect.height()), (192, 135));
+    }
+
+    #[test]
+    fn test_position_serde() {
+        let abs: Position = serde_json::from_str(r#"{"left":1,"top":0,"height":520}"#).unwrap();
+        assert_eq!(abs, Position::new(1, 0, 520));
+
+        let rel: Position =
+            serde_json::from_str(r#"{"rel_left":0.0,"rel_top":0.5,"rel_height":0.2}"#).unwrap();
+        assert_eq!(rel, Position::relative(0.0, 0.5, 0.2));
+
+        let json = serde_json::to_string(&Position::new(1, 0, 520)).unwrap();
+        assert_eq!(json, r#"{"left":1,"top":0,"height":520}"#);
+    }
+
+    #[test]
+    fn test_generation_condition() {
+        let condition: Condition =
+            serde_json::from_str(r#"{"test":"non_empty","column":1}"#).unwrap();
+        let operations = [
+            Operation::Color {
+                color: Color([0, 0, 0]),
+                pos: Position::new(0, 0, 100),
+                z_index: 0,
+                shadow: None,
+                condition: Some(condition),
+                opacity: 1.0,
+            },
+            Operation::Text {
+                scale: 60.0,
+                color: Color([255, 255, 255]),
+                pos: Position::new(0, 0, 100),
+                z_index: 1,
+                condition: None,
+                format: None,
+            },
+            Operation::Text {
+                scale: 60.0,
+                color: Color([255, 255, 255]),
+                pos: Position::new(0, 0, 100),
+                z_index: 2,
+                condition: Some(Condition::NonEmpty { column: 1 }),
+                format: None,
+            },
+        ];
+        let data = |skin: &str| vec!["name".to_string(), skin.to_string()];
+
+        assert_eq!(
+            Slide::generation(&operations, data("skin"))
+                .unwrap()
+                .elements
+                .len(),
+            3
+        );
+        // 隐藏的文本仍然消耗第 1 列
+        let slide = Slide::generation(&operations, data(" ")).unwrap();
+        assert_eq!(slide.elements.len(), 1);
+        assert!(matches!(&slide.elements[0], Element::Text { content, .. } if content == "name"));
+
+        let equals = Condition::Equals {
+            column: 0,
+            value: "name".to_string(),
+        };
+        assert!(equals.eval(&data("")));
+        assert!(
+            !Condition::NotEquals {
+                column: 5,
+                value: String::new()
+            }
+            .eval(&data(""))
+        );
+    }
+
+    fn test_context() -> RenderContext {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../example/MiSans-Demibold.ttf"
+        );
+        RenderContext::new(ab_glyph::FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_fit_text_min_scale() {
+        let ctx = RenderContext {
+            min_text_scale: 40.0,
+            ..test_context()
+        };
+        let text = "一段很长很长很长很长很长的说明文字";
+
+        let short = fit_text("短", 60.0, (400, 200), &ctx);
+        assert_eq!((short.scale, short.below_min), (60.0, false));
+
+        let shrunk = fit_text(text, 60.0, (400, 200), &ctx);
+        assert!(shrunk.scale < 40.0 && shrunk.below_min && !shrunk.wrapped);
+
+        let ctx = RenderContext {
+            text_overflow: TextOverflow::Wrap,
+            ..ctx
+        };
+        let wrapped = fit_text(text, 60.0, (400, 200), &ctx);
+        assert!(wrapped.wrapped && !wrapped.below_min, "{wrapped:?}");
+        assert!(wrapped.scale >= 40.0);
+        assert_eq!(wrapped.content.replace('\n', ""), text);
+
+        let ctx = RenderContext {
+            text_overflow: TextOverflow::Error,
+            ..ctx
+        };
+        let mut slide = Slide::new();
+        slide.add_text(text, 60.0, Color([0, 0, 0]), Position::new(0, 0, 200));
+        assert!(slide.render((400, 200), &ctx).is_err());
+        let fits = slide.text_fits((400, 200), &ctx);
+        assert_eq!(fits.len(), 1);
+        assert!(fits[0].1.below_min);
+    }
+
+    #[test]
+    fn test_text_scale_alias() {
+        let operation: Operation = serde_json::from_str(
+            r#"{"Text":{"max_scale":60.0,"color":[0,0,0],"pos":{"left":0,"top":0,"height":100},"z_index":1}}"#,
+        )
+        .unwrap();
+        assert!(matches!(operation, Operation::Text { scale, .. } if scale == 60.0));
+
+        let element: Element = serde_json::from_str(
+            r#"{"Text":{"content":"a","scale":60.0,"color":[0,0,0],"pos":{"left":0,"top":0,"height":100}}}"#,
+        )
+        .unwrap();
+        assert!(matches!(element, Element::Text { max_scale, .. } if max_scale == 60.0));
+    }
+
+    #[test]
+    fn test_slide_serde_roundtrip() {
+        let mut slide = Slide::new().with_width(300);
+        slide.add_image("example/1.png", Position::new(0, 0, 200));
+        slide.add_text(
+            "<b>name</b>",
+            60.0,
+            Color([0, 0, 0]),
+            Position::new(0, 200, 100),
+        );
+        slide.add_color(Color([255, 0, 0]), Position::relative(0.0, 0.5, 0.5));
+
+        let json = serde_json::to_string(&slide).unwrap();
+        assert!(!json.contains("shadow"), "{json}");
+        let loaded: Slide = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{loaded:?}"), format!("{slide:?}"));
+        assert_eq!(loaded.width(), Some(300));
+    }
+
+    #[test]
+    fn test_translucent_color_tints() {
+        let ctx = test_context();
+        let mut slide = Slide::new();
+        slide.add_color(Color([200, 0, 0]), Position::new(0, 0, 100));
+        slide.elements.push(Element::Color {
+            color: Color([0, 0, 200]),
+            pos: Position::new(20, 20, 60),
+            shadow: None,
+            opacity: 0.5,
+        });
+        let img = slide.render((100, 100), &ctx).unwrap().into_rgba8();
+        assert_eq!(img.get_pixel(50, 50).0, [100, 0, 100, 255]);
+        // 色块外没有被覆盖
+        assert_eq!(img.get_pixel(10, 50).0, [200, 0, 0, 255]);
+
+        let json = serde_json::to_string(&slide.elements[0]).unwrap();
+        assert!(!json.contains("opacity"), "{json}");
+    }
+
+    #[test]
+    fn test_panel_renders_in_its_column() {
+        let ctx = test_context();
+        let mut panel = Slide::new();
+        panel.add_color(Color([0, 0, 200]), Position::new(10, 0, 100));
+        let mut slide = Slide::new();
+        slide.add_panel(panel, 0.5, 0.5, true);
+        let img = slide.render((200, 100), &ctx).unwrap().into_rgba8();
+        // 栏宽 100，色块在栏内左右各留 10 像素
+        assert_eq!(img.get_pixel(150, 50).0, [0, 0, 200, 255]);
+        assert_eq!(img.get_pixel(105, 50).0, [0, 0, 0, 0]);
+        assert_eq!(img.get_pixel(50, 50).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_render_into_matches_render() {
+        let ctx = RenderContext {
+            split_line_color: Some(Color([255, 255, 255])),
+            ..test_context()
+        };
+        let mut slide = Slide::new();
+        slide.add_color(Color([200, 0, 0]), Position::new(10, 10, 80));
+        slide.add_text("文字", 40.0, Color([0, 0, 0]), Position::new(10, 100, 60));
+        let size = (120, 200);
+        let img = slide.render(size, &ctx).unwrap();
+
+        let mut target = DynamicImage::new_rgba8(360, 200);
+        slide.render_into(&mut target, 120, size, &ctx).unwrap();
+        assert_eq!(target.crop_imm(120, 0, 120, 200).to_rgba8(), img.to_rgba8());
+        assert!(
+            target
+                .crop_imm(0, 0, 120, 200)
+                .to_rgba8()
+                .pixels()
+                .all(|p| p.0[3] == 0)
+        );
+        assert!(slide.render_into(&mut target, 300, size, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_auto_palette() {
+        let path = std::env::temp_dir().join(format!("auto_palette_{}.png", std::process::id()));
+        RgbaImage::from_fn(64, 64, |x, _| match x {
+            0..48 => Rgba([30, 60, 200, 255]),
+            _ => Rgba([240, 200, 20, 255]),
+        })
+        .save(&path)
+        .unwrap();
+        let mut slide = Slide::new();
+        slide.add_image(&path, Position::new(0, 0, 100));
+        slide.add_color(Color([0, 0, 0]), Position::new(0, 100, 50));
+        slide.add_text("name", 40.0, Color([0, 0, 0]), Position::new(0, 100, 50));
+        slide.add_color(Color([0, 0, 0]), Position::new(0, 150, 50));
+
+        let colors = |ctx: &RenderContext| -> Vec<Color> {
+            slide
+                .resolved_elements((100, 200), ctx)
+                .unwrap()
+                .iter()
+                .filter_map(|element| match element {
+                    Element::Color { color, .. } => Some(*color),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(colors(&test_context()), [Color([0, 0, 0]); 2]);
+        let ctx = RenderContext {
+            auto_palette: true,
+            ..test_context()
+        };
+        let palette = colors(&ctx);
+        std::fs::remove_file(&path).unwrap();
+        // 像素最多的颜色在前
+        let close = |a: Color, b: [u8; 3]| a.iter().zip(b).all(|(a, b)| a.abs_diff(b) <= 8);
+        assert!(close(palette[0], [30, 60, 200]), "{palette:?}");
+        assert!(close(palette[1], [240, 200, 20]), "{palette:?}");
+    }
+
+    #[test]
+    fn test_low_contrast_texts() {
+        let mut slide = Slide::new();
+        slide.add_color(Color([250, 240, 200]), Position::new(0, 0, 100));
+        slide.add_text(
+            "light",
+            40.0,
+            Color([255, 255, 255]),
+            Position::new(0, 20, 60),
+        );
+        slide.add_color(Color([20, 20, 60]), Position::new(0, 100, 100));
+        slide.add_text(
+            "dark",
+            40.0,
+            Color([255, 255, 255]),
+            Position::new(0, 120, 60),
+        );
+        // 没有色块的文字不检查
+        slide.add_text(
+            "free",
+            40.0,
+            Color([255, 255, 255]),
+            Position::new(0, 220, 60),
+        );
+        let size = (200, 300);
+
+        let lows = slide.low_contrast_texts(size, &test_context()).unwrap();
+        assert_eq!(lows.len(), 1);
+        assert_eq!(lows[0].element, 1);
+        assert!(lows[0].ratio < MIN_TEXT_CONTRAST);
+
+        let ignore = RenderContext {
+            text_contrast: TextContrast::Ignore,
+            ..test_context()
+        };
+        assert!(slide.low_contrast_texts(size, &ignore).unwrap().is_empty());
+
+        let auto = RenderContext {
+            text_contrast: TextContrast::Auto,
+            ..test_context()
+        };
+        assert!(slide.low_contrast_texts(size, &auto).unwrap().is_empty());
+        let elements = slide.resolved_elements(size, &auto).unwrap();
+        let Element::Text { color, .. } = &elements[1] else {
+            panic!("expected Text");
+        };
+        assert_eq!(*color, BLACK);
+        let Element::Text { color, .. } = &elements[3] else {
+            panic!("expected Text");
+        };
+        assert_eq!(*color, WHITE);
+    }
+}
@@ -0,0 +1,253 @@
+//! 幻灯片模式：不滚动，每页显示一个（或几个并排的）slide，停留固定的秒数后切换到下一页。
+//!
+//! 与滚动模式使用相同的数据绑定和 slide 布局，每页生成一段视频，
+//! 切换效果包含在后一页的视频开头，因此各段视频仍然可以直接合并。
+
+use super::{
+    Renderer, Video,
+    chunk::Chunk,
+    encoder::{EncodeSegment, SegmentPlan, VideoEncoder},
+    ffmpeg::combain_rendered_slides,
+    report::RenderReport,
+    scale_image,
+    scroll::FrameClock,
+    slide::Slide,
+};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// 视频的呈现方式
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Presentation {
+    /// 所有 slide 组成长条图，从封面开始滚动
+    #[default]
+    Scroll,
+    /// 每页铺满屏幕，停留 `sec_per_slide` 秒。
+    ///
+    /// 每页并排显示 `per_screen` 个 slide，每个 slide 的宽度为屏幕宽度除以 `per_screen`，
+    /// 忽略 `width_slides` 和单独设置的宽度。不生成封面，最后一页额外停留 `ending_sec` 秒。
+    Slideshow {
+        sec_per_slide: f32,
+        #[serde(default = "default_per_screen")]
+        per_screen: u32,
+        #[serde(default)]
+        transition: Transition,
+        /// 切换效果的秒数，包含在后一页的停留时间内
+        #[serde(default = "default_transition_sec")]
+        transition_sec: f32,
+    },
+}
+
+fn default_per_screen() -> u32 {
+    1
+}
+
+fn default_transition_sec() -> f32 {
+    0.5
+}
+
+/// 幻灯片模式中两页之间的切换效果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    /// 直接切换
+    Cut,
+    /// 交叉淡入淡出
+    #[default]
+    Fade,
+    Dissolve,
+    /// 新的一页从右侧推入
+    SlideLeft,
+    /// 新的一页从下方推入
+    SlideUp,
+    WipeLeft,
+}
+
+impl Transition {
+    /// ffmpeg `xfade` 滤镜的效果名称，直接切换时为 `None`
+    pub fn xfade(self) -> Option<&'static str> {
+        match self {
+            Transition::Cut => None,
+            Transition::Fade => Some("fade"),
+            Transition::Dissolve => Some("dissolve"),
+            Transition::SlideLeft => Some("slideleft"),
+            Transition::SlideUp => Some("slideup"),
+            Transition::WipeLeft => Some("wipeleft"),
+        }
+    }
+}
+
+/// 把 slide 按每页 `per_screen` 个分页，返回的 chunk 互不重叠，滚动距离为 0
+pub(super) fn pages(slides: Vec<Slide>, per_screen: u32, page_width: u32) -> Vec<Chunk> {
+    let per_screen = per_screen.max(1) as usize;
+    let slides: Vec<_> = slides
+        .into_iter()
+        .map(|slide| slide.with_width(page_width))
+        .collect();
+    slides
+        .chunks(per_screen)
+        .enumerate()
+        .map(|(index, page)| Chunk {
+            offset: (index * per_screen) as u32 * page_width,
+            first_slide: index * per_screen,
+            slides: page.to_vec(),
+            distance: 0,
+            keyframes: None,
+        })
+        .collect()
+}
+
+impl Video {
+    /// 幻灯片模式的 [`run_with`](Self::run_with)
+    pub(super) fn run_slideshow<F>(
+        mut self,
+        encoder: &mut dyn VideoEncoder,
+        handle_progress: F,
+    ) -> Result<RenderReport>
+    where
+        F: Fn(&Path, usize, usize) -> std::result::Result<(), String>,
+    {
+        let pages_len = self.chunks.len();
+        let mut narrator = self.take_narrator();
+        let renderer = Renderer::new(&self.config, &self.rows)?;
+        let mut report = self.checked_text_report(&renderer.ctx)?;
+        encoder.begin(&renderer.work_dir)?;
+        let mut clock = FrameClock::new(self.config.fps);
+        let mut videos = Vec::with_capacity(pages_len);
+        // 翻页的帧，即各页切换效果开始的位置
+        let mut boundaries = Vec::with_capacity(pages_len);
+        let mut previous = None;
+        for (index, page) in self.chunks.iter().enumerate() {
+            if index > 0 {
+                boundaries.push(clock.elapsed_frames());
+            }
+            let segment = renderer.page(index, page, pages_len, previous.take(), &mut clock)?;
+            encoder.push_segment(&segment, &renderer.work_dir)?;
+            if let SegmentPlan::Page { image, x, .. } = &segment.plan {
+                previous = Some((image.clone(), *x));
+            }
+            handle_progress(&segment.name, index + 1, pages_len)?;
+            videos.push(segment.name);
+        }
+        let narration = match narrator.as_deref_mut() {
+            Some(narrator) => self.narrate(narrator, &renderer)?,
+            None => Vec::new(),
+        };
+        let duration = clock.elapsed_frames() as f64 / f64::from(clock.fps());
+        let progress = self.progress_overlay(&renderer, duration);
+        let steps = self.post_steps(&boundaries, &narration, progress.as_ref());
+        self.finish(encoder, &videos, &renderer.work_dir, &steps)?;
+
+        if self.config.clean_temp && !renderer.ffmpeg.dry_run {
+            fs::remove_dir_all(&renderer.work_dir)?;
+            println!("cleanup successed");
+        }
+        report.glyph_cache = renderer.ctx.font.glyph_cache().stats();
+        Ok(report)
+    }
+}
+
+impl Renderer<'_> {
+    /// 渲染第 `index` 页的图片，返回需要编码的视频，`previous` 为上一页的图片和 x 坐标
+    fn page(
+        &self,
+        index: usize,
+        page: &Chunk,
+        pages_len: usize,
+        previous: Option<(PathBuf, u32)>,
+        clock: &mut FrameClock,
+    ) -> Result<EncodeSegment> {
+        let config = self.config;
+        let Presentation::Slideshow {
+            sec_per_slide,
+            transition,
+            transition_sec,
+            ..
+        } = config.presentation
+        else {
+            return Err("slideshow pages require the slideshow presentation".into());
+        };
+        // 动态图片在视频中叠加播放，静态图片中不绘制
+        let slides: Vec<Slide> = page.slides.iter().map(Slide::without_animated).collect();
+        let rendered = self.prerender(&slides, &[])?;
+        let img = combain_rendered_slides(
+            &slides,
+            &rendered,
+            &self.ctx,
+            config.width_slides,
+            config.screen,
+            self.background,
+        )?;
+        let img = scale_image(img, self.scale, self.out_screen.1);
+        // 最后一页不足 per_screen 个 slide 时居中显示
+        let x = self.out_screen.0.saturating_sub(img.width()) / 2;
+        let animated = self.animated_overlays(page, x)?;
+        let image = PathBuf::from(format!("page_{index:0>3}.png"));
+        img.save(self.work_dir.join(&image))?;
+
+        let last = index + 1 == pages_len;
+        let ending_sec = if last { config.ending_sec } else { 0 };
+        let frames = clock.advance(f64::from(sec_per_slide) + f64::from(ending_sec));
+        let fade = last
+            .then(|| {
+                let duration = frames as f64 / f64::from(config.fps);
+                config
+                    .ending
+                    .fade(duration, config.ending_sec, &config.back_color)
+            })
+            .flatten();
+        Ok(EncodeSegment {
+            name: PathBuf::from(format!("page_{index:0>3}.mp4")),
+            screen: self.out_screen,
+            orientation: self.orientation,
+            fps: config.fps,
+            frames,
+            back_color: config.back_color.clone(),
+            plan: SegmentPlan::Page {
+                image,
+                x,
+                previous,
+                animated,
+                transition,
+                transition_sec: transition_sec.min(sec_per_slide),
+                fade,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages() {
+        let pages = pages(vec![Slide::new(); 5], 2, 960);
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[1].first_slide, 2);
+        assert_eq!(pages[1].offset, 1920);
+        assert_eq!(pages[2].slides.len(), 1);
+        assert!(pages.iter().all(|page| page.distance == 0));
+        assert_eq!(pages[2].slides[0].width(), Some(960));
+    }
+
+    #[test]
+    fn test_presentation_serde() {
+        let presentation: Presentation =
+            serde_json::from_str(r#"{"Slideshow": {"sec_per_slide": 3.0}}"#).unwrap();
+        assert_eq!(
+            presentation,
+            Presentation::Slideshow {
+                sec_per_slide: 3.0,
+                per_screen: 1,
+                transition: Transition::Fade,
+                transition_sec: 0.5,
+            }
+        );
+        assert_eq!(Transition::Cut.xfade(), None);
+    }
+}
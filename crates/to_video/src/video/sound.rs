@@ -0,0 +1,103 @@
+//! 音效：在各段视频的衔接处（幻灯片模式中每次翻页，滚动模式中封面结束和每个 chunk 开始时）
+//! 混入一段短音效，例如翻页的“嗒”声或滚动的“嗖”声。
+
+use super::ffmpeg::{Ffmpeg, FfmpegCommand};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// 一段音效及其音量
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SoundEffect {
+    /// 音效文件，ffmpeg 能读取的任何音频格式
+    pub path: PathBuf,
+    /// 音量增益（dB），负数为减小音量
+    #[serde(default)]
+    pub gain_db: f32,
+}
+
+impl SoundEffect {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            gain_db: 0.0,
+        }
+    }
+
+    pub fn gain_db(mut self, gain_db: f32) -> Self {
+        self.gain_db = gain_db;
+        self
+    }
+}
+
+/// 把 `effect` 在 `times`（秒）处混入 `source` 的命令，视频流直接复制，音频编码为 AAC。
+///
+/// 每个时间点通过 `adelay` 延迟一份音效，再由 `amix` 混合为一路音频，
+/// `source` 本身的音频（如果有）被替换。`times` 不能为空。
+pub fn sound_effects_command(
+    ffmpeg: &Ffmpeg,
+    source: &Path,
+    effect: &SoundEffect,
+    times: &[f64],
+    output: &Path,
+    metadata: &HashMap<String, String>,
+) -> FfmpegCommand {
+    let count = times.len();
+    let mut filters = format!("[1]volume={}dB,asplit={count}", effect.gain_db);
+    for i in 0..count {
+        filters.push_str(&format!("[s{i}]"));
+    }
+    for (i, time) in times.iter().enumerate() {
+        let ms = (time * 1000.0).round() as u64;
+        filters.push_str(&format!(";[s{i}]adelay=delays={ms}:all=1[d{i}]"));
+    }
+    filters.push(';');
+    for i in 0..count {
+        filters.push_str(&format!("[d{i}]"));
+    }
+    filters.push_str(&format!(
+        "amix=inputs={count}:duration=longest:normalize=0[sfx]"
+    ));
+    ffmpeg
+        .command()
+        .input(source)
+        .input(&effect.path)
+        .filter_complex(&filters)
+        .args(["-map", "0:v"])
+        .map("sfx")
+        .args(["-c:v", "copy", "-c:a", "aac"])
+        .metadata(metadata)
+        .output(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sound_effects_command() {
+        let command = sound_effects_command(
+            &Ffmpeg::new("ffmpeg"),
+            Path::new("silent.mp4"),
+            &SoundEffect::new("tick.wav").gain_db(-6.0),
+            &[2.0, 4.5],
+            Path::new("out.mp4"),
+            &HashMap::new(),
+        );
+        let args: Vec<_> = command
+            .get_args()
+            .iter()
+            .map(|a| a.to_str().unwrap())
+            .collect();
+        assert!(args.contains(
+            &"[1]volume=-6dB,asplit=2[s0][s1];\
+              [s0]adelay=delays=2000:all=1[d0];[s1]adelay=delays=4500:all=1[d1];\
+              [d0][d1]amix=inputs=2:duration=longest:normalize=0[sfx]"
+        ));
+        assert!(args.windows(2).any(|w| w == ["-map", "0:v"]));
+        assert!(args.windows(2).any(|w| w == ["-map", "[sfx]"]));
+        assert_eq!(args.last(), Some(&"out.mp4"));
+    }
+}
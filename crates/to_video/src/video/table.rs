@@ -0,0 +1,146 @@
+use super::slide::RenderContext;
+use crate::{
+    color::Color,
+    imageproc::{
+        drawing::{DrawMut, DrawText},
+        rect::Rect,
+    },
+};
+use image::{GenericImage, Rgba};
+use serde::{Deserialize, Serialize};
+
+/// 表格的文字和线条样式
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct TableStyle {
+    /// 单元格文字的最大缩放，文字放不下时自动缩小
+    pub scale: f32,
+    pub color: Color,
+    pub header_color: Color,
+    /// 表头背景色，`None` 时不填充
+    #[serde(default)]
+    pub header_background: Option<Color>,
+    /// 网格线颜色，`None` 时不绘制网格线
+    #[serde(default)]
+    pub grid_color: Option<Color>,
+}
+
+/// 单元格文字与边框之间的留白（像素）
+const CELL_PADDING: u32 = 4;
+
+/// 按 `column_widths` 的比例把 `width` 分给各列，返回每列的 (左边界, 宽度)，
+/// 最后一列吸收取整误差，保证各列宽度之和等于 `width`。
+pub fn column_bounds(width: u32, column_widths: &[f32]) -> Vec<(u32, u32)> {
+    let total: f32 = column_widths.iter().map(|w| w.max(0.0)).sum();
+    if column_widths.is_empty() || total <= 0.0 {
+        return vec![];
+    }
+    let mut left = 0;
+    let mut acc = 0.0;
+    column_widths
+        .iter()
+        .enumerate()
+        .map(|(i, w)| {
+            acc += w.max(0.0);
+            let right = if i + 1 == column_widths.len() {
+                width
+            } else {
+                ((acc / total) * width as f32).round() as u32
+            };
+            let bound = (left, right.saturating_sub(left));
+            left = right;
+            bound
+        })
+        .collect()
+}
+
+/// 在 `rect` 内绘制表格：可选的表头加 `cells` 中的各行，行高平均分配。
+pub fn draw_table(
+    img: &mut impl GenericImage<Pixel = Rgba<u8>>,
+    rect: Rect,
+    header: &[String],
+    cells: &[Vec<String>],
+    column_widths: &[f32],
+    style: &TableStyle,
+    ctx: &RenderContext,
+) {
+    let columns = column_bounds(rect.width(), column_widths);
+    let row_count = cells.len() as u32 + u32::from(!header.is_empty());
+    if columns.is_empty() || row_count == 0 {
+        return;
+    }
+    let row_height = rect.height() / row_count;
+    if row_height == 0 {
+        return;
+    }
+
+    let rows = (!header.is_empty())
+        .then_some((header, style.header_color))
+        .into_iter()
+        .chain(cells.iter().map(|row| (row.as_slice(), style.color)));
+    for (row_index, (row, color)) in rows.enumerate() {
+        let top = rect.top() + (row_index as u32 * row_height) as i32;
+        if row_index == 0
+            && !header.is_empty()
+            && let Some(background) = style.header_background
+        {
+            img.draw_filled_rect_mut(
+                Rect::at(rect.left(), top).of_size(rect.width(), row_height),
+                background.into(),
+            );
+        }
+        for (text, &(left, width)) in row.iter().zip(&columns) {
+            if text.is_empty() || width <= 2 * CELL_PADDING || row_height <= 2 * CELL_PADDING {
+                continue;
+            }
+            let cell = Rect::at(
+                rect.left() + (left + CELL_PADDING) as i32,
+                top + CELL_PADDING as i32,
+            )
+            .of_size(width - 2 * CELL_PADDING, row_height - 2 * CELL_PADDING);
+            img.draw_text_center_supersampled_mut(
+                color.into(),
+                cell,
+                style.scale,
+                &ctx.font,
+                text,
+                ctx.text_quality.factor(),
+            );
+        }
+    }
+
+    if let Some(grid) = style.grid_color {
+        let (left, top) = (rect.left() as f32, rect.top() as f32);
+        let (right, bottom) = (
+            left + rect.width() as f32 - 1.0,
+            top + (row_height * row_count) as f32 - 1.0,
+        );
+        for row in 0..=row_count {
+            let y = (top + (row * row_height) as f32).min(bottom);
+            img.draw_line_segment_mut((left, y), (right, y), grid.into());
+        }
+        for &(column_left, _) in &columns {
+            let x = left + column_left as f32;
+            img.draw_line_segment_mut((x, top), (x, bottom), grid.into());
+        }
+        img.draw_line_segment_mut((right, top), (right, bottom), grid.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::column_bounds;
+
+    #[test]
+    fn test_column_bounds() {
+        assert_eq!(column_bounds(100, &[1.0, 1.0]), vec![(0, 50), (50, 50)]);
+        assert_eq!(
+            column_bounds(100, &[1.0, 2.0, 1.0]),
+            vec![(0, 25), (25, 50), (75, 25)]
+        );
+        assert_eq!(
+            column_bounds(10, &[1.0, 1.0, 1.0]),
+            vec![(0, 3), (3, 4), (7, 3)]
+        );
+        assert!(column_bounds(100, &[]).is_empty());
+    }
+}
@@ -0,0 +1,305 @@
+//! 时间线：把带日期的数据（例如角色的获得日期、皮肤的上线日期）按日期排序，
+//! 按月或按年分段，并在每段之前插入显示时间段的分隔 slide。
+//!
+//! 相邻两段之间有空缺（例如一整年没有数据）时，可以在分隔 slide 上注明间隔，
+//! 或按空缺的长度加宽分隔 slide，让滚动的距离与时间的跨度大致成比例。
+
+use super::{
+    format::{Locale, MONTHS, parse_date},
+    slide::{Position, Slide},
+};
+use crate::{BLACK, Result, WHITE, color::Color};
+use serde::{Deserialize, Serialize};
+
+/// 按比例加宽分隔 slide 时最多计入的空缺时间段数
+const MAX_GAP_PERIODS: u32 = 10;
+
+/// 时间线的分段单位
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Month,
+    #[default]
+    Year,
+}
+
+/// 相邻两段之间有空缺时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapMode {
+    /// 不处理
+    Ignore,
+    /// 在分隔 slide 的标题下方注明间隔，例如 “间隔3年”
+    #[default]
+    Annotate,
+    /// 每个空缺的时间段把分隔 slide 加宽 `gap_width` 像素
+    Proportional,
+}
+
+/// 按日期列生成时间线，与 `group_by` 不能同时使用
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Timeline {
+    /// 日期所在的数据列序号，从 0 开始，写法见 [`Format::Date`](super::format::Format::Date)
+    pub column: usize,
+    #[serde(default)]
+    pub period: Period,
+    #[serde(default)]
+    pub locale: Locale,
+    #[serde(default)]
+    pub gaps: GapMode,
+    /// [`GapMode::Proportional`] 中每个空缺时间段增加的宽度，最多计入 10 个时间段
+    #[serde(default = "default_gap_width")]
+    pub gap_width: u32,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default = "default_color")]
+    pub color: Color,
+    #[serde(default = "default_background")]
+    pub background: Color,
+    /// 分隔 slide 的宽度，`None` 时使用 `width_slides`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+}
+
+impl Timeline {
+    pub fn new(column: usize) -> Self {
+        Self {
+            column,
+            period: Period::default(),
+            locale: Locale::default(),
+            gaps: GapMode::default(),
+            gap_width: default_gap_width(),
+            scale: default_scale(),
+            color: default_color(),
+            background: default_background(),
+            width: None,
+        }
+    }
+
+    pub fn period(mut self, period: Period) -> Self {
+        self.period = period;
+        self
+    }
+
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    pub fn gaps(mut self, gaps: GapMode) -> Self {
+        self.gaps = gaps;
+        self
+    }
+
+    /// 日期所在的时间段，以月或年为单位计数
+    fn bucket(&self, (year, month, _): (u16, u8, u8)) -> i32 {
+        match self.period {
+            Period::Month => i32::from(year) * 12 + i32::from(month) - 1,
+            Period::Year => i32::from(year),
+        }
+    }
+
+    /// 时间段的标题，例如 “2021年3月”、“Mar 2021”
+    fn label(&self, bucket: i32) -> String {
+        match (self.period, self.locale) {
+            (Period::Year, Locale::Zh) => format!("{bucket}年"),
+            (Period::Year, Locale::En) => bucket.to_string(),
+            (Period::Month, Locale::Zh) => format!("{}年{}月", bucket / 12, bucket % 12 + 1),
+            (Period::Month, Locale::En) => {
+                format!("{} {}", MONTHS[(bucket % 12) as usize], bucket / 12)
+            }
+        }
+    }
+
+    /// 与上一段相差 `distance` 个时间段的注释，例如 “间隔3年”、“3 years later”
+    fn gap_note(&self, distance: u32) -> String {
+        match self.locale {
+            Locale::Zh => {
+                let unit = match self.period {
+                    Period::Month => "个月",
+                    Period::Year => "年",
+                };
+                format!("间隔{distance}{unit}")
+            }
+            Locale::En => {
+                let unit = match self.period {
+                    Period::Month => "month",
+                    Period::Year => "year",
+                };
+                format!("{distance} {unit}s later")
+            }
+        }
+    }
+
+    /// 生成分隔 slide：整页背景色加上时间段标题，`gap` 为与上一段之间空缺的时间段数
+    pub fn separator_slide(&self, label: &str, gap: u32, width_slides: u32) -> Slide {
+        let mut slide = Slide::new();
+        slide.add_color(self.background, Position::relative(0.0, 0.0, 1.0));
+        slide.add_text(
+            label,
+            self.scale,
+            self.color,
+            Position::relative(0.05, 0.35, 0.3),
+        );
+        let width = match self.gaps {
+            _ if gap == 0 => self.width,
+            GapMode::Ignore => self.width,
+            GapMode::Annotate => {
+                slide.add_text(
+                    &self.gap_note(gap + 1),
+                    self.scale / 2.0,
+                    self.color,
+                    Position::relative(0.05, 0.6, 0.15),
+                );
+                self.width
+            }
+            GapMode::Proportional => {
+                Some(self.width.unwrap_or(width_slides) + self.gap_width * gap.min(MAX_GAP_PERIODS))
+            }
+        };
+        match width {
+            Some(width) => slide.with_width(width),
+            None => slide,
+        }
+    }
+}
+
+/// 时间线中的一行：分隔 slide 或原始数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimelineRow {
+    /// 时间段标题，以及与上一段之间空缺的时间段数
+    Separator {
+        label: String,
+        gap: u32,
+    },
+    Data(Vec<String>),
+}
+
+/// 按日期列排序数据（日期相同的行保持原有顺序），并在时间段变化处插入分隔行。
+///
+/// # Errors
+/// - 某一行缺少日期列，或日期无法解析时返回 `Err`。
+///
+pub fn timeline_rows(datas: Vec<Vec<String>>, timeline: &Timeline) -> Result<Vec<TimelineRow>> {
+    let column = timeline.column;
+    let mut dated = datas
+        .into_iter()
+        .enumerate()
+        .map(|(index, data)| {
+            let date = data
+                .get(column)
+                .and_then(|cell| parse_date(cell))
+                .ok_or_else(|| format!("timeline: row {index} has no date in column {column}"))?;
+            Ok((date, data))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    dated.sort_by_key(|(date, _)| *date);
+
+    let mut rows = Vec::with_capacity(dated.len());
+    let mut current: Option<i32> = None;
+    for (date, data) in dated {
+        let bucket = timeline.bucket(date);
+        if current != Some(bucket) {
+            let gap = current.map_or(0, |previous| (bucket - previous - 1) as u32);
+            current = Some(bucket);
+            rows.push(TimelineRow::Separator {
+                label: timeline.label(bucket),
+                gap,
+            });
+        }
+        rows.push(TimelineRow::Data(data));
+    }
+    Ok(rows)
+}
+
+fn default_gap_width() -> u32 {
+    120
+}
+
+const fn default_scale() -> f32 {
+    160.0
+}
+
+const fn default_color() -> Color {
+    WHITE
+}
+
+const fn default_background() -> Color {
+    BLACK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str, date: &str) -> Vec<String> {
+        vec![name.to_string(), date.to_string()]
+    }
+
+    fn separator(label: &str, gap: u32) -> TimelineRow {
+        TimelineRow::Separator {
+            label: label.to_string(),
+            gap,
+        }
+    }
+
+    #[test]
+    fn test_timeline_rows() {
+        let datas = vec![
+            row("c", "2023-05-01"),
+            row("a", "2019-07-15"),
+            row("b", "2019-02-01"),
+            row("d", "2023-01-20"),
+        ];
+        let rows = timeline_rows(datas.clone(), &Timeline::new(1)).unwrap();
+        assert_eq!(
+            rows,
+            [
+                separator("2019年", 0),
+                TimelineRow::Data(row("b", "2019-02-01")),
+                TimelineRow::Data(row("a", "2019-07-15")),
+                separator("2023年", 3),
+                TimelineRow::Data(row("d", "2023-01-20")),
+                TimelineRow::Data(row("c", "2023-05-01")),
+            ]
+        );
+
+        let monthly = Timeline::new(1).period(Period::Month).locale(Locale::En);
+        let rows = timeline_rows(datas, &monthly).unwrap();
+        let labels: Vec<_> = rows
+            .iter()
+            .filter_map(|row| match row {
+                TimelineRow::Separator { label, gap } => Some((label.as_str(), *gap)),
+                TimelineRow::Data(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                ("Feb 2019", 0),
+                ("Jul 2019", 4),
+                ("Jan 2023", 41),
+                ("May 2023", 3)
+            ]
+        );
+
+        assert!(timeline_rows(vec![row("x", "unknown")], &Timeline::new(1)).is_err());
+    }
+
+    #[test]
+    fn test_separator_slide() {
+        let timeline = Timeline::new(1);
+        assert_eq!(timeline.separator_slide("2019年", 0, 600).width(), None);
+        assert_eq!(timeline.gap_note(4), "间隔4年");
+
+        let proportional = Timeline::new(1).gaps(GapMode::Proportional);
+        assert_eq!(
+            proportional.separator_slide("2023年", 3, 600).width(),
+            Some(960)
+        );
+        assert_eq!(
+            proportional.separator_slide("2040年", 30, 600).width(),
+            Some(1800)
+        );
+    }
+}
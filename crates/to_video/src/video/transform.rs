@@ -0,0 +1,187 @@
+use super::{collation::Collation, format::parse_date, slide::Condition};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashSet};
+
+/// 排序方向
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// 按某一列排序。数字按数值、日期按时间比较，其他按 `collation` 比较文字；
+/// 数字排在日期之前，日期排在其他文字之前。排序是稳定的
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SortBy {
+    pub column: usize,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// 文字的排序规则，中文名称可以使用 [`Collation::Pinyin`]
+    #[serde(default)]
+    pub collation: Collation,
+}
+
+/// 读取数据后、生成 slide 前对数据行的变换，依次执行过滤、去重、排序和截取
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct Transform {
+    /// 只保留满足所有条件的行
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filter: Vec<Condition>,
+    /// 这一列相同的行只保留第一行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedupe_by: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<SortBy>,
+    /// 最多保留的行数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+impl Transform {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn apply(&self, mut datas: Vec<Vec<String>>) -> Vec<Vec<String>> {
+        datas.retain(|data| self.filter.iter().all(|condition| condition.eval(data)));
+        if let Some(column) = self.dedupe_by {
+            let mut seen = HashSet::new();
+            datas.retain(|data| seen.insert(cell(data, column).to_string()));
+        }
+        if let Some(SortBy {
+            column,
+            order,
+            collation,
+        }) = self.sort_by
+        {
+            datas.sort_by(|a, b| {
+                let ordering = SortKey::new(cell(a, column))
+                    .compare(&SortKey::new(cell(b, column)), collation);
+                match order {
+                    SortOrder::Ascending => ordering,
+                    SortOrder::Descending => ordering.reverse(),
+                }
+            });
+        }
+        if let Some(limit) = self.limit {
+            datas.truncate(limit);
+        }
+        datas
+    }
+}
+
+fn cell(data: &[String], column: usize) -> &str {
+    data.get(column).map_or("", |s| s.trim())
+}
+
+/// 解析数字，允许千位分隔符和百分号
+pub(super) fn parse_number(cell: &str) -> Option<f64> {
+    let cell = cell.trim();
+    let cell = cell.strip_suffix('%').unwrap_or(cell);
+    cell.replace(',', "")
+        .parse()
+        .ok()
+        .filter(|value: &f64| value.is_finite())
+}
+
+#[derive(Debug, PartialEq, PartialOrd)]
+enum SortKey<'a> {
+    Number(f64),
+    Date((u16, u8, u8)),
+    Text(&'a str),
+}
+
+impl<'a> SortKey<'a> {
+    fn new(cell: &'a str) -> Self {
+        if let Some(number) = parse_number(cell) {
+            SortKey::Number(number)
+        } else if let Some(date) = parse_date(cell) {
+            SortKey::Date(date)
+        } else {
+            SortKey::Text(cell)
+        }
+    }
+
+    fn compare(&self, other: &Self, collation: Collation) -> Ordering {
+        match (self, other) {
+            (SortKey::Text(a), SortKey::Text(b)) => collation.compare(a, b),
+            _ => self.partial_cmp(other).unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_transform() {
+        let datas = rows(&[
+            &["a", "1,200", "2024-03-15"],
+            &["b", "30", "2023-12-01"],
+            &["a", "5", "2024-01-02"],
+            &["c", "", "2022-07-07"],
+            &["d", "100", "2024-01-10"],
+        ]);
+        let transform = Transform {
+            filter: vec![Condition::NonEmpty { column: 1 }],
+            dedupe_by: Some(0),
+            sort_by: Some(SortBy {
+                column: 1,
+                order: SortOrder::Descending,
+                collation: Collation::Codepoint,
+            }),
+            limit: Some(2),
+        };
+        let names: Vec<String> = transform
+            .apply(datas.clone())
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect();
+        assert_eq!(names, ["a", "d"]);
+
+        let by_date = Transform {
+            sort_by: Some(SortBy {
+                column: 2,
+                order: SortOrder::Ascending,
+                collation: Collation::Codepoint,
+            }),
+            ..Transform::default()
+        };
+        let names: Vec<String> = by_date
+            .apply(datas.clone())
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect();
+        assert_eq!(names, ["c", "b", "a", "d", "a"]);
+        assert!(Transform::default().is_empty());
+
+        let greater = Condition::GreaterThan {
+            column: 1,
+            value: 50.0,
+        };
+        assert!(greater.eval(&datas[0]) && !greater.eval(&datas[1]) && !greater.eval(&datas[3]));
+
+        let by_pinyin = Transform {
+            sort_by: Some(SortBy {
+                column: 0,
+                order: SortOrder::Ascending,
+                collation: Collation::Pinyin,
+            }),
+            ..Transform::default()
+        };
+        let names: Vec<String> = by_pinyin
+            .apply(rows(&[&["张"], &["李"], &["王"], &["陈"]]))
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect();
+        assert_eq!(names, ["陈", "李", "王", "张"]);
+    }
+}
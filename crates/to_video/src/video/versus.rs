@@ -0,0 +1,173 @@
+//! 对比模式：每行数据包含两个对象，在同一个 slide 中左右并排显示，中间是 “VS” 分隔，
+//! 例如模组（`Mod`）升级前后的属性。
+//!
+//! 两个对象使用同一组操作，按半个 slide 的宽度布局。元素的位置左右对称，
+//! 因此右侧的布局就是左侧的镜像；`mirror_images` 时右侧的图片也水平翻转，让两边的立绘相对。
+
+use super::slide::{Operation, Position, Slide};
+use crate::{Result, WHITE, color::Color};
+use serde::{Deserialize, Serialize};
+
+/// 把每行数据分为左右两个对象
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Versus {
+    /// 右侧对象的数据从这一列开始，之前的列属于左侧对象
+    pub split: usize,
+    /// 对比 slide 的宽度，`None` 时为 `width_slides` 的两倍
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default = "default_mirror_images")]
+    pub mirror_images: bool,
+    /// 中间分隔线的颜色和宽度（像素），宽度为 0 时不绘制
+    #[serde(default = "default_divider")]
+    pub divider: Color,
+    #[serde(default = "default_divider_width")]
+    pub divider_width: u32,
+    /// 分隔线中间的文字，为空时不绘制
+    #[serde(default = "default_label")]
+    pub label: String,
+    #[serde(default = "default_label_scale")]
+    pub label_scale: f32,
+    #[serde(default = "default_divider")]
+    pub label_color: Color,
+}
+
+impl Versus {
+    pub fn new(split: usize) -> Self {
+        Self {
+            split,
+            width: None,
+            mirror_images: default_mirror_images(),
+            divider: default_divider(),
+            divider_width: default_divider_width(),
+            label: default_label(),
+            label_scale: default_label_scale(),
+            label_color: default_divider(),
+        }
+    }
+
+    pub fn width(mut self, width: Option<u32>) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn mirror_images(mut self, mirror_images: bool) -> Self {
+        self.mirror_images = mirror_images;
+        self
+    }
+
+    /// 对比 slide 的宽度
+    pub fn slide_width(&self, width_slides: u32) -> u32 {
+        self.width.unwrap_or(width_slides * 2)
+    }
+
+    /// 用 `operations` 分别生成左右两个对象，组合为一个 slide。
+    ///
+    /// # Errors
+    /// - 数据不足 `split` 列，或生成某一侧时出错返回 `Err`。
+    ///
+    pub fn generation(
+        &self,
+        operations: &[Operation],
+        mut datas: Vec<String>,
+        width_slides: u32,
+    ) -> Result<Slide> {
+        if datas.len() < self.split {
+            return Err(format!(
+                "versus: row has {} columns, split is {}",
+                datas.len(),
+                self.split
+            )
+            .into());
+        }
+        let right = datas.split_off(self.split);
+        let left = Slide::generation(operations, datas)?;
+        let right = Slide::generation(operations, right)?;
+
+        let width = self.slide_width(width_slides);
+        let mut slide = Slide::new();
+        slide.add_panel(left, 0.0, 0.5, false);
+        slide.add_panel(right, 0.5, 0.5, self.mirror_images);
+        if self.divider_width > 0 {
+            let half = self.divider_width as f32 / 2.0 / width as f32;
+            slide.add_color(self.divider, Position::relative(0.5 - half, 0.0, 1.0));
+        }
+        if !self.label.is_empty() {
+            slide.add_text(
+                &self.label,
+                self.label_scale,
+                self.label_color,
+                Position::relative(0.4, 0.45, 0.1),
+            );
+        }
+        Ok(slide.with_width(width))
+    }
+}
+
+const fn default_mirror_images() -> bool {
+    true
+}
+
+const fn default_divider() -> Color {
+    WHITE
+}
+
+const fn default_divider_width() -> u32 {
+    6
+}
+
+fn default_label() -> String {
+    "VS".to_string()
+}
+
+const fn default_label_scale() -> f32 {
+    120.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BLACK, video::slide::Element};
+
+    #[test]
+    fn test_versus_generation() {
+        let operations = [Operation::Text {
+            pos: Position::relative(0.1, 0.1, 0.1),
+            z_index: 0,
+            scale: 60.0,
+            color: BLACK,
+            format: None,
+            condition: None,
+        }];
+        let datas = vec!["before".to_string(), "after".to_string()];
+        let slide = Versus::new(1).generation(&operations, datas, 600).unwrap();
+        assert_eq!(slide.width(), Some(1200));
+        let elements = slide.elements();
+        assert!(matches!(
+            &elements[0],
+            Element::Panel {
+                rel_left: 0.0,
+                mirror_images: false,
+                ..
+            }
+        ));
+        let Element::Panel {
+            elements: right,
+            rel_left,
+            mirror_images,
+            ..
+        } = &elements[1]
+        else {
+            panic!("expected the right panel");
+        };
+        assert_eq!((*rel_left, *mirror_images), (0.5, true));
+        assert!(matches!(&right[0], Element::Text { content, .. } if content == "after"));
+        assert_eq!(slide.plain_text(), "before\nafter\nVS");
+
+        assert!(
+            Versus::new(3)
+                .generation(&operations, vec!["a".to_string()], 600)
+                .is_err()
+        );
+    }
+}
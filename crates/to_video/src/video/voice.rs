@@ -0,0 +1,183 @@
+//! 语音台词：把 [`Voice`] 中的每条语音转换为一个 slide，用于生成语音合集视频。
+//!
+//! slide 上方是语音的标题（例如 “任命助理”），下方按 `IndexMap` 中的顺序从上到下排列各语言的台词，
+//! 每种语言一段：语言名称在上，台词在下。生成的 slide 通过
+//! [`VideoBuilder::add_slides`](super::VideoBuilder::add_slides) 加入视频。
+
+use super::slide::{Position, Slide};
+use crate::{BLACK, GRAY, WHITE, color::Color};
+use serde::{Deserialize, Serialize};
+use structs::{Voice, VoiceItem};
+
+/// 标题所占的高度比例，其余部分由各语言平分
+const TITLE_HEIGHT: f32 = 0.2;
+
+/// 语音 slide 的样式
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VoiceStyle {
+    #[serde(default = "default_title_scale")]
+    pub title_scale: f32,
+    #[serde(default = "default_text_color")]
+    pub title_color: Color,
+    #[serde(default = "default_language_scale")]
+    pub language_scale: f32,
+    #[serde(default = "default_language_color")]
+    pub language_color: Color,
+    /// 台词的最大字号，放不下时自动缩小
+    #[serde(default = "default_line_scale")]
+    pub line_scale: f32,
+    #[serde(default = "default_text_color")]
+    pub line_color: Color,
+    /// 整页的背景色，`None` 时透明
+    #[serde(default = "default_background")]
+    pub background: Option<Color>,
+    /// 只显示这些语言（仍按 `IndexMap` 中的顺序），为空时显示全部语言
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<String>,
+}
+
+impl Default for VoiceStyle {
+    fn default() -> Self {
+        Self {
+            title_scale: default_title_scale(),
+            title_color: default_text_color(),
+            language_scale: default_language_scale(),
+            language_color: default_language_color(),
+            line_scale: default_line_scale(),
+            line_color: default_text_color(),
+            background: default_background(),
+            languages: Vec::new(),
+        }
+    }
+}
+
+impl VoiceStyle {
+    pub fn languages(mut self, languages: &[&str]) -> Self {
+        self.languages = languages.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    pub fn background(mut self, background: Option<Color>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// 需要显示的台词，跳过空台词
+    fn lines<'a>(&self, item: &'a VoiceItem) -> Vec<(&'a str, &'a str)> {
+        item.item
+            .iter()
+            .filter(|(language, line)| {
+                !line.trim().is_empty()
+                    && (self.languages.is_empty() || self.languages.contains(language))
+            })
+            .map(|(language, line)| (language.as_str(), line.as_str()))
+            .collect()
+    }
+}
+
+/// 把一条语音转换为 slide，`title` 为语音的标题
+pub fn voice_item_slide(title: &str, item: &VoiceItem, style: &VoiceStyle) -> Slide {
+    let mut slide = Slide::new();
+    if let Some(background) = style.background {
+        slide.add_color(background, Position::relative(0.0, 0.0, 1.0));
+    }
+    slide.add_text(
+        title,
+        style.title_scale,
+        style.title_color,
+        Position::relative(0.05, 0.04, TITLE_HEIGHT - 0.08),
+    );
+    let lines = style.lines(item);
+    let block = (1.0 - TITLE_HEIGHT) / lines.len().max(1) as f32;
+    for (index, (language, line)) in lines.into_iter().enumerate() {
+        let top = TITLE_HEIGHT + block * index as f32;
+        slide.add_text(
+            language,
+            style.language_scale,
+            style.language_color,
+            Position::relative(0.05, top, block * 0.25),
+        );
+        slide.add_text(
+            line,
+            style.line_scale,
+            style.line_color,
+            Position::relative(0.05, top + block * 0.25, block * 0.65),
+        );
+    }
+    slide
+}
+
+/// 把所有语音按 `voice_item` 中的顺序转换为 slide，见 [`voice_item_slide`]
+pub fn voice_slides(voice: &Voice, style: &VoiceStyle) -> Vec<Slide> {
+    voice
+        .voice_item
+        .iter()
+        .map(|(title, item)| voice_item_slide(title, item, style))
+        .collect()
+}
+
+const fn default_title_scale() -> f32 {
+    80.0
+}
+
+const fn default_language_scale() -> f32 {
+    36.0
+}
+
+const fn default_line_scale() -> f32 {
+    52.0
+}
+
+const fn default_text_color() -> Color {
+    WHITE
+}
+
+const fn default_language_color() -> Color {
+    GRAY
+}
+
+const fn default_background() -> Option<Color> {
+    Some(BLACK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::slide::Element;
+
+    const VOICE: &str = r#"{
+        "voice_base": {"日文": "voice", "中文": "voice_cn"},
+        "voice_item": {
+            "任命助理": {
+                "voice_filename": "CN_001",
+                "item": {"日文": "ドクター。", "中文": "博士。", "英文": ""}
+            },
+            "交谈1": {
+                "voice_filename": "CN_002",
+                "item": {"中文": "今天也要加油。", "日文": "今日も頑張ろう。"}
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_voice_slides() {
+        let voice: Voice = serde_json::from_str(VOICE).unwrap();
+        let slides = voice_slides(&voice, &VoiceStyle::default());
+        assert_eq!(slides.len(), 2);
+        // 保持 IndexMap 的顺序，跳过空台词
+        assert_eq!(
+            slides[0].plain_text(),
+            "任命助理\n日文\nドクター。\n中文\n博士。"
+        );
+        assert_eq!(
+            slides[1].plain_text(),
+            "交谈1\n中文\n今天也要加油。\n日文\n今日も頑張ろう。"
+        );
+        assert!(matches!(slides[0].elements()[0], Element::Color { .. }));
+
+        let style = VoiceStyle::default().languages(&["日文"]).background(None);
+        let slides = voice_slides(&voice, &style);
+        assert_eq!(slides[1].plain_text(), "交谈1\n日文\n今日も頑張ろう。");
+        assert_eq!(slides[1].elements().len(), 3);
+    }
+}
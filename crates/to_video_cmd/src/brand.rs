@@ -0,0 +1,225 @@
+//! 时装品牌展示：从 [`Brand`] 直接生成 info 和数据文件。
+//!
+//! 第一个 slide 是品牌封面（品牌名和介绍），之后每件时装一个 slide：立绘、名称、画师、获得方式和上线日期。
+//! 立绘的路径由模板生成，例如 `skins/{owner}/{name}.png`，可用的占位符见 [`skin_image`]。
+
+use crate::Info;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use structs::{
+    Brand, Skin,
+    parse::{ParseMode, parse_one},
+};
+use to_video::{
+    BLACK, GRAY, Result, WHITE,
+    slide::{Condition, Operation, Position},
+    video::{VideoConfig, transform::Transform},
+};
+
+/// 立绘路径的默认模板
+pub const DEFAULT_IMAGE_PATTERN: &str = "skins/{name}.png";
+
+/// 数据列：立绘、时装名、画师、获得方式、上线日期、品牌名、品牌介绍。
+/// 时装行的品牌列为空，封面行只有品牌列，各元素按所在列是否为空决定是否绘制
+const COLUMNS: usize = 7;
+
+/// 按模板生成时装立绘的路径，支持 `{name}`、`{owner}`、`{brand}` 和 `{painter}` 占位符
+pub fn skin_image(pattern: &str, skin: &Skin) -> String {
+    pattern
+        .replace("{name}", &skin.name)
+        .replace("{owner}", &skin.owner)
+        .replace("{brand}", &skin.brand_group)
+        .replace("{painter}", &skin.painter)
+}
+
+/// 品牌封面和各时装的数据行
+pub fn brand_rows(brand: &Brand, image_pattern: &str) -> Vec<Vec<String>> {
+    let mut cover = vec![String::new(); COLUMNS];
+    cover[5] = brand.name.clone();
+    cover[6] = brand.intro.clone();
+    let skins = brand.skin.iter().map(|skin| {
+        let date = skin
+            .date_launch
+            .map(|(year, month, day)| format!("{year}-{month:0>2}-{day:0>2}"))
+            .unwrap_or_default();
+        let painter = match skin.painter.trim() {
+            "" => String::new(),
+            painter => format!("画师：{painter}"),
+        };
+        vec![
+            skin_image(image_pattern, skin),
+            skin.name.clone(),
+            painter,
+            skin.access.clone(),
+            date,
+            String::new(),
+            String::new(),
+        ]
+    });
+    std::iter::once(cover).chain(skins).collect()
+}
+
+/// 品牌展示的操作，依次读取 [`brand_rows`] 中的各列
+pub fn brand_operations() -> Vec<Operation> {
+    let text = |column: usize, scale: f32, color, pos| Operation::Text {
+        scale,
+        color,
+        pos,
+        z_index: column as u8 + 1,
+        condition: Some(Condition::NonEmpty { column }),
+        format: None,
+    };
+    vec![
+        Operation::Color {
+            color: BLACK,
+            pos: Position::relative(0.0, 0.0, 1.0),
+            z_index: 0,
+            condition: None,
+            shadow: None,
+            opacity: 1.0,
+        },
+        Operation::Image {
+            pos: Position::relative(0.05, 0.04, 0.6),
+            z_index: 1,
+            condition: Some(Condition::NonEmpty { column: 0 }),
+            rotation_deg: 0.0,
+            shadow: None,
+            ken_burns: None,
+            animate: false,
+        },
+        text(1, 90.0, WHITE, Position::relative(0.05, 0.66, 0.1)),
+        text(2, 48.0, GRAY, Position::relative(0.05, 0.77, 0.06)),
+        text(3, 48.0, GRAY, Position::relative(0.05, 0.84, 0.06)),
+        text(4, 48.0, GRAY, Position::relative(0.05, 0.91, 0.06)),
+        text(5, 140.0, WHITE, Position::relative(0.05, 0.2, 0.18)),
+        text(6, 56.0, WHITE, Position::relative(0.05, 0.45, 0.4)),
+    ]
+}
+
+/// 生成品牌展示的 info 和数据，info 中的数据文件为 `data`
+pub fn brand_showcase(
+    brand: &Brand,
+    image_pattern: &str,
+    data: PathBuf,
+) -> (Info, Vec<Vec<String>>) {
+    let info = Info {
+        operations: brand_operations(),
+        components: HashMap::new(),
+        instances: Vec::new(),
+        theme: None,
+        config: VideoConfig::builder(),
+        data,
+        transform: Transform::default(),
+        widths: Vec::new(),
+    };
+    (info, brand_rows(brand, image_pattern))
+}
+
+/// 按 `mode` 读取品牌文件，在 `dir` 中写入 `info.json` 和 `data.json`，返回 info 文件的路径。
+/// 宽松模式中转换过的值打印到标准错误
+///
+/// # Errors
+/// - 品牌文件无法读取或解析，或写入文件失败时返回 `Err`。
+///
+pub fn write_brand_showcase(
+    brand_file: &Path,
+    image_pattern: &str,
+    dir: &Path,
+    mode: ParseMode,
+) -> Result<PathBuf> {
+    let (brand, report) = parse_one::<Brand>(&fs::read(brand_file)?, mode)
+        .map_err(|e| format!("Invalid brand file {}: {e}", brand_file.display()))?;
+    for coercion in &report.coerced {
+        eprintln!("coerced {coercion}");
+    }
+    fs::create_dir_all(dir)?;
+    let (info, data) = brand_showcase(&brand, image_pattern, dir.join("data.json"));
+    fs::write(&info.data, serde_json::to_string_pretty(&data)?)?;
+    let info_file = dir.join("info.json");
+    fs::write(&info_file, serde_json::to_string_pretty(&info)?)?;
+    Ok(info_file)
+}
+
+/// 命令行参数 `brand` 的内容，见 [`brand_arg`]
+pub struct BrandArgs {
+    pub brand: PathBuf,
+    pub dir: PathBuf,
+    pub image_pattern: String,
+    pub mode: ParseMode,
+}
+
+/// 读取命令行参数 `brand <brand.json> <输出目录> [--images <模板>] [--strict]`
+pub fn brand_arg() -> Result<Option<BrandArgs>> {
+    let mut args = std::env::args().skip(1);
+    if args.next().is_none_or(|arg| arg != "brand") {
+        return Ok(None);
+    }
+    let usage =
+        "usage: to_video_cmd brand <brand.json> <output dir> [--images <pattern>] [--strict]";
+    let (Some(brand), Some(dir)) = (args.next(), args.next()) else {
+        return Err(usage.into());
+    };
+    let mut parsed = BrandArgs {
+        brand: PathBuf::from(brand),
+        dir: PathBuf::from(dir),
+        image_pattern: DEFAULT_IMAGE_PATTERN.to_string(),
+        mode: ParseMode::Lenient,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--images" => parsed.image_pattern = args.next().ok_or(usage)?,
+            "--strict" => parsed.mode = ParseMode::Strict,
+            _ => return Err(usage.into()),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use to_video::slide::Slide;
+
+    const BRAND: &str = r#"{
+        "name": "忒斯特收藏",
+        "intro": "品牌介绍",
+        "skin": [{
+            "name": "夏日", "is_animated": false, "has_intro_animation": false,
+            "has_exclusive_voice": false, "has_multiple_actions": false,
+            "owner": "阿米娅", "essay": "", "painter": "某画师", "access": "限时获得",
+            "brand_group": "忒斯特收藏", "date_launch": [2021, 3, 5], "description": ""
+        }]
+    }"#;
+
+    #[test]
+    fn test_brand_showcase() {
+        let brand: Brand = serde_json::from_str(BRAND).unwrap();
+        let (info, data) = brand_showcase(&brand, "skins/{owner}/{name}.png", "data.json".into());
+        assert_eq!(data.len(), 2);
+        assert_eq!(
+            data[1][..5],
+            [
+                "skins/阿米娅/夏日.png",
+                "夏日",
+                "画师：某画师",
+                "限时获得",
+                "2021-03-05"
+            ]
+        );
+
+        // 封面只绘制品牌名和介绍，时装 slide 不绘制品牌列
+        let mut operations = info.operations;
+        operations.sort();
+        let cover = Slide::generation(&operations, data[0].clone()).unwrap();
+        assert_eq!(cover.plain_text(), "忒斯特收藏\n品牌介绍");
+        assert_eq!(cover.elements().len(), 3);
+        let skin = Slide::generation(&operations, data[1].clone()).unwrap();
+        assert_eq!(
+            skin.plain_text(),
+            "夏日\n画师：某画师\n限时获得\n2021-03-05"
+        );
+    }
+}
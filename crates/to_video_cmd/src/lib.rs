@@ -1,17 +1,55 @@
+pub mod brand;
+#[cfg(feature = "prts")]
+pub mod prts;
+pub mod remote;
+pub mod sqlite;
+pub mod watch;
+pub mod xlsx;
+
+use remote::{RemoteSource, offline_arg};
 use rfd::FileDialog;
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use sqlite::SqliteSource;
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 use to_video::{
     BLACK, COLOR_3_1, POSITION_4_2, Result,
     slide::{Operation, Position},
-    video::{Video, VideoBuilder, VideoConfig, VideoConfigBuilder},
+    theme::Theme,
+    video::{
+        Video, VideoBuilder, VideoConfig, VideoConfigBuilder,
+        component::{Component, ComponentInstance, expand_components},
+        transform::Transform,
+    },
 };
+use xlsx::XlsxSource;
 
 #[derive(Deserialize, Serialize)]
 pub struct Info {
     pub operations: Vec<Operation>,
+    /// 可复用的组件，按名称在 `instances` 中使用
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub components: HashMap<String, Component>,
+    /// 组件的实例，展开后与 `operations` 一起生成每个 slide
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instances: Vec<ComponentInstance>,
+    /// 颜色主题，文件中的 `"@primary"` 等颜色引用在读取时替换为主题中的颜色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
     pub config: VideoConfigBuilder,
+    /// JSON 或 Excel 数据文件（见 [`xlsx`]），`sqlite://<文件>?query=<SQL>` 形式的 SQLite 查询（见 [`sqlite`]），
+    /// 或 CSV、JSON 数据的 HTTP(S) 地址（见 [`remote`]）
     pub data: PathBuf,
+    /// 读取数据后对数据行的过滤、去重、排序和截取
+    #[serde(default, skip_serializing_if = "Transform::is_empty")]
+    pub transform: Transform,
+    /// 按（变换后的）数据行顺序设置的 slide 宽度，缺少的使用 `config.width_slides`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub widths: Vec<u32>,
 }
 
 pub fn example() -> Result<()> {
@@ -41,43 +79,68 @@ pub fn example() -> Result<()> {
                 Operation::Image {
                     pos: POSITION_4_2.0,
                     z_index: 0,
+                    condition: None,
+                    rotation_deg: 0.0,
+                    shadow: None,
+                    ken_burns: None,
+                    animate: false,
                 },
                 Operation::Color {
                     color: COLOR_3_1.0,
                     pos: POSITION_4_2.1,
                     z_index: 1,
+                    condition: None,
+                    shadow: None,
+                    opacity: 1.0,
                 },
                 Operation::Color {
                     color: COLOR_3_1.1,
                     pos: POSITION_4_2.2,
                     z_index: 2,
+                    condition: None,
+                    shadow: None,
+                    opacity: 1.0,
                 },
                 Operation::Color {
                     color: COLOR_3_1.2,
                     pos: Position::new(1, 900, 180),
                     z_index: 3,
+                    condition: None,
+                    shadow: None,
+                    opacity: 1.0,
                 },
                 Operation::Text {
                     scale: 120.0,
                     color: BLACK,
                     pos: POSITION_4_2.1,
                     z_index: 4,
+                    condition: None,
+                    format: None,
                 },
                 Operation::Text {
                     scale: 120.0,
                     color: BLACK,
                     pos: POSITION_4_2.2,
                     z_index: 5,
+                    condition: None,
+                    format: None,
                 },
                 Operation::Text {
                     scale: 120.0,
                     color: BLACK,
                     pos: POSITION_4_2.3,
                     z_index: 6,
+                    condition: None,
+                    format: None,
                 },
             ],
+            components: HashMap::new(),
+            instances: Vec::new(),
+            theme: None,
             config: VideoConfig::builder().fps(30).step(15),
             data: data_example,
+            transform: Transform::default(),
+            widths: Vec::new(),
         };
         let example = serde_json::to_string_pretty(&info).unwrap();
         fs::write(info_example, example)?;
@@ -85,12 +148,30 @@ pub fn example() -> Result<()> {
     Ok(())
 }
 
+/// 读取数据文件、SQLite 查询或远程数据，每一行数据对应一个 slide。
+/// 命令行参数中有 `--offline` 时远程数据只使用缓存
+pub fn load_data(path: &Path) -> Result<Vec<Vec<String>>> {
+    if let Some(source) = RemoteSource::parse(path)? {
+        return source.load_rows(offline_arg());
+    }
+    if let Some(source) = SqliteSource::parse(path)? {
+        return source.load_rows();
+    }
+    if let Some(source) = XlsxSource::parse(path)? {
+        return source.load_rows();
+    }
+    let data = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| format!("Invalid data file {}: {e}", path.display()))?;
+    Ok(data)
+}
+
 pub fn parse() -> Result<VideoBuilder> {
-    if let Some(s) = std::env::args().skip(1).next() {
-        if s == "--example" || s == "-e" {
-            example()?;
-            std::process::exit(0);
-        }
+    if std::env::args()
+        .nth(1)
+        .is_some_and(|s| s == "--example" || s == "-e")
+    {
+        example()?;
+        std::process::exit(0);
     }
     let file = loop {
         match FileDialog::new()
@@ -106,14 +187,97 @@ pub fn parse() -> Result<VideoBuilder> {
             }
         }
     };
-    let info = serde_json::from_slice(&fs::read(&file)?)
-        .map_err(|e| format!("Invalid info file:  {e}"))?;
-    let Info {
-        mut operations,
-        config,
-        data,
-    } = info;
-    let data: Vec<Vec<String>> = serde_json::from_slice(&fs::read(data)?)?;
-    let video_builder = Video::builder(&mut operations, data, config.build()?)?;
-    Ok(video_builder)
+    let mut info = load_info(&file)?;
+    if std::env::args().any(|arg| arg == "--print-commands") {
+        info.config = info.config.print_commands(true);
+    }
+    info.into_builder()
+}
+
+/// 读取 info 文件，`theme` 存在时先替换其中的颜色引用，见 [`Theme::resolve`]
+pub fn load_info(path: &Path) -> Result<Info> {
+    let invalid = |e: serde_json::Error| format!("Invalid info file {}: {e}", path.display());
+    let mut value: serde_json::Value = serde_json::from_slice(&fs::read(path)?).map_err(invalid)?;
+    if let Some(theme) = value.get("theme") {
+        let theme: Theme = serde_json::from_value(theme.clone()).map_err(invalid)?;
+        for key in ["operations", "components", "instances", "config"] {
+            if let Some(value) = value.get_mut(key) {
+                theme.resolve(value);
+            }
+        }
+    }
+    Ok(serde_json::from_value(value).map_err(invalid)?)
+}
+
+impl Info {
+    /// 读取数据文件并创建 [`VideoBuilder`]
+    pub fn into_builder(self) -> Result<VideoBuilder> {
+        let Info {
+            mut operations,
+            components,
+            instances,
+            config,
+            theme: _,
+            data,
+            transform,
+            widths,
+        } = self;
+        operations.extend(expand_components(&components, &instances)?);
+        let data = transform.apply(load_data(&data)?);
+        Video::builder(&mut operations, data, config.build()?)?.slide_widths(&widths)
+    }
+}
+
+/// 读取命令行参数 `--chunks <范围>`，只生成指定的 chunk，见 [`parse_chunk_range`]
+pub fn chunks_arg() -> Result<Option<Range<usize>>> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--chunks");
+    if args.next().is_none() {
+        return Ok(None);
+    }
+    let range = args.next().ok_or("--chunks requires a range, e.g. 3..5")?;
+    parse_chunk_range(&range).map(Some)
+}
+
+/// 读取命令行参数 `--export <目录>`，只导出图片和时间线，见 [`Video::export`]
+pub fn export_arg() -> Result<Option<PathBuf>> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--export");
+    if args.next().is_none() {
+        return Ok(None);
+    }
+    let dir = args.next().ok_or("--export requires a directory")?;
+    Ok(Some(PathBuf::from(dir)))
+}
+
+/// 解析 chunk 范围，支持 `3..5`、`3..=5` 和单个序号 `3`
+pub fn parse_chunk_range(s: &str) -> Result<Range<usize>> {
+    let invalid = || format!("Invalid chunk range {s:?}, expected e.g. 3..5, 3..=5 or 3");
+    let range = if let Some((start, end)) = s.split_once("..=") {
+        let end: usize = end.trim().parse().map_err(|_| invalid())?;
+        start.trim().parse().map_err(|_| invalid())?..end.checked_add(1).ok_or_else(invalid)?
+    } else if let Some((start, end)) = s.split_once("..") {
+        start.trim().parse().map_err(|_| invalid())?..end.trim().parse().map_err(|_| invalid())?
+    } else {
+        let index: usize = s.trim().parse().map_err(|_| invalid())?;
+        index..index.checked_add(1).ok_or_else(invalid)?
+    };
+    if range.is_empty() {
+        return Err(invalid().into());
+    }
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_chunk_range;
+
+    #[test]
+    fn test_parse_chunk_range() {
+        assert_eq!(parse_chunk_range("3..5").unwrap(), 3..5);
+        assert_eq!(parse_chunk_range("3..=5").unwrap(), 3..6);
+        assert_eq!(parse_chunk_range("7").unwrap(), 7..8);
+        assert!(parse_chunk_range("5..3").is_err());
+        assert!(parse_chunk_range("a..3").is_err());
+        assert!(parse_chunk_range(&format!("0..={}", usize::MAX)).is_err());
+        assert!(parse_chunk_range(&usize::MAX.to_string()).is_err());
+    }
 }
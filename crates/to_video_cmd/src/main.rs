@@ -1,23 +1,79 @@
 use std::{path::Path, time::Instant};
-use to_video::{Result, test_encoder};
-use to_video_cmd::parse;
+use to_video::{Result, test_encoder, video::ConfigIssues};
+use to_video_cmd::{
+    brand::{brand_arg, write_brand_showcase},
+    chunks_arg, export_arg, parse,
+    watch::{watch, watch_arg},
+};
 
 fn main() -> Result<()> {
     let encoders = test_encoder()?;
     println!("Useable encoders: {:?}", encoders);
 
+    if let Some(info) = watch_arg()? {
+        return watch(&info);
+    }
+    if let Some(args) = brand_arg()? {
+        let info = write_brand_showcase(&args.brand, &args.image_pattern, &args.dir, args.mode)?;
+        println!("brand showcase written to {}", info.display());
+        return Ok(());
+    }
+    #[cfg(feature = "prts")]
+    if let Some(args) = to_video_cmd::prts::prts_arg()? {
+        return to_video_cmd::prts::refresh_dataset(&args);
+    }
+
     let t = Instant::now();
 
-    let video_builder = parse()?;
+    let chunks = chunks_arg()?;
+    let export = export_arg()?;
+    let video_builder = match parse() {
+        Ok(video_builder) => video_builder,
+        Err(e) => {
+            // 一次列出配置中的所有问题
+            if let Some(ConfigIssues(issues)) = e.downcast_ref::<ConfigIssues>() {
+                for issue in issues {
+                    eprintln!("{}: {issue}", issue.field);
+                }
+                std::process::exit(1);
+            }
+            return Err(e);
+        }
+    };
 
     let video = video_builder.build()?;
 
+    if let Some(dir) = export {
+        let manifest = video.export(&dir)?;
+        println!("exported to {}", manifest.display());
+        return Ok(());
+    }
+
     let handle_progress = move |file: &Path, generate_len: usize, total: usize| {
         println!("{} / {} : {}  success", generate_len, total, file.display());
         Ok(())
     };
 
-    video.run(handle_progress)?;
+    match chunks {
+        Some(range) => {
+            for output in video.run_range(range, handle_progress)? {
+                println!("{} successed", output.display());
+            }
+        }
+        None => {
+            let report = video.run(handle_progress)?;
+            for text in report.shrunk() {
+                println!("shrunk {text}");
+            }
+            for text in &report.contrast {
+                println!("low contrast {text}");
+            }
+            for element in &report.outside_safe_area {
+                println!("{element}");
+            }
+            println!("{}", report.glyph_cache);
+        }
+    }
     let cost = t.elapsed().as_millis();
     println!("cost {} s {} ms", cost / 1000, cost % 1000);
     std::io::stdin().read_line(&mut String::new())?;
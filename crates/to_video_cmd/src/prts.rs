@@ -0,0 +1,99 @@
+//! 命令 `prts`：从 prts.wiki 下载干员的档案、信息、时装和引用的图片，一次更新数据集。
+//!
+//! 结果写入输出目录中的 `char_file.json`、`char_info.json`、`skin.json`，图片保存在 `images` 子目录。
+//! 某个干员的某项数据读取失败时只打印警告，不影响其他数据。
+
+use std::{fs, path::PathBuf};
+use structs::{
+    prts::Fetcher,
+    wikitext::{Page, Templates},
+};
+use to_video::Result;
+
+/// 命令行参数 `prts` 的内容，见 [`prts_arg`]
+pub struct PrtsArgs {
+    pub dir: PathBuf,
+    /// 干员页面的标题
+    pub titles: Vec<String>,
+}
+
+/// 读取命令行参数 `prts <输出目录> <干员>...`
+pub fn prts_arg() -> Result<Option<PrtsArgs>> {
+    let mut args = std::env::args().skip(1);
+    if args.next().is_none_or(|arg| arg != "prts") {
+        return Ok(None);
+    }
+    let usage = "usage: to_video_cmd prts <output dir> <operator>...";
+    let dir = args.next().ok_or(usage)?;
+    let titles: Vec<String> = args.collect();
+    if titles.is_empty() {
+        return Err(usage.into());
+    }
+    Ok(Some(PrtsArgs {
+        dir: PathBuf::from(dir),
+        titles,
+    }))
+}
+
+/// 下载 `args.titles` 中各干员的数据和图片，写入 `args.dir`
+///
+/// # Errors
+/// - 无法创建 HTTP 客户端或写入文件失败时返回 `Err`。
+///
+pub fn refresh_dataset(args: &PrtsArgs) -> Result<()> {
+    let fetcher = Fetcher::new(args.dir.join("images"))?;
+    let templates = Templates::default();
+    let (mut char_files, mut char_infos, mut skins) = (Vec::new(), Vec::new(), Vec::new());
+    for title in &args.titles {
+        let page = match fetcher.page(title) {
+            Ok(page) => page,
+            Err(e) => {
+                eprintln!("{title}: {e}");
+                continue;
+            }
+        };
+        let warn = |e: &dyn std::fmt::Display| eprintln!("{}: {e}", page.title);
+        match page.char_file(&templates.char_file) {
+            Ok(char_file) => char_files.push(char_file),
+            Err(e) => warn(&e),
+        }
+        match page.char_info(&templates.char_info) {
+            Ok(char_info) => char_infos.push(char_info),
+            Err(e) => warn(&e),
+        }
+        match page.skins(&templates.skin) {
+            Ok(page_skins) => skins.extend(page_skins),
+            Err(e) => warn(&e),
+        }
+        download_images(&fetcher, &page);
+    }
+    fs::create_dir_all(&args.dir)?;
+    fs::write(
+        args.dir.join("char_file.json"),
+        serde_json::to_string_pretty(&char_files)?,
+    )?;
+    fs::write(
+        args.dir.join("char_info.json"),
+        serde_json::to_string_pretty(&char_infos)?,
+    )?;
+    fs::write(
+        args.dir.join("skin.json"),
+        serde_json::to_string_pretty(&skins)?,
+    )?;
+    println!(
+        "{} char files, {} char infos, {} skins written to {}",
+        char_files.len(),
+        char_infos.len(),
+        skins.len(),
+        args.dir.display()
+    );
+    Ok(())
+}
+
+fn download_images(fetcher: &Fetcher, page: &Page) {
+    for file in page.images() {
+        if let Err(e) = fetcher.download_image(&file) {
+            eprintln!("{}: image {file}: {e}", page.title);
+        }
+    }
+}
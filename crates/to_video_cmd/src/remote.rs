@@ -0,0 +1,236 @@
+//! 远程数据：info 文件中的 `data` 可以是 `https://` 或 `http://` 地址，
+//! 例如 Google Sheets “发布到网络” 得到的 CSV 地址，或 JSON 数据文件的地址。
+//!
+//! - 内容以 `[` 开头时按 JSON 数据文件读取，否则按 CSV 读取；
+//! - CSV 默认跳过 1 行表头，可以在地址后加 `#header=0` 修改（`#` 之后的部分不会发送到服务器）；
+//! - 下载的内容缓存在 [`CACHE_DIR`]，下次请求时带上 `If-None-Match`，内容没有变化时直接使用缓存；
+//!   下载失败时打印警告并使用缓存；
+//! - 命令行参数 `--offline` 只使用缓存，不访问网络。
+//!
+//! 下载需要启用 `remote` feature，只读取缓存时不需要。
+
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+};
+use to_video::Result;
+
+/// 缓存目录
+pub const CACHE_DIR: &str = "data_cache";
+
+/// 一个远程数据地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSource {
+    /// 不含 `#` 之后部分的地址
+    pub url: String,
+    /// CSV 跳过的表头行数
+    pub header_rows: usize,
+    pub cache_dir: PathBuf,
+}
+
+/// 读取命令行参数 `--offline`，只使用缓存的远程数据
+pub fn offline_arg() -> bool {
+    std::env::args().any(|arg| arg == "--offline")
+}
+
+impl RemoteSource {
+    /// 解析远程数据地址，`data` 不是 HTTP(S) 地址时为 `None`
+    ///
+    /// # Errors
+    /// - `#` 之后的参数无效时返回 `Err`。
+    ///
+    pub fn parse(data: &Path) -> Result<Option<Self>> {
+        let Some(data) = data
+            .to_str()
+            .filter(|s| s.starts_with("https://") || s.starts_with("http://"))
+        else {
+            return Ok(None);
+        };
+        let (url, fragment) = data.split_once('#').unwrap_or((data, ""));
+        let mut source = Self {
+            url: url.to_string(),
+            header_rows: 1,
+            cache_dir: PathBuf::from(CACHE_DIR),
+        };
+        for param in fragment.split('&').filter(|param| !param.is_empty()) {
+            let invalid = || format!("Invalid data url parameter {param:?} in {data:?}");
+            match param.split_once('=').ok_or_else(invalid)? {
+                ("header", rows) => source.header_rows = rows.parse().map_err(|_| invalid())?,
+                _ => return Err(invalid().into()),
+            }
+        }
+        Ok(Some(source))
+    }
+
+    /// 缓存文件，以地址的哈希命名
+    pub fn cache_file(&self) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        self.url.hash(&mut hasher);
+        self.cache_dir.join(format!("{:016x}.txt", hasher.finish()))
+    }
+
+    #[cfg(feature = "remote")]
+    fn etag_file(&self) -> PathBuf {
+        self.cache_file().with_extension("etag")
+    }
+
+    /// 下载（或 `offline` 时从缓存读取）数据
+    ///
+    /// # Errors
+    /// - 下载失败且没有缓存，或内容无法解析时返回 `Err`。
+    ///
+    pub fn load_rows(&self, offline: bool) -> Result<Vec<Vec<String>>> {
+        let text = if offline {
+            self.cached()?
+        } else {
+            match self.fetch() {
+                Ok(text) => text,
+                Err(e) => {
+                    let text = self.cached().map_err(|_| e.to_string())?;
+                    eprintln!("failed to fetch {}, using cached data: {e}", self.url);
+                    text
+                }
+            }
+        };
+        self.parse_rows(&text)
+    }
+
+    fn cached(&self) -> Result<String> {
+        fs::read_to_string(self.cache_file())
+            .map_err(|_| format!("No cached data for {}", self.url).into())
+    }
+
+    /// 下载并更新缓存，服务器返回 304 时使用缓存
+    #[cfg(feature = "remote")]
+    fn fetch(&self) -> Result<String> {
+        use reqwest::{
+            StatusCode,
+            header::{ETAG, IF_NONE_MATCH},
+        };
+
+        let cache_file = self.cache_file();
+        let mut request = reqwest::blocking::Client::new()
+            .get(&self.url)
+            .timeout(std::time::Duration::from_secs(30));
+        if cache_file.exists()
+            && let Ok(etag) = fs::read_to_string(self.etag_file())
+        {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send()?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return self.cached();
+        }
+        let response = response.error_for_status()?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(ToString::to_string);
+        let text = response.text()?;
+        fs::create_dir_all(&self.cache_dir)?;
+        fs::write(&cache_file, &text)?;
+        match etag {
+            Some(etag) => fs::write(self.etag_file(), etag)?,
+            None => {
+                let _ = fs::remove_file(self.etag_file());
+            }
+        }
+        Ok(text)
+    }
+
+    #[cfg(not(feature = "remote"))]
+    fn fetch(&self) -> Result<String> {
+        Err(format!(
+            "Cannot fetch {}: remote data requires the `remote` feature",
+            self.url
+        )
+        .into())
+    }
+
+    fn parse_rows(&self, text: &str) -> Result<Vec<Vec<String>>> {
+        let text = text.trim_start_matches('\u{feff}');
+        if text.trim_start().starts_with('[') {
+            return Ok(serde_json::from_str(text)
+                .map_err(|e| format!("Invalid data from {}: {e}", self.url))?);
+        }
+        Ok(parse_csv(text)
+            .into_iter()
+            .skip(self.header_rows)
+            .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+            .collect())
+    }
+}
+
+/// 解析 CSV：逗号分隔，双引号包围的字段可以包含逗号、换行和写作 `""` 的双引号
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "名称,介绍\r\n阿米娅,\"罗德岛的\"\"领袖\"\"，\n公开身份\"\r\n\r\n凯尔希,";
+        assert_eq!(
+            parse_csv(csv),
+            [
+                vec!["名称", "介绍"],
+                vec!["阿米娅", "罗德岛的\"领袖\"，\n公开身份"],
+                vec![""],
+                vec!["凯尔希", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_offline_cache() {
+        let url = "https://docs.google.com/spreadsheets/d/e/x/pub?output=csv#header=0";
+        let mut source = RemoteSource::parse(Path::new(url)).unwrap().unwrap();
+        assert_eq!(source.header_rows, 0);
+        assert!(!source.url.contains('#'));
+        assert_eq!(RemoteSource::parse(Path::new("data.json")).unwrap(), None);
+
+        source.cache_dir =
+            std::env::temp_dir().join(format!("to_video_remote_{}", std::process::id()));
+        assert!(source.load_rows(true).is_err());
+        fs::create_dir_all(&source.cache_dir).unwrap();
+        fs::write(source.cache_file(), "\u{feff}a,b\n\nc,d\n").unwrap();
+        let csv = source.load_rows(true);
+        fs::write(source.cache_file(), r#"[["a", "b"]]"#).unwrap();
+        let json = source.load_rows(true);
+        fs::remove_dir_all(&source.cache_dir).unwrap();
+        assert_eq!(csv.unwrap(), [["a", "b"], ["c", "d"]]);
+        assert_eq!(json.unwrap(), [["a", "b"]]);
+    }
+}
@@ -0,0 +1,150 @@
+//! SQLite 数据源：info 文件中的 `data` 写作 `sqlite://<数据库文件>?query=<SQL>`，
+//! 查询结果的每一行是一个 slide 的数据，各列依次转为文字。
+//!
+//! 例如 `sqlite://chars.db?query=SELECT avatar, name, rarity FROM chars ORDER BY rarity DESC`。
+//! 查询中的 `?`、`#` 等字符可以写作 `%3F`、`%23`。需要启用 `sqlite` feature。
+
+use std::path::{Path, PathBuf};
+use to_video::Result;
+
+const SCHEME: &str = "sqlite://";
+
+/// 一个 SQLite 查询
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteSource {
+    /// 数据库文件
+    pub path: PathBuf,
+    pub query: String,
+}
+
+impl SqliteSource {
+    /// 解析 `sqlite://` 数据源，`data` 不以 `sqlite://` 开头时为 `None`
+    ///
+    /// # Errors
+    /// - 缺少数据库文件或 `query` 参数时返回 `Err`。
+    ///
+    pub fn parse(data: &Path) -> Result<Option<Self>> {
+        let Some(url) = data.to_str().and_then(|s| s.strip_prefix(SCHEME)) else {
+            return Ok(None);
+        };
+        let usage = || format!("Invalid data source {url:?}, expected sqlite://<file>?query=<SQL>");
+        let (path, params) = url.split_once('?').ok_or_else(usage)?;
+        let query = params.strip_prefix("query=").ok_or_else(usage)?;
+        if path.is_empty() || query.trim().is_empty() {
+            return Err(usage().into());
+        }
+        Ok(Some(Self {
+            path: PathBuf::from(percent_decode(path)?),
+            query: percent_decode(query)?,
+        }))
+    }
+
+    /// 执行查询，每一行结果转为一行数据：`NULL` 为空字符串，数字和文字转为文字
+    ///
+    /// # Errors
+    /// - 数据库无法打开、查询无效或结果中有二进制数据时返回 `Err`。
+    ///
+    #[cfg(feature = "sqlite")]
+    pub fn load_rows(&self) -> Result<Vec<Vec<String>>> {
+        use rusqlite::{Connection, OpenFlags, types::ValueRef};
+
+        let invalid = |e: rusqlite::Error| format!("SQLite {}: {e}", self.path.display());
+        let connection = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(invalid)?;
+        let mut statement = connection.prepare(&self.query).map_err(invalid)?;
+        let columns = statement.column_count();
+        let mut rows = statement.query([]).map_err(invalid)?;
+        let mut datas = Vec::new();
+        while let Some(row) = rows.next().map_err(invalid)? {
+            let data = (0..columns)
+                .map(|column| match row.get_ref(column).map_err(invalid)? {
+                    ValueRef::Null => Ok(String::new()),
+                    ValueRef::Integer(n) => Ok(n.to_string()),
+                    ValueRef::Real(n) => Ok(n.to_string()),
+                    ValueRef::Text(text) => Ok(String::from_utf8_lossy(text).into_owned()),
+                    ValueRef::Blob(_) => Err(format!(
+                        "SQLite {}: column {column} is a blob, expected text or a number",
+                        self.path.display()
+                    )),
+                })
+                .collect::<std::result::Result<_, _>>()?;
+            datas.push(data);
+        }
+        Ok(datas)
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    pub fn load_rows(&self) -> Result<Vec<Vec<String>>> {
+        Err(format!(
+            "Cannot read {}: sqlite data sources require the `sqlite` feature",
+            self.path.display()
+        )
+        .into())
+    }
+}
+
+/// 解码 `%XX` 形式的转义
+pub(crate) fn percent_decode(s: &str) -> Result<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("Invalid percent escape in {s:?}"))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(String::from_utf8(decoded)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let source = SqliteSource::parse(Path::new(
+            "sqlite://db/chars.db?query=SELECT name FROM chars WHERE name LIKE '%25米%3F'",
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(source.path, PathBuf::from("db/chars.db"));
+        assert_eq!(
+            source.query,
+            "SELECT name FROM chars WHERE name LIKE '%米?'"
+        );
+
+        assert_eq!(SqliteSource::parse(Path::new("data.json")).unwrap(), None);
+        assert!(SqliteSource::parse(Path::new("sqlite://chars.db")).is_err());
+        assert!(SqliteSource::parse(Path::new("sqlite://chars.db?query=%zz")).is_err());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_load_rows() {
+        let path = std::env::temp_dir().join(format!("to_video_sqlite_{}.db", std::process::id()));
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE chars (name TEXT, rarity INTEGER, note TEXT);
+                 INSERT INTO chars VALUES ('阿米娅', 5, NULL), ('凯尔希', 6, '医疗');",
+            )
+            .unwrap();
+        drop(connection);
+
+        let data = PathBuf::from(format!(
+            "sqlite://{}?query=SELECT name, rarity, note FROM chars ORDER BY rarity DESC",
+            path.display()
+        ));
+        let rows = crate::load_data(&data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rows, [["凯尔希", "6", "医疗"], ["阿米娅", "5", ""]]);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{Info, load_data, load_info, sqlite::SqliteSource, xlsx::XlsxSource};
+use notify::{Config, Event, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant},
+};
+use to_video::Result;
+
+/// 系统的文件通知不可用时，轮询文件变化的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+/// 文件在这段时间内没有再变化才重新生成，避免保存一次触发多次生成
+const DEBOUNCE: Duration = Duration::from_millis(500);
+/// 预览视频的缩放比例
+pub const PREVIEW_SCALE: f32 = 0.5;
+
+/// 读取命令行参数 `watch --info <info.json>`
+pub fn watch_arg() -> Result<Option<PathBuf>> {
+    let mut args = std::env::args().skip(1);
+    if args.next().is_none_or(|arg| arg != "watch") {
+        return Ok(None);
+    }
+    match (args.next().as_deref(), args.next()) {
+        (Some("--info"), Some(info)) => Ok(Some(PathBuf::from(info))),
+        _ => Err("usage: to_video_cmd watch --info <info.json>".into()),
+    }
+}
+
+/// 生成低分辨率预览，之后每当 info 文件、数据文件、字体或数据中引用的文件变化时重新生成。
+///
+/// 文件变化由 `notify` 监听，系统的文件通知不可用时改为轮询。
+/// 生成失败时只打印错误并继续监视，修改文件后会再次尝试。
+///
+/// # Errors
+/// - 无法监视文件时返回 `Err`。
+///
+pub fn watch(info_file: &Path) -> Result<()> {
+    loop {
+        let files = watched_files(info_file);
+        // 在生成之前开始监视，生成期间的修改也会触发下一次生成
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher: Box<dyn Watcher> =
+            match RecommendedWatcher::new(sender.clone(), Config::default()) {
+                Ok(watcher) => Box::new(watcher),
+                Err(e) => {
+                    eprintln!("file notifications unavailable ({e}), polling for changes");
+                    Box::new(poll_watcher(sender, POLL_INTERVAL)?)
+                }
+            };
+        let files = watch_files(watcher.as_mut(), &files)?;
+        let t = Instant::now();
+        match render_preview(info_file) {
+            Ok(path) => println!(
+                "preview {} generated in {} ms",
+                path.display(),
+                t.elapsed().as_millis()
+            ),
+            Err(e) => eprintln!("preview failed: {e}"),
+        }
+        println!("watching {} files for changes...", files.len());
+        wait_for_change(&receiver, &files);
+    }
+}
+
+fn render_preview(info_file: &Path) -> Result<PathBuf> {
+    let mut info = load_info(info_file)?;
+    let save_path = preview_path(info.config.save_path.as_deref());
+    info.config = info.config.save_path(save_path.clone());
+    info.into_builder()?
+        .build_preview(PREVIEW_SCALE)?
+        .run(|_, _, _| Ok(()))?;
+    Ok(save_path)
+}
+
+/// 预览视频保存在正式视频旁边，文件名加上 `_preview`，避免覆盖正式视频
+fn preview_path(save_path: Option<&Path>) -> PathBuf {
+    match save_path {
+        Some(path) => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{stem}_preview.mp4"))
+        }
+        None => PathBuf::from("preview.mp4"),
+    }
+}
+
+/// 需要监视的文件。info 文件或数据文件无法读取时只监视能读取到的部分
+fn watched_files(info_file: &Path) -> Vec<PathBuf> {
+    let mut files = vec![info_file.to_path_buf()];
+    let Ok(Info { config, data, .. }) = load_info(info_file) else {
+        return files;
+    };
+    files.extend(config.font);
+    files.extend(config.fallback_fonts);
+    if let Ok(rows) = load_data(&data) {
+        files.extend(referenced_files(&rows));
+    }
+    // SQLite 数据源监视数据库文件，Excel 数据文件去掉参数
+    if let Ok(Some(source)) = SqliteSource::parse(&data) {
+        files.push(source.path);
+    } else if let Ok(Some(source)) = XlsxSource::parse(&data) {
+        files.push(source.path);
+    } else {
+        files.push(data);
+    }
+    files
+}
+
+/// 数据中指向已存在文件的单元格，即图片等素材
+fn referenced_files(rows: &[Vec<String>]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = rows
+        .iter()
+        .flatten()
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// 轮询文件变化的 watcher。修改时间只精确到秒，因此同时比较文件内容
+fn poll_watcher(sender: Sender<notify::Result<Event>>, interval: Duration) -> Result<PollWatcher> {
+    let config = Config::default()
+        .with_poll_interval(interval)
+        .with_compare_contents(true);
+    Ok(PollWatcher::new(sender, config)?)
+}
+
+/// 监视 `files` 所在的目录，返回监视的文件的完整路径。
+///
+/// 编辑器保存时常常写入新文件再替换原文件，因此监视目录而不是文件本身
+fn watch_files(watcher: &mut dyn Watcher, files: &[PathBuf]) -> Result<HashSet<PathBuf>> {
+    let mut dirs = HashSet::new();
+    let mut watched = HashSet::new();
+    for file in files {
+        let (Some(dir), Some(name)) = (file.parent(), file.file_name()) else {
+            continue;
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let Ok(dir) = dir.canonicalize() else {
+            continue;
+        };
+        if dirs.insert(dir.clone()) {
+            watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        }
+        watched.insert(dir.join(name));
+    }
+    Ok(watched)
+}
+
+/// 事件是否涉及 `files` 中的文件
+fn is_relevant(event: &notify::Result<Event>, files: &HashSet<PathBuf>) -> bool {
+    match event {
+        Ok(event) => !event.kind.is_access() && event.paths.iter().any(|path| files.contains(path)),
+        // 无法确定哪些文件变化了，重新生成
+        Err(_) => true,
+    }
+}
+
+/// 等待 `files` 中的文件变化，并且在 [`DEBOUNCE`] 内不再变化
+fn wait_for_change(receiver: &Receiver<notify::Result<Event>>, files: &HashSet<PathBuf>) {
+    loop {
+        let Ok(event) = receiver.recv() else {
+            return;
+        };
+        if is_relevant(&event, files) {
+            break;
+        }
+    }
+    let mut changed_at = Instant::now();
+    while let Some(timeout) = DEBOUNCE.checked_sub(changed_at.elapsed()) {
+        match receiver.recv_timeout(timeout) {
+            Ok(event) if is_relevant(&event, files) => changed_at = Instant::now(),
+            Ok(_) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_preview_path() {
+        assert_eq!(
+            preview_path(Some(Path::new("out/video.mp4"))),
+            Path::new("out/video_preview.mp4")
+        );
+        assert_eq!(preview_path(None), Path::new("preview.mp4"));
+    }
+
+    #[test]
+    fn test_referenced_files() {
+        let image = std::env::temp_dir().join(format!(
+            "to_video_cmd_test_watch_{}.png",
+            std::process::id()
+        ));
+        fs::write(&image, b"").unwrap();
+        let image_str = image.to_string_lossy().to_string();
+        let rows = vec![
+            vec![image_str.clone(), "name".to_string()],
+            vec![image_str, "no/such/file.png".to_string()],
+        ];
+        assert_eq!(referenced_files(&rows), std::slice::from_ref(&image));
+        fs::remove_file(image).unwrap();
+    }
+
+    #[test]
+    fn test_wait_for_change() {
+        let dir =
+            std::env::temp_dir().join(format!("to_video_cmd_test_notify_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (watched, other) = (dir.join("data.json"), dir.join("other.json"));
+        fs::write(&watched, "[]").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = poll_watcher(sender, Duration::from_millis(50)).unwrap();
+        let files = watch_files(&mut watcher, std::slice::from_ref(&watched)).unwrap();
+        assert!(files.contains(&dir.canonicalize().unwrap().join("data.json")));
+
+        fs::write(&other, "[]").unwrap();
+        fs::write(&watched, "[[\"a\"]]").unwrap();
+        let t = Instant::now();
+        wait_for_change(&receiver, &files);
+        assert!(t.elapsed() >= DEBOUNCE);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+//! Excel 数据文件：info 文件中的 `data` 可以是 `.xlsx`、`.xlsm`、`.xls` 或 `.ods` 文件，
+//! 每一行是一个 slide 的数据。需要启用 `xlsx` feature。
+//!
+//! 文件名之后可以加参数，例如 `干员.xlsx?sheet=六星&header=2`：
+//! - `sheet`：工作表的名称，或从 1 开始的序号，默认为第一个工作表；
+//! - `header`：开头跳过的表头行数，默认为 1，没有表头时写 `header=0`。
+//!
+//! 完全空白的行被忽略。数字按显示的形式转为文字（`5.0` 为 `5`），日期转为 `2024-01-31`。
+
+use crate::sqlite::percent_decode;
+use std::path::{Path, PathBuf};
+use to_video::Result;
+
+const EXTENSIONS: [&str; 4] = ["xlsx", "xlsm", "xls", "ods"];
+
+/// 一个工作表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XlsxSource {
+    /// 工作簿文件
+    pub path: PathBuf,
+    /// 工作表名称或从 1 开始的序号，`None` 时为第一个工作表
+    pub sheet: Option<String>,
+    /// 跳过的表头行数
+    pub header_rows: usize,
+}
+
+impl XlsxSource {
+    /// 解析 Excel 数据文件，`data` 不是 Excel 文件时为 `None`
+    ///
+    /// # Errors
+    /// - 参数无效时返回 `Err`。
+    ///
+    pub fn parse(data: &Path) -> Result<Option<Self>> {
+        let Some(data) = data.to_str() else {
+            return Ok(None);
+        };
+        let (path, params) = data.split_once('?').unwrap_or((data, ""));
+        let is_excel = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        if !is_excel {
+            return Ok(None);
+        }
+        let mut source = Self {
+            path: PathBuf::from(path),
+            sheet: None,
+            header_rows: 1,
+        };
+        for param in params.split('&').filter(|param| !param.is_empty()) {
+            let invalid = || format!("Invalid data file parameter {param:?} in {data:?}");
+            match param.split_once('=').ok_or_else(invalid)? {
+                ("sheet", sheet) => source.sheet = Some(percent_decode(sheet)?),
+                ("header", rows) => source.header_rows = rows.parse().map_err(|_| invalid())?,
+                _ => return Err(invalid().into()),
+            }
+        }
+        Ok(Some(source))
+    }
+
+    /// 读取工作表，每一行转为一行数据
+    ///
+    /// # Errors
+    /// - 文件无法读取、工作表不存在或单元格中有错误值（例如 `#DIV/0!`）时返回 `Err`。
+    ///
+    #[cfg(feature = "xlsx")]
+    pub fn load_rows(&self) -> Result<Vec<Vec<String>>> {
+        use calamine::{DataType, Reader, open_workbook_auto};
+
+        let file = self.path.display();
+        let mut workbook = open_workbook_auto(&self.path).map_err(|e| format!("{file}: {e}"))?;
+        let names = workbook.sheet_names();
+        let name = match &self.sheet {
+            None => names.first(),
+            Some(sheet) => names.iter().find(|name| *name == sheet).or_else(|| {
+                let index = sheet.parse::<usize>().ok()?;
+                names.get(index.checked_sub(1)?)
+            }),
+        }
+        .ok_or_else(|| format!("{file}: sheet {:?} not found", self.sheet))?
+        .clone();
+        let range = workbook
+            .worksheet_range(&name)
+            .map_err(|e| format!("{file}: {e}"))?;
+        let (first_row, _) = range.start().unwrap_or_default();
+        range
+            .rows()
+            .enumerate()
+            .skip(self.header_rows)
+            .filter(|(_, row)| row.iter().any(|cell| !cell.is_empty()))
+            .map(|(index, row)| {
+                row.iter()
+                    .map(|cell| {
+                        cell_text(cell).ok_or_else(|| {
+                            let row = first_row as usize + index + 1;
+                            format!("{file}: sheet {name:?} row {row} contains error {cell}").into()
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[cfg(not(feature = "xlsx"))]
+    pub fn load_rows(&self) -> Result<Vec<Vec<String>>> {
+        Err(format!(
+            "Cannot read {}: Excel data files require the `xlsx` feature",
+            self.path.display()
+        )
+        .into())
+    }
+}
+
+/// 单元格的文字，错误值为 `None`
+#[cfg(feature = "xlsx")]
+fn cell_text(cell: &calamine::Data) -> Option<String> {
+    use calamine::Data;
+
+    let text = match cell {
+        Data::Error(_) => return None,
+        Data::DateTime(datetime) if datetime.is_datetime() => {
+            let (year, month, day, hour, minute, second, _) = datetime.to_ymd_hms_milli();
+            match (hour, minute, second) {
+                (0, 0, 0) => format!("{year}-{month:0>2}-{day:0>2}"),
+                _ => format!("{year}-{month:0>2}-{day:0>2} {hour:0>2}:{minute:0>2}:{second:0>2}"),
+            }
+        }
+        cell => cell.to_string(),
+    };
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let source = XlsxSource::parse(Path::new(
+            "data/干员.XLSX?sheet=%E5%85%AD%E6%98%9F&header=2",
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            source,
+            XlsxSource {
+                path: PathBuf::from("data/干员.XLSX"),
+                sheet: Some("六星".to_string()),
+                header_rows: 2,
+            }
+        );
+        assert_eq!(
+            XlsxSource::parse(Path::new("a.ods"))
+                .unwrap()
+                .unwrap()
+                .header_rows,
+            1
+        );
+        assert_eq!(XlsxSource::parse(Path::new("data.json")).unwrap(), None);
+        assert!(XlsxSource::parse(Path::new("a.xlsx?rows=1")).is_err());
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[test]
+    fn test_load_rows() {
+        use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+        let path = std::env::temp_dir().join(format!("to_video_xlsx_{}.xlsx", std::process::id()));
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("其他").unwrap();
+        let sheet = workbook.add_worksheet().set_name("干员").unwrap();
+        sheet.write_row(0, 0, ["名称", "星级", "上线日期"]).unwrap();
+        sheet.write(1, 0, "阿米娅").unwrap();
+        sheet.write(1, 1, 5).unwrap();
+        let date = ExcelDateTime::from_ymd(2019, 4, 30).unwrap();
+        let format = Format::new().set_num_format("yyyy/mm/dd");
+        sheet
+            .write_datetime_with_format(1, 2, &date, &format)
+            .unwrap();
+        sheet.write(3, 0, "凯尔希").unwrap();
+        sheet.write(3, 1, 6.5).unwrap();
+        workbook.save(&path).unwrap();
+
+        let data = PathBuf::from(format!("{}?sheet=2", path.display()));
+        let rows = crate::load_data(&data);
+        let named = crate::load_data(&PathBuf::from(format!(
+            "{}?sheet=干员&header=0",
+            path.display()
+        )));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            rows.unwrap(),
+            [["阿米娅", "5", "2019-04-30"], ["凯尔希", "6.5", ""]]
+        );
+        assert_eq!(named.unwrap().len(), 3);
+    }
+}
@@ -1,20 +1,35 @@
-#![allow(unused)]
-use super::set_fonts;
-use crate::{
-    color::Color,
-    init::Operation,
-    slide::{Position, Slide},
-    video::{Video, VideoConfigBuilder},
-    Result,
+use super::{
+    editor::LayoutEditor,
+    history::History,
+    set_fonts,
+    timeline::Timeline,
+    widgets::{number_edit, parses, path_edit, validated_edit},
+    worker::{Progress, RenderJob},
 };
 use ab_glyph::FontArc;
-use eframe::egui;
+use eframe::egui::{
+    self, ColorImage, Key, KeyboardShortcut, Modifiers, TextureHandle, TextureOptions,
+};
+use rfd::FileDialog;
 use std::{fs, path::PathBuf};
+use to_video::{
+    Result,
+    color::Color,
+    slide::{Operation, Position, RenderContext, Shadow, Slide, TextQuality},
+    video::{
+        ConfigIssues, Video, VideoConfigBuilder, chunk::chunk_ranges, ffmpeg::combain_slides,
+        repeat::RepeatItem,
+    },
+};
 
 pub struct MyApp {
     pub operation: Vec<Operation>,
     pub selected_var: String,
     pub input_fields: Vec<String>,
+    /// 新建文字/背景色操作时使用的颜色
+    pub input_color: [u8; 3],
+    /// 新建图片/背景色操作时是否添加投影
+    pub input_shadow: bool,
     pub screen: (String, String),
     pub fps: String,
     pub work_dir: String,
@@ -26,26 +41,77 @@ pub struct MyApp {
     pub save_path: String,
     pub step: String,
     pub font: String,
+    pub data_path: String,
+    pub text_quality: TextQuality,
     pub frame: u32,
-    pub preview: bool,
+    pub preview: Option<TextureHandle>,
     pub output: Vec<String>,
+    editor: LayoutEditor,
+    job: Option<RenderJob>,
+    /// 渲染进度 (已完成, 总数)
+    progress: Option<(usize, usize)>,
+    /// 已加载的数据，每行对应一个 slide
+    data: Vec<Vec<String>>,
+    /// 预览中显示的数据行
+    row: usize,
+    history: History<Snapshot>,
+    timeline: Timeline,
+    /// 时间轴中显示的 chunk 序号
+    timeline_chunk: usize,
+}
+
+/// 可撤销的编辑状态：操作列表和视频参数
+#[derive(Clone, Default)]
+struct Snapshot {
+    operation: Vec<Operation>,
+    screen: (String, String),
+    fps: String,
+    work_dir: String,
+    back_color: String,
+    cover_sec: String,
+    ending_sec: String,
+    swip_pixels_per_sec: String,
+    width_slides: String,
+    save_path: String,
+    step: String,
+    font: String,
+    data_path: String,
+    text_quality: TextQuality,
 }
 
-#[derive(PartialEq, Debug)]
-enum Operation1 {
-    Image(i32),
-    Text,
-    Color,
+impl PartialEq for Snapshot {
+    fn eq(&self, other: &Self) -> bool {
+        self.operation.len() == other.operation.len()
+            && self
+                .operation
+                .iter()
+                .zip(&other.operation)
+                .all(|(a, b)| a.same_as(b))
+            && self.screen == other.screen
+            && self.fps == other.fps
+            && self.work_dir == other.work_dir
+            && self.back_color == other.back_color
+            && self.cover_sec == other.cover_sec
+            && self.ending_sec == other.ending_sec
+            && self.swip_pixels_per_sec == other.swip_pixels_per_sec
+            && self.width_slides == other.width_slides
+            && self.save_path == other.save_path
+            && self.step == other.step
+            && self.font == other.font
+            && self.data_path == other.data_path
+            && self.text_quality == other.text_quality
+    }
 }
 
 impl MyApp {
     pub fn new(cc: &eframe::CreationContext) -> Self {
         set_fonts(&cc.egui_ctx);
-        egui_extras::install_image_loaders(&cc.egui_ctx);
-        Self {
+        let mut app = Self {
             operation: vec![],
             selected_var: "".to_string(),
             input_fields: vec![],
+            input_color: [0, 0, 0],
+            input_shadow: false,
             screen: ("1920".to_string(), "1080".to_string()),
             fps: "60".to_string(),
             work_dir: "".to_string(),
@@ -57,26 +123,125 @@ impl MyApp {
             save_path: "".to_string(),
             step: "20".to_string(),
             font: "".to_string(),
+            data_path: "".to_string(),
+            text_quality: TextQuality::Normal,
             frame: 0,
-            preview: false,
+            preview: None,
             output: vec![],
+            editor: LayoutEditor::default(),
+            job: None,
+            progress: None,
+            data: vec![],
+            row: 0,
+            history: History::new(Snapshot::default(), 0),
+            timeline: Timeline::default(),
+            timeline_chunk: 0,
+        };
+        app.history = History::new(app.snapshot(), 100);
+        app
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            operation: self.operation.clone(),
+            screen: self.screen.clone(),
+            fps: self.fps.clone(),
+            work_dir: self.work_dir.clone(),
+            back_color: self.back_color.clone(),
+            cover_sec: self.cover_sec.clone(),
+            ending_sec: self.ending_sec.clone(),
+            swip_pixels_per_sec: self.swip_pixels_per_sec.clone(),
+            width_slides: self.width_slides.clone(),
+            save_path: self.save_path.clone(),
+            step: self.step.clone(),
+            font: self.font.clone(),
+            data_path: self.data_path.clone(),
+            text_quality: self.text_quality,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot, ctx: &egui::Context) {
+        self.operation = snapshot.operation;
+        self.screen = snapshot.screen;
+        self.fps = snapshot.fps;
+        self.work_dir = snapshot.work_dir;
+        self.back_color = snapshot.back_color;
+        self.cover_sec = snapshot.cover_sec;
+        self.ending_sec = snapshot.ending_sec;
+        self.swip_pixels_per_sec = snapshot.swip_pixels_per_sec;
+        self.width_slides = snapshot.width_slides;
+        self.save_path = snapshot.save_path;
+        self.step = snapshot.step;
+        self.font = snapshot.font;
+        self.data_path = snapshot.data_path;
+        self.text_quality = snapshot.text_quality;
+        if self
+            .editor
+            .selected
+            .is_some_and(|index| index >= self.operation.len())
+        {
+            self.editor.selected = None;
+        }
+        if self.preview.is_some()
+            && let Err(e) = self.preview(ctx)
+        {
+            self.output.push(e.to_string());
+        }
+    }
+
+    /// 在没有输入框获得焦点、也没有正在拖动时记录当前状态，
+    /// 这样一次输入或一次拖动只形成一步撤销
+    fn record_history(&mut self, ctx: &egui::Context) {
+        let editing = ctx.memory(|m| m.focused().is_some());
+        let dragging = ctx.input(|i| i.pointer.any_down());
+        if !editing && !dragging {
+            let snapshot = self.snapshot();
+            self.history.record(&snapshot);
+        }
+    }
+
+    fn undo(&mut self, ctx: &egui::Context) {
+        if let Some(snapshot) = self.history.undo() {
+            self.restore(snapshot, ctx);
+        }
+    }
+
+    fn redo(&mut self, ctx: &egui::Context) {
+        if let Some(snapshot) = self.history.redo() {
+            self.restore(snapshot, ctx);
+        }
+    }
+
+    /// Ctrl+Z 撤销，Ctrl+Y / Ctrl+Shift+Z 重做；输入框获得焦点时交给输入框自己处理
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        let redo_shift = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+        let redo = KeyboardShortcut::new(Modifiers::COMMAND, Key::Y);
+        let undo = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+        if ctx.input_mut(|i| i.consume_shortcut(&redo_shift) || i.consume_shortcut(&redo)) {
+            self.redo(ctx);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+            self.undo(ctx);
         }
     }
 
     pub fn show_content(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label("screen: ");
-            ui.add(egui::TextEdit::singleline(&mut self.screen.0).desired_width(50.0));
-            ui.add(egui::TextEdit::singleline(&mut self.screen.1).desired_width(50.0));
+            number_edit::<u32>(ui, &mut self.screen.0, 50.0);
+            number_edit::<u32>(ui, &mut self.screen.1, 50.0);
         });
         ui.horizontal(|ui| {
             let fps_label = ui.label("fps: ");
-            ui.add(egui::TextEdit::singleline(&mut self.fps).desired_width(50.0))
-                .labelled_by(fps_label.id);
+            number_edit::<u32>(ui, &mut self.fps, 50.0).labelled_by(fps_label.id);
         });
         ui.horizontal(|ui| {
             ui.label("work_dir: ");
-            ui.add(egui::TextEdit::singleline(&mut self.work_dir).hint_text("default: ./work"));
+            path_edit(ui, &mut self.work_dir, "default: ./work", || {
+                FileDialog::new().set_title("Select work dir").pick_folder()
+            });
         });
         ui.horizontal(|ui| {
             ui.label("back_color: ");
@@ -84,37 +249,92 @@ impl MyApp {
         });
         ui.horizontal(|ui| {
             ui.label("cover_sec: ");
-            ui.add(egui::TextEdit::singleline(&mut self.cover_sec).desired_width(50.0));
+            number_edit::<f32>(ui, &mut self.cover_sec, 50.0);
         });
         ui.horizontal(|ui| {
             ui.label("ending_sec: ");
-            ui.add(egui::TextEdit::singleline(&mut self.ending_sec).desired_width(50.0));
+            number_edit::<u32>(ui, &mut self.ending_sec, 50.0);
         });
         ui.horizontal(|ui| {
             ui.label("swip_pixels_per_sec: ");
-            ui.add(egui::TextEdit::singleline(&mut self.swip_pixels_per_sec).desired_width(50.0));
+            number_edit::<u32>(ui, &mut self.swip_pixels_per_sec, 50.0);
         });
         ui.horizontal(|ui| {
             ui.label("width_slides: ");
-            ui.add(egui::TextEdit::singleline(&mut self.width_slides).desired_width(50.0));
+            number_edit::<u32>(ui, &mut self.width_slides, 50.0);
         });
         ui.horizontal(|ui| {
             ui.label("save_path: ");
-            ui.add(
-                egui::TextEdit::singleline(&mut self.save_path)
-                    .hint_text("default: work_dir/output.mp4"),
+            path_edit(
+                ui,
+                &mut self.save_path,
+                "default: work_dir/output.mp4",
+                || {
+                    FileDialog::new()
+                        .add_filter("mp4", &["mp4"])
+                        .set_title("Save video as")
+                        .save_file()
+                },
             );
         });
         ui.horizontal(|ui| {
             ui.label("step: ");
-            ui.add(egui::TextEdit::singleline(&mut self.step).desired_width(50.0));
+            number_edit::<u32>(ui, &mut self.step, 50.0);
+        });
+        ui.horizontal(|ui| {
+            ui.label("text_quality: ");
+            egui::ComboBox::from_id_salt("text_quality")
+                .selected_text(format!("{:?}", self.text_quality))
+                .show_ui(ui, |ui| {
+                    for quality in [
+                        TextQuality::Normal,
+                        TextQuality::Supersample2x,
+                        TextQuality::Supersample4x,
+                    ] {
+                        ui.selectable_value(
+                            &mut self.text_quality,
+                            quality,
+                            format!("{quality:?}"),
+                        );
+                    }
+                });
         });
         ui.horizontal(|ui| {
             ui.label("font: ");
-            ui.add(egui::TextEdit::singleline(&mut self.font));
+            path_edit(ui, &mut self.font, "ttf / otf", || {
+                FileDialog::new()
+                    .add_filter("font", &["ttf", "otf"])
+                    .set_title("Select font file")
+                    .pick_file()
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.label("data: ");
+            let picked = path_edit(ui, &mut self.data_path, "data json", || {
+                FileDialog::new()
+                    .add_filter("json", &["json"])
+                    .set_title("Select data json file")
+                    .pick_file()
+            });
+            if picked || ui.button("加载").clicked() {
+                self.load_data();
+            }
+            ui.label(format!("{} 行", self.data.len()));
         });
     }
 
+    /// 使用与 `to_video_cmd` 相同的逻辑读取数据文件
+    fn load_data(&mut self) {
+        match to_video_cmd::load_data(PathBuf::from(&self.data_path).as_path()) {
+            Ok(data) => {
+                self.output.push(format!("已加载 {} 行数据", data.len()));
+                self.data = data;
+                self.row = 0;
+            }
+            Err(e) => self.output.push(e.to_string()),
+        }
+    }
+
     pub fn to_config(&self) -> Result<VideoConfigBuilder> {
         let work_dir = if self.work_dir.is_empty() {
             None
@@ -143,50 +363,317 @@ impl MyApp {
             save_path,
             step: self.step.parse()?,
             font,
+            text_quality: self.text_quality,
+            ..VideoConfigBuilder::new()
         })
     }
 
-    pub fn preview(&self) -> Result<()> {
-        let font_buf = fs::read(&self.font).unwrap_or_else(|_| {
-            let font = std::env::current_dir()
-                .unwrap()
+    /// slide 的尺寸 (width_slides, screen.1)
+    fn slide_size(&self) -> Result<(u32, u32)> {
+        Ok((self.width_slides.parse()?, self.screen.1.parse()?))
+    }
+
+    /// 按 z_index 排序后的操作列表，与 `Video::builder` 中的顺序一致
+    fn sorted_operations(&self) -> Vec<Operation> {
+        let mut operations = self.operation.clone();
+        operations.sort();
+        operations
+    }
+
+    /// 预览使用的渲染上下文，不绘制分割线
+    fn render_context(&self) -> Result<RenderContext> {
+        Ok(RenderContext {
+            text_quality: self.text_quality,
+            ..RenderContext::new(self.load_font()?)
+        })
+    }
+
+    fn load_font(&self) -> Result<FontArc> {
+        let font_buf = fs::read(&self.font).or_else(|_| {
+            let font = std::env::current_dir()?
                 .join("example")
                 .join("MiSans-Demibold.ttf");
-            fs::read(font).unwrap()
-        });
-        let font = FontArc::try_from_vec(font_buf)?;
-        Slide::new_default(1)
-            .render(self.width_slides.parse()?, self.screen.1.parse()?, &font)?
-            .save("preview.png")?;
+            fs::read(font)
+        })?;
+        Ok(FontArc::try_from_vec(font_buf)?)
+    }
+
+    pub fn preview(&mut self, ctx: &egui::Context) -> Result<()> {
+        let render_ctx = self.render_context()?;
+        let operations = self.sorted_operations();
+        let size = self.slide_size()?;
+        let row = match self.data.get(self.row) {
+            Some(row) => row.clone(),
+            None => sample_row(&operations),
+        };
+        let img = Slide::generation(&operations, row)?
+            .render(size, &render_ctx)?
+            .into_rgba8();
+        let image = ColorImage::from_rgba_unmultiplied(
+            [img.width() as usize, img.height() as usize],
+            img.as_raw(),
+        );
+        self.preview = Some(ctx.load_texture("preview", image, TextureOptions::LINEAR));
         Ok(())
     }
 
-    pub fn run(&self) -> Result<()> {
-        let font_buf = fs::read(&self.font)?;
-        let font = FontArc::try_from_vec(font_buf)?;
-        Video::new(
-            vec![
-                Slide::new_default(1),
-                Slide::new_default(1),
-                Slide::new_default(1),
-                Slide::new_default(1),
-                Slide::new_default(1),
-                Slide::new_default(1),
-                Slide::new_default(1),
-                Slide::new_default(1),
-            ],
-            self.to_config()?.build(),
-        )
-        .run()?;
+    /// 组合第 `timeline_chunk` 个 chunk 的长条图并显示在时间轴中，不进行编码
+    pub fn build_timeline(&mut self, ctx: &egui::Context) -> Result<()> {
+        let operations = self.sorted_operations();
+        let config = self.to_config()?;
+        let render_ctx = RenderContext {
+            split_line_color: config.split_line_color,
+            split_line_width: config.split_line_width,
+            ..self.render_context()?
+        };
+        let (step, overlap) = (config.step as usize, self.slide_overlap()? as usize);
+        if step <= overlap {
+            return Err("step is shorter than overlap".into());
+        }
+        let rows = if self.data.is_empty() {
+            vec![sample_row(&operations); step]
+        } else {
+            self.data.clone()
+        };
+        // 与 `VideoBuilder::build` 的分块方式相同
+        let ranges = chunk_ranges(
+            &vec![config.width_slides; rows.len()],
+            config.step * config.width_slides,
+            config.screen.0,
+        )?;
+        let chunk = self.timeline_chunk.min(ranges.len().saturating_sub(1));
+        self.timeline_chunk = chunk;
+        let (range, _) = ranges[chunk].clone();
+        let slides = rows[range]
+            .iter()
+            .map(|row| Slide::generation(&operations, row.clone()))
+            .collect::<Result<Vec<Slide>>>()?;
+        let strip = combain_slides(&slides, &render_ctx, config.width_slides, config.screen)?;
+        let static_sec = if chunk + 1 == ranges.len() {
+            config.ending_sec
+        } else {
+            0
+        };
+        self.timeline.set_strip(
+            ctx,
+            &strip,
+            config.screen.0,
+            config.swip_pixels_per_sec,
+            static_sec,
+        );
+        Ok(())
+    }
+
+    /// 封面中同时显示的 slide 数量
+    fn slide_overlap(&self) -> Result<u32> {
+        let (screen_width, width_slides): (u32, u32) =
+            (self.screen.0.parse()?, self.width_slides.parse()?);
+        if width_slides == 0 {
+            return Err("width_slides is zero".into());
+        }
+        Ok(screen_width / width_slides)
+    }
+
+    /// 在后台线程中开始渲染视频，界面保持响应
+    pub fn run(&mut self, ctx: &egui::Context) -> Result<()> {
+        if self.data.is_empty() {
+            return Err("请先加载数据文件".into());
+        }
+        let mut operations = self.operation.clone();
+        let video = Video::builder(
+            &mut operations,
+            self.data.clone(),
+            self.to_config()?.build()?,
+        )?
+        .build()?;
+        self.output.push("开始生成".to_string());
+        self.progress = Some((0, 0));
+        self.job = Some(RenderJob::spawn(video, ctx.clone()));
         Ok(())
     }
+
+    /// 处理后台渲染线程发来的进度事件
+    fn poll_job(&mut self) {
+        let Some(job) = &self.job else {
+            return;
+        };
+        let mut finished = false;
+        for progress in job.poll() {
+            match progress {
+                Progress::Step { file, done, total } => {
+                    self.output
+                        .push(format!("{done} / {total} : {}  success", file.display()));
+                    self.progress = Some((done, total));
+                }
+                Progress::Finished(result) => {
+                    self.output.push(match result {
+                        Ok(()) => "生成完成".to_string(),
+                        Err(e) => format!("生成失败: {e}"),
+                    });
+                    finished = true;
+                }
+            }
+        }
+        if finished {
+            self.job = None;
+        }
+    }
+
+    /// 根据左侧输入框的内容创建操作，无法解析的输入返回 `None`
+    fn parse_operation(&self) -> Option<Operation> {
+        let inputs: Vec<&str> = self.input_fields.iter().map(|s| s.trim()).collect();
+        let z_index = u8::try_from(self.operation.len()).ok()?;
+        let color = Color(self.input_color);
+        let shadow = self.input_shadow.then_some(DEFAULT_SHADOW);
+        match self.selected_var.as_str() {
+            "图片" if inputs.len() == 4 => Some(Operation::Image {
+                pos: Position::new(
+                    inputs[0].parse().ok()?,
+                    inputs[1].parse().ok()?,
+                    inputs[2].parse().ok()?,
+                ),
+                z_index,
+                // 旋转角度可以留空
+                rotation_deg: match inputs[3] {
+                    "" => 0.0,
+                    deg => deg.parse().ok()?,
+                },
+                shadow,
+                ken_burns: None,
+                animate: false,
+                condition: None,
+            }),
+            "文字" if inputs.len() == 4 => Some(Operation::Text {
+                scale: inputs[0].parse().ok()?,
+                color,
+                pos: Position::new(
+                    inputs[1].parse().ok()?,
+                    inputs[2].parse().ok()?,
+                    inputs[3].parse().ok()?,
+                ),
+                z_index,
+                condition: None,
+                format: None,
+            }),
+            "背景色" if inputs.len() == 3 => Some(Operation::Color {
+                color,
+                pos: Position::new(
+                    inputs[0].parse().ok()?,
+                    inputs[1].parse().ok()?,
+                    inputs[2].parse().ok()?,
+                ),
+                z_index,
+                shadow,
+                condition: None,
+                opacity: 1.0,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// 界面中添加的投影：向右下偏移，半透明黑色
+const DEFAULT_SHADOW: Shadow = Shadow {
+    offset: (6, 6),
+    blur: 6.0,
+    color: Color([0, 0, 0]),
+    opacity: 0.4,
+};
+
+/// 校验函数类型
+type Validator = fn(&str) -> bool;
+
+/// 各操作类型需要输入的字段：(提示文字, 校验函数)
+fn operation_fields(kind: &str) -> &'static [(&'static str, Validator)] {
+    const POSITION: [(&str, Validator); 3] = [
+        ("left", parses::<i32>),
+        ("top", parses::<i32>),
+        ("height", parses::<u32>),
+    ];
+    const IMAGE: [(&str, Validator); 4] = [
+        POSITION[0],
+        POSITION[1],
+        POSITION[2],
+        ("rotation", parses::<f32>),
+    ];
+    const TEXT: [(&str, Validator); 4] = [
+        ("scale", parses::<f32>),
+        POSITION[0],
+        POSITION[1],
+        POSITION[2],
+    ];
+    match kind {
+        "图片" => &IMAGE,
+        "背景色" => &POSITION,
+        "文字" => &TEXT,
+        _ => &[],
+    }
+}
+
+/// 未加载数据时为预览生成一行示例数据：图片使用 example 目录中的示例图片，文字使用占位文本。
+fn sample_row(operations: &[Operation]) -> Vec<String> {
+    operations
+        .iter()
+        .enumerate()
+        .flat_map(|(i, op)| match op {
+            Operation::Image { .. } => vec![format!(
+                "{}",
+                PathBuf::from("example").join("1.png").display()
+            )],
+            Operation::Text { .. } => vec![format!("text_{i}")],
+            Operation::Timestamp { .. } => vec!["2024-03-15".to_string()],
+            Operation::Table {
+                column_widths,
+                rows,
+                ..
+            } => (0..*rows as usize * column_widths.len())
+                .map(|cell| format!("{cell}"))
+                .collect(),
+            Operation::BarChart { labels, .. } | Operation::RadarChart { labels, .. } => {
+                (0..labels.len()).map(|v| format!("{}", v + 1)).collect()
+            }
+            Operation::Repeat {
+                item_template,
+                max,
+                separator,
+                ..
+            } => vec![
+                (0..*max)
+                    .map(|item| match item_template {
+                        RepeatItem::Image => {
+                            format!("{}", PathBuf::from("example").join("1.png").display())
+                        }
+                        RepeatItem::Text { .. } => format!("item_{item}"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(separator),
+            ],
+            Operation::Color { .. } | Operation::Frame { .. } => vec![],
+        })
+        .collect()
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &eframe::egui::Context, _: &mut eframe::Frame) {
-        let mut myenum = Operation1::Text;
+        self.poll_job();
+        self.record_history(ctx);
+        self.handle_shortcuts(ctx);
         egui::SidePanel::left("left").show(ctx, |ui| {
             ui.heading("Slide元素");
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(self.history.can_undo(), egui::Button::new("撤销"))
+                    .clicked()
+                {
+                    self.undo(ctx);
+                }
+                if ui
+                    .add_enabled(self.history.can_redo(), egui::Button::new("重做"))
+                    .clicked()
+                {
+                    self.redo(ctx);
+                }
+            });
             ui.add_space(50.0);
             ui.horizontal(|ui| {
                 egui::ComboBox::from_label("选择操作:")
@@ -196,76 +683,71 @@ impl eframe::App for MyApp {
                         ui.selectable_value(&mut self.selected_var, "文字".to_string(), "文字");
                         ui.selectable_value(&mut self.selected_var, "背景色".to_string(), "背景色");
                     });
-                let num_field = match self.selected_var.as_str() {
-                    "图片" => 3,
-                    "文字" => 7,
-                    "背景色" => 6,
-                    _ => 1,
-                };
-                if self.input_fields.len() != num_field {
-                    self.input_fields.resize(num_field, "".to_string());
+                let fields = operation_fields(&self.selected_var);
+                if self.input_fields.len() != fields.len() {
+                    self.input_fields.resize(fields.len(), "".to_string());
+                }
+                for (field, (hint, valid)) in self.input_fields.iter_mut().zip(fields) {
+                    let ok = field.is_empty() || valid(field);
+                    validated_edit(ui, field, hint, ok, 50.0);
                 }
-                for field in self.input_fields.iter_mut() {
-                    ui.add(egui::TextEdit::singleline(field).desired_width(50.0));
+                if matches!(self.selected_var.as_str(), "文字" | "背景色") {
+                    ui.color_edit_button_srgb(&mut self.input_color);
+                }
+                if matches!(self.selected_var.as_str(), "图片" | "背景色") {
+                    ui.checkbox(&mut self.input_shadow, "阴影");
                 }
                 if ui.button("添加").clicked() {
-                    let inputs: Vec<&str> = self.input_fields.iter().map(|s| s.trim()).collect();
-
-                    match self.selected_var.as_str() {
-                        "图片" if inputs.len() == 3 => {
-                            self.operation.push(Operation::Image(Position::new(
-                                inputs[0].parse().unwrap(),
-                                inputs[1].parse().unwrap(),
-                                inputs[2].parse().unwrap(),
-                            )));
-                        }
-                        "文字" if inputs.len() == 7 => {
-                            self.operation.push(Operation::Text(
-                                inputs[0].parse().unwrap(),
-                                Color([
-                                    inputs[1].parse().unwrap(),
-                                    inputs[2].parse().unwrap(),
-                                    inputs[3].parse().unwrap(),
-                                ]),
-                                Position::new(
-                                    inputs[4].parse().unwrap(),
-                                    inputs[5].parse().unwrap(),
-                                    inputs[6].parse().unwrap(),
-                                ),
-                            ));
-                        }
-                        "背景色" if inputs.len() == 6 => {
-                            self.operation.push(Operation::Color(
-                                Color([
-                                    inputs[0].parse().unwrap(),
-                                    inputs[1].parse().unwrap(),
-                                    inputs[2].parse().unwrap(),
-                                ]),
-                                Position::new(
-                                    inputs[3].parse().unwrap(),
-                                    inputs[4].parse().unwrap(),
-                                    inputs[5].parse().unwrap(),
-                                ),
-                            ));
-                        }
-                        _ => {}
+                    match self.parse_operation() {
+                        Some(op) => self.operation.push(op),
+                        None => self.output.push("无法解析输入的操作参数".to_string()),
                     }
                 }
             });
             ui.add_space(50.0);
-            let mut del = -1;
+            let mut del = None;
+            let mut recolored = false;
             for (index, op) in self.operation.iter_mut().enumerate() {
                 ui.horizontal(|ui| {
-                    ui.label(format!("{:?}", op));
+                    if let Operation::Text { color, .. } | Operation::Color { color, .. } = op {
+                        recolored |= ui.color_edit_button_srgb(&mut color.0).changed();
+                    }
+                    let selected = self.editor.selected == Some(index);
+                    if ui
+                        .selectable_label(selected, format!("{index}: {:?}", op))
+                        .clicked()
+                    {
+                        self.editor.selected = Some(index);
+                    }
                     if ui.button("Delet").clicked() {
-                        del = index as i32;
+                        del = Some(index);
                     };
                 });
             }
-            if del != -1 {
-                self.operation.remove(del as usize);
+            if let Some(index) = del {
+                self.operation.remove(index);
+                self.editor.selected = None;
+            }
+            if recolored
+                && self.preview.is_some()
+                && let Err(e) = self.preview(ctx)
+            {
+                self.output.push(e.to_string());
             }
         });
+        egui::TopBottomPanel::bottom("timeline").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("时间轴预览");
+                ui.label("chunk: ");
+                ui.add(egui::DragValue::new(&mut self.timeline_chunk));
+                if ui.button("时间轴").clicked()
+                    && let Err(e) = self.build_timeline(ctx)
+                {
+                    self.output.push(e.to_string());
+                }
+            });
+            self.timeline.show(ui);
+        });
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("视频参数");
 
@@ -278,36 +760,95 @@ impl eframe::App for MyApp {
             ui.add_space(50.0);
 
             ui.horizontal(|ui| {
-                if ui.button("测试").clicked() {
-                    match self.preview() {
-                        Ok(_) => {
-                            self.preview = true;
-                        }
-                        Err(e) => {
-                            self.output.push(e.to_string());
-                        }
-                    };
+                if ui.button("测试").clicked()
+                    && let Err(e) = self.preview(ctx)
+                {
+                    self.output.push(e.to_string());
                 }
 
-                if ui.button("生成").clicked() {
-                    if let Err(e) = self.run() {
-                        self.output.push(e.to_string());
-                    };
+                let running = self.job.is_some();
+                if ui
+                    .add_enabled(!running, egui::Button::new("生成"))
+                    .clicked()
+                    && let Err(e) = self.run(ctx)
+                {
+                    // 配置中的每个问题单独显示一行
+                    match e.downcast_ref::<ConfigIssues>() {
+                        Some(ConfigIssues(issues)) => self.output.extend(
+                            issues
+                                .iter()
+                                .map(|issue| format!("配置错误 {}: {issue}", issue.field)),
+                        ),
+                        None => self.output.push(e.to_string()),
+                    }
+                }
+
+                if let Some(job) = &self.job {
+                    let cancelling = job.is_cancelling();
+                    if ui
+                        .add_enabled(!cancelling, egui::Button::new("取消"))
+                        .clicked()
+                    {
+                        job.cancel();
+                        self.output.push("正在取消，当前片段完成后停止".to_string());
+                    }
                 }
             });
 
+            if let Some((done, total)) = self.progress {
+                let fraction = if total == 0 {
+                    0.0
+                } else {
+                    done as f32 / total as f32
+                };
+                ui.add(
+                    egui::ProgressBar::new(fraction)
+                        .text(format!("{done} / {total}"))
+                        .animate(self.job.is_some()),
+                );
+            }
+
             ui.add_space(50.0);
 
-            for i in self.output.iter() {
-                ui.label(i);
-            }
-        });
-        egui::SidePanel::right("right").show(ctx, |ui| {
-            ui.heading("预览: ");
-            if self.preview {
-                ui.image("file://preview.png");
-            }
+            egui::ScrollArea::vertical()
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for i in self.output.iter() {
+                        ui.label(i);
+                    }
+                });
         });
+        egui::SidePanel::right("right")
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ui.heading("预览: ");
+                ui.label("拖动元素上下移动，拖动右下角调整边距和高度");
+                if !self.data.is_empty() {
+                    let max_row = self.data.len() - 1;
+                    let slider = ui.add(egui::Slider::new(&mut self.row, 0..=max_row).text("行"));
+                    if slider.changed()
+                        && let Err(e) = self.preview(ctx)
+                    {
+                        self.output.push(e.to_string());
+                    }
+                }
+                match self.slide_size() {
+                    Ok(size) => {
+                        let changed =
+                            self.editor
+                                .show(ui, &mut self.operation, size, self.preview.as_ref());
+                        if changed
+                            && self.preview.is_some()
+                            && let Err(e) = self.preview(ctx)
+                        {
+                            self.output.push(e.to_string());
+                        }
+                    }
+                    Err(e) => {
+                        ui.label(format!("无效的 slide 尺寸: {e}"));
+                    }
+                }
+            });
         self.frame += 1;
     }
 }
@@ -0,0 +1,181 @@
+use eframe::egui::{
+    self, Align2, Color32, CursorIcon, FontId, Pos2, Rect, Sense, Stroke, StrokeKind,
+    TextureHandle, Vec2, pos2, vec2,
+};
+use to_video::slide::{Operation, Position};
+
+/// 右下角缩放手柄的命中半径（屏幕像素）
+const HANDLE: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DragKind {
+    /// 拖动元素主体：上下移动
+    Move,
+    /// 拖动右下角：左右调整边距，上下调整高度
+    Resize,
+}
+
+/// 可视化布局画布：按比例显示 slide 预览，并可用鼠标拖拽、缩放各元素，
+/// 修改结果直接写回 `Operation` 的 `Position`。
+///
+/// `Position` 的左右边距是对称的，因此水平方向的拖拽只能改变元素的宽度（边距），
+/// 无法单独平移。
+#[derive(Default)]
+pub struct LayoutEditor {
+    pub selected: Option<usize>,
+    dragging: Option<(usize, DragKind)>,
+    /// 绝对坐标取整后剩余的拖拽量（slide 像素）
+    residual: Vec2,
+}
+
+impl LayoutEditor {
+    /// 绘制画布并处理拖拽，返回布局是否在本帧完成了一次修改（拖拽结束）。
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        operations: &mut [Operation],
+        slide_size: (u32, u32),
+        background: Option<&TextureHandle>,
+    ) -> bool {
+        let (w, h) = (slide_size.0 as f32, slide_size.1 as f32);
+        let avail = ui.available_size();
+        let scale = (avail.x / w).min(avail.y / h).max(0.05);
+        let (response, painter) =
+            ui.allocate_painter(vec2(w * scale, h * scale), Sense::click_and_drag());
+        let canvas = response.rect;
+
+        painter.rect_filled(canvas, 0.0, Color32::from_gray(40));
+        if let Some(texture) = background {
+            painter.image(
+                texture.id(),
+                canvas,
+                Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                Color32::WHITE,
+            );
+        }
+
+        let rects: Vec<Rect> = operations
+            .iter()
+            .map(|op| {
+                let r = op.pos().to_rect(slide_size);
+                Rect::from_min_size(
+                    canvas.min + vec2(r.left() as f32, r.top() as f32) * scale,
+                    vec2(r.width() as f32, r.height() as f32) * scale,
+                )
+            })
+            .collect();
+
+        if response.drag_started() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                self.dragging = hit_test(&rects, operations, pointer);
+                self.selected = self.dragging.map(|(i, _)| i);
+                self.residual = Vec2::ZERO;
+            }
+        } else if response.clicked() {
+            self.selected = response
+                .interact_pointer_pos()
+                .and_then(|pointer| hit_test(&rects, operations, pointer))
+                .map(|(i, _)| i);
+        }
+
+        let mut finished = false;
+        if let Some((index, kind)) = self.dragging {
+            if response.dragged() {
+                self.residual += response.drag_delta() / scale;
+                apply_drag(
+                    operations[index].pos_mut(),
+                    kind,
+                    &mut self.residual,
+                    slide_size,
+                );
+            }
+            if response.drag_stopped() {
+                self.dragging = None;
+                finished = true;
+            }
+        }
+
+        let hovered = response
+            .hover_pos()
+            .and_then(|pointer| hit_test(&rects, operations, pointer));
+        match self.dragging.or(hovered) {
+            Some((_, DragKind::Resize)) => ui.ctx().set_cursor_icon(CursorIcon::ResizeNwSe),
+            Some((_, DragKind::Move)) => ui.ctx().set_cursor_icon(CursorIcon::ResizeVertical),
+            None => {}
+        }
+
+        for (index, rect) in rects.iter().enumerate() {
+            let selected = self.selected == Some(index);
+            let color = if selected {
+                Color32::YELLOW
+            } else {
+                Color32::LIGHT_BLUE
+            };
+            painter.rect_stroke(*rect, 0.0, Stroke::new(1.5, color), StrokeKind::Inside);
+            painter.text(
+                rect.left_top() + vec2(3.0, 2.0),
+                Align2::LEFT_TOP,
+                index.to_string(),
+                FontId::proportional(12.0),
+                color,
+            );
+            if selected {
+                painter.rect_filled(
+                    Rect::from_center_size(rect.right_bottom(), Vec2::splat(HANDLE)),
+                    0.0,
+                    color,
+                );
+            }
+        }
+        finished
+    }
+}
+
+/// 按 z_index 从高到低查找指针下的元素，靠近右下角时视为缩放。
+fn hit_test(rects: &[Rect], operations: &[Operation], pointer: Pos2) -> Option<(usize, DragKind)> {
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(operations[i].z_index()));
+    order.into_iter().find_map(|i| {
+        let rect = rects[i];
+        if rect.right_bottom().distance(pointer) <= HANDLE {
+            Some((i, DragKind::Resize))
+        } else if rect.contains(pointer) {
+            Some((i, DragKind::Move))
+        } else {
+            None
+        }
+    })
+}
+
+/// 将拖拽量（slide 像素）写回位置。绝对坐标只消耗整数部分，余量留到下一帧。
+fn apply_drag(pos: &mut Position, kind: DragKind, delta: &mut Vec2, slide_size: (u32, u32)) {
+    let (w, h) = (slide_size.0 as f32, slide_size.1 as f32);
+    match pos {
+        Position::Absolute { left, top, height } => {
+            let (dx, dy) = (delta.x.trunc(), delta.y.trunc());
+            *delta -= vec2(dx, dy);
+            match kind {
+                DragKind::Move => *top += dy as i32,
+                DragKind::Resize => {
+                    *left = (*left - dx as i32).min((slide_size.0 as i32 - 1) / 2);
+                    *height = (*height as i32 + dy as i32).max(1) as u32;
+                }
+            }
+        }
+        Position::Relative {
+            rel_left,
+            rel_top,
+            rel_height,
+        } => {
+            let (dx, dy) = (delta.x / w, delta.y / h);
+            *delta = Vec2::ZERO;
+            match kind {
+                DragKind::Move => *rel_top += dy,
+                DragKind::Resize => {
+                    *rel_left = (*rel_left - dx).min(0.5 - 1.0 / w);
+                    *rel_height = (*rel_height + dy).max(1.0 / h);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/// 撤销/重做记录
+///
+/// 保存若干个历史状态，`record` 在状态变化时入栈，`undo`/`redo` 返回需要恢复的状态。
+pub struct History<T> {
+    current: T,
+    undo: Vec<T>,
+    redo: Vec<T>,
+    limit: usize,
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    /// 以初始状态创建，最多保留 `limit` 步撤销
+    pub fn new(state: T, limit: usize) -> Self {
+        Self {
+            current: state,
+            undo: vec![],
+            redo: vec![],
+            limit,
+        }
+    }
+
+    /// 记录新状态，与当前状态相同时忽略；有新记录时清空重做栈
+    pub fn record(&mut self, state: &T) {
+        if *state == self.current {
+            return;
+        }
+        let prev = std::mem::replace(&mut self.current, state.clone());
+        self.undo.push(prev);
+        if self.undo.len() > self.limit {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// 撤销，返回上一个状态
+    pub fn undo(&mut self) -> Option<T> {
+        let prev = self.undo.pop()?;
+        let current = std::mem::replace(&mut self.current, prev);
+        self.redo.push(current);
+        Some(self.current.clone())
+    }
+
+    /// 重做，返回下一个状态
+    pub fn redo(&mut self) -> Option<T> {
+        let next = self.redo.pop()?;
+        let current = std::mem::replace(&mut self.current, next);
+        self.undo.push(current);
+        Some(self.current.clone())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test]
+    fn undo_redo() {
+        let mut history = History::new(0, 10);
+        history.record(&1);
+        history.record(&1);
+        history.record(&2);
+        assert_eq!(history.undo(), Some(1));
+        assert_eq!(history.undo(), Some(0));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), Some(1));
+        history.record(&3);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(), Some(1));
+    }
+
+    #[test]
+    fn limit() {
+        let mut history = History::new(0, 2);
+        for i in 1..=5 {
+            history.record(&i);
+        }
+        assert_eq!(history.undo(), Some(4));
+        assert_eq!(history.undo(), Some(3));
+        assert_eq!(history.undo(), None);
+    }
+}
@@ -1,4 +1,9 @@
 pub mod app;
+mod editor;
+mod history;
+mod timeline;
+mod widgets;
+mod worker;
 use std::sync::Arc;
 
 use eframe::egui::{
@@ -12,7 +17,7 @@ pub fn set_fonts(ctx: &egui::Context) {
 
     // Install my own font (maybe supporting non-latin characters).
     // .ttf and .otf files supported.
-    let font_buf = include_bytes!("../../example/MiSans-Demibold.ttf");
+    let font_buf = include_bytes!("../../../../example/MiSans-Demibold.ttf");
     let font = FontData::from_static(font_buf);
     fonts.font_data.insert("my_font".to_owned(), Arc::new(font));
 
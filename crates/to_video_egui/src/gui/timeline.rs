@@ -0,0 +1,146 @@
+use eframe::egui::{
+    self, Color32, ColorImage, Rect, Sense, Stroke, StrokeKind, TextureHandle, TextureOptions,
+    pos2, vec2,
+};
+use image::{DynamicImage, imageops::FilterType};
+
+/// 缩略图的高度（像素），长条图按比例缩小到该高度后上传为纹理
+const THUMB_HEIGHT: u32 = 160;
+
+/// 时间轴预览：显示某个 chunk 组合后的长条图缩略图，
+/// 并用可拖动的播放头标出 t 时刻屏幕上可见的部分。
+#[derive(Default)]
+pub struct Timeline {
+    texture: Option<TextureHandle>,
+    /// 原始长条图的宽度（像素）
+    strip_width: u32,
+    screen_width: u32,
+    swip_pixels_per_sec: u32,
+    /// 该 chunk 结尾静止的秒数
+    static_sec: u32,
+    /// 播放头所在的时刻（秒）
+    pub time: f32,
+}
+
+impl Timeline {
+    /// 设置新的长条图，并把播放头移回开头
+    pub fn set_strip(
+        &mut self,
+        ctx: &egui::Context,
+        strip: &DynamicImage,
+        screen_width: u32,
+        swip_pixels_per_sec: u32,
+        static_sec: u32,
+    ) {
+        let thumb_width =
+            (strip.width() as u64 * THUMB_HEIGHT as u64 / strip.height() as u64).max(1) as u32;
+        let thumb = strip
+            .resize_exact(thumb_width, THUMB_HEIGHT, FilterType::Triangle)
+            .into_rgba8();
+        let image = ColorImage::from_rgba_unmultiplied(
+            [thumb.width() as usize, thumb.height() as usize],
+            thumb.as_raw(),
+        );
+        self.texture = Some(ctx.load_texture("timeline", image, TextureOptions::LINEAR));
+        self.strip_width = strip.width();
+        self.screen_width = screen_width;
+        self.swip_pixels_per_sec = swip_pixels_per_sec.max(1);
+        self.static_sec = static_sec;
+        self.time = 0.0;
+    }
+
+    /// 滚动所需的秒数，与 `Video::run` 中的计算一致
+    pub fn move_sec(&self) -> f32 {
+        self.strip_width.saturating_sub(self.screen_width) as f32 / self.swip_pixels_per_sec as f32
+    }
+
+    /// 片段总时长（秒）
+    pub fn duration(&self) -> f32 {
+        self.move_sec() + self.static_sec as f32
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let Some(texture) = &self.texture else {
+            ui.label("点击 \"时间轴\" 生成长条图预览");
+            return;
+        };
+        let duration = self.duration();
+        ui.add(
+            egui::Slider::new(&mut self.time, 0.0..=duration)
+                .text("秒")
+                .max_decimals(2),
+        );
+
+        let strip_width = self.strip_width as f32;
+        let thumb = texture.size_vec2();
+        let width = ui.available_width().max(1.0);
+        let scale = width / strip_width;
+        let (response, painter) = ui.allocate_painter(
+            vec2(width, thumb.y * width / thumb.x),
+            Sense::click_and_drag(),
+        );
+        let canvas = response.rect;
+        painter.image(
+            texture.id(),
+            canvas,
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+
+        // 拖动或点击时把可见窗口的中心移动到指针位置
+        if let Some(pointer) = response.interact_pointer_pos()
+            && (response.dragged() || response.clicked())
+        {
+            let center = (pointer.x - canvas.left()) / scale;
+            let offset = center - self.screen_width as f32 / 2.0;
+            self.time = (offset / self.swip_pixels_per_sec as f32).clamp(0.0, duration);
+        }
+
+        let offset = visible_offset(self.time, self.swip_pixels_per_sec, self.move_sec());
+        let window = Rect::from_min_size(
+            pos2(canvas.left() + offset * scale, canvas.top()),
+            vec2(self.screen_width as f32 * scale, canvas.height()),
+        );
+        painter.rect_filled(
+            Rect::from_min_max(canvas.min, pos2(window.left(), canvas.bottom())),
+            0.0,
+            Color32::from_black_alpha(140),
+        );
+        painter.rect_filled(
+            Rect::from_min_max(pos2(window.right(), canvas.top()), canvas.max),
+            0.0,
+            Color32::from_black_alpha(140),
+        );
+        painter.rect_stroke(
+            window,
+            0.0,
+            Stroke::new(2.0, Color32::YELLOW),
+            StrokeKind::Inside,
+        );
+
+        ui.label(format!(
+            "t = {:.2}s / {:.2}s, x = {:.0}px / {}px",
+            self.time,
+            self.duration(),
+            offset,
+            self.strip_width
+        ));
+    }
+}
+
+/// t 时刻长条图向左移动的像素数，对应 ffmpeg overlay 的 `x='-speed*clip(t,0,move_sec)'`
+pub fn visible_offset(time: f32, swip_pixels_per_sec: u32, move_sec: f32) -> f32 {
+    swip_pixels_per_sec as f32 * time.clamp(0.0, move_sec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::visible_offset;
+
+    #[test]
+    fn offset_stops_after_move_sec() {
+        assert_eq!(visible_offset(-1.0, 160, 10.0), 0.0);
+        assert_eq!(visible_offset(2.5, 160, 10.0), 400.0);
+        assert_eq!(visible_offset(12.0, 160, 10.0), 1600.0);
+    }
+}
@@ -0,0 +1,52 @@
+use eframe::egui::{self, Color32, Response};
+use std::{path::PathBuf, str::FromStr};
+
+/// 判断输入能否解析为 `T`，用于输入框的校验
+pub fn parses<T: FromStr>(value: &str) -> bool {
+    value.trim().parse::<T>().is_ok()
+}
+
+/// 带校验的单行输入框：`valid` 为 `false` 时以红色显示并给出提示，避免生成时才报错
+pub fn validated_edit(
+    ui: &mut egui::Ui,
+    value: &mut String,
+    hint: &str,
+    valid: bool,
+    width: f32,
+) -> Response {
+    let mut edit = egui::TextEdit::singleline(value)
+        .hint_text(hint)
+        .desired_width(width);
+    if !valid {
+        edit = edit.text_color(Color32::RED);
+    }
+    let response = ui.add(edit);
+    if valid {
+        response
+    } else {
+        response.on_hover_text(format!("无法解析 {hint}"))
+    }
+}
+
+/// 数字输入框，内容无法解析为 `T` 时高亮
+pub fn number_edit<T: FromStr>(ui: &mut egui::Ui, value: &mut String, width: f32) -> Response {
+    let valid = parses::<T>(value);
+    validated_edit(ui, value, "数字", valid, width)
+}
+
+/// 路径输入框加一个打开文件对话框的按钮，返回路径是否通过对话框被修改
+pub fn path_edit(
+    ui: &mut egui::Ui,
+    value: &mut String,
+    hint: &str,
+    dialog: impl FnOnce() -> Option<PathBuf>,
+) -> bool {
+    ui.add(egui::TextEdit::singleline(value).hint_text(hint));
+    if ui.button("...").clicked()
+        && let Some(path) = dialog()
+    {
+        *value = path.display().to_string();
+        return true;
+    }
+    false
+}
@@ -0,0 +1,80 @@
+use eframe::egui;
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+};
+use to_video::video::Video;
+
+/// 后台渲染线程发送给界面的事件
+pub enum Progress {
+    /// 一个视频片段生成完成
+    Step {
+        file: PathBuf,
+        done: usize,
+        total: usize,
+    },
+    /// 渲染结束，`Err` 中为错误信息（包括被取消）
+    Finished(Result<(), String>),
+}
+
+/// 在后台线程中运行的渲染任务。
+///
+/// 取消请求在每个片段生成完成后的进度回调中检查，因此正在执行的 ffmpeg 命令会先运行完毕。
+pub struct RenderJob {
+    receiver: Receiver<Progress>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl RenderJob {
+    pub fn spawn(video: Video, ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_flag = Arc::clone(&cancel);
+        thread::spawn(move || {
+            let step_sender = sender.clone();
+            let step_ctx = ctx.clone();
+            let result = video.run(move |file, done, total| {
+                if cancel_flag.load(Ordering::Relaxed) {
+                    return Err("已取消".to_string());
+                }
+                let _ = step_sender.send(Progress::Step {
+                    file: file.to_path_buf(),
+                    done,
+                    total,
+                });
+                step_ctx.request_repaint();
+                Ok(())
+            });
+            let _ = sender.send(Progress::Finished(
+                result.map(|_| ()).map_err(|e| e.to_string()),
+            ));
+            ctx.request_repaint();
+        });
+        Self { receiver, cancel }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// 取出目前已收到的所有事件，不会阻塞界面线程
+    pub fn poll(&self) -> Vec<Progress> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl Drop for RenderJob {
+    /// 丢弃任务（例如关闭窗口）时请求后台线程尽快停止，但不阻塞等待
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
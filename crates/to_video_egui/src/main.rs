@@ -1,26 +1,29 @@
-// mod gui;
+mod gui;
+use std::sync::Arc;
 use to_video::Result;
 
 fn main() -> Result<()> {
-    // let mut option = eframe::NativeOptions::default();
-    // let rgba =
-    //     image::load_from_memory_with_format(include_bytes!("icon.png"), image::ImageFormat::Png)?
-    //         .into_rgba8();
-    // let (width, height) = rgba.dimensions();
-    // option.viewport.icon = Some(Arc::new(eframe::egui::IconData {
-    //     width,
-    //     height,
-    //     rgba: rgba.into_raw(),
-    // }));
-    // match eframe::run_native(
-    //     "ImageToVideo",
-    //     option,
-    //     Box::new(|cc| Ok(Box::new(app::MyApp::new(cc)))),
-    // ) {
-    //     Ok(_) => {}
-    //     Err(e) => {
-    //         println!("{}", e)
-    //     }
-    // };
+    let mut option = eframe::NativeOptions::default();
+    let rgba = image::load_from_memory_with_format(
+        include_bytes!("../icon.png"),
+        image::ImageFormat::Png,
+    )?
+    .into_rgba8();
+    let (width, height) = rgba.dimensions();
+    option.viewport.icon = Some(Arc::new(eframe::egui::IconData {
+        width,
+        height,
+        rgba: rgba.into_raw(),
+    }));
+    match eframe::run_native(
+        "ImageToVideo",
+        option,
+        Box::new(|cc| Ok(Box::new(gui::app::MyApp::new(cc)))),
+    ) {
+        Ok(_) => {}
+        Err(e) => {
+            println!("{}", e)
+        }
+    };
     Ok(())
 }
@@ -0,0 +1,168 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+use to_video::Result;
+
+/// 请求体的最大长度
+const MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// 一个 HTTP/1.1 请求，只解析服务需要的部分
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// 从连接中读取一个请求。
+    ///
+    /// # Errors
+    /// - 请求行或头部格式错误、请求体超过 64 MiB 或连接提前关闭时返回 `Err`。
+    ///
+    pub fn read(reader: &mut impl BufRead) -> Result<Self> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(format!("invalid request line: {line:?}").into());
+        };
+        let method = method.to_string();
+        // 忽略查询参数
+        let path = target.split('?').next().unwrap_or_default().to_string();
+
+        let mut content_length = 0;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err("connection closed in headers".into());
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let Some((name, value)) = header.split_once(':') else {
+                return Err(format!("invalid header: {header:?}").into());
+            };
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid Content-Length: {value:?}"))?;
+            }
+        }
+        if content_length > MAX_BODY {
+            return Err(format!("request body too large: {content_length} bytes").into());
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        Ok(Self { method, path, body })
+    }
+
+    /// 去掉首尾的 `/` 后按 `/` 分割的路径
+    pub fn segments(&self) -> Vec<&str> {
+        self.path
+            .trim_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+fn write_head(
+    stream: &mut impl Write,
+    status: u16,
+    content_type: &str,
+    content_length: Option<u64>,
+) -> io::Result<()> {
+    write!(stream, "HTTP/1.1 {status} {}\r\n", reason(status))?;
+    write!(stream, "Content-Type: {content_type}\r\n")?;
+    if let Some(len) = content_length {
+        write!(stream, "Content-Length: {len}\r\n")?;
+    }
+    write!(stream, "Connection: close\r\n\r\n")
+}
+
+/// 返回 JSON 响应
+pub fn json(stream: &mut impl Write, status: u16, body: &serde_json::Value) -> io::Result<()> {
+    let body = body.to_string();
+    write_head(stream, status, "application/json", Some(body.len() as u64))?;
+    stream.write_all(body.as_bytes())
+}
+
+/// 返回 `{"error": message}`
+pub fn error(stream: &mut impl Write, status: u16, message: &str) -> io::Result<()> {
+    json(stream, status, &serde_json::json!({ "error": message }))
+}
+
+/// 返回文件内容
+pub fn file(stream: &mut impl Write, path: &Path, content_type: &str) -> io::Result<()> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    write_head(stream, 200, content_type, Some(len))?;
+    io::copy(&mut file, stream)?;
+    Ok(())
+}
+
+/// 开始一个 Server-Sent Events 响应，之后用 [`event`] 发送事件
+pub fn start_events(stream: &mut impl Write) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n"
+    )?;
+    stream.flush()
+}
+
+/// 发送一个 SSE 事件
+pub fn event(stream: &mut impl Write, data: &serde_json::Value) -> io::Result<()> {
+    write!(stream, "data: {data}\n\n")?;
+    stream.flush()
+}
+
+/// 发送 SSE 注释，用于保持空闲的连接
+pub fn keep_alive(stream: &mut impl Write) -> io::Result<()> {
+    stream.write_all(b": keep-alive\n\n")?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let raw = b"POST /render?x=1 HTTP/1.1\r\nHost: a\r\ncontent-length: 4\r\n\r\n{}{}";
+        let request = Request::read(&mut &raw[..]).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/render");
+        assert_eq!(request.body, b"{}{}");
+
+        let raw = b"GET /jobs/3/events/ HTTP/1.1\r\n\r\n";
+        let request = Request::read(&mut &raw[..]).unwrap();
+        assert_eq!(request.segments(), ["jobs", "3", "events"]);
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn test_read_invalid_request() {
+        assert!(Request::read(&mut &b"\r\n"[..]).is_err());
+        assert!(Request::read(&mut &b"GET / HTTP/1.1\r\nbad\r\n\r\n"[..]).is_err());
+        // 请求体不完整
+        let raw = b"POST /render HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}";
+        assert!(Request::read(&mut &raw[..]).is_err());
+    }
+}
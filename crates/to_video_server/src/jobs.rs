@@ -0,0 +1,338 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Condvar, Mutex, MutexGuard, mpsc},
+    time::Duration,
+};
+use to_video::{
+    Result,
+    color::Color,
+    slide::{Operation, TextQuality},
+    video::{
+        Video, VideoConfig, VideoConfigBuilder, ffmpeg::MotionType, group::GroupBy,
+        scroll::ChunkOverride,
+    },
+};
+
+/// `POST /render` 的请求体：与命令行的 info.json 相同，但数据直接内联
+#[derive(Deserialize)]
+pub struct RenderRequest {
+    pub operations: Vec<Operation>,
+    pub config: RenderConfig,
+    pub data: Vec<Vec<String>>,
+    #[serde(default)]
+    pub widths: Vec<u32>,
+}
+
+/// 客户端可以设置的配置，未设置的使用 [`VideoConfigBuilder::new`] 的默认值。
+///
+/// 要执行的程序、输出和临时文件的位置等由服务端决定，请求中出现其他字段时拒绝该请求
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    pub encoder: Option<String>,
+    pub preset: Option<String>,
+    pub screen: Option<(u32, u32)>,
+    pub fps: Option<u32>,
+    pub back_color: Option<String>,
+    pub cover_sec: Option<f32>,
+    pub motion_type: Option<MotionType>,
+    pub ending_sec: Option<u32>,
+    pub swip_pixels_per_sec: Option<u32>,
+    pub width_slides: Option<u32>,
+    pub step: Option<u32>,
+    /// 服务端的字体文件
+    pub font: Option<PathBuf>,
+    /// `null` 表示不绘制分割线
+    #[serde(deserialize_with = "set")]
+    pub split_line_color: Option<Option<Color>>,
+    pub split_line_width: Option<u32>,
+    pub text_quality: Option<TextQuality>,
+    pub chunk_overrides: Vec<ChunkOverride>,
+    pub group_by: Option<GroupBy>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// 出现的字段（包括 `null`）都为 `Some`，与缺少的字段区分
+fn set<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+impl RenderConfig {
+    /// 转为 [`VideoConfigBuilder`]，其余配置保持默认值
+    pub fn into_builder(self) -> VideoConfigBuilder {
+        let mut config = VideoConfig::builder();
+        let Self {
+            encoder,
+            preset,
+            screen,
+            fps,
+            back_color,
+            cover_sec,
+            motion_type,
+            ending_sec,
+            swip_pixels_per_sec,
+            width_slides,
+            step,
+            font,
+            split_line_color,
+            split_line_width,
+            text_quality,
+            chunk_overrides,
+            group_by,
+            metadata,
+        } = self;
+        config.encoder = encoder.unwrap_or(config.encoder);
+        config.preset = preset.unwrap_or(config.preset);
+        config.screen = screen.unwrap_or(config.screen);
+        config.fps = fps.unwrap_or(config.fps);
+        config.back_color = back_color.unwrap_or(config.back_color);
+        config.cover_sec = cover_sec.unwrap_or(config.cover_sec);
+        config.motion_type = motion_type.unwrap_or(config.motion_type);
+        config.ending_sec = ending_sec.unwrap_or(config.ending_sec);
+        config.swip_pixels_per_sec = swip_pixels_per_sec.unwrap_or(config.swip_pixels_per_sec);
+        config.width_slides = width_slides.unwrap_or(config.width_slides);
+        config.step = step.unwrap_or(config.step);
+        config.font = font.or(config.font);
+        config.split_line_color = split_line_color.unwrap_or(config.split_line_color);
+        config.split_line_width = split_line_width.unwrap_or(config.split_line_width);
+        config.text_quality = text_quality.unwrap_or(config.text_quality);
+        config.chunk_overrides = chunk_overrides;
+        config.group_by = group_by;
+        config.metadata = metadata;
+        config
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    Queued,
+    Running,
+    Done,
+    Failed { error: String },
+}
+
+impl Status {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::Failed { .. })
+    }
+}
+
+/// 任务的当前状态，作为 SSE 事件发送
+#[derive(Debug, Clone, Serialize)]
+pub struct JobState {
+    pub id: u64,
+    #[serde(flatten)]
+    pub status: Status,
+    /// 已生成的视频段数
+    pub done: usize,
+    pub total: usize,
+    /// 每次状态变化加 1，用于判断是否需要发送新事件
+    #[serde(skip)]
+    pub version: u64,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    jobs: HashMap<u64, JobState>,
+}
+
+/// 渲染任务队列，任务按提交顺序在一个后台线程中逐个渲染
+pub struct Jobs {
+    dir: PathBuf,
+    state: Mutex<State>,
+    changed: Condvar,
+    queue: Mutex<mpsc::Sender<(u64, RenderRequest)>>,
+}
+
+impl Jobs {
+    /// 创建任务队列并启动渲染线程，输出视频保存在 `dir` 中
+    pub fn start(dir: PathBuf) -> Result<&'static Self> {
+        std::fs::create_dir_all(&dir)?;
+        let (sender, receiver) = mpsc::channel();
+        let jobs: &'static Self = Box::leak(Box::new(Self {
+            dir,
+            state: Mutex::default(),
+            changed: Condvar::new(),
+            queue: Mutex::new(sender),
+        }));
+        std::thread::spawn(move || {
+            for (id, request) in receiver {
+                jobs.update(id, |job| job.status = Status::Running);
+                let status = match jobs.render(id, request) {
+                    Ok(()) => Status::Done,
+                    Err(e) => Status::Failed {
+                        error: e.to_string(),
+                    },
+                };
+                jobs.update(id, |job| job.status = status);
+            }
+        });
+        Ok(jobs)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 把任务加入队列，返回任务 id
+    pub fn submit(&self, request: RenderRequest) -> u64 {
+        let id = {
+            let mut state = self.lock();
+            state.next_id += 1;
+            let id = state.next_id;
+            state.jobs.insert(
+                id,
+                JobState {
+                    id,
+                    status: Status::Queued,
+                    done: 0,
+                    total: 0,
+                    version: 0,
+                },
+            );
+            id
+        };
+        if self
+            .queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .send((id, request))
+            .is_err()
+        {
+            self.update(id, |job| {
+                job.status = Status::Failed {
+                    error: "render worker stopped".to_string(),
+                }
+            });
+        }
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobState> {
+        self.lock().jobs.get(&id).cloned()
+    }
+
+    /// 等待任务的版本号大于 `version`，超时或任务不存在时返回当前状态
+    pub fn wait(&self, id: u64, version: u64, timeout: Duration) -> Option<JobState> {
+        let state = self.lock();
+        let (state, _) = self
+            .changed
+            .wait_timeout_while(state, timeout, |state| {
+                state
+                    .jobs
+                    .get(&id)
+                    .is_some_and(|job| job.version <= version)
+            })
+            .unwrap_or_else(|e| e.into_inner());
+        state.jobs.get(&id).cloned()
+    }
+
+    /// 任务的输出视频
+    pub fn output(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{id}.mp4"))
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut JobState)) {
+        if let Some(job) = self.lock().jobs.get_mut(&id) {
+            f(job);
+            job.version += 1;
+        }
+        self.changed.notify_all();
+    }
+
+    fn render(&self, id: u64, mut request: RenderRequest) -> Result<()> {
+        let mut config = request.config.into_builder();
+        config.save_path = Some(self.output(id));
+        config.work_dir = Some(self.dir.join("work"));
+
+        Video::builder(&mut request.operations, request.data, config.build()?)?
+            .slide_widths(&request.widths)?
+            .build()?
+            .run(|_, done, total| {
+                self.update(id, |job| {
+                    job.done = done;
+                    job.total = total;
+                });
+                Ok(())
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_json() {
+        let job = JobState {
+            id: 3,
+            status: Status::Failed {
+                error: "boom".to_string(),
+            },
+            done: 1,
+            total: 4,
+            version: 7,
+        };
+        assert_eq!(
+            serde_json::to_value(&job).unwrap(),
+            serde_json::json!({"id": 3, "status": "failed", "error": "boom", "done": 1, "total": 4})
+        );
+        assert!(job.status.is_finished());
+        assert!(!Status::Running.is_finished());
+    }
+
+    #[test]
+    fn test_render_config() {
+        let config: RenderConfig = serde_json::from_value(serde_json::json!({
+            "fps": 30,
+            "split_line_color": null,
+            "font": "fonts/a.ttf",
+        }))
+        .unwrap();
+        let config = config.into_builder();
+        assert_eq!(config.fps, 30);
+        assert_eq!(config.split_line_color, None);
+        assert_eq!(config.font, Some(PathBuf::from("fonts/a.ttf")));
+        assert_eq!(config.encoder, VideoConfig::builder().encoder);
+
+        let config = RenderConfig::default().into_builder();
+        assert_eq!(
+            config.split_line_color,
+            VideoConfig::builder().split_line_color
+        );
+    }
+
+    #[test]
+    fn test_render_config_rejects_server_fields() {
+        for field in ["ffmpeg_path", "save_path", "work_dir", "print_commands"] {
+            let json = serde_json::json!({ field: "/bin/sh" });
+            assert!(
+                serde_json::from_value::<RenderConfig>(json).is_err(),
+                "{field}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_request_rejects_narration() {
+        let request = serde_json::json!({
+            "operations": [],
+            "config": {"narration": {"program": "touch", "args": ["narrated", "{output}"]}},
+            "data": [["a"]],
+        });
+        let err = serde_json::from_value::<RenderRequest>(request)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("narration"), "{err}");
+    }
+}
@@ -0,0 +1,100 @@
+//! 渲染任务的 HTTP 服务。
+//!
+//! - `POST /render`：提交任务，请求体为 [`RenderRequest`]，返回 `{"id": ..}`
+//! - `GET /jobs/<id>`：任务状态
+//! - `GET /jobs/<id>/events`：以 Server-Sent Events 推送任务进度，任务结束后关闭
+//! - `GET /jobs/<id>/video`：下载生成的视频
+//!
+//! 数据中的图片路径和字体路径在服务端读取，服务只应监听在可信的网络中。
+//!
+//! 用法：`to_video_server [addr] [jobs_dir]`，默认为 `127.0.0.1:8080` 和 `jobs`。
+
+mod http;
+mod jobs;
+
+use http::Request;
+use jobs::{JobState, Jobs, RenderRequest, Status};
+use std::{
+    io::{self, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    time::Duration,
+};
+use to_video::Result;
+
+/// 没有新进度时发送 keep-alive 的间隔
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let dir = args
+        .next()
+        .map_or_else(|| PathBuf::from("jobs"), PathBuf::from);
+
+    let jobs = Jobs::start(dir)?;
+    let listener = TcpListener::bind(&addr)?;
+    println!("listening on http://{addr}");
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        std::thread::spawn(move || {
+            if let Err(e) = handle(jobs, stream) {
+                eprintln!("{e}");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(jobs: &Jobs, mut stream: TcpStream) -> Result<()> {
+    let request = match Request::read(&mut BufReader::new(&stream)) {
+        Ok(request) => request,
+        Err(e) => return Ok(http::error(&mut stream, 400, &e.to_string())?),
+    };
+    let segments = request.segments();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["render"]) => match serde_json::from_slice::<RenderRequest>(&request.body) {
+            Ok(render) => {
+                let id = jobs.submit(render);
+                http::json(&mut stream, 202, &serde_json::json!({ "id": id }))?;
+            }
+            Err(e) => http::error(&mut stream, 400, &format!("invalid render request: {e}"))?,
+        },
+        ("GET", ["jobs", id, rest @ ..]) => {
+            let Some(job) = id.parse().ok().and_then(|id| jobs.get(id)) else {
+                return Ok(http::error(&mut stream, 404, "no such job")?);
+            };
+            match rest {
+                [] => http::json(&mut stream, 200, &serde_json::to_value(&job)?)?,
+                ["events"] => events(jobs, job, &mut stream)?,
+                ["video"] if job.status == Status::Done => {
+                    http::file(&mut stream, &jobs.output(job.id), "video/mp4")?
+                }
+                ["video"] => http::error(&mut stream, 409, "video is not ready")?,
+                _ => http::error(&mut stream, 404, "not found")?,
+            }
+        }
+        (_, ["render"]) | (_, ["jobs", ..]) => http::error(&mut stream, 405, "method not allowed")?,
+        _ => http::error(&mut stream, 404, "not found")?,
+    }
+    Ok(())
+}
+
+/// 先发送任务的当前状态，之后每当状态变化时发送一个事件，任务结束后返回
+fn events(jobs: &Jobs, mut job: JobState, stream: &mut impl Write) -> io::Result<()> {
+    http::start_events(stream)?;
+    loop {
+        http::event(stream, &serde_json::to_value(&job)?)?;
+        if job.status.is_finished() {
+            return Ok(());
+        }
+        let version = job.version;
+        job = loop {
+            match jobs.wait(job.id, version, KEEP_ALIVE) {
+                Some(next) if next.version > version => break next,
+                Some(_) => http::keep_alive(stream)?,
+                None => return Ok(()),
+            }
+        };
+    }
+}